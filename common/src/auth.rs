@@ -0,0 +1,208 @@
+use crate::Timestamp;
+use anyhow::anyhow;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sp_core::crypto::Ss58Codec;
+use sp_core::sr25519;
+use sp_core::Pair as PairTrait;
+use std::fmt;
+
+pub const HEADER_TIMESTAMP: &str = "x-mbr-timestamp";
+pub const HEADER_SIGNATURE: &str = "x-mbr-signature";
+pub const HEADER_WORKER_ID: &str = "x-mbr-worker-id";
+const SECRET_LENGTH: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/*
+ * Secret issued by scheduler to a worker at registration.
+ * Scheduler signs control requests to the worker with it (HMAC-SHA256 over timestamp, path and body)
+ */
+#[derive(Clone, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WorkerSecret(String);
+
+impl WorkerSecret {
+    pub fn new(secret: &str) -> Self {
+        WorkerSecret(secret.to_string())
+    }
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; SECRET_LENGTH];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        WorkerSecret(hex::encode(bytes))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+    fn create_mac(
+        &self,
+        timestamp: Timestamp,
+        path: &str,
+        body: &[u8],
+    ) -> Result<HmacSha256, anyhow::Error> {
+        if self.is_empty() {
+            return Err(anyhow!("Worker secret is not issued"));
+        }
+        let mut mac = HmacSha256::new_from_slice(self.0.as_bytes())
+            .map_err(|err| anyhow!("Invalid worker secret: {:?}", err))?;
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(path.trim_start_matches('/').as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        Ok(mac)
+    }
+    pub fn sign(
+        &self,
+        timestamp: Timestamp,
+        path: &str,
+        body: &[u8],
+    ) -> Result<String, anyhow::Error> {
+        let mac = self.create_mac(timestamp, path, body)?;
+        Ok(hex::encode(mac.finalize().into_bytes()))
+    }
+    pub fn verify(
+        &self,
+        timestamp: Timestamp,
+        path: &str,
+        body: &[u8],
+        signature: &str,
+    ) -> Result<(), anyhow::Error> {
+        let signature =
+            hex::decode(signature).map_err(|err| anyhow!("Invalid signature: {:?}", err))?;
+        self.create_mac(timestamp, path, body)?
+            .verify_slice(&signature)
+            .map_err(|_| anyhow!("Signature mismatched"))
+    }
+}
+
+// Never write the secret to logs
+impl fmt::Debug for WorkerSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            write!(f, "WorkerSecret(None)")
+        } else {
+            write!(f, "WorkerSecret(***)")
+        }
+    }
+}
+
+/*
+ * Worker sr25519 keypair for signing job result batches.
+ * Public key (ss58) is sent to scheduler at registration and stored in workers table
+ */
+#[derive(Clone)]
+pub struct ResultSigner {
+    pair: sr25519::Pair,
+}
+
+impl ResultSigner {
+    pub fn from_phrase(phrase: &str) -> Result<Self, anyhow::Error> {
+        let pair = sr25519::Pair::from_string(phrase, None)
+            .map_err(|err| anyhow!("Invalid signer phrase: {:?}", err))?;
+        Ok(ResultSigner { pair })
+    }
+    pub fn generate() -> Self {
+        let (pair, _) = sr25519::Pair::generate();
+        ResultSigner { pair }
+    }
+    pub fn get_public_key(&self) -> String {
+        self.pair.public().to_ss58check()
+    }
+    pub fn sign(&self, message: &[u8]) -> String {
+        hex::encode(self.pair.sign(message))
+    }
+}
+
+impl fmt::Debug for ResultSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ResultSigner({})", self.get_public_key())
+    }
+}
+
+pub fn parse_public_key(public_key: &str) -> Result<sr25519::Public, anyhow::Error> {
+    sr25519::Public::from_ss58check(public_key)
+        .map_err(|err| anyhow!("Invalid public key {}: {:?}", public_key, err))
+}
+
+pub fn verify_signature(
+    public_key: &str,
+    message: &[u8],
+    signature: &str,
+) -> Result<(), anyhow::Error> {
+    let public = parse_public_key(public_key)?;
+    let signature =
+        hex::decode(signature).map_err(|err| anyhow!("Invalid signature: {:?}", err))?;
+    let signature = sr25519::Signature::try_from(signature.as_slice())
+        .map_err(|_| anyhow!("Invalid signature length {}", signature.len()))?;
+    if sr25519::Pair::verify(&signature, message, &public) {
+        Ok(())
+    } else {
+        Err(anyhow!("Signature mismatched"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let secret = WorkerSecret::generate();
+        assert_eq!(secret.as_str().len(), SECRET_LENGTH * 2);
+        assert_ne!(secret, WorkerSecret::generate());
+        assert_eq!(format!("{:?}", secret), "WorkerSecret(***)");
+
+        let body = br#"["plan_id"]"#;
+        let signature = secret.sign(1000, "cancel_plans", body).unwrap();
+        assert!(secret
+            .verify(1000, "/cancel_plans", body, &signature)
+            .is_ok());
+        assert!(secret
+            .verify(1001, "cancel_plans", body, &signature)
+            .is_err());
+        assert!(secret
+            .verify(1000, "cancel_jobs", body, &signature)
+            .is_err());
+        assert!(secret
+            .verify(1000, "cancel_plans", b"[]", &signature)
+            .is_err());
+        assert!(WorkerSecret::generate()
+            .verify(1000, "cancel_plans", body, &signature)
+            .is_err());
+        assert!(secret.verify(1000, "cancel_plans", body, "zz").is_err());
+        assert!(WorkerSecret::default()
+            .sign(1000, "cancel_plans", body)
+            .is_err());
+    }
+
+    #[test]
+    fn test_sign_and_verify_results() {
+        let signer = ResultSigner::generate();
+        let public_key = signer.get_public_key();
+        assert!(parse_public_key(&public_key).is_ok());
+        assert!(parse_public_key("public_key").is_err());
+
+        let body = br#"[{"job_id":"job_id","worker_id":"worker_id"}]"#;
+        let signature = signer.sign(body);
+        assert!(verify_signature(&public_key, body, &signature).is_ok());
+        assert!(verify_signature(&public_key, b"[]", &signature).is_err());
+        assert!(verify_signature(&public_key, body, "00").is_err());
+        let other = ResultSigner::generate();
+        assert!(verify_signature(&other.get_public_key(), body, &signature).is_err());
+
+        let signer = ResultSigner::from_phrase("//Alice").unwrap();
+        assert_eq!(
+            signer.get_public_key(),
+            ResultSigner::from_phrase("//Alice")
+                .unwrap()
+                .get_public_key()
+        );
+        assert!(ResultSigner::from_phrase("not a phrase").is_err());
+    }
+}
@@ -1,10 +1,14 @@
 use crate::{ComponentId, NetworkType};
 use crate::{Deserialize, Serialize};
 use anyhow::{anyhow, Error};
+use lazy_static::lazy_static;
+use serde::{Deserializer, Serializer};
 
+use std::collections::HashSet;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
+use std::sync::RwLock;
 
 #[derive(Clone, Debug, Deserialize, Serialize, Default, Hash, PartialEq, Eq)]
 pub struct ComponentInfo {
@@ -28,27 +32,16 @@ pub struct ComponentInfo {
     pub status: String,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, Hash, PartialEq, Eq)]
+/*
+ * Chain id of a provider. Chains other than the built in ones are described in chain registry (chain_type.json)
+ */
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum BlockChainType {
-    #[serde(rename = "eth")]
     Eth,
-    #[serde(rename = "dot")]
     Dot,
-    #[serde(rename = "bsc")]
     Bsc,
-    #[serde(rename = "matic")]
     Matic,
-}
-
-impl BlockChainType {
-    pub fn get_family(&self) -> BlockChainFamily {
-        match self {
-            BlockChainType::Eth | BlockChainType::Bsc | BlockChainType::Matic => {
-                BlockChainFamily::Ethereum
-            }
-            BlockChainType::Dot => BlockChainFamily::Polkadot,
-        }
-    }
+    Other(String),
 }
 
 impl Default for BlockChainType {
@@ -57,16 +50,44 @@ impl Default for BlockChainType {
     }
 }
 
-impl FromStr for BlockChainType {
-    type Err = Error;
+lazy_static! {
+    //Ids of chains registered from chain registry besides the built in ones
+    static ref REGISTERED_CHAINS: RwLock<HashSet<String>> = RwLock::new(HashSet::new());
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
+impl BlockChainType {
+    /*
+     * Accept chain ids of chain registry in from_str
+     */
+    pub fn register_chains(ids: impl IntoIterator<Item = String>) {
+        let mut chains = REGISTERED_CHAINS.write().unwrap();
+        chains.extend(ids.into_iter().map(|id| id.to_lowercase()));
+    }
+    fn from_id(id: &str) -> Result<Self, Error> {
+        match id.to_lowercase().as_str() {
             "eth" => Ok(BlockChainType::Eth),
             "dot" => Ok(BlockChainType::Dot),
             "bsc" => Ok(BlockChainType::Bsc),
-            "matic" => Ok(BlockChainType::Bsc),
-            _ => Err(anyhow!("Cannot parse {} to BlockChainType", s)),
+            "matic" => Ok(BlockChainType::Matic),
+            "" => Err(anyhow!("Cannot parse empty string to BlockChainType")),
+            other => Ok(BlockChainType::Other(other.to_string())),
+        }
+    }
+}
+
+impl FromStr for BlockChainType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match BlockChainType::from_id(s)? {
+            BlockChainType::Other(id) => {
+                if REGISTERED_CHAINS.read().unwrap().contains(&id) {
+                    Ok(BlockChainType::Other(id))
+                } else {
+                    Err(anyhow!("Unknown blockchain {}", s))
+                }
+            }
+            chain => Ok(chain),
         }
     }
 }
@@ -84,13 +105,32 @@ impl Display for BlockChainType {
                 write!(f, "bsc")
             }
             BlockChainType::Matic => {
-                write!(f, "bsc")
+                write!(f, "matic")
+            }
+            BlockChainType::Other(id) => {
+                write!(f, "{}", id)
             }
         }
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, Hash, PartialEq, Eq)]
+// Chain is serialized as its id, e.g. "eth"
+impl Serialize for BlockChainType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockChainType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        //Workers do not load chain registry, they keep chain ids sent by scheduler
+        BlockChainType::from_id(&id).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
 pub enum BlockChainFamily {
     Ethereum,
     Polkadot,
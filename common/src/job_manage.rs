@@ -9,6 +9,8 @@ use crate::component::ComponentType;
 use crate::jobs::Job;
 use crate::tasks::command::{JobCommand, JobCommandResult};
 use crate::tasks::compound::JobCompound;
+use crate::tasks::dot::{JobSubstrate, JobSubstrateResult};
+use crate::tasks::eth::random_block::{JobRandomBlock, JobRandomBlockResult};
 use crate::tasks::eth::CallBenchmarkError;
 use crate::tasks::http_request::{JobHttpRequest, JobHttpResult};
 use crate::tasks::ping::JobPingResult;
@@ -45,8 +47,8 @@ pub struct JobBenchmark {
     pub component_type: ComponentType,
     pub chain_type: BlockChainType,
     pub connection: u32,
-    pub thread: u32,
-    pub rate: u32, // Requests/sec
+    pub thread: u32, // Kept for wrk compatible configs, not used by native load generator
+    pub rate: u32,   // Requests/sec
     pub timeout: Timestamp,
     pub duration: Timestamp,  // Time to perform benchmark in ms
     pub script: String,       // Name of .lua script
@@ -90,6 +92,28 @@ pub struct BenchmarkResponse {
     pub histograms: HashMap<u32, f32>,
     pub error_code: u32,
     pub message: String,
+    #[serde(default)]
+    pub request_number: u64,
+    //Responses with non-2xx status
+    #[serde(default)]
+    pub non_success_number: u64,
+    //Requests without response (timeout, connection error)
+    #[serde(default)]
+    pub error_number: u64,
+}
+
+impl BenchmarkResponse {
+    pub fn get_success_percent(&self) -> Option<f32> {
+        if self.request_number > 0 {
+            let failed_number = self.non_success_number + self.error_number;
+            Some(
+                self.request_number.saturating_sub(failed_number) as f32 * 100f32
+                    / self.request_number as f32,
+            )
+        } else {
+            None
+        }
+    }
 }
 
 impl From<CallBenchmarkError> for BenchmarkResponse {
@@ -111,6 +135,10 @@ pub enum JobDetail {
     //LatestBlock(JobLatestBlock),
     // perform benchmark checking
     Benchmark(JobBenchmark),
+    // compare a random block with reference
+    RandomBlock(JobRandomBlock),
+    // perform a substrate health check
+    Substrate(JobSubstrate),
 }
 
 impl Default for JobDetail {
@@ -130,6 +158,8 @@ impl JobDetail {
             JobDetail::Ping(_) => "Ping".to_string(),
             //JobDetail::LatestBlock(_) => "LatestBlock".to_string(),
             JobDetail::Benchmark(_) => "Benchmark".to_string(),
+            JobDetail::RandomBlock(_) => "RandomBlock".to_string(),
+            JobDetail::Substrate(_) => "Substrate".to_string(),
         }
     }
 }
@@ -145,6 +175,10 @@ pub enum JobResultDetail {
     //LatestBlock(JobLatestBlockResult),
     // perform benchmark checking
     Benchmark(JobBenchmarkResult),
+    // compare a random block with reference
+    RandomBlock(JobRandomBlockResult),
+    // perform a substrate health check
+    Substrate(JobSubstrateResult),
     // Perform some request to node/gateway
     Compound(JobCompoundResult),
 }
@@ -184,6 +218,8 @@ impl JobResultDetail {
             JobResultDetail::Ping(_) => "Ping".to_string(),
             //JobResultDetail::LatestBlock(_) => "LatestBlock".to_string(),
             JobResultDetail::Benchmark(_) => "Benchmark".to_string(),
+            JobResultDetail::RandomBlock(_) => "RandomBlock".to_string(),
+            JobResultDetail::Substrate(_) => "Substrate".to_string(),
             JobResultDetail::Compound(_) => "Compound".to_string(),
             JobResultDetail::Websocket(_) => "Websocket".to_string(),
         }
@@ -218,7 +254,6 @@ pub struct Config {
     pub benchmark_duration: String,
     pub benchmark_rate: i32,
     pub benchmark_script: String,
-    pub benchmark_wrk_path: String,
     pub check_path_timeout_ms: u64,
     pub success_percent_threshold: u32,
     pub node_response_time_threshold_ms: f32,
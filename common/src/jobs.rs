@@ -8,6 +8,7 @@ use crate::{
 };
 use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Formatter};
+use std::str::FromStr;
 
 use std::sync::Arc;
 use uuid::Uuid;
@@ -21,6 +22,8 @@ pub enum JobStatus {
     DELIVERED,
     //Receive job result
     DONE,
+    //Job cannot be delivered to any worker
+    FAILED,
 }
 
 impl Default for JobStatus {
@@ -29,6 +32,70 @@ impl Default for JobStatus {
     }
 }
 
+// Status string stored in job_assignments table
+impl ToString for JobStatus {
+    fn to_string(&self) -> String {
+        match self {
+            JobStatus::CREATED => "created".to_string(),
+            JobStatus::ASSIGNED => "assigned".to_string(),
+            JobStatus::DELIVERED => "delivered".to_string(),
+            JobStatus::DONE => "done".to_string(),
+            JobStatus::FAILED => "failed".to_string(),
+        }
+    }
+}
+
+impl FromStr for JobStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "created" => Ok(JobStatus::CREATED),
+            "assigned" => Ok(JobStatus::ASSIGNED),
+            "delivered" => Ok(JobStatus::DELIVERED),
+            "done" => Ok(JobStatus::DONE),
+            "failed" => Ok(JobStatus::FAILED),
+            _ => Err(anyhow::anyhow!("Invalid job status {}", value)),
+        }
+    }
+}
+
+/*
+ * Resource class decides how a job shares worker resources with other jobs:
+ * LatencySensitive and Bulk jobs run in parallel, each class with its own concurrency cap,
+ * Exclusive job waits until all running jobs finished and runs alone.
+ */
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize, Hash, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceClass {
+    LatencySensitive,
+    Bulk,
+    Exclusive,
+}
+
+impl ToString for ResourceClass {
+    fn to_string(&self) -> String {
+        match self {
+            ResourceClass::LatencySensitive => "latency_sensitive".to_string(),
+            ResourceClass::Bulk => "bulk".to_string(),
+            ResourceClass::Exclusive => "exclusive".to_string(),
+        }
+    }
+}
+
+impl FromStr for ResourceClass {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "latency_sensitive" => Ok(ResourceClass::LatencySensitive),
+            "bulk" => Ok(ResourceClass::Bulk),
+            "exclusive" => Ok(ResourceClass::Exclusive),
+            _ => Err(anyhow::anyhow!("Invalid resource class {}", value)),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
 pub struct Job {
     pub job_id: JobId,
@@ -46,6 +113,8 @@ pub struct Job {
     pub interval: Timestamp, //
     pub job_detail: JobDetail,
     pub phase: JobRole,
+    #[serde(default)]
+    pub resource_class: Option<ResourceClass>, //If not set, it is derived from parallelable
 }
 
 impl From<&Job> for reqwest::Body {
@@ -88,6 +157,14 @@ impl Job {
             parallelable: false,
             component_url: "".to_string(),
             phase,
+            resource_class: None,
+        }
+    }
+    pub fn get_resource_class(&self) -> ResourceClass {
+        match &self.resource_class {
+            Some(resource_class) => resource_class.clone(),
+            None if self.parallelable => ResourceClass::LatencySensitive,
+            None => ResourceClass::Exclusive,
         }
     }
 }
@@ -101,6 +178,10 @@ pub struct JobAssignment {
     pub assigned_at: u64, //Timestamp to assign job
     pub finished_at: u64, //Timestamp when result has arrived
     pub result: Option<JobResult>,
+    pub delivery_attempts: u32,   //Failed deliveries to current worker
+    pub next_delivery: Timestamp, //Earliest time to retry a failed delivery
+    pub failed_workers: Vec<WorkerId>, //Workers which job cannot be delivered to
+    pub assignment_config: Option<AssignmentConfig>, //Config of the task which generated job
 }
 
 impl JobAssignment {
@@ -109,10 +190,26 @@ impl JobAssignment {
             zone: worker.get_zone(),
             worker,
             job: job.clone(),
-            status: Default::default(),
-            assigned_at: 0,
+            status: JobStatus::ASSIGNED,
+            assigned_at: get_current_time() as u64,
             finished_at: 0,
             result: None,
+            delivery_attempts: 0,
+            next_delivery: 0,
+            failed_workers: vec![],
+            assignment_config: None,
+        }
+    }
+    /*
+     * Move undelivered job to another worker, workers failed before are remembered
+     */
+    pub fn reassign(self, worker: Arc<Worker>) -> JobAssignment {
+        let mut failed_workers = self.failed_workers;
+        failed_workers.push(self.worker.get_id());
+        JobAssignment {
+            failed_workers,
+            assignment_config: self.assignment_config,
+            ..JobAssignment::new(worker, &self.job)
         }
     }
 }
@@ -123,6 +220,20 @@ pub struct AssignmentConfig {
     pub worker_number: Option<usize>,
     pub nearby_only: Option<bool>,
     pub by_distance: Option<bool>,
+    pub resource_class: Option<ResourceClass>,
+    //Keep a provider on the same workers, disabled by default
+    pub rendezvous_hashing: Option<bool>,
+}
+
+impl AssignmentConfig {
+    pub fn is_broadcast(&self) -> bool {
+        self.broadcast == Some(true)
+    }
+    pub fn use_rendezvous_hashing(&self) -> bool {
+        !self.is_broadcast()
+            && self.worker_number.is_some()
+            && self.rendezvous_hashing == Some(true)
+    }
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -154,6 +265,12 @@ impl Debug for JobResult {
             JobResultDetail::Benchmark(detail) => {
                 format!("{:?}", detail.response)
             }
+            JobResultDetail::RandomBlock(detail) => {
+                format!("{:?}", detail.response)
+            }
+            JobResultDetail::Substrate(detail) => {
+                format!("{:?}", detail.response)
+            }
             _ => Default::default(),
         };
         write!(
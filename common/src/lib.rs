@@ -1,3 +1,4 @@
+pub mod auth;
 pub mod component;
 pub mod job_action;
 pub mod job_manage;
@@ -8,13 +9,16 @@ pub use logger;
 use std::str::FromStr;
 
 pub mod models;
+pub mod shard;
 pub mod task_spawn;
 pub mod tasks;
+pub mod tls;
 pub mod types;
 pub mod util;
 pub mod workers;
 
 pub use crate::component::ComponentInfo;
+use crate::tls::TlsConfig;
 use lazy_static::lazy_static;
 use log::error;
 pub use types::*;
@@ -27,6 +31,11 @@ lazy_static! {
     pub static ref COMMON_CONFIG_FILE: String =
         env::var("COMMON_CONFIG_FILE").expect("There is no env var COMMON_CONFIG_FILE");
     pub static ref COMMON_CONFIG: Config = Config::load(COMMON_CONFIG_FILE.as_str());
+    //TLS settings for traffic between scheduler and workers
+    pub static ref TLS_CONFIG: TlsConfig = TlsConfig::from_env();
+    //Client for requests between scheduler and workers, it is built once so connections and TLS sessions are reused
+    pub static ref TLS_CLIENT: Result<reqwest::Client, String> =
+        TLS_CONFIG.build_client().map_err(|err| format!("{:?}", err));
 }
 
 #[derive(Deserialize, Debug)]
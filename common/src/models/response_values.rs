@@ -2,6 +2,8 @@ use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Formatter;
 use std::ops::{Deref, DerefMut};
 
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
@@ -9,7 +11,87 @@ pub struct ResponseConfig {
     #[serde(default)]
     pub response_type: String, //Response type: json or text
     #[serde(default)]
-    pub values: HashMap<String, Vec<Value>>, //Path to values
+    pub values: HashMap<String, ValuePath>, //Path to values
+}
+
+//Suffix of json path to get number of matched values or length of matched array
+const LENGTH_FUNCTION: &str = ".length()";
+
+/*
+ * Path to a value in json response, in one of forms:
+ * - list of object keys and array indices: ["result", "transactions", 0]
+ * - JSONPath expression: "$.result.transactions[?(@.value != '0x0')].hash"
+ * A JSONPath matching one node gives the node, matching several nodes gives an array.
+ * Suffix ".length()" gives the number of matched nodes (or array length if single array matched).
+ */
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ValuePath {
+    Keys(Vec<Value>),
+    JsonPath(String),
+}
+
+impl Default for ValuePath {
+    fn default() -> Self {
+        ValuePath::Keys(Vec::new())
+    }
+}
+
+impl fmt::Display for ValuePath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ValuePath::Keys(keys) => write!(f, "{}", Value::from(keys.clone())),
+            ValuePath::JsonPath(path) => write!(f, "{}", path),
+        }
+    }
+}
+
+impl ValuePath {
+    pub fn extract(&self, body: &Value) -> Result<Value, anyhow::Error> {
+        match self {
+            ValuePath::Keys(keys) => Self::extract_keys(body, keys).cloned(),
+            ValuePath::JsonPath(path) => Self::extract_json_path(body, path),
+        }
+    }
+    fn extract_keys<'a>(body: &'a Value, keys: &Vec<Value>) -> Result<&'a Value, anyhow::Error> {
+        let mut tmp_value = body;
+        for (ind, field) in keys.iter().enumerate() {
+            let next_value = match (field, tmp_value) {
+                (Value::String(key), Value::Object(map)) => map.get(key),
+                (Value::Number(index), Value::Array(items)) => {
+                    index.as_u64().and_then(|index| items.get(index as usize))
+                }
+                _ => None,
+            };
+            tmp_value = next_value.ok_or_else(|| {
+                anyhow!(
+                    "Path {} does not resolve at {}",
+                    Value::from(keys.clone()),
+                    Value::from(keys[..=ind].to_vec())
+                )
+            })?;
+        }
+        Ok(tmp_value)
+    }
+    fn extract_json_path(body: &Value, path: &str) -> Result<Value, anyhow::Error> {
+        let (path, get_length) = match path.strip_suffix(LENGTH_FUNCTION) {
+            Some(path) => (path, true),
+            None => (path, false),
+        };
+        let mut matched = jsonpath_lib::select(body, path)
+            .map_err(|err| anyhow!("Invalid json path {}: {:?}", path, err))?;
+        if get_length {
+            return match matched.as_slice() {
+                [Value::Array(items)] => Ok(Value::from(items.len())),
+                _ => Ok(Value::from(matched.len())),
+            };
+        }
+        match matched.len() {
+            0 => Err(anyhow!("Path {} does not resolve", path)),
+            1 => Ok(matched.remove(0).clone()),
+            _ => Ok(Value::Array(matched.into_iter().cloned().collect())),
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
@@ -23,27 +105,28 @@ impl ResponseValues {
     }
     pub fn extract_values(
         content: &str,
-        value_paths: &HashMap<String, Vec<Value>>,
+        value_paths: &HashMap<String, ValuePath>,
     ) -> Result<ResponseValues, anyhow::Error> {
         let body: Value = serde_json::from_str(content)
             .map_err(|e| anyhow!("Err {} when parsing response", e))?;
+        Self::extract_from_value(&body, value_paths)
+    }
+    pub fn extract_from_value(
+        body: &Value,
+        value_paths: &HashMap<String, ValuePath>,
+    ) -> Result<ResponseValues, anyhow::Error> {
         let mut results = ResponseValues::default();
-        for (key, paths) in value_paths.iter() {
-            let mut ind = 0_usize;
-            let mut tmp_value = &body;
-            while ind < paths.len() {
-                let field: &Value = paths.get(ind).unwrap();
-                if field.is_string() && tmp_value.is_object() {
-                    tmp_value = &tmp_value[field.as_str().unwrap()]
-                } else if field.is_number() && tmp_value.is_array() {
-                    tmp_value = &tmp_value[field.as_u64().unwrap() as usize]
-                }
-                ind = ind + 1;
-            }
-            results.insert(key.clone(), tmp_value.clone());
+        for (key, path) in value_paths.iter() {
+            let value = path
+                .extract(body)
+                .map_err(|err| anyhow!("Cannot extract value {}: {}", key, err))?;
+            results.insert(key.clone(), value);
         }
         Ok(results)
     }
+    pub fn into_inner(self) -> HashMap<String, Value> {
+        self.inner
+    }
 }
 
 impl Deref for ResponseValues {
@@ -59,3 +142,79 @@ impl DerefMut for ResponseValues {
         &mut self.inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn mock_block() -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "hash": "0x6a40",
+                "number": "0xe2e63a",
+                "parentHash": null,
+                "transactions": [
+                    {"hash": "0x4e3a", "value": "0x0"},
+                    {"hash": "0x00e0", "value": "0x10"},
+                    {"hash": "0x1f2b", "value": "0x20"}
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn test_extract_values() {
+        let paths: HashMap<String, ValuePath> = serde_json::from_value(json!({
+            "hash": ["result", "hash"],
+            "parent_hash": ["result", "parentHash"],
+            "first_tx": ["result", "transactions", 0, "hash"],
+            "number": "$.result.number",
+            "tx_hashes": "$.result.transactions[*].hash",
+            "value_tx": "$.result.transactions[?(@.value == '0x10')].hash",
+            "tx_count": "$.result.transactions.length()",
+            "non_zero_count": "$.result.transactions[?(@.value != '0x0')].length()"
+        }))
+        .unwrap();
+        let values = ResponseValues::extract_from_value(&mock_block(), &paths).unwrap();
+        assert_eq!(values["hash"], "0x6a40");
+        assert_eq!(values["parent_hash"], Value::Null);
+        assert_eq!(values["first_tx"], "0x4e3a");
+        assert_eq!(values["number"], "0xe2e63a");
+        assert_eq!(values["tx_hashes"], json!(["0x4e3a", "0x00e0", "0x1f2b"]));
+        assert_eq!(values["value_tx"], "0x00e0");
+        assert_eq!(values["tx_count"], 3);
+        assert_eq!(values["non_zero_count"], 2);
+    }
+
+    #[test]
+    fn test_extract_missing_path() {
+        let body = mock_block();
+        let err = ValuePath::Keys(vec![json!("result"), json!("miner")])
+            .extract(&body)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Path ["result","miner"] does not resolve at ["result","miner"]"#
+        );
+        let err = ValuePath::Keys(vec![json!("result"), json!("transactions"), json!(5)])
+            .extract(&body)
+            .unwrap_err();
+        assert!(err.to_string().contains("does not resolve"));
+        let err = ValuePath::JsonPath("$.result.miner".to_string())
+            .extract(&body)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Path $.result.miner does not resolve");
+        let paths = HashMap::from([(
+            "miner".to_string(),
+            ValuePath::JsonPath("$.result.miner".to_string()),
+        )]);
+        let err = ResponseValues::extract_from_value(&body, &paths).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot extract value miner: Path $.result.miner does not resolve"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/*
+ * Providers are partitioned across scheduler shards by hash of provider id.
+ * Each shard generates, judges and reports jobs of its providers,
+ * workers send results of a provider to its shard
+ */
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct ShardMap {
+    //Base urls of scheduler shards in shard index order, empty for a single scheduler
+    pub shards: Vec<String>,
+}
+
+impl ShardMap {
+    pub fn new(shards: Vec<String>) -> Self {
+        ShardMap { shards }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.shards.is_empty()
+    }
+    pub fn get_shard(&self, provider_id: &str) -> usize {
+        if self.shards.is_empty() {
+            return 0;
+        }
+        let digest = Sha256::digest(provider_id.as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        (u64::from_be_bytes(bytes) % self.shards.len() as u64) as usize
+    }
+    pub fn is_owned(&self, provider_id: &str, shard_index: usize) -> bool {
+        self.shards.is_empty() || self.get_shard(provider_id) == shard_index
+    }
+    pub fn get_report_callback(&self, provider_id: &str) -> Option<String> {
+        self.shards
+            .get(self.get_shard(provider_id))
+            .map(|url| format!("{}/report", url.trim_end_matches('/')))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_map() {
+        let single = ShardMap::default();
+        assert!(single.is_owned("provider", 1));
+        assert_eq!(single.get_report_callback("provider"), None);
+
+        let shard_map = ShardMap::new(vec![
+            "https://scheduler-0:3031".to_string(),
+            "https://scheduler-1:3031/".to_string(),
+            "https://scheduler-2:3031".to_string(),
+        ]);
+        let mut counters = [0usize; 3];
+        for ind in 0..300 {
+            let provider_id = format!("provider-{}", ind);
+            let shard = shard_map.get_shard(&provider_id);
+            // Same provider is always in the same shard
+            assert_eq!(shard, shard_map.get_shard(&provider_id));
+            assert!((0..3).all(|index| shard_map.is_owned(&provider_id, index) == (index == shard)));
+            assert_eq!(
+                shard_map.get_report_callback(&provider_id),
+                Some(format!("https://scheduler-{}:3031/report", shard))
+            );
+            counters[shard] += 1;
+        }
+        assert!(counters.iter().all(|counter| *counter > 50));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::job_manage::{JobDetail, JobResultDetail};
+use crate::jobs::{Job, JobResult};
+use crate::tasks::dot::{
+    CallSubstrateError, JobSubstrate, JobSubstrateResult, SubstrateCheck, SubstrateResponse,
+    SubstrateValues,
+};
+use crate::tasks::executor::TaskExecutor;
+use crate::util::get_current_time;
+use crate::WorkerId;
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use log::debug;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+
+#[derive(Clone, Debug, Default)]
+pub struct SubstrateExecutor {
+    worker_id: WorkerId,
+    http_client: Client,
+}
+
+impl SubstrateExecutor {
+    pub fn new(worker_id: WorkerId) -> Self {
+        SubstrateExecutor {
+            worker_id,
+            http_client: reqwest::Client::builder()
+                .danger_accept_invalid_certs(true)
+                .build()
+                .unwrap(),
+        }
+    }
+    async fn call_rpc(
+        &self,
+        job_detail: &JobSubstrate,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, CallSubstrateError> {
+        let body = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1});
+        let mut req_builder = self
+            .http_client
+            .post(&job_detail.url)
+            .timeout(Duration::from_millis(job_detail.timeout as u64))
+            .header("content-type", "application/json");
+        for (key, value) in job_detail.headers.iter() {
+            req_builder = req_builder.header(key, value);
+        }
+        let text = async {
+            req_builder
+                .body(body.to_string())
+                .send()
+                .await?
+                .text()
+                .await
+        }
+        .await
+        .map_err(|err| CallSubstrateError::RequestError(format!("{}: {}", method, err)))?;
+        let response = serde_json::from_str::<Value>(&text)
+            .map_err(|err| CallSubstrateError::ParseResultError(format!("{}: {}", method, err)))?;
+        if !response["error"].is_null() {
+            return Err(CallSubstrateError::RequestError(format!(
+                "{} returns error {}",
+                method, response["error"]
+            )));
+        }
+        Ok(response["result"].clone())
+    }
+    pub async fn call_check(
+        &self,
+        job_detail: &JobSubstrate,
+    ) -> Result<SubstrateValues, CallSubstrateError> {
+        let values = match job_detail.check {
+            SubstrateCheck::Health => {
+                let result = self
+                    .call_rpc(job_detail, "system_health", json!([]))
+                    .await?;
+                SubstrateValues::from_health(&result)
+            }
+            SubstrateCheck::SyncState => {
+                let result = self
+                    .call_rpc(job_detail, "system_syncState", json!([]))
+                    .await?;
+                SubstrateValues::from_sync_state(&result)
+            }
+            SubstrateCheck::FinalizedHead => {
+                // Finalized head is requested first, it is never ahead of the best head
+                let finalized_hash = self
+                    .call_rpc(job_detail, "chain_getFinalizedHead", json!([]))
+                    .await?;
+                let (best, finalized) = tokio::join!(
+                    self.call_rpc(job_detail, "chain_getHeader", json!([])),
+                    self.call_rpc(job_detail, "chain_getHeader", json!([finalized_hash]))
+                );
+                SubstrateValues::from_headers(&best?, &finalized?)
+            }
+            SubstrateCheck::RuntimeVersion => {
+                let result = self
+                    .call_rpc(job_detail, "state_getRuntimeVersion", json!([]))
+                    .await?;
+                SubstrateValues::from_runtime_version(&result)
+            }
+        };
+        values.map_err(|err| CallSubstrateError::ParseResultError(format!("{}", err)))
+    }
+}
+
+#[async_trait]
+impl TaskExecutor for SubstrateExecutor {
+    async fn execute(&self, job: &Job, result_sender: Sender<JobResult>) -> Result<(), Error> {
+        debug!("Substrate execute for job {:?}", &job);
+        if let JobDetail::Substrate(job_detail) = &job.job_detail {
+            let request_timestamp = get_current_time();
+            let response = match self.call_check(job_detail).await {
+                Ok(values) => SubstrateResponse {
+                    request_timestamp,
+                    response_duration: get_current_time() - request_timestamp,
+                    values,
+                    error_code: 0,
+                    message: String::new(),
+                },
+                Err(err) => SubstrateResponse::new_error(
+                    request_timestamp,
+                    err.get_code(),
+                    err.get_message().as_str(),
+                ),
+            };
+            debug!("Substrate {} result {:?}", job_detail.check, &response);
+            let result = JobSubstrateResult {
+                job: job.clone(),
+                worker_id: self.worker_id.clone(),
+                response,
+            };
+            let res = result_sender
+                .send(JobResult::new(
+                    JobResultDetail::Substrate(result),
+                    Some(job_detail.chain_info.clone()),
+                    job,
+                ))
+                .await;
+            debug!("send res: {:?}", res);
+            Ok(())
+        } else {
+            Err(anyhow!("Execute wrong job type"))
+        }
+    }
+    fn can_apply(&self, job: &Job) -> bool {
+        matches!(job.job_detail, JobDetail::Substrate(_))
+    }
+}
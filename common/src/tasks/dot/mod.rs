@@ -0,0 +1,264 @@
+pub mod executor;
+
+pub use executor::*;
+
+use crate::component::ChainInfo;
+use crate::jobs::Job;
+use crate::util::from_str_radix16;
+use crate::{Timestamp, WorkerId};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use thiserror::Error;
+
+/*
+ * Health checks of Substrate nodes, each check is a separated job
+ */
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SubstrateCheck {
+    // system_health
+    Health,
+    // system_syncState
+    SyncState,
+    // chain_getFinalizedHead compared with best head
+    FinalizedHead,
+    // state_getRuntimeVersion
+    RuntimeVersion,
+}
+
+impl Default for SubstrateCheck {
+    fn default() -> Self {
+        SubstrateCheck::Health
+    }
+}
+
+impl Display for SubstrateCheck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SubstrateCheck::Health => "health",
+            SubstrateCheck::SyncState => "sync_state",
+            SubstrateCheck::FinalizedHead => "finalized_head",
+            SubstrateCheck::RuntimeVersion => "runtime_version",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct JobSubstrate {
+    pub chain_info: ChainInfo,
+    pub check: SubstrateCheck,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub timeout: Timestamp,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RuntimeVersion {
+    pub spec_name: String,
+    pub spec_version: u64,
+    pub transaction_version: u64,
+}
+
+impl Display for RuntimeVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{} (tx {})",
+            self.spec_name, self.spec_version, self.transaction_version
+        )
+    }
+}
+
+/*
+ * Values returned by a check
+ */
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum SubstrateValues {
+    None,
+    Health {
+        is_syncing: bool,
+        peers: u64,
+        should_have_peers: bool,
+    },
+    SyncState {
+        current_block: u64,
+        highest_block: u64,
+    },
+    FinalizedHead {
+        best_number: u64,
+        finalized_number: u64,
+    },
+    RuntimeVersion(RuntimeVersion),
+}
+
+impl Default for SubstrateValues {
+    fn default() -> Self {
+        SubstrateValues::None
+    }
+}
+
+impl SubstrateValues {
+    /*
+     * Parse result of system_health
+     */
+    pub fn from_health(result: &Value) -> Result<Self, anyhow::Error> {
+        Ok(SubstrateValues::Health {
+            is_syncing: get_bool(result, "isSyncing")?,
+            peers: get_u64(result, "peers")?,
+            should_have_peers: get_bool(result, "shouldHavePeers")?,
+        })
+    }
+    /*
+     * Parse result of system_syncState
+     */
+    pub fn from_sync_state(result: &Value) -> Result<Self, anyhow::Error> {
+        Ok(SubstrateValues::SyncState {
+            current_block: get_u64(result, "currentBlock")?,
+            highest_block: get_u64(result, "highestBlock")?,
+        })
+    }
+    /*
+     * Parse results of chain_getHeader for best and finalized heads
+     */
+    pub fn from_headers(best: &Value, finalized: &Value) -> Result<Self, anyhow::Error> {
+        Ok(SubstrateValues::FinalizedHead {
+            best_number: get_header_number(best)?,
+            finalized_number: get_header_number(finalized)?,
+        })
+    }
+    /*
+     * Parse result of state_getRuntimeVersion
+     */
+    pub fn from_runtime_version(result: &Value) -> Result<Self, anyhow::Error> {
+        let spec_name = result["specName"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Missing specName in {}", result))?;
+        Ok(SubstrateValues::RuntimeVersion(RuntimeVersion {
+            spec_name: spec_name.to_string(),
+            spec_version: get_u64(result, "specVersion")?,
+            transaction_version: get_u64(result, "transactionVersion")?,
+        }))
+    }
+}
+
+fn get_bool(result: &Value, field: &str) -> Result<bool, anyhow::Error> {
+    result[field]
+        .as_bool()
+        .ok_or_else(|| anyhow!("Missing {} in {}", field, result))
+}
+
+fn get_u64(result: &Value, field: &str) -> Result<u64, anyhow::Error> {
+    result[field]
+        .as_u64()
+        .ok_or_else(|| anyhow!("Missing {} in {}", field, result))
+}
+
+fn get_header_number(header: &Value) -> Result<u64, anyhow::Error> {
+    let number = header["number"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Missing number in header {}", header))?;
+    Ok(from_str_radix16(number)? as u64)
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct SubstrateResponse {
+    pub request_timestamp: Timestamp,
+    pub response_duration: Timestamp,
+    pub values: SubstrateValues,
+    pub error_code: u32,
+    pub message: String,
+}
+
+impl SubstrateResponse {
+    pub fn new_error(request_timestamp: Timestamp, error_code: u32, message: &str) -> Self {
+        SubstrateResponse {
+            request_timestamp,
+            error_code,
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct JobSubstrateResult {
+    pub job: Job,
+    pub worker_id: WorkerId,
+    pub response: SubstrateResponse,
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum CallSubstrateError {
+    #[error("request error")]
+    RequestError(String),
+    #[error("parse result error")]
+    ParseResultError(String),
+}
+
+impl CallSubstrateError {
+    pub fn get_message(&self) -> String {
+        match self {
+            CallSubstrateError::RequestError(message)
+            | CallSubstrateError::ParseResultError(message) => message.to_string(),
+        }
+    }
+    pub fn get_code(&self) -> u32 {
+        match self {
+            CallSubstrateError::RequestError(_) => 1u32,
+            CallSubstrateError::ParseResultError(_) => 2u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_substrate_values() -> Result<(), anyhow::Error> {
+        let health = json!({"isSyncing": false, "peers": 40, "shouldHavePeers": true});
+        assert_eq!(
+            SubstrateValues::from_health(&health)?,
+            SubstrateValues::Health {
+                is_syncing: false,
+                peers: 40,
+                should_have_peers: true
+            }
+        );
+        let sync_state =
+            json!({"startingBlock": 0, "currentBlock": 11045944, "highestBlock": 11045946});
+        assert_eq!(
+            SubstrateValues::from_sync_state(&sync_state)?,
+            SubstrateValues::SyncState {
+                current_block: 11045944,
+                highest_block: 11045946
+            }
+        );
+        let best = json!({"number": "0xa88c38", "parentHash": "0x9dc0f5d6"});
+        let finalized = json!({"number": "0xa88c36", "parentHash": "0x1f18"});
+        assert_eq!(
+            SubstrateValues::from_headers(&best, &finalized)?,
+            SubstrateValues::FinalizedHead {
+                best_number: 0xa88c38,
+                finalized_number: 0xa88c36
+            }
+        );
+        let runtime = json!({"specName": "polkadot", "specVersion": 9270, "transactionVersion": 13, "implVersion": 0});
+        assert_eq!(
+            SubstrateValues::from_runtime_version(&runtime)?,
+            SubstrateValues::RuntimeVersion(RuntimeVersion {
+                spec_name: "polkadot".to_string(),
+                spec_version: 9270,
+                transaction_version: 13
+            })
+        );
+        assert!(SubstrateValues::from_health(&json!(null)).is_err());
+        assert!(SubstrateValues::from_headers(&best, &json!({"number": 1})).is_err());
+        Ok(())
+    }
+}
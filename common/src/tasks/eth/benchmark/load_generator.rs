@@ -0,0 +1,249 @@
+use crate::job_manage::{BenchmarkResponse, JobBenchmark};
+use crate::tasks::eth::CallBenchmarkError;
+use futures::future::join_all;
+use hdrhistogram::Histogram;
+use log::{debug, trace};
+use reqwest::{Client, Method};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::time::{sleep_until, Instant};
+
+//Highest latency can be recorded, in microseconds
+const MAX_RECORDED_LATENCY: u64 = 3_600_000_000;
+
+#[derive(Debug)]
+struct LoadStats {
+    //Latency in microseconds
+    latencies: Histogram<u64>,
+    response_number: u64,
+    non_success_number: u64,
+    error_number: u64,
+    received_bytes: u64,
+    last_error: Option<String>,
+}
+
+impl LoadStats {
+    fn new() -> Result<Self, CallBenchmarkError> {
+        let latencies = Histogram::<u64>::new_with_bounds(1, MAX_RECORDED_LATENCY, 3)
+            .map_err(|err| CallBenchmarkError::GetJobInfoError(format!("{:?}", err)))?;
+        Ok(LoadStats {
+            latencies,
+            response_number: 0,
+            non_success_number: 0,
+            error_number: 0,
+            received_bytes: 0,
+            last_error: None,
+        })
+    }
+}
+
+/*
+ * Constant rate load generator, request i is scheduled at start + i/rate.
+ * Number of in-flight requests is limited by connection number,
+ * latency is measured from the scheduled time so the delay caused by a slow server
+ * is included in the histogram (no coordinated omission).
+ */
+#[derive(Debug)]
+pub struct LoadGenerator {
+    client: Client,
+    method: Method,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+    rate: u32,
+    connection: u32,
+    duration: Duration,
+    histograms: Vec<u32>,
+}
+
+impl LoadGenerator {
+    pub fn new(job_detail: &JobBenchmark) -> Result<Self, CallBenchmarkError> {
+        if job_detail.rate == 0 {
+            return Err(CallBenchmarkError::GetJobInfoError(
+                "Benchmark rate must be greater than 0".to_string(),
+            ));
+        }
+        let method = Method::from_str(&job_detail.method.to_uppercase())
+            .map_err(|err| CallBenchmarkError::GetJobInfoError(format!("{:?}", err)))?;
+        let connection = job_detail.connection.max(1);
+        let client = Client::builder()
+            .danger_accept_invalid_certs(true)
+            .pool_max_idle_per_host(connection as usize)
+            .timeout(Duration::from_millis(job_detail.timeout.max(1) as u64))
+            .build()
+            .map_err(|err| CallBenchmarkError::GetJobInfoError(format!("{:?}", err)))?;
+        Ok(LoadGenerator {
+            client,
+            method,
+            url: job_detail.url_path.clone(),
+            headers: job_detail
+                .headers
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            body: job_detail.body.as_ref().map(|body| body.to_string()),
+            rate: job_detail.rate,
+            connection,
+            duration: Duration::from_millis(job_detail.duration.max(0) as u64),
+            histograms: job_detail.histograms.clone(),
+        })
+    }
+
+    pub async fn run(&self) -> Result<BenchmarkResponse, CallBenchmarkError> {
+        let request_number = (self.rate as u128 * self.duration.as_millis() / 1000).max(1) as u64;
+        let interval = Duration::from_secs_f64(1f64 / self.rate as f64);
+        let connections = Arc::new(Semaphore::new(self.connection as usize));
+        let stats = Arc::new(Mutex::new(LoadStats::new()?));
+        debug!(
+            "Start benchmark {} {} with {} requests at {} req/s over {} connections",
+            &self.method, &self.url, request_number, self.rate, self.connection
+        );
+        let start = Instant::now();
+        let mut tasks = Vec::with_capacity(request_number as usize);
+        for ind in 0..request_number {
+            let scheduled_time = start + interval.mul_f64(ind as f64);
+            sleep_until(scheduled_time).await;
+            let permit = connections
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|err| CallBenchmarkError::SendError(format!("{:?}", err)))?;
+            let request = self.build_request();
+            let stats = stats.clone();
+            tasks.push(tokio::spawn(async move {
+                let res = match request.send().await {
+                    Ok(response) => {
+                        let status = response.status();
+                        response
+                            .bytes()
+                            .await
+                            .map(|bytes| (status, bytes.len() as u64))
+                    }
+                    Err(err) => Err(err),
+                };
+                let latency = scheduled_time.elapsed();
+                drop(permit);
+                let mut stats = stats.lock().unwrap();
+                match res {
+                    Ok((status, size)) => {
+                        trace!("Benchmark response {} in {:?}", status, latency);
+                        stats.response_number += 1;
+                        stats.received_bytes += size;
+                        if !status.is_success() {
+                            stats.non_success_number += 1;
+                        }
+                        stats
+                            .latencies
+                            .saturating_record((latency.as_micros() as u64).max(1));
+                    }
+                    Err(err) => {
+                        stats.error_number += 1;
+                        stats.last_error = Some(format!("{}", err));
+                    }
+                }
+            }));
+        }
+        join_all(tasks).await;
+        let elapsed = start.elapsed().as_secs_f32();
+        let stats = stats.lock().unwrap();
+        debug!("Benchmark {} finished: {:?}", &self.url, &stats);
+        self.get_response(&stats, elapsed)
+    }
+
+    fn build_request(&self) -> reqwest::RequestBuilder {
+        let mut req_builder = self.client.request(self.method.clone(), self.url.as_str());
+        for (key, value) in self.headers.iter() {
+            req_builder = req_builder.header(key, value);
+        }
+        if let Some(body) = &self.body {
+            req_builder = req_builder.body(body.clone());
+        }
+        req_builder
+    }
+
+    fn get_response(
+        &self,
+        stats: &LoadStats,
+        elapsed: f32,
+    ) -> Result<BenchmarkResponse, CallBenchmarkError> {
+        if stats.response_number == 0 {
+            return Err(CallBenchmarkError::SendError(format!(
+                "All {} requests failed, last error: {}",
+                stats.error_number,
+                stats.last_error.clone().unwrap_or_default()
+            )));
+        }
+        let histograms = self
+            .histograms
+            .iter()
+            .map(|percent| {
+                let latency = stats
+                    .latencies
+                    .value_at_quantile((*percent as f64 / 100f64).min(1f64));
+                (*percent, latency as f32 / 1000f32)
+            })
+            .collect();
+        let message = if stats.non_success_number == 0 && stats.error_number == 0 {
+            "success".to_string()
+        } else {
+            format!(
+                "success with {} non-2xx responses and {} errors in {} requests",
+                stats.non_success_number,
+                stats.error_number,
+                stats.response_number + stats.error_number
+            )
+        };
+        Ok(BenchmarkResponse {
+            request_rate: stats.response_number as f32 / elapsed,
+            transfer_rate: stats.received_bytes as f32 / elapsed,
+            average_latency: (stats.latencies.mean() / 1000f64) as f32,
+            histograms,
+            error_code: 0,
+            message,
+            request_number: stats.response_number + stats.error_number,
+            non_success_number: stats.non_success_number,
+            error_number: stats.error_number,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_constant_rate_load() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/").body(r#"{"id":1}"#);
+            then.status(200).body("0123456789");
+        });
+        let job_detail = JobBenchmark {
+            connection: 2,
+            thread: 1,
+            rate: 20,
+            timeout: 1000,
+            duration: 1000,
+            histograms: vec![90, 95, 99, 100],
+            url_path: server.url("/"),
+            method: "post".to_string(),
+            headers: HashMap::from([("content-type".to_string(), "application/json".to_string())]),
+            body: Some(serde_json::json!({"id": 1})),
+            ..Default::default()
+        };
+        let generator = LoadGenerator::new(&job_detail).unwrap();
+        let response = generator.run().await.unwrap();
+        mock.assert_hits(20);
+        assert_eq!(response.error_code, 0);
+        assert_eq!(response.message, "success");
+        assert_eq!(response.request_number, 20);
+        assert_eq!(response.get_success_percent(), Some(100f32));
+        assert_eq!(response.histograms.len(), 4);
+        assert!(response.histograms[&90] <= response.histograms[&100]);
+        assert!(response.request_rate > 10f32 && response.request_rate <= 22f32);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod executor;
+pub mod load_generator;
 use thiserror::Error;
 
 #[derive(Error, Debug, Clone)]
@@ -0,0 +1,197 @@
+use crate::job_manage::{JobDetail, JobResultDetail};
+use crate::jobs::{Job, JobResult};
+use crate::tasks::eth::random_block::{
+    BlockSummary, CallRandomBlockError, JobRandomBlock, JobRandomBlockResult, RandomBlockResponse,
+};
+use crate::tasks::executor::TaskExecutor;
+use crate::util::{from_str_radix16, get_current_time};
+use crate::WorkerId;
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use log::debug;
+use rand::Rng;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+
+#[derive(Clone, Debug, Default)]
+pub struct RandomBlockExecutor {
+    worker_id: WorkerId,
+    http_client: Client,
+}
+
+impl RandomBlockExecutor {
+    pub fn new(worker_id: WorkerId) -> Self {
+        RandomBlockExecutor {
+            worker_id,
+            http_client: reqwest::Client::builder()
+                .danger_accept_invalid_certs(true)
+                .build()
+                .unwrap(),
+        }
+    }
+    async fn call_rpc(
+        &self,
+        url: &str,
+        headers: &HashMap<String, String>,
+        job_detail: &JobRandomBlock,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, Error> {
+        let body = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1});
+        let mut req_builder = self
+            .http_client
+            .post(url)
+            .timeout(Duration::from_millis(job_detail.timeout as u64))
+            .header("content-type", "application/json");
+        for (key, value) in headers.iter() {
+            req_builder = req_builder.header(key, value);
+        }
+        let text = req_builder
+            .body(body.to_string())
+            .send()
+            .await?
+            .text()
+            .await?;
+        let response = serde_json::from_str::<Value>(&text)?;
+        if !response["error"].is_null() {
+            return Err(anyhow!("{} returns error {}", method, response["error"]));
+        }
+        Ok(response["result"].clone())
+    }
+    pub fn pick_block_number(head: u64, depth: u64, confirmations: u64) -> u64 {
+        let highest = head.saturating_sub(confirmations);
+        let lowest = if depth == 0 {
+            0
+        } else {
+            head.saturating_sub(depth).min(highest)
+        };
+        rand::thread_rng().gen_range(lowest..=highest)
+    }
+    pub async fn call_random_block(
+        &self,
+        job_detail: &JobRandomBlock,
+    ) -> Result<RandomBlockResponse, CallRandomBlockError> {
+        let request_timestamp = get_current_time();
+        let head = self
+            .call_rpc(
+                &job_detail.reference_url,
+                &job_detail.reference_headers,
+                job_detail,
+                "eth_blockNumber",
+                json!([]),
+            )
+            .await
+            .and_then(|head| {
+                head.as_str()
+                    .ok_or_else(|| anyhow!("Invalid block number {}", head))
+                    .and_then(from_str_radix16)
+            })
+            .map_err(|err| CallRandomBlockError::ReferenceError(format!("{}", err)))?;
+        let block_number =
+            Self::pick_block_number(head as u64, job_detail.depth, job_detail.confirmations);
+        // Full transactions are requested, as normal clients do
+        let params = json!([format!("0x{:x}", block_number), true]);
+        let (reference, provider) = tokio::join!(
+            self.call_rpc(
+                &job_detail.reference_url,
+                &job_detail.reference_headers,
+                job_detail,
+                "eth_getBlockByNumber",
+                params.clone(),
+            ),
+            self.call_rpc(
+                &job_detail.url,
+                &job_detail.headers,
+                job_detail,
+                "eth_getBlockByNumber",
+                params,
+            )
+        );
+        let reference_block = reference
+            .and_then(|block| BlockSummary::from_rpc_block(&block))
+            .map_err(|err| {
+                CallRandomBlockError::ReferenceError(format!("Block {}: {}", block_number, err))
+            })?;
+        let provider_block = provider
+            .map_err(|err| {
+                CallRandomBlockError::ProviderError(format!("Block {}: {}", block_number, err))
+            })
+            .and_then(|block| {
+                BlockSummary::from_rpc_block(&block).map_err(|err| {
+                    CallRandomBlockError::ParseResultError(format!(
+                        "Block {}: {}",
+                        block_number, err
+                    ))
+                })
+            })?;
+        Ok(RandomBlockResponse {
+            request_timestamp,
+            response_duration: get_current_time() - request_timestamp,
+            block_number,
+            provider_block: Some(provider_block),
+            reference_block: Some(reference_block),
+            error_code: 0,
+            message: String::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl TaskExecutor for RandomBlockExecutor {
+    async fn execute(&self, job: &Job, result_sender: Sender<JobResult>) -> Result<(), Error> {
+        debug!("RandomBlock execute for job {:?}", &job);
+        if let JobDetail::RandomBlock(job_detail) = &job.job_detail {
+            let request_timestamp = get_current_time();
+            let response = self
+                .call_random_block(job_detail)
+                .await
+                .unwrap_or_else(|err| {
+                    RandomBlockResponse::new_error(
+                        request_timestamp,
+                        err.get_code(),
+                        err.get_message().as_str(),
+                    )
+                });
+            debug!("RandomBlock result {:?}", &response);
+            let result = JobRandomBlockResult {
+                job: job.clone(),
+                worker_id: self.worker_id.clone(),
+                response,
+            };
+            let res = result_sender
+                .send(JobResult::new(
+                    JobResultDetail::RandomBlock(result),
+                    Some(job_detail.chain_info.clone()),
+                    job,
+                ))
+                .await;
+            debug!("send res: {:?}", res);
+            Ok(())
+        } else {
+            Err(Error::msg("Execute wrong job type"))
+        }
+    }
+    fn can_apply(&self, job: &Job) -> bool {
+        matches!(job.job_detail, JobDetail::RandomBlock(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_block_number() {
+        for _ in 0..100 {
+            let number = RandomBlockExecutor::pick_block_number(1000, 128, 12);
+            assert!((872..=988).contains(&number));
+            // Archive depth
+            assert!(RandomBlockExecutor::pick_block_number(1000, 0, 12) <= 988);
+        }
+        // Chain shorter than confirmations
+        assert_eq!(RandomBlockExecutor::pick_block_number(5, 128, 12), 0);
+    }
+}
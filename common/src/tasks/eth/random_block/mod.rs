@@ -1,3 +1,173 @@
 pub mod executor;
 
 pub use executor::*;
+
+use crate::component::ChainInfo;
+use crate::jobs::Job;
+use crate::util::from_str_radix16;
+use crate::{Timestamp, WorkerId};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/*
+ * Compare a random historical block of a provider with the same block from a reference endpoint.
+ * Block number is picked by worker at each execution in [head - depth, head - confirmations] of the reference head,
+ * depth 0 means any block of the chain (archive nodes)
+ */
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct JobRandomBlock {
+    pub chain_info: ChainInfo,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub reference_url: String,
+    #[serde(default)]
+    pub reference_headers: HashMap<String, String>,
+    pub depth: u64,
+    pub confirmations: u64,
+    pub timeout: Timestamp,
+}
+
+/*
+ * Fields of a block compared between provider and reference
+ */
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct BlockSummary {
+    pub number: u64,
+    pub hash: String,
+    pub parent_hash: String,
+    pub state_root: String,
+    pub transaction_count: usize,
+}
+
+impl BlockSummary {
+    /*
+     * Parse result of eth_getBlockByNumber
+     */
+    pub fn from_rpc_block(block: &Value) -> Result<Self, anyhow::Error> {
+        let get_field = |field: &str| {
+            block[field]
+                .as_str()
+                .map(|value| value.to_lowercase())
+                .ok_or_else(|| anyhow!("Missing {} in block {}", field, block))
+        };
+        Ok(BlockSummary {
+            number: from_str_radix16(&get_field("number")?)? as u64,
+            hash: get_field("hash")?,
+            parent_hash: get_field("parentHash")?,
+            state_root: get_field("stateRoot")?,
+            transaction_count: block["transactions"]
+                .as_array()
+                .map(|transactions| transactions.len())
+                .ok_or_else(|| anyhow!("Missing transactions in block {}", block))?,
+        })
+    }
+    pub fn get_mismatched_fields(&self, other: &BlockSummary) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        if self.hash != other.hash {
+            fields.push("hash");
+        }
+        if self.parent_hash != other.parent_hash {
+            fields.push("parentHash");
+        }
+        if self.state_root != other.state_root {
+            fields.push("stateRoot");
+        }
+        if self.transaction_count != other.transaction_count {
+            fields.push("transactions");
+        }
+        fields
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct RandomBlockResponse {
+    pub request_timestamp: Timestamp,
+    pub response_duration: Timestamp,
+    pub block_number: u64,
+    pub provider_block: Option<BlockSummary>,
+    pub reference_block: Option<BlockSummary>,
+    pub error_code: u32,
+    pub message: String,
+}
+
+impl RandomBlockResponse {
+    pub fn new_error(request_timestamp: Timestamp, error_code: u32, message: &str) -> Self {
+        RandomBlockResponse {
+            request_timestamp,
+            error_code,
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+    pub fn is_reference_error(&self) -> bool {
+        self.error_code == CallRandomBlockError::ReferenceError(String::new()).get_code()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct JobRandomBlockResult {
+    pub job: Job,
+    pub worker_id: WorkerId,
+    pub response: RandomBlockResponse,
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum CallRandomBlockError {
+    #[error("reference error")]
+    ReferenceError(String),
+    #[error("provider error")]
+    ProviderError(String),
+    #[error("parse result error")]
+    ParseResultError(String),
+}
+
+impl CallRandomBlockError {
+    pub fn get_message(&self) -> String {
+        match self {
+            CallRandomBlockError::ReferenceError(message)
+            | CallRandomBlockError::ProviderError(message)
+            | CallRandomBlockError::ParseResultError(message) => message.to_string(),
+        }
+    }
+    pub fn get_code(&self) -> u32 {
+        match self {
+            CallRandomBlockError::ReferenceError(_) => 1u32,
+            CallRandomBlockError::ProviderError(_) => 2u32,
+            CallRandomBlockError::ParseResultError(_) => 3u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_block_summary() -> Result<(), anyhow::Error> {
+        let block = json!({
+            "number": "0xe5a51a",
+            "hash": "0x7E915FA2",
+            "parentHash": "0x9dc0f5d6",
+            "stateRoot": "0x367ffd56",
+            "transactions": [{"hash": "0x1f18"}, {"hash": "0x6758"}]
+        });
+        let summary = BlockSummary::from_rpc_block(&block)?;
+        assert_eq!(summary.number, 0xe5a51a);
+        assert_eq!(summary.hash, "0x7e915fa2");
+        assert_eq!(summary.transaction_count, 2);
+        let mut other = summary.clone();
+        assert!(summary.get_mismatched_fields(&other).is_empty());
+        other.state_root = "0x0".to_string();
+        other.transaction_count = 1;
+        assert_eq!(
+            summary.get_mismatched_fields(&other),
+            vec!["stateRoot", "transactions"]
+        );
+        assert!(BlockSummary::from_rpc_block(&json!(null)).is_err());
+        Ok(())
+    }
+}
@@ -1,12 +1,14 @@
 use crate::component::ChainInfo;
 use crate::job_manage::JobRole;
 use crate::jobs::{AssignmentConfig, Job};
-use crate::tasks::{LoadConfigs, TaskConfigTrait};
+use crate::models::ValuePath;
+use crate::tasks::{validate_phases, LoadConfigs, TaskConfigTrait};
 use crate::{ComponentInfo, Timestamp};
+use anyhow::anyhow;
 use handlebars::Handlebars;
 use log::error;
 use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value};
+use serde_json::{json, Map, Value};
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Formatter;
@@ -22,10 +24,51 @@ pub struct JobHttpRequest {
     pub headers: HashMap<String, String>,
     pub body: Option<Value>,
     pub response_type: String,
-    pub response_values: HashMap<String, Vec<Value>>,
+    pub response_values: HashMap<String, ValuePath>,
+    //Calls in json-rpc batch body, empty for single request
+    #[serde(default)]
+    pub batch: Vec<JsonRpcCall>,
+}
+
+impl JobHttpRequest {
+    pub fn is_batch(&self) -> bool {
+        !self.batch.is_empty()
+    }
+}
+
+/*
+ * One call in a json-rpc batch request.
+ * Response of the call is matched by id and its values are reported with prefix "{name}."
+ */
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+pub struct JsonRpcCall {
+    #[serde(default)]
+    pub id: u64,
+    pub name: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    #[serde(default)]
+    pub values: HashMap<String, ValuePath>,
+}
+
+impl JsonRpcCall {
+    pub fn get_value_key(&self, key: &str) -> String {
+        format!("{}.{}", self.name, key)
+    }
+    pub fn get_error_key(&self) -> String {
+        self.get_value_key(BATCH_ERROR_KEY)
+    }
 }
 
-impl JobHttpRequest {}
+// Keys of batch summary in HttpResponseValues
+pub const BATCH_ERROR_KEY: &str = "error";
+pub const BATCH_SIZE_KEY: &str = "batch_size";
+pub const BATCH_RESPONSE_NUMBER_KEY: &str = "batch_response_number";
+pub const BATCH_ERROR_NUMBER_KEY: &str = "batch_error_number";
+pub const BATCH_UNKNOWN_IDS_KEY: &str = "batch_unknown_ids";
+// Methods supported by http request executor
+pub const HTTP_METHODS: [&str; 4] = ["get", "post", "put", "patch"];
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct JobHttpResponse {
     pub request_timestamp: Timestamp, //Time to call request in second
@@ -50,6 +93,40 @@ impl HttpResponseValues {
     pub fn new(inner: HashMap<String, Value>) -> Self {
         HttpResponseValues { inner }
     }
+    fn get_number(&self, key: &str) -> u64 {
+        self.inner
+            .get(key)
+            .and_then(|val| val.as_u64())
+            .unwrap_or_default()
+    }
+    pub fn get_batch_size(&self) -> u64 {
+        self.get_number(BATCH_SIZE_KEY)
+    }
+    pub fn get_batch_response_number(&self) -> u64 {
+        self.get_number(BATCH_RESPONSE_NUMBER_KEY)
+    }
+    pub fn get_batch_error_number(&self) -> u64 {
+        self.get_number(BATCH_ERROR_NUMBER_KEY)
+    }
+    /// Ids in batch response which do not match any call or are duplicated
+    pub fn get_batch_unknown_ids(&self) -> Vec<Value> {
+        self.inner
+            .get(BATCH_UNKNOWN_IDS_KEY)
+            .and_then(|val| val.as_array())
+            .cloned()
+            .unwrap_or_default()
+    }
+    /// Errors of failed calls by call name
+    pub fn get_batch_errors(&self) -> HashMap<String, Value> {
+        let suffix = format!(".{}", BATCH_ERROR_KEY);
+        self.inner
+            .iter()
+            .filter_map(|(key, value)| {
+                key.strip_suffix(suffix.as_str())
+                    .map(|name| (name.to_string(), value.clone()))
+            })
+            .collect()
+    }
 }
 
 impl Deref for HttpResponseValues {
@@ -159,6 +236,9 @@ pub struct HttpRequestJobConfig {
     pub interval: Timestamp,
     #[serde(default)]
     pub thresholds: serde_json::Map<String, serde_json::Value>,
+    //Json-rpc calls sent in one batch request instead of body
+    #[serde(default)]
+    pub batch: Vec<JsonRpcCall>,
 }
 
 impl fmt::Display for HttpRequestJobConfig {
@@ -176,7 +256,7 @@ pub struct HttpResponseConfig {
     #[serde(default)]
     pub response_type: String, //Response type: json or text
     #[serde(default)]
-    pub values: HashMap<String, Vec<Value>>, //Path to values
+    pub values: HashMap<String, ValuePath>, //Path to values
 }
 
 impl LoadConfigs<HttpRequestJobConfig> for HttpRequestJobConfig {}
@@ -208,16 +288,47 @@ impl HttpRequestJobConfig {
     //     }
     //     task_configs
     // }
-
-    //Todo: Implement Deep append
-    pub fn append(target: &mut Map<String, Value>, source: &mut Map<String, Value>) {
-        target.append(source);
-    }
 }
 impl TaskConfigTrait for HttpRequestJobConfig {
     fn match_phase(&self, phase: &JobRole) -> bool {
         self.phases.contains(&String::from("*")) || self.phases.contains(&phase.to_string())
     }
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name must not be empty"));
+        }
+        validate_phases(&self.phases)?;
+        if self.url_template.is_empty() {
+            return Err(anyhow!("url_template must not be empty"));
+        }
+        if !HTTP_METHODS.contains(&self.http_method.to_lowercase().as_str()) {
+            return Err(anyhow!(
+                "http_method {:?} is not one of {:?}",
+                self.http_method,
+                HTTP_METHODS
+            ));
+        }
+        if self.request_timeout <= 0 {
+            return Err(anyhow!("request_timeout must be positive"));
+        }
+        if self.interval <= 0 {
+            return Err(anyhow!("interval must be positive"));
+        }
+        if !self.response.values.is_empty() && self.response.response_type.as_str() != "json" {
+            return Err(anyhow!("response values require response_type json"));
+        }
+        let mut call_names = Vec::new();
+        for call in self.batch.iter() {
+            if call.name.is_empty() || call.method.is_empty() {
+                return Err(anyhow!("batch call must have name and method"));
+            }
+            if call_names.contains(&call.name) {
+                return Err(anyhow!("batch call name {} is duplicated", call.name));
+            }
+            call_names.push(call.name.clone());
+        }
+        Ok(())
+    }
     fn get_blockchain(&self) -> &Vec<String> {
         &self.blockchains
     }
@@ -334,6 +445,62 @@ impl HttpRequestJobConfig {
         //.map(|value| value.to_string())
     }
 
+    /*
+     * Render batch calls with ids from 1 and the json-rpc array body
+     */
+    pub fn generate_batch(
+        &self,
+        handlebars: &Handlebars,
+        context: &Value,
+    ) -> Result<(Vec<JsonRpcCall>, Value), anyhow::Error> {
+        let mut calls = Vec::with_capacity(self.batch.len());
+        let mut body = Vec::with_capacity(self.batch.len());
+        for (ind, call) in self.batch.iter().enumerate() {
+            let mut call = call.clone();
+            call.id = ind as u64 + 1;
+            call.params = Self::render_params(handlebars, &call.params, context)
+                .map_err(|err| anyhow!("Invalid params of batch call {}: {:?}", &call.name, err))?;
+            body.push(json!({
+                "jsonrpc": "2.0",
+                "method": call.method,
+                "params": call.params,
+                "id": call.id
+            }));
+            calls.push(call);
+        }
+        Ok((calls, Value::Array(body)))
+    }
+
+    /*
+     * Unlike other templates, a batch call param which cannot be rendered fails the whole batch,
+     * because the batch response is judged by the position of each call
+     */
+    fn render_params(
+        handlebars: &Handlebars,
+        value: &Value,
+        context: &Value,
+    ) -> Result<Value, anyhow::Error> {
+        match value {
+            Value::String(val) => Ok(Value::String(
+                handlebars.render_template(val.as_str(), context)?,
+            )),
+            Value::Array(arrs) => arrs
+                .iter()
+                .map(|item| Self::render_params(handlebars, item, context))
+                .collect::<Result<Vec<Value>, _>>()
+                .map(Value::Array),
+            Value::Object(map) => {
+                let mut rendered_map: Map<String, Value> = Map::new();
+                for (key, item) in map.iter() {
+                    rendered_map
+                        .insert(key.clone(), Self::render_params(handlebars, item, context)?);
+                }
+                Ok(Value::Object(rendered_map))
+            }
+            _ => Ok(value.clone()),
+        }
+    }
+
     pub fn render_template_value(
         &self,
         handlebars: &Handlebars,
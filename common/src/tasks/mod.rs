@@ -12,16 +12,113 @@ use crate::job_manage::JobRole;
 use crate::{BlockChainType, ComponentInfo, NetworkType};
 use anyhow::{anyhow, Error};
 
-use log::debug;
+use log::{debug, warn};
 use serde::de::DeserializeOwned;
 use serde_json::{Map, Value};
 
-use std::fmt::Debug;
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
 use std::fs::metadata;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-const DEFAULT_KEY: &str = "default";
+pub const DEFAULT_KEY: &str = "default";
 const TASKS_KEY: &str = "tasks";
+/*
+ * Task config which is rejected by validation, name is empty if whole file is invalid
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct RejectedConfig {
+    pub path: PathBuf,
+    pub name: String,
+    pub reason: String,
+}
+
+impl RejectedConfig {
+    pub fn new(path: &Path, name: &str, reason: String) -> Self {
+        RejectedConfig {
+            path: path.to_path_buf(),
+            name: name.to_string(),
+            reason,
+        }
+    }
+}
+
+impl Display for RejectedConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "{}: {}", self.path.display(), self.reason)
+        } else {
+            write!(
+                f,
+                "{} [{}]: {}",
+                self.path.display(),
+                self.name,
+                self.reason
+            )
+        }
+    }
+}
+
+/*
+ * Deep merge source into target: nested objects are merged key by key,
+ * other values (arrays included) in source replace values in target
+ */
+pub fn merge_config(target: &mut Map<String, Value>, source: &Map<String, Value>) {
+    for (key, value) in source.iter() {
+        if let (Some(Value::Object(target_map)), Value::Object(source_map)) =
+            (target.get_mut(key), value)
+        {
+            merge_config(target_map, source_map);
+            continue;
+        }
+        target.insert(key.clone(), value.clone());
+    }
+}
+
+pub fn validate_phases(phases: &Vec<String>) -> Result<(), Error> {
+    if phases.is_empty() {
+        return Err(anyhow!("phases must not be empty"));
+    }
+    for phase in phases.iter() {
+        if phase.as_str() != "*"
+            && phase != &JobRole::Verification.to_string()
+            && phase != &JobRole::Regular.to_string()
+        {
+            return Err(anyhow!("unknown phase {:?}", phase));
+        }
+    }
+    Ok(())
+}
+
+pub fn read_json_file(path: &Path) -> Result<Value, Error> {
+    let content = std::fs::read_to_string(path).map_err(|err| anyhow!("{}", err))?;
+    serde_json::from_str(content.as_str()).map_err(|err| anyhow!("invalid json: {}", err))
+}
+
+// Json files in config dir except default config
+pub fn get_config_files(config_dir: &Path) -> Vec<PathBuf> {
+    let mut files = std::fs::read_dir(config_dir)
+        .map(|paths| {
+            paths
+                .filter_map(|path| path.ok().map(|path| path.path()))
+                .filter(|path| {
+                    path.extension().map(|ext| ext == "json").unwrap_or(false)
+                        && !path.ends_with(format!("{}.json", DEFAULT_KEY))
+                })
+                .collect::<Vec<PathBuf>>()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn get_config_name(config: &Map<String, Value>) -> &str {
+    config
+        .get("name")
+        .and_then(|name| name.as_str())
+        .unwrap_or_default()
+}
+
 /*
  * Load config from a directory of a single file
  */
@@ -33,52 +130,48 @@ pub trait LoadConfigs<T: TaskConfigTrait + DeserializeOwned + Default + Debug> {
         if md.is_dir() {
             Self::read_config_dir(config_path, phase)
         } else {
-            let configs = std::fs::read_to_string(config_path)
-                .ok()
-                .and_then(|json_content| {
-                    let value: Option<Value> = serde_json::from_str(json_content.as_str()).ok();
-                    value.map(|config_value| Self::parse_root_value(config_value, phase))
-                });
-            configs.unwrap_or_default()
+            match read_json_file(config_path) {
+                Ok(config_value) => Self::parse_root_value(config_value, phase),
+                Err(err) => {
+                    warn!("Skip config file {:?}: {}", config_path, err);
+                    Vec::default()
+                }
+            }
         }
     }
 
     fn read_config_dir(config_path: &Path, phase: &JobRole) -> Vec<T> {
         //First read default config if exists
-        let default_config =
-            //std::fs::read_to_string(format!("{}/{}.json", config_path, DEFAULT_KEY))
-            std::fs::read_to_string(config_path.join(format!("{DEFAULT_KEY}.json")))
-                .map_err(|err| anyhow!("{:?}", &err))
-                .and_then(|content| {
-                    let config: Result<Map<String, Value>, Error> =
-                        serde_json::from_str(content.as_str()).map_err(|err| anyhow!("{:?}", &err));
-                    config
-                })
-                .ok();
-        let paths = std::fs::read_dir(config_path).unwrap();
+        let default_config = Self::read_default_config(config_path)
+            .map_err(|err| warn!("Skip default config in {:?}: {}", config_path, err))
+            .ok()
+            .flatten();
         let mut results = Vec::default();
-        for path in paths {
-            let config_path = path.unwrap().path();
+        for config_path in get_config_files(config_path) {
             debug!("Parse config from path: {}", config_path.display());
-            if !config_path.ends_with(format!("{}.json", DEFAULT_KEY)) {
-                let configs = std::fs::read_to_string(config_path)
-                    .ok()
-                    //.map_err(|err| anyhow!("{:?}", &err))
-                    .and_then(|json_content| {
-                        let config: Option<Value> =
-                            serde_json::from_str(json_content.as_str()).ok();
-                        //    .map_err(|err| anyhow!("{:?}", &err));
-                        config
-                    })
-                    .and_then(|json_value| Self::parse_value(json_value, &default_config, phase));
-
-                if let Some(mut configs) = configs {
-                    results.append(&mut configs);
+            match read_json_file(&config_path) {
+                Ok(json_value) => {
+                    if let Some(mut configs) = Self::parse_value(json_value, &default_config, phase)
+                    {
+                        results.append(&mut configs);
+                    }
                 }
+                Err(err) => warn!("Skip config file {:?}: {}", config_path, err),
             }
         }
         results
     }
+    fn read_default_config(config_dir: &Path) -> Result<Option<Map<String, Value>>, Error> {
+        let path = config_dir.join(format!("{DEFAULT_KEY}.json"));
+        if !path.exists() {
+            return Ok(None);
+        }
+        read_json_file(&path)?
+            .as_object()
+            .cloned()
+            .map(Some)
+            .ok_or(anyhow!("default config must be an object"))
+    }
     fn parse_root_value(config_value: Value, phase: &JobRole) -> Vec<T> {
         let def_config: Option<Map<String, Value>> = config_value
             .get(DEFAULT_KEY)
@@ -102,9 +195,11 @@ pub trait LoadConfigs<T: TaskConfigTrait + DeserializeOwned + Default + Debug> {
         phase: &JobRole,
     ) -> Option<Vec<T>> {
         if config_value.is_object() {
-            Self::parse_object_config(config_value.as_object().unwrap(), default_config, phase)
-                .ok()
-                .map(|config| vec![config])
+            Some(Self::parse_array_config(
+                &vec![config_value],
+                default_config,
+                phase,
+            ))
         } else if config_value.is_array() {
             Some(Self::parse_array_config(
                 config_value.as_array().unwrap(),
@@ -115,26 +210,21 @@ pub trait LoadConfigs<T: TaskConfigTrait + DeserializeOwned + Default + Debug> {
             None
         }
     }
-    fn parse_object_config(
+    /*
+     * Merge config with default one, then deserialize and validate final config
+     */
+    fn build_config(
         config: &Map<String, Value>,
         default: &Option<Map<String, Value>>,
-        phase: &JobRole,
     ) -> Result<T, anyhow::Error> {
         let mut map_config = default.as_ref().map(|val| val.clone()).unwrap_or_default();
         //log::debug!("Task config before append {:?}", &task_config);
         Self::append(&mut map_config, config);
         let value = serde_json::Value::Object(map_config);
         log::trace!("Final task config {:?}", &value);
-        match serde_json::from_value::<T>(value) {
-            Ok(config) => {
-                if config.match_phase(phase) {
-                    Ok(config)
-                } else {
-                    Err(anyhow!("Phase not match"))
-                }
-            }
-            Err(err) => Err(anyhow!("{:?}", &err)),
-        }
+        let config = serde_json::from_value::<T>(value).map_err(|err| anyhow!("{}", err))?;
+        config.validate()?;
+        Ok(config)
     }
     fn parse_array_config(
         configs: &Vec<Value>,
@@ -144,21 +234,113 @@ pub trait LoadConfigs<T: TaskConfigTrait + DeserializeOwned + Default + Debug> {
         let mut result = Vec::new();
         for config in configs.iter() {
             if let Some(config_value) = config.as_object() {
-                if let Ok(config) = Self::parse_object_config(config_value, default, phase) {
-                    result.push(config);
+                match Self::build_config(config_value, default) {
+                    Ok(config) => {
+                        if config.match_phase(phase) {
+                            result.push(config);
+                        }
+                    }
+                    Err(err) => warn!(
+                        "Rejected task config {}: {}",
+                        get_config_name(config_value),
+                        err
+                    ),
                 }
+            } else {
+                warn!("Rejected task config {}: not an object", config);
             }
         }
         result
     }
-    //Todo: Implement Deep append
     fn append(target: &mut Map<String, Value>, source: &Map<String, Value>) {
-        target.append(&mut source.clone());
+        merge_config(target, source);
+    }
+    /*
+     * Check every config in path regardless of phase, return all rejected ones
+     */
+    fn validate_configs(config_path: &Path) -> Vec<RejectedConfig> {
+        let mut rejected = Vec::new();
+        let mut config_files = Vec::new();
+        if config_path.is_dir() {
+            let default_config = match Self::read_default_config(config_path) {
+                Ok(default_config) => default_config,
+                Err(err) => {
+                    let default_path = config_path.join(format!("{DEFAULT_KEY}.json"));
+                    rejected.push(RejectedConfig::new(&default_path, "", err.to_string()));
+                    return rejected;
+                }
+            };
+            for path in get_config_files(config_path) {
+                match read_json_file(&path) {
+                    Ok(Value::Array(configs)) => {
+                        config_files.push((path, default_config.clone(), configs))
+                    }
+                    Ok(config @ Value::Object(_)) => {
+                        config_files.push((path, default_config.clone(), vec![config]))
+                    }
+                    Ok(_) => rejected.push(RejectedConfig::new(
+                        &path,
+                        "",
+                        "config file must contain an object or an array".to_string(),
+                    )),
+                    Err(err) => rejected.push(RejectedConfig::new(&path, "", err.to_string())),
+                }
+            }
+        } else {
+            match read_json_file(config_path) {
+                Ok(Value::Array(configs)) => {
+                    config_files.push((config_path.to_path_buf(), None, configs))
+                }
+                Ok(Value::Object(root)) => {
+                    let default_config = root
+                        .get(DEFAULT_KEY)
+                        .and_then(|value| value.as_object())
+                        .cloned();
+                    let configs = root
+                        .get(TASKS_KEY)
+                        .and_then(|value| value.as_array())
+                        .cloned()
+                        .unwrap_or_default();
+                    config_files.push((config_path.to_path_buf(), default_config, configs));
+                }
+                Ok(_) => rejected.push(RejectedConfig::new(
+                    config_path,
+                    "",
+                    "config file must contain an object or an array".to_string(),
+                )),
+                Err(err) => rejected.push(RejectedConfig::new(config_path, "", err.to_string())),
+            }
+        }
+        for (path, default_config, configs) in config_files.iter() {
+            for config in configs.iter() {
+                match config.as_object() {
+                    Some(config) => {
+                        if let Err(err) = Self::build_config(config, default_config) {
+                            rejected.push(RejectedConfig::new(
+                                path,
+                                get_config_name(config),
+                                err.to_string(),
+                            ));
+                        }
+                    }
+                    None => rejected.push(RejectedConfig::new(
+                        path,
+                        "",
+                        format!("task config {} is not an object", config),
+                    )),
+                }
+            }
+        }
+        rejected
     }
 }
 
 pub trait TaskConfigTrait {
     fn match_phase(&self, phase: &JobRole) -> bool;
+    // Typed validation after deserialization, rejected configs are not loaded
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
     fn get_blockchain(&self) -> &Vec<String>;
     fn match_blockchain(&self, blockchain: &BlockChainType) -> bool {
         let blockchain = blockchain.to_string().to_lowercase();
@@ -178,3 +360,69 @@ pub trait TaskConfigTrait {
     fn match_provider_type(&self, provider_type: &String) -> bool;
     fn can_apply(&self, provider: &ComponentInfo, phase: &JobRole) -> bool;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::http_request::HttpRequestJobConfig;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_config() {
+        let mut target = json!({
+            "headers": {"content-type": "application/json", "Host": "default"},
+            "assignment": {"broadcast": false, "worker_number": 1},
+            "phases": ["regular"]
+        })
+        .as_object()
+        .cloned()
+        .unwrap();
+        let source = json!({
+            "headers": {"Host": "node"},
+            "assignment": {"broadcast": true},
+            "phases": ["verification"]
+        });
+        merge_config(&mut target, source.as_object().unwrap());
+        assert_eq!(
+            Value::Object(target),
+            json!({
+                "headers": {"content-type": "application/json", "Host": "node"},
+                "assignment": {"broadcast": true, "worker_number": 1},
+                "phases": ["verification"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_configs() {
+        let dir = std::env::temp_dir().join(format!("http_request_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let default = json!({
+            "active": true, "phases": ["regular"], "http_method": "post", "request_timeout": 3000,
+            "repeat_number": 0, "interval": 30000, "body": "", "response": {"response_type": "json"},
+            "headers": {"content-type": "application/json"}, "assignment": {"worker_number": 1}
+        });
+        let tasks = json!([
+            {"name": "Valid", "url_template": "http://node", "headers": {"Host": "node"}},
+            {"name": "NoUrl"},
+            {"name": "WrongPhase", "url_template": "http://node", "phases": ["Regular"]},
+            {"name": "WrongType", "url_template": "http://node", "interval": "30s"}
+        ]);
+        std::fs::write(dir.join("default.json"), default.to_string()).unwrap();
+        std::fs::write(dir.join("tasks.json"), tasks.to_string()).unwrap();
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+
+        let rejected = HttpRequestJobConfig::validate_configs(&dir);
+        let names = rejected
+            .iter()
+            .map(|config| config.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, vec!["", "NoUrl", "WrongPhase", "WrongType"]);
+        assert!(rejected[0].path.ends_with("broken.json"));
+
+        let configs = HttpRequestJobConfig::read_configs(&dir, &JobRole::Regular);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].headers.len(), 2);
+    }
+}
@@ -1,9 +1,10 @@
 use crate::component::ChainInfo;
 use crate::job_manage::JobRole;
 use crate::jobs::AssignmentConfig;
-use crate::models::{ResponseConfig, ResponseValues};
-use crate::tasks::{LoadConfigs, TaskConfigTrait};
+use crate::models::{ResponseConfig, ResponseValues, ValuePath};
+use crate::tasks::{validate_phases, LoadConfigs, TaskConfigTrait};
 use crate::{BlockChainType, ComponentInfo, NetworkType, Timestamp};
+use anyhow::anyhow;
 use handlebars::Handlebars;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
@@ -19,7 +20,7 @@ pub struct JobWebsocket {
     pub headers: HashMap<String, String>,
     pub body: Option<Value>,
     pub response_type: String,
-    pub response_values: HashMap<String, Vec<Value>>,
+    pub response_values: HashMap<String, ValuePath>,
 }
 
 impl JobWebsocket {}
@@ -159,10 +160,6 @@ impl JobWebsocketConfig {
     //     }
     //     task_configs
     // }
-    //Todo: Implement Deep append
-    pub fn append(target: &mut Map<String, Value>, source: &mut Map<String, Value>) {
-        target.append(source);
-    }
 }
 
 impl TaskConfigTrait for JobWebsocketConfig {
@@ -172,6 +169,22 @@ impl TaskConfigTrait for JobWebsocketConfig {
     fn match_phase(&self, phase: &JobRole) -> bool {
         self.phases.contains(&String::from("*")) || self.phases.contains(&phase.to_string())
     }
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name must not be empty"));
+        }
+        validate_phases(&self.phases)?;
+        if self.url_template.is_empty() {
+            return Err(anyhow!("url_template must not be empty"));
+        }
+        if self.request_timeout <= 0 {
+            return Err(anyhow!("request_timeout must be positive"));
+        }
+        if self.interval <= 0 {
+            return Err(anyhow!("interval must be positive"));
+        }
+        Ok(())
+    }
     fn match_blockchain(&self, blockchain: &BlockChainType) -> bool {
         let blockchain = blockchain.to_string().to_lowercase();
         if !self.blockchains.contains(&String::from("*")) && !self.blockchains.contains(&blockchain)
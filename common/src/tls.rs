@@ -0,0 +1,228 @@
+use anyhow::anyhow;
+use log::{info, warn};
+use reqwest::{Certificate, Client, ClientBuilder, Identity};
+use std::convert::Infallible;
+use std::env;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use warp::{Filter, Reply};
+
+const PEM_CERTIFICATE_END: &str = "-----END CERTIFICATE-----";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientAuth {
+    None,
+    //Verify client cert if it is presented
+    Optional,
+    Required,
+}
+
+impl FromStr for ClientAuth {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "" | "none" => Ok(ClientAuth::None),
+            "optional" => Ok(ClientAuth::Optional),
+            "required" => Ok(ClientAuth::Required),
+            _ => Err(anyhow!("Invalid client auth mode {}", value)),
+        }
+    }
+}
+
+impl Default for ClientAuth {
+    fn default() -> Self {
+        ClientAuth::None
+    }
+}
+
+/*
+ * TLS settings for traffic between scheduler and workers.
+ * - ca_file: CA bundle (pem) for verifying peer certs, server side it verifies client certs
+ * - cert_file, key_file: own cert (pem) and pkcs8 key (pem), used for serving TLS and as client identity
+ * - pinned: trust only certs from ca_file, built-in root certs are not used
+ * - insecure: accept any server cert, must be enabled explicitly
+ */
+#[derive(Clone, Debug, Default)]
+pub struct TlsConfig {
+    pub ca_file: Option<PathBuf>,
+    pub cert_file: Option<PathBuf>,
+    pub key_file: Option<PathBuf>,
+    pub client_auth: ClientAuth,
+    pub pinned: bool,
+    pub insecure: bool,
+}
+
+impl TlsConfig {
+    pub fn from_env() -> Self {
+        let get_path = |name: &str| {
+            env::var(name)
+                .ok()
+                .filter(|val| !val.is_empty())
+                .map(PathBuf::from)
+        };
+        let get_flag = |name: &str| {
+            env::var(name)
+                .map(|val| val.eq_ignore_ascii_case("true") || val == "1")
+                .unwrap_or(false)
+        };
+        let client_auth = env::var("TLS_CLIENT_AUTH")
+            .ok()
+            .map(|val| {
+                ClientAuth::from_str(&val).unwrap_or_else(|err| {
+                    panic!("TLS_CLIENT_AUTH={}, {:?}", val, err);
+                })
+            })
+            .unwrap_or_default();
+        TlsConfig {
+            ca_file: get_path("TLS_CA_FILE"),
+            cert_file: get_path("TLS_CERT_FILE"),
+            key_file: get_path("TLS_KEY_FILE"),
+            client_auth,
+            pinned: get_flag("TLS_PINNED"),
+            insecure: get_flag("TLS_INSECURE"),
+        }
+    }
+    /*
+     * Cert and key for serving TLS, server speaks plain http if they are not configured
+     */
+    pub fn get_server_identity(&self) -> Option<(&Path, &Path)> {
+        match (self.cert_file.as_ref(), self.key_file.as_ref()) {
+            (Some(cert_file), Some(key_file)) => Some((cert_file.as_path(), key_file.as_path())),
+            _ => None,
+        }
+    }
+    /*
+     * CA bundle for verifying client certs on server side
+     */
+    pub fn get_client_ca(&self) -> Option<&Path> {
+        match self.client_auth {
+            ClientAuth::None => None,
+            _ => self.ca_file.as_ref().map(|path| path.as_path()),
+        }
+    }
+    pub fn client_builder(&self) -> Result<ClientBuilder, anyhow::Error> {
+        let mut builder = ClientBuilder::new();
+        if self.insecure {
+            warn!("TLS_INSECURE is enabled, server certs are not verified");
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        if let Some(ca_file) = self.ca_file.as_ref() {
+            for cert in read_certificates(ca_file)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if self.pinned {
+            if self.ca_file.is_none() {
+                return Err(anyhow!("TLS_PINNED requires TLS_CA_FILE"));
+            }
+            builder = builder.tls_built_in_root_certs(false);
+        }
+        if let Some((cert_file, key_file)) = self.get_server_identity() {
+            let cert = std::fs::read(cert_file)
+                .map_err(|err| anyhow!("Cannot read cert file {:?}: {:?}", cert_file, err))?;
+            let key = std::fs::read(key_file)
+                .map_err(|err| anyhow!("Cannot read key file {:?}: {:?}", key_file, err))?;
+            builder = builder.identity(Identity::from_pkcs8_pem(&cert, &key)?);
+        }
+        Ok(builder)
+    }
+    pub fn build_client(&self) -> Result<Client, anyhow::Error> {
+        Ok(self.client_builder()?.build()?)
+    }
+    /*
+     * Serve routes with TLS if cert and key are configured, client certs are verified by client_auth mode
+     */
+    pub async fn serve<F>(&self, routes: F, socket_addr: SocketAddr)
+    where
+        F: Filter<Error = Infallible> + Clone + Send + Sync + 'static,
+        F::Extract: Reply,
+    {
+        match self.get_server_identity() {
+            Some((cert_file, key_file)) => {
+                info!("Serve TLS with cert {:?}", cert_file);
+                let server = warp::serve(routes)
+                    .tls()
+                    .cert_path(cert_file)
+                    .key_path(key_file);
+                match self.get_client_ca() {
+                    Some(ca_file) if self.client_auth == ClientAuth::Required => {
+                        server
+                            .client_auth_required_path(ca_file)
+                            .run(socket_addr)
+                            .await
+                    }
+                    Some(ca_file) => {
+                        server
+                            .client_auth_optional_path(ca_file)
+                            .run(socket_addr)
+                            .await
+                    }
+                    None => server.run(socket_addr).await,
+                }
+            }
+            None => warp::serve(routes).run(socket_addr).await,
+        }
+    }
+}
+
+/*
+ * Shared client built from TLS_CONFIG, cloning it is cheap and keeps the connection pool
+ */
+pub fn get_tls_client() -> Result<Client, anyhow::Error> {
+    crate::TLS_CLIENT
+        .clone()
+        .map_err(|err| anyhow!("Cannot build tls client: {}", err))
+}
+
+/*
+ * Split a pem bundle into certificates
+ */
+fn read_certificates(path: &Path) -> Result<Vec<Certificate>, anyhow::Error> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| anyhow!("Cannot read CA file {:?}: {:?}", path, err))?;
+    let mut certs = Vec::new();
+    for block in content.split_inclusive(PEM_CERTIFICATE_END) {
+        if block.contains(PEM_CERTIFICATE_END) {
+            certs.push(Certificate::from_pem(block.trim().as_bytes())?);
+        }
+    }
+    if certs.is_empty() {
+        return Err(anyhow!("There is no certificate in CA file {:?}", path));
+    }
+    Ok(certs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tls_config() {
+        assert_eq!(
+            ClientAuth::from_str("Required").unwrap(),
+            ClientAuth::Required
+        );
+        assert_eq!(ClientAuth::from_str("").unwrap(), ClientAuth::None);
+        assert!(ClientAuth::from_str("always").is_err());
+
+        let config = TlsConfig::default();
+        assert!(config.get_server_identity().is_none());
+        assert!(config.build_client().is_ok());
+
+        let config = TlsConfig {
+            ca_file: Some(PathBuf::from("ca.pem")),
+            ..Default::default()
+        };
+        assert!(config.get_client_ca().is_none());
+        // Missing CA file
+        assert!(config.build_client().is_err());
+
+        let config = TlsConfig {
+            pinned: true,
+            ..Default::default()
+        };
+        assert!(config.build_client().is_err());
+    }
+}
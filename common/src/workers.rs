@@ -1,21 +1,62 @@
+use crate::auth::{WorkerSecret, HEADER_SIGNATURE, HEADER_TIMESTAMP};
 use crate::component::Zone;
-use crate::jobs::Job;
+use crate::job_manage::JobDetail;
+use crate::jobs::{AssignmentConfig, Job};
 use crate::models::TimeFrames;
-use crate::{ComponentInfo, IPAddress, PlanId, WorkerId, COMMON_CONFIG};
+use crate::shard::ShardMap;
+use crate::tls::get_tls_client;
+use crate::util::get_current_time;
+use crate::{ComponentInfo, IPAddress, JobId, PlanId, Timestamp, WorkerId, COMMON_CONFIG};
 use anyhow::anyhow;
 use rand::Rng;
 use reqwest::Body;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Mutex;
 
 #[derive(Default, Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct WorkerStatus {
     pub jobs_number_in_queue: usize,
     pub reports_number_in_queue: usize,
+    //Results waiting for delivery to scheduler
+    #[serde(default)]
+    pub reports_number_in_outbox: usize,
     pub jobs_stat: HashMap<String, Vec<String>>,
+    //Scheduling lag by job type and job name
+    #[serde(default)]
+    pub scheduling_lags: HashMap<String, SchedulingLag>,
+}
+
+/*
+ * Delay in milliseconds between expected runtime of a job and the time it is really executed
+ */
+#[derive(Default, Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct SchedulingLag {
+    pub last: Timestamp,
+    pub max: Timestamp,
+    pub total: Timestamp,
+    pub count: u64,
+}
+
+impl SchedulingLag {
+    pub fn add(&mut self, lag: Timestamp) {
+        self.last = lag;
+        self.max = self.max.max(lag);
+        self.total += lag;
+        self.count += 1;
+    }
+    pub fn get_average(&self) -> Timestamp {
+        if self.count == 0 {
+            0
+        } else {
+            self.total / self.count as Timestamp
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, Default, Eq, PartialEq, Hash)]
@@ -30,12 +71,176 @@ pub struct WorkerInfo {
     pub zone: Zone,
     pub worker_spec: WorkerSpec,
     pub available_time_frame: Option<TimeFrames>,
+    //Ss58 sr25519 public key for verifying signed job results
+    #[serde(default)]
+    pub public_key: String,
+    #[serde(default)]
+    pub delivery_mode: DeliveryMode,
+}
+
+/*
+ * How scheduler delivers jobs and cancel plans to a worker:
+ * - Push: scheduler sends them to worker endpoints, worker must be reachable from scheduler
+ * - Pull: worker polls them from scheduler endpoint worker/{id}/jobs, for workers behind NAT or firewalls
+ */
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryMode {
+    Push,
+    Pull,
+}
+
+impl Default for DeliveryMode {
+    fn default() -> Self {
+        DeliveryMode::Push
+    }
+}
+
+impl ToString for DeliveryMode {
+    fn to_string(&self) -> String {
+        match self {
+            DeliveryMode::Push => "push".to_string(),
+            DeliveryMode::Pull => "pull".to_string(),
+        }
+    }
 }
+
+impl FromStr for DeliveryMode {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "" | "push" => Ok(DeliveryMode::Push),
+            "pull" => Ok(DeliveryMode::Pull),
+            _ => Err(anyhow!("Invalid delivery mode {}", value)),
+        }
+    }
+}
+// Ram in Mb and bandwidth in megabits/sec of a capacity unit
+const CAPACITY_UNIT_RAM: u64 = 1024;
+const CAPACITY_UNIT_BANDWIDTH: u64 = 10;
+
 #[derive(Clone, Serialize, Deserialize, Debug, Default, Eq, PartialEq, Hash)]
 pub struct WorkerSpec {
-    cpus: u16,      //Number of cpus
-    ram: u32,       //Ram capacity in Mb
-    bandwidth: u32, //Bandwidth in megabits/sec,
+    pub cpus: u16,      //Number of cpus
+    pub ram: u32,       //Ram capacity in Mb
+    pub bandwidth: u32, //Bandwidth in megabits/sec,
+}
+
+impl WorkerSpec {
+    pub fn new(cpus: u16, ram: u32, bandwidth: u32) -> Self {
+        WorkerSpec {
+            cpus,
+            ram,
+            bandwidth,
+        }
+    }
+    /*
+     * Relative capacity for job assignment: number of units (1 cpu, 1024Mb ram, 10 megabits/sec)
+     * limited by the scarcest declared resource, worker without declared spec has capacity 1
+     */
+    pub fn get_capacity(&self) -> u64 {
+        [
+            (self.cpus as u64, 1),
+            (self.ram as u64, CAPACITY_UNIT_RAM),
+            (self.bandwidth as u64, CAPACITY_UNIT_BANDWIDTH),
+        ]
+        .iter()
+        .filter(|(amount, _)| *amount > 0)
+        .map(|(amount, unit)| amount / unit)
+        .min()
+        .unwrap_or(1)
+        .max(1)
+    }
+}
+
+/*
+ * Load of a worker for capacity aware assignment
+ */
+#[derive(Default, Debug, Clone)]
+pub struct WorkerLoad {
+    pub queue_length: usize, //Jobs in worker queue, reported by get_status
+    pub assigned_jobs: HashMap<JobId, PlanId>, //Jobs assigned to the worker and not finished
+    pub benchmark_jobs: HashMap<JobId, Timestamp>, //Unfinished benchmark jobs with their deadline
+}
+
+impl WorkerLoad {
+    /*
+     * Reported queue contains the delivered jobs, so assigned jobs are not added to it
+     */
+    pub fn get_load(&self) -> usize {
+        self.queue_length.max(self.assigned_jobs.len())
+    }
+}
+
+/*
+ * Loads of all workers, shared by worker pool and matched workers,
+ * so jobs assigned by one task are counted in the next assignments
+ */
+#[derive(Default, Debug, Clone)]
+pub struct WorkerLoads {
+    inner: Arc<Mutex<HashMap<WorkerId, WorkerLoad>>>,
+}
+
+impl WorkerLoads {
+    pub async fn update_queue_length(&self, worker_id: &WorkerId, queue_length: usize) {
+        let mut loads = self.inner.lock().await;
+        loads.entry(worker_id.clone()).or_default().queue_length = queue_length;
+    }
+    pub async fn add_job(&self, worker_id: &WorkerId, job: &Job) {
+        let mut loads = self.inner.lock().await;
+        let load = loads.entry(worker_id.clone()).or_default();
+        load.assigned_jobs
+            .insert(job.job_id.clone(), job.plan_id.clone());
+        if let JobDetail::Benchmark(_) = job.job_detail {
+            let deadline = get_current_time().max(job.expected_runtime) + job.timeout;
+            load.benchmark_jobs.insert(job.job_id.clone(), deadline);
+        }
+    }
+    /*
+     * Jobs with result, moved to another worker or failed are not counted any more
+     */
+    pub async fn finish_jobs(&self, worker_id: &WorkerId, job_ids: &[JobId]) {
+        if let Some(load) = self.inner.lock().await.get_mut(worker_id) {
+            load.assigned_jobs
+                .retain(|job_id, _| !job_ids.contains(job_id));
+            load.benchmark_jobs
+                .retain(|job_id, _| !job_ids.contains(job_id));
+        }
+    }
+    pub async fn cancel_plans(&self, plans: &HashSet<PlanId>) {
+        for load in self.inner.lock().await.values_mut() {
+            load.assigned_jobs
+                .retain(|_, plan_id| !plans.contains(plan_id));
+        }
+    }
+    pub async fn remove_workers(&self, worker_ids: &[&WorkerId]) {
+        let mut loads = self.inner.lock().await;
+        for worker_id in worker_ids {
+            loads.remove(*worker_id);
+        }
+    }
+    pub async fn get_load(&self, worker_id: &WorkerId) -> usize {
+        self.inner
+            .lock()
+            .await
+            .get(worker_id)
+            .map(|load| load.get_load())
+            .unwrap_or_default()
+    }
+    /*
+     * Number of benchmark jobs without result, jobs over their deadline are not counted
+     */
+    pub async fn get_benchmark_jobs(&self, worker_id: &WorkerId) -> usize {
+        let now = get_current_time();
+        match self.inner.lock().await.get_mut(worker_id) {
+            Some(load) => {
+                load.benchmark_jobs.retain(|_, deadline| *deadline > now);
+                load.benchmark_jobs.len()
+            }
+            None => 0,
+        }
+    }
 }
 impl WorkerInfo {
     pub fn new(worker_id: &str, worker_endpoint: &str, worker_ip: &str, zone: &str) -> Self {
@@ -52,8 +257,22 @@ impl WorkerInfo {
             zone,
             worker_spec: WorkerSpec::default(),
             available_time_frame: None,
+            public_key: String::default(),
+            delivery_mode: DeliveryMode::default(),
         }
     }
+    pub fn with_public_key(mut self, public_key: String) -> Self {
+        self.public_key = public_key;
+        self
+    }
+    pub fn with_delivery_mode(mut self, delivery_mode: DeliveryMode) -> Self {
+        self.delivery_mode = delivery_mode;
+        self
+    }
+    pub fn with_worker_spec(mut self, worker_spec: WorkerSpec) -> Self {
+        self.worker_spec = worker_spec;
+        self
+    }
 }
 
 impl Into<Body> for WorkerInfo {
@@ -66,26 +285,63 @@ impl Into<Body> for WorkerInfo {
 pub struct WorkerRegisterResult {
     pub worker_id: String,
     pub report_callback: String,
+    //Secret for verifying control requests from scheduler
+    #[serde(default)]
+    pub worker_secret: WorkerSecret,
+    //Scheduler shards, results are sent to the shard of their provider
+    #[serde(default)]
+    pub shard_map: ShardMap,
 }
 
 impl WorkerRegisterResult {
-    pub fn new(worker_id: String, report_callback: String) -> Self {
+    pub fn new(worker_id: String, report_callback: String, worker_secret: WorkerSecret) -> Self {
         WorkerRegisterResult {
             worker_id,
             report_callback,
+            worker_secret,
+            shard_map: ShardMap::default(),
         }
     }
+    pub fn with_shard_map(mut self, shard_map: ShardMap) -> Self {
+        self.shard_map = shard_map;
+        self
+    }
+}
+/*
+ * Jobs and cancel plans polled by a pull mode worker.
+ * Worker acknowledges received jobs to worker/{id}/jobs/ack with their ids
+ */
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct WorkerJobsResult {
+    pub jobs: Vec<Job>,
+    pub cancel_plans: Vec<PlanId>,
+}
+
+impl WorkerJobsResult {
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty() && self.cancel_plans.is_empty()
+    }
 }
+
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct WorkerStateParam {}
 #[derive(Default, Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Hash)]
 pub struct Worker {
     pub worker_info: WorkerInfo,
+    #[serde(skip)]
+    pub secret: WorkerSecret,
 }
 
 impl Worker {
     pub fn new(info: WorkerInfo) -> Worker {
-        Worker { worker_info: info }
+        Worker {
+            worker_info: info,
+            secret: WorkerSecret::default(),
+        }
+    }
+    pub fn with_secret(mut self, secret: WorkerSecret) -> Self {
+        self.secret = secret;
+        self
     }
     pub fn get_id(&self) -> WorkerId {
         self.worker_info.worker_id.clone()
@@ -96,6 +352,9 @@ impl Worker {
     pub fn get_zone(&self) -> Zone {
         self.worker_info.zone.clone()
     }
+    pub fn is_pull_mode(&self) -> bool {
+        self.worker_info.delivery_mode == DeliveryMode::Pull
+    }
     pub fn has_id(&self, id: &WorkerId) -> bool {
         self.worker_info.worker_id.eq(id)
     }
@@ -120,8 +379,7 @@ impl Worker {
     }
 
     pub async fn send_post_request(&self, path: &str, body: &str) -> Result<(), anyhow::Error> {
-        let client_builder = reqwest::ClientBuilder::new();
-        let client = client_builder.danger_accept_invalid_certs(true).build()?;
+        let client = get_tls_client()?;
         let url = self.get_url(path);
         log::debug!(
             "Send request {path} to worker {:?} by url {:?} and body {:?}",
@@ -129,10 +387,14 @@ impl Worker {
             url.as_str(),
             &body
         );
+        let timestamp = get_current_time();
+        let signature = self.secret.sign(timestamp, path, body.as_bytes())?;
         let request_builder = client
             .post(url.as_str())
             .header("content-type", "application/json")
             .header("Host", self.get_host())
+            .header(HEADER_TIMESTAMP, timestamp.to_string())
+            .header(HEADER_SIGNATURE, signature)
             .body(body.to_string())
             .timeout(Duration::from_millis(
                 COMMON_CONFIG.default_http_request_timeout_ms,
@@ -157,6 +419,20 @@ impl Worker {
         }
     }
 }
+/*
+ * Stable hash of a key and a worker id, it is the same on every scheduler start
+ */
+fn rendezvous_hash(key: &str, worker_id: &str) -> u64 {
+    let digest = Sha256::new()
+        .chain_update(key.as_bytes())
+        .chain_update(b".")
+        .chain_update(worker_id.as_bytes())
+        .finalize();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes)
+}
+
 /*
  */
 #[derive(Default, Debug)]
@@ -165,6 +441,8 @@ pub struct MatchedWorkers {
     pub nearby_workers: Vec<Arc<Worker>>, //Workers defined by zone
     pub measured_workers: Vec<Arc<Worker>>, //Workers order by round trip time
     pub remain_workers: Vec<Arc<Worker>>, //All remain workers
+    pub distances: HashMap<WorkerId, i32>, //Round trip time of measured workers
+    pub loads: WorkerLoads,
 }
 
 impl MatchedWorkers {
@@ -183,6 +461,17 @@ impl MatchedWorkers {
             None
         }
     }
+    /*
+     * First worker not in excluded list, nearby workers are preferred, then workers by round trip time
+     */
+    pub fn get_candidate(&self, excluded: &[WorkerId]) -> Option<Arc<Worker>> {
+        self.nearby_workers
+            .iter()
+            .chain(self.measured_workers.iter())
+            .chain(self.remain_workers.iter())
+            .find(|worker| !excluded.contains(&worker.worker_info.worker_id))
+            .cloned()
+    }
     pub fn get_all_workers(&self) -> Vec<Arc<Worker>> {
         let mut all_workers = Vec::new();
         for worker in self.measured_workers.iter() {
@@ -193,4 +482,109 @@ impl MatchedWorkers {
         }
         all_workers
     }
+    /*
+     * Matched workers without the excluded ones
+     */
+    pub fn exclude(&self, excluded: &[WorkerId]) -> MatchedWorkers {
+        let filter = |workers: &Vec<Arc<Worker>>| {
+            workers
+                .iter()
+                .filter(|worker| !excluded.contains(&worker.worker_info.worker_id))
+                .cloned()
+                .collect::<Vec<Arc<Worker>>>()
+        };
+        MatchedWorkers {
+            provider: self.provider.clone(),
+            nearby_workers: filter(&self.nearby_workers),
+            measured_workers: filter(&self.measured_workers),
+            remain_workers: filter(&self.remain_workers),
+            distances: self.distances.clone(),
+            loads: self.loads.clone(),
+        }
+    }
+    /*
+     * Candidates ranked by weighted rendezvous hashing of the key, workers in provider zone first,
+     * then the other workers fill the remaining slots in the same hash order.
+     * Ranking does not depend on other workers, so a key moves only when one of its top workers leaves
+     * or a joining worker ranks higher
+     */
+    pub fn get_rendezvous_workers(
+        &self,
+        candidates: &[Arc<Worker>],
+        key: &str,
+    ) -> Vec<Arc<Worker>> {
+        let max_hash = (1u64 << 53) as f64;
+        let mut ranked_workers = candidates
+            .iter()
+            .cloned()
+            .map(|worker| {
+                // Uniform value in (0, 1), score is -capacity / ln(value)
+                let hash = rendezvous_hash(key, &worker.worker_info.worker_id) >> 11;
+                let value = (hash as f64 + 1.0) / (max_hash + 1.0);
+                let capacity = worker.worker_info.worker_spec.get_capacity() as f64;
+                (-capacity / value.ln(), worker)
+            })
+            .collect::<Vec<(f64, Arc<Worker>)>>();
+        ranked_workers.sort_by(|(score1, _), (score2, _)| {
+            score2.partial_cmp(score1).unwrap_or(Ordering::Equal)
+        });
+        let (nearby_workers, other_workers): (Vec<Arc<Worker>>, Vec<Arc<Worker>>) = ranked_workers
+            .into_iter()
+            .map(|(_, worker)| worker)
+            .partition(|worker| worker.worker_info.zone == self.provider.zone);
+        nearby_workers.into_iter().chain(other_workers).collect()
+    }
+    /*
+     * Worker with the lowest load per capacity unit, the first candidate is taken on ties
+     */
+    pub async fn get_least_loaded_worker(&self, candidates: &[Arc<Worker>]) -> Option<Arc<Worker>> {
+        let mut worker_loads = Vec::new();
+        for worker in candidates.iter() {
+            let load = self.loads.get_load(&worker.worker_info.worker_id).await as u64 + 1;
+            worker_loads.push((worker, load, worker.worker_info.worker_spec.get_capacity()));
+        }
+        worker_loads
+            .into_iter()
+            .min_by(|(_, load1, capacity1), (_, load2, capacity2)| {
+                (load1 * capacity2).cmp(&(load2 * capacity1))
+            })
+            .map(|(worker, _, _)| worker.clone())
+    }
+    /*
+     * Candidates with the shortest round trip time, candidates without measurement are taken
+     * only if none of them is measured
+     */
+    pub fn get_nearest_workers(&self, candidates: &[Arc<Worker>]) -> Vec<Arc<Worker>> {
+        let distance = |worker: &Arc<Worker>| self.distances.get(&worker.worker_info.worker_id);
+        match candidates.iter().filter_map(distance).min() {
+            Some(min_distance) => candidates
+                .iter()
+                .filter(|&worker| distance(worker) == Some(min_distance))
+                .cloned()
+                .collect(),
+            None => candidates.to_vec(),
+        }
+    }
+    /*
+     * Workers which a job with the assignment config can be assigned to, same rules as job assignment:
+     * without config nearby workers are preferred, then measured workers, then remain workers
+     */
+    pub fn get_eligible_workers(&self, config: &Option<AssignmentConfig>) -> Vec<Arc<Worker>> {
+        match config {
+            None => {
+                if !self.nearby_workers.is_empty() {
+                    self.nearby_workers.clone()
+                } else if !self.measured_workers.is_empty() {
+                    self.measured_workers.clone()
+                } else {
+                    self.remain_workers.clone()
+                }
+            }
+            Some(config) if config.is_broadcast() => self.get_all_workers(),
+            Some(config) if config.worker_number.is_none() => vec![],
+            Some(config) if config.nearby_only == Some(true) => self.nearby_workers.clone(),
+            Some(config) if config.by_distance == Some(true) => self.measured_workers.clone(),
+            Some(_) => self.get_all_workers(),
+        }
+    }
 }
@@ -5,13 +5,14 @@ use crate::seaorm::{
 };
 use common::component::{ChainInfo, ComponentType, Zone};
 use common::job_manage::{JobBenchmarkResult, JobResultDetail, JobRole};
-use common::jobs::{Job, JobAssignment, JobResult};
+use common::jobs::{Job, JobAssignment, JobResult, ResourceClass};
 use common::models::plan_entity::PlanStatus;
 use common::models::PlanEntity;
 // use common::tasks::eth::JobLatestBlockResult;
+use common::auth::WorkerSecret;
 use common::tasks::ping::JobPingResult;
 use common::util::get_current_time;
-use common::workers::WorkerInfo;
+use common::workers::{DeliveryMode, Worker, WorkerInfo};
 use core::default::Default;
 
 use sea_orm::ActiveValue::Set;
@@ -28,12 +29,25 @@ impl From<&WorkerInfo> for workers::ActiveModel {
             active: Set(1),
             zone: Set(format!("{:?}", &worker.zone)),
             url: Set(worker.url.to_owned()),
+            public_key: Set(Some(worker.public_key.to_owned()).filter(|key| !key.is_empty())),
+            delivery_mode: Set(Some(worker.delivery_mode.to_string())),
+            specification: Set(serde_json::to_value(&worker.worker_spec).ok()),
             ..Default::default()
         };
         workers
     }
 }
 
+impl From<&Worker> for workers::ActiveModel {
+    fn from(worker: &Worker) -> Self {
+        let mut model = workers::ActiveModel::from(&worker.worker_info);
+        if !worker.secret.is_empty() {
+            model.secret = Set(Some(worker.secret.as_str().to_string()));
+        }
+        model
+    }
+}
+
 impl From<&Job> for jobs::ActiveModel {
     fn from(job: &Job) -> Self {
         //debug!("job.job_detail: {:?}", job.job_detail);
@@ -58,6 +72,10 @@ impl From<&Job> for jobs::ActiveModel {
             repeat_number: Set(job.repeat_number),
             id: NotSet,
             phase: Set(job.phase.to_string()),
+            resource_class: Set(job
+                .resource_class
+                .as_ref()
+                .map(|resource_class| resource_class.to_string())),
         }
     }
 }
@@ -70,7 +88,7 @@ impl From<&JobAssignment> for job_assignments::ActiveModel {
             job_name: Set(assign.job.job_name.to_owned()),
             worker_id: Set(assign.worker.get_id()),
             plan_id: Set(assign.job.plan_id.to_owned()),
-            status: Set(String::from("assigned")),
+            status: Set(assign.status.to_string()),
             assign_time: Set(get_current_time() as i64),
             ..Default::default()
         }
@@ -106,6 +124,10 @@ impl From<&jobs::Model> for Job {
             interval: model.interval.clone(),
             job_detail: serde_json::from_value(model.job_detail.clone()).unwrap_or_default(),
             phase: JobRole::from_str(model.phase.as_str()).unwrap_or_default(),
+            resource_class: model
+                .resource_class
+                .as_ref()
+                .and_then(|resource_class| ResourceClass::from_str(resource_class).ok()),
         }
     }
 }
@@ -328,8 +350,29 @@ impl From<&workers::Model> for WorkerInfo {
             worker_ip: info.worker_ip.clone(),
             url: info.url.clone(),
             zone,
-            worker_spec: Default::default(),
+            worker_spec: info
+                .specification
+                .as_ref()
+                .and_then(|spec| serde_json::from_value(spec.clone()).ok())
+                .unwrap_or_default(),
             available_time_frame: None,
+            public_key: info.public_key.clone().unwrap_or_default(),
+            delivery_mode: info
+                .delivery_mode
+                .as_ref()
+                .and_then(|mode| DeliveryMode::from_str(mode).ok())
+                .unwrap_or_default(),
         }
     }
 }
+
+impl From<&workers::Model> for Worker {
+    fn from(model: &workers::Model) -> Self {
+        let secret = model
+            .secret
+            .as_ref()
+            .map(|secret| WorkerSecret::new(secret))
+            .unwrap_or_default();
+        Worker::new(WorkerInfo::from(model)).with_secret(secret)
+    }
+}
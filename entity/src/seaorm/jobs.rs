@@ -22,6 +22,7 @@ pub struct Model {
     pub plan_id: String,
     pub component_type: String,
     pub phase: String,
+    pub resource_class: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter)]
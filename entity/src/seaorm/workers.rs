@@ -15,6 +15,9 @@ pub struct Model {
     pub worker_id: String,
     pub worker_ip: String,
     pub active: i32,
+    pub secret: Option<String>,
+    pub public_key: Option<String>,
+    pub delivery_mode: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter)]
@@ -7,14 +7,15 @@ pub mod services;
 pub mod state;
 pub mod tasks;
 
+use common::workers::DeliveryMode;
 use lazy_static::lazy_static;
 use std::env;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use uuid::Uuid;
 
 //pub const CONFIG_FILE: &str = "config_check_component.json";
-pub const JOB_EXECUTOR_PERIOD: u64 = 1000; //In milliseconds
-                                           //pub const JOB_RESULT_REPORTER_PERIOD: u64 = 2000; //In milliseconds
+//pub const JOB_RESULT_REPORTER_PERIOD: u64 = 2000; //In milliseconds
 
 lazy_static! {
     pub static ref SCHEDULER_ENDPOINT: String = env::var("SCHEDULER_ENDPOINT")
@@ -34,20 +35,46 @@ lazy_static! {
     pub static ref WORKER_IP: String =
         env::var("WORKER_IP").unwrap_or(String::from("127.0.0.1"));
     pub static ref MAX_THREAD_COUNTER: usize = env::var("MAX_THREAD_COUNTER").ok().and_then(|val|{ val.parse::<usize>().ok()}).unwrap_or(4);
-    //Waiting time period for parallelable threads in milliseconds
-    pub static ref WAITING_TIME_FOR_EXECUTING_THREAD: u64 = env::var("WAITING_TIME_FOR_EXECUTING_THREAD").ok().and_then(|val|{ val.parse::<u64>().ok()}).unwrap_or(100);
+    //Max number of latency sensitive jobs executed at the same time
+    pub static ref MAX_LATENCY_SENSITIVE_JOBS: usize = env::var("MAX_LATENCY_SENSITIVE_JOBS").ok().and_then(|val|{ val.parse::<usize>().ok()}).unwrap_or(256);
+    //Max number of bulk jobs executed at the same time
+    pub static ref MAX_BULK_JOBS: usize = env::var("MAX_BULK_JOBS").ok().and_then(|val|{ val.parse::<usize>().ok()}).unwrap_or(*MAX_THREAD_COUNTER);
         //.unwrap_or(Strin"4").parse::<usize>().unwrap();
     pub static ref JOB_RESULT_REPORTER_PERIOD: u64 = env::var("JOB_RESULT_REPORTER_PERIOD").ok().and_then(|val|{ val.parse::<u64>().ok()}).unwrap_or(2000);
+    //Max number of results kept in memory while waiting for delivery
+    pub static ref REPORT_OUTBOX_CAPACITY: usize = env::var("REPORT_OUTBOX_CAPACITY").ok().and_then(|val|{ val.parse::<usize>().ok()}).unwrap_or(10000);
+    //Results over outbox capacity are spilled to this file, they are dropped if it is not set
+    pub static ref REPORT_OUTBOX_SPILL_FILE: Option<PathBuf> = env::var("REPORT_OUTBOX_SPILL_FILE").ok().filter(|val| !val.is_empty()).map(PathBuf::from);
+    pub static ref REPORT_BATCH_SIZE: usize = env::var("REPORT_BATCH_SIZE").ok().and_then(|val|{ val.parse::<usize>().ok()}).unwrap_or(1000);
+    //Max delay between retries of a failed report in milliseconds
+    pub static ref REPORT_RETRY_MAX_DELAY: u64 = env::var("REPORT_RETRY_MAX_DELAY").ok().and_then(|val|{ val.parse::<u64>().ok()}).unwrap_or(60000);
     pub static ref LOCAL_IP: String = local_ip_address::local_ip().unwrap().to_string();
     pub static ref HASH_TEST_20K: String = "95c5679435a0a714918dc92b546dc0ba".to_string();
     //pub(crate) static ref CONFIG: Config = get_config();
     pub static ref DOMAIN: String = env::var("DOMAIN").expect("There is no env var DOMAIN");
-    pub static ref BENCHMARK_WRK_PATH: String =
-        env::var("BENCHMARK_WRK_PATH").expect("There is no env var BENCHMARK_WRK_PATH");
     pub static ref SCHEDULER_AUTHORIZATION: String =
         env::var("SCHEDULER_AUTHORIZATION").expect("There is no env var SCHEDULER_AUTHORIZATION");
+    //Phrase of sr25519 key for signing job results, a new key is generated for each start if it is not set
+    pub static ref WORKER_SIGNER_PHRASE: Option<String> = env::var("WORKER_SIGNER_PHRASE").ok().filter(|val| !val.is_empty());
+    //File keeping the secret issued at registration, a restarted worker signs its registration with it.
+    //Scheduler rejects registration of a live worker without the current secret
+    pub static ref WORKER_SECRET_FILE: Option<PathBuf> = env::var("WORKER_SECRET_FILE").ok().filter(|val| !val.is_empty()).map(PathBuf::from);
+    //Max difference in milliseconds between timestamp of a signed scheduler request and local time
+    pub static ref REQUEST_MAX_CLOCK_SKEW: i64 = env::var("REQUEST_MAX_CLOCK_SKEW").ok().and_then(|val|{ val.parse::<i64>().ok()}).unwrap_or(30000);
+    //Push: scheduler sends jobs to WORKER_ENDPOINT, pull: worker polls jobs from scheduler (for workers behind NAT)
+    pub static ref WORKER_DELIVERY_MODE: DeliveryMode = DeliveryMode::from_str(&env::var("WORKER_DELIVERY_MODE").unwrap_or_default())
+        .expect("Invalid env var WORKER_DELIVERY_MODE, e.g. push or pull");
+    //Time in seconds to wait for new jobs in each poll, scheduler caps it by its PULL_JOBS_MAX_WAIT
+    pub static ref PULL_JOBS_WAIT: u64 = env::var("PULL_JOBS_WAIT").ok().and_then(|val|{ val.parse::<u64>().ok()}).unwrap_or(20);
+    //Declared capacity, scheduler assigns more jobs to workers with more cpus
+    pub static ref WORKER_CPUS: u16 = env::var("WORKER_CPUS").ok().and_then(|val|{ val.parse::<u16>().ok()})
+        .unwrap_or_else(|| std::thread::available_parallelism().map(|cpus| cpus.get() as u16).unwrap_or(1));
+    pub static ref WORKER_RAM: u32 = env::var("WORKER_RAM").ok().and_then(|val|{ val.parse::<u32>().ok()}).unwrap_or_default();
+    pub static ref WORKER_BANDWIDTH: u32 = env::var("WORKER_BANDWIDTH").ok().and_then(|val|{ val.parse::<u32>().ok()}).unwrap_or_default();
     pub static ref BUILD_VERSION: String = format!("{}", env!("BUILD_VERSION"));
     pub static ref CONFIG_DIR: String =
         env::var("CONFIG_DIR").unwrap_or_else(|_| String::from("configs/"));
     pub static ref LOG_CONFIG: PathBuf = Path::new(&*CONFIG_DIR).join("log.yaml");
+    //Journal file for pending jobs, jobs are kept in memory only if it is not set
+    pub static ref JOB_BUFFER_STORE: Option<PathBuf> = env::var("JOB_BUFFER_STORE").ok().filter(|val| !val.is_empty()).map(PathBuf::from);
 }
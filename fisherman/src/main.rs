@@ -1,22 +1,32 @@
 use anyhow::Error;
+use common::auth::{ResultSigner, WorkerSecret, HEADER_SIGNATURE, HEADER_TIMESTAMP};
 use common::jobs::JobResult;
 use common::logger::init_logger;
-use common::workers::{WorkerInfo, WorkerRegisterResult};
+use common::util::get_current_time;
+use common::workers::{DeliveryMode, WorkerInfo, WorkerRegisterResult, WorkerSpec};
 
+use common::tls::get_tls_client;
 use common::COMMON_CONFIG;
 use fisherman::models::job::JobBuffer;
+use fisherman::models::outbox::ShardOutboxes;
 use fisherman::server_builder::WebServerBuilder;
 use fisherman::server_config::AccessControl;
 use fisherman::services::service_status::WorkerStatusCheck;
-use fisherman::services::{JobExecution, JobResultReporter, WebServiceBuilder};
+use fisherman::services::{
+    JobExecution, JobPuller, JobResultReporter, RequestAuthenticator, WebServiceBuilder,
+};
 use fisherman::state::WorkerState;
 use fisherman::{
-    LOG_CONFIG, SCHEDULER_AUTHORIZATION, SCHEDULER_ENDPOINT, WORKER_ENDPOINT, WORKER_ID, WORKER_IP,
-    WORKER_SERVICE_ENDPOINT, ZONE,
+    JOB_BUFFER_STORE, LOG_CONFIG, REPORT_OUTBOX_CAPACITY, REPORT_OUTBOX_SPILL_FILE,
+    REQUEST_MAX_CLOCK_SKEW, SCHEDULER_AUTHORIZATION, SCHEDULER_ENDPOINT, WORKER_BANDWIDTH,
+    WORKER_CPUS, WORKER_DELIVERY_MODE, WORKER_ENDPOINT, WORKER_ID, WORKER_IP, WORKER_RAM,
+    WORKER_SECRET_FILE, WORKER_SERVICE_ENDPOINT, WORKER_SIGNER_PHRASE, ZONE,
 };
 use futures_util::future::join3;
 use log::{debug, error, info, warn};
 use reqwest::StatusCode;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
@@ -36,36 +46,78 @@ async fn main() {
     // Show env list
     info!("Envs list");
     for (key, value) in std::env::vars() {
-        info!("{key}: {value}");
+        if key == "WORKER_SIGNER_PHRASE" {
+            info!("{key}: ***");
+        } else {
+            info!("{key}: {value}");
+        }
     }
 
+    let signer = Arc::new(create_signer());
+    info!("Sign job results with key {}", signer.get_public_key());
     // Create job queue
     //Call to scheduler to register worker
     if let Ok(WorkerRegisterResult {
         report_callback,
         worker_id,
-    }) = try_register().await
+        worker_secret,
+        shard_map,
+    }) = try_register(&signer.get_public_key(), &load_worker_secret()).await
     {
         info!(
             "Successfully register worker {}, report_callback: {}",
             &worker_id, report_callback
         );
+        if worker_secret.is_empty() {
+            error!("Scheduler does not issue worker secret, all control requests will be rejected");
+        }
+        store_worker_secret(&worker_secret);
         let (sender, receiver): (Sender<JobResult>, Receiver<JobResult>) = channel(1024);
-        let job_buffer = Arc::new(Mutex::new(JobBuffer::new()));
-        let mut reporter = JobResultReporter::new(receiver, report_callback);
+        let job_buffer = Arc::new(Mutex::new(create_job_buffer()));
+        let outbox = Arc::new(create_outbox(shard_map.shards.len()));
+        let mut reporter =
+            JobResultReporter::new(receiver, report_callback, outbox.clone(), signer)
+                .with_shard_map(shard_map.clone());
 
         let mut execution = JobExecution::new(sender.clone(), job_buffer.clone());
         let service = WebServiceBuilder::new().build();
         let access_control = AccessControl::default();
         // Create status worker check
-        let worker_status_check = WorkerStatusCheck::new(sender, job_buffer.clone());
+        let worker_status_check = WorkerStatusCheck::new(
+            sender,
+            job_buffer.clone(),
+            outbox,
+            execution.get_scheduling_lags(),
+        );
         let worker_status = worker_status_check.get_status();
+        let worker_state = WorkerState::new(job_buffer.clone());
+        if *WORKER_DELIVERY_MODE == DeliveryMode::Pull {
+            //Each scheduler shard delivers jobs of its providers
+            let scheduler_endpoints = if shard_map.is_empty() {
+                vec![SCHEDULER_ENDPOINT.to_string()]
+            } else {
+                shard_map.shards.clone()
+            };
+            for scheduler_endpoint in scheduler_endpoints {
+                let mut puller = JobPuller::new(
+                    scheduler_endpoint,
+                    worker_id.clone(),
+                    worker_secret.clone(),
+                    worker_state.clone(),
+                );
+                let _task_puller = task::spawn(async move { puller.run().await });
+            }
+        }
 
         // Create job process thread
         let server = WebServerBuilder::default()
             .with_entry_point(WORKER_SERVICE_ENDPOINT.as_str())
             .with_access_control(access_control)
-            .with_worker_state(WorkerState::new(job_buffer.clone()))
+            .with_worker_state(worker_state)
+            .with_authenticator(RequestAuthenticator::new(
+                worker_secret,
+                *REQUEST_MAX_CLOCK_SKEW,
+            ))
             .build(service, worker_status);
 
         let _task_execution = tokio::spawn(async move { execution.run().await });
@@ -78,29 +130,126 @@ async fn main() {
     }
 }
 
-async fn try_register() -> Result<WorkerRegisterResult, Error> {
-    let client_builder = reqwest::ClientBuilder::new();
-    let client = client_builder.danger_accept_invalid_certs(true).build()?;
+fn create_signer() -> ResultSigner {
+    match WORKER_SIGNER_PHRASE.as_ref() {
+        Some(phrase) => match ResultSigner::from_phrase(phrase) {
+            Ok(signer) => signer,
+            Err(err) => {
+                error!("Cannot create signer from WORKER_SIGNER_PHRASE: {:?}", err);
+                panic!("Cannot create signer from WORKER_SIGNER_PHRASE");
+            }
+        },
+        None => {
+            warn!("There is no WORKER_SIGNER_PHRASE, generate a new key for signing results");
+            ResultSigner::generate()
+        }
+    }
+}
+
+fn create_job_buffer() -> JobBuffer {
+    match JOB_BUFFER_STORE.as_ref() {
+        Some(path) => match JobBuffer::new_with_store(path) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                error!("Cannot open job store {:?}: {:?}", path, err);
+                panic!("Cannot open job store {:?}", path);
+            }
+        },
+        None => JobBuffer::new(),
+    }
+}
+
+fn create_outbox(shard_number: usize) -> ShardOutboxes {
+    match ShardOutboxes::new(
+        shard_number,
+        *REPORT_OUTBOX_CAPACITY,
+        REPORT_OUTBOX_SPILL_FILE.clone(),
+    ) {
+        Ok(outbox) => outbox,
+        Err(err) => {
+            error!(
+                "Cannot open result outbox {:?}: {:?}",
+                &*REPORT_OUTBOX_SPILL_FILE, err
+            );
+            panic!("Cannot open result outbox");
+        }
+    }
+}
+
+fn load_worker_secret() -> WorkerSecret {
+    match WORKER_SECRET_FILE.as_ref() {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(secret) => WorkerSecret::new(secret.trim()),
+            Err(err) => {
+                info!("No worker secret in {:?}: {:?}", path, err);
+                WorkerSecret::default()
+            }
+        },
+        None => WorkerSecret::default(),
+    }
+}
+
+fn store_worker_secret(secret: &WorkerSecret) {
+    match WORKER_SECRET_FILE.as_ref() {
+        Some(path) => {
+            //Only the worker user can read the secret
+            let res = std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(path)
+                .and_then(|mut file| file.write_all(secret.as_str().as_bytes()));
+            if let Err(err) = res {
+                error!("Cannot store worker secret to {:?}: {:?}", path, err);
+            }
+        }
+        None => warn!("There is no WORKER_SECRET_FILE, worker cannot register again after restart while scheduler keeps it alive"),
+    }
+}
+
+/*
+ * Register worker, the request is signed with the secret from previous registration if there is one
+ */
+async fn try_register(
+    public_key: &str,
+    secret: &WorkerSecret,
+) -> Result<WorkerRegisterResult, Error> {
+    let client = get_tls_client()?;
     let worker_info = WorkerInfo::new(
         WORKER_ID.as_str(),
         WORKER_ENDPOINT.as_str(),
         WORKER_IP.as_str(),
         ZONE.as_str(),
-    );
+    )
+    .with_public_key(public_key.to_string())
+    .with_delivery_mode(WORKER_DELIVERY_MODE.clone())
+    .with_worker_spec(WorkerSpec::new(
+        *WORKER_CPUS,
+        *WORKER_RAM,
+        *WORKER_BANDWIDTH,
+    ));
     let body = serde_json::to_string(&worker_info)?;
     loop {
         let scheduler_url = format!("{}/worker/register", SCHEDULER_ENDPOINT.as_str());
         let clone_client = client.clone();
         let clone_body = body.clone();
         debug!("Register worker to scheduler {}", scheduler_url);
-        let request_builder = clone_client
+        let mut request_builder = clone_client
             .post(scheduler_url)
             .header("content-type", "application/json")
             .header("authorization", &*SCHEDULER_AUTHORIZATION)
-            .body(clone_body)
             .timeout(Duration::from_millis(
                 COMMON_CONFIG.default_http_request_timeout_ms,
             ));
+        if !secret.is_empty() {
+            let timestamp = get_current_time();
+            let signature = secret.sign(timestamp, "worker/register", clone_body.as_bytes())?;
+            request_builder = request_builder
+                .header(HEADER_TIMESTAMP, timestamp.to_string())
+                .header(HEADER_SIGNATURE, signature);
+        }
+        let request_builder = request_builder.body(clone_body);
         debug!("Register worker request builder: {:?}", request_builder);
         let response = request_builder.send().await;
         if response.is_err() {
@@ -166,7 +315,11 @@ mod tests {
         env::set_var("WORKER_IP", "WORKER_IP");
         env::set_var("ZONE", "AS");
         env::set_var("SCHEDULER_AUTHORIZATION", "DEFAULT_SCHEDULER_AUTHORIZATION");
-        let res = try_register().await;
+        let res = try_register(
+            &ResultSigner::generate().get_public_key(),
+            &WorkerSecret::default(),
+        )
+        .await;
         println!("res: {:?}", res);
 
         if let Ok(res) = res {
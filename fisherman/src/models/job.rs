@@ -1,34 +1,60 @@
+use crate::models::job_store::{JobStore, JobStoreRecord};
+use anyhow::Error;
 use common::jobs::Job;
-use common::util::get_current_time;
+use common::util::{get_current_time, warning_if_error};
 
-use common::{JobId, PlanId};
-use log::{debug, trace};
-use serde::{Deserialize, Serialize};
+use common::{JobId, PlanId, Timestamp};
+use log::{debug, info, trace};
 use std::collections::{HashMap, VecDeque};
-use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Notify;
 
-#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+/*
+ * Jobs waiting for execution. Every change goes through methods below so the journal stays in sync
+ */
+#[derive(Debug, Default)]
 pub struct JobBuffer {
     jobs: VecDeque<Job>,
-}
-
-impl Deref for JobBuffer {
-    type Target = VecDeque<Job>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.jobs
-    }
-}
-impl DerefMut for JobBuffer {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.jobs
-    }
+    //Optional on-disk journal, jobs are only kept in memory if it is None
+    store: Option<JobStore>,
+    //Wake up job execution when new jobs are pushed
+    notifier: Arc<Notify>,
 }
 
 impl JobBuffer {
     pub fn new() -> Self {
         JobBuffer {
             jobs: VecDeque::new(),
+            store: None,
+            notifier: Arc::new(Notify::new()),
+        }
+    }
+    /// Create buffer backed by a journal file and replay pending jobs stored in it
+    pub fn new_with_store(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let (mut store, records) = JobStore::open(path)?;
+        let mut buffer = JobBuffer::new();
+        for record in records {
+            match record {
+                JobStoreRecord::Add(job) => buffer.add_job(job),
+                JobStoreRecord::Remove(job_id) => buffer.jobs.retain(|job| job.job_id != job_id),
+            }
+        }
+        store.compact(buffer.jobs.iter())?;
+        info!(
+            "Replay {} pending jobs from job store {:?}",
+            buffer.jobs.len(),
+            store.get_path()
+        );
+        buffer.store = Some(store);
+        Ok(buffer)
+    }
+    fn persist(&mut self, records: Vec<JobStoreRecord>) {
+        if let Some(store) = self.store.as_mut() {
+            warning_if_error("Append records to job store", store.append(&records));
+            if store.need_compact(self.jobs.len()) {
+                warning_if_error("Compact job store", store.compact(self.jobs.iter()));
+            }
         }
     }
     /// Add job by expected_runtime and priority order
@@ -59,22 +85,44 @@ impl JobBuffer {
     }
 
     pub fn add_jobs(&mut self, jobs: Vec<Job>) -> usize {
+        let mut records = Vec::new();
         for job in jobs {
+            if self.store.is_some() {
+                records.push(JobStoreRecord::Add(job.clone()));
+            }
             self.add_job(job);
         }
+        self.persist(records);
+        self.notifier.notify_one();
         self.jobs.len()
     }
 
     pub fn cancel_jobs(&mut self, jobs: &Vec<JobId>) -> usize {
         trace!("Remove jobs: {jobs:?} in {:?}", self.jobs);
-        self.jobs.retain(|job| !jobs.contains(&job.job_id));
+        let mut records = Vec::new();
+        self.jobs.retain(|job| {
+            let keep = !jobs.contains(&job.job_id);
+            if !keep {
+                records.push(JobStoreRecord::Remove(job.job_id.clone()));
+            }
+            keep
+        });
+        self.persist(records);
         self.jobs.len()
     }
 
     pub fn cancel_plans(&mut self, plans: &Vec<PlanId>) -> usize {
         let start_len = self.jobs.len();
         trace!("Remove plans: {plans:?} in {:?}", self.jobs);
-        self.jobs.retain(|job| !plans.contains(&job.plan_id));
+        let mut records = Vec::new();
+        self.jobs.retain(|job| {
+            let keep = !plans.contains(&job.plan_id);
+            if !keep {
+                records.push(JobStoreRecord::Remove(job.job_id.clone()));
+            }
+            keep
+        });
+        self.persist(records);
         start_len - self.jobs.len()
     }
 
@@ -98,15 +146,21 @@ impl JobBuffer {
                     current_time
                 );
                 let job = self.jobs.pop_front();
+                let mut records = Vec::new();
                 if let Some(inner) = job.as_ref() {
+                    records.push(JobStoreRecord::Remove(inner.job_id.clone()));
                     let mut next_job = inner.clone();
                     if inner.repeat_number > 0 {
                         next_job.expected_runtime = current_time + inner.interval;
                         next_job.repeat_number -= 1;
                         trace!("Schedule new repeat job: {:?}", &next_job);
+                        if self.store.is_some() {
+                            records.push(JobStoreRecord::Add(next_job.clone()));
+                        }
                         self.add_job(next_job);
                     }
                 }
+                self.persist(records);
                 job
             } else {
                 debug!("Next job executed in: {} ms", expected_time - current_time);
@@ -117,6 +171,19 @@ impl JobBuffer {
             None
         }
     }
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+    /// Expected runtime of the first job in queue
+    pub fn get_next_runtime(&self) -> Option<Timestamp> {
+        self.jobs.front().map(|job| job.expected_runtime)
+    }
+    pub fn get_notifier(&self) -> Arc<Notify> {
+        self.notifier.clone()
+    }
     pub fn statistic_jobs(&self) -> HashMap<String, Vec<String>> {
         let mut result = HashMap::default();
         self.jobs.iter().for_each(|job| {
@@ -137,6 +204,8 @@ mod test {
     use common::tasks::http_request::JobHttpRequest;
     use common::util::get_current_time;
     use common::Timestamp;
+    use std::fs;
+    use uuid::Uuid;
 
     fn new_test_job(expected_runtime: Timestamp, priority: i32, plan_id: String) -> Job {
         let mut job = Job::new(
@@ -225,4 +294,51 @@ mod test {
         assert_eq!(buffer.jobs.pop_front().unwrap().plan_id, "job_4");
         assert_eq!(buffer.jobs.pop_front().unwrap().plan_id, "job_5");
     }
+
+    #[test]
+    fn test_replay_job_store() {
+        let now = get_current_time();
+        let path = std::env::temp_dir().join(format!("job_store_{}.log", Uuid::new_v4()));
+        {
+            let mut buffer = JobBuffer::new_with_store(&path).unwrap();
+            let mut job_1 = new_test_job(0, 1, "job_1".to_string());
+            job_1.repeat_number = 2;
+            job_1.interval = 60000;
+            let job_2 = new_test_job(now + 1000, 1, "job_2".to_string());
+            let job_3 = new_test_job(now + 2000, 1, "job_3".to_string());
+            let job_4 = new_test_job(now + 3000, 1, "job_4".to_string());
+            let job_3_id = job_3.job_id.clone();
+            buffer.add_jobs(vec![job_4, job_3, job_2, job_1]);
+            // job_1 is rescheduled after job_4
+            let popped = buffer.pop_job().unwrap();
+            assert_eq!(popped.plan_id, "job_1");
+            buffer.cancel_jobs(&vec![job_3_id]);
+            buffer.cancel_plans(&vec!["job_4".to_string()]);
+        }
+        let mut buffer = JobBuffer::new_with_store(&path).unwrap();
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.jobs.pop_front().unwrap().plan_id, "job_2");
+        let repeat_job = buffer.jobs.pop_front().unwrap();
+        assert_eq!(repeat_job.plan_id, "job_1");
+        assert_eq!(repeat_job.repeat_number, 1);
+        assert!(repeat_job.expected_runtime >= now + 60000);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_notify_new_jobs() {
+        let now = get_current_time();
+        let mut buffer = JobBuffer::new();
+        assert_eq!(buffer.get_next_runtime(), None);
+        let notifier = buffer.get_notifier();
+        buffer.add_jobs(vec![
+            new_test_job(now + 2000, 1, "job_2".to_string()),
+            new_test_job(now + 1000, 1, "job_1".to_string()),
+        ]);
+        assert_eq!(buffer.get_next_runtime(), Some(now + 1000));
+        // Permit is stored so waiting after jobs are pushed returns immediately
+        tokio::time::timeout(std::time::Duration::from_millis(100), notifier.notified())
+            .await
+            .expect("Job execution is not notified");
+    }
 }
@@ -0,0 +1,108 @@
+use anyhow::Error;
+use common::jobs::Job;
+use common::JobId;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+//Rewrite the journal when it holds this many records more than the jobs in buffer
+const COMPACT_THRESHOLD: usize = 1000;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum JobStoreRecord {
+    Add(Job),
+    Remove(JobId),
+}
+
+/*
+ * Append-only journal of JobBuffer changes, one json record per line.
+ * Replaying the records in order rebuilds the pending jobs after a restart.
+ */
+#[derive(Debug)]
+pub struct JobStore {
+    path: PathBuf,
+    record_number: usize,
+}
+
+impl JobStore {
+    /// Open journal file and return all valid records in it
+    pub fn open(path: impl AsRef<Path>) -> Result<(Self, Vec<JobStoreRecord>), Error> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut records = Vec::new();
+        if path.exists() {
+            let reader = BufReader::new(File::open(&path)?);
+            for (ind, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                // The last line may be truncated if the worker is killed while writing
+                match serde_json::from_str::<JobStoreRecord>(&line) {
+                    Ok(record) => records.push(record),
+                    Err(err) => warn!(
+                        "Skip invalid record at line {} of job store {:?}: {:?}",
+                        ind + 1,
+                        &path,
+                        err
+                    ),
+                }
+            }
+        }
+        debug!("Load {} records from job store {:?}", records.len(), &path);
+        let store = JobStore {
+            path,
+            record_number: records.len(),
+        };
+        Ok((store, records))
+    }
+
+    pub fn append(&mut self, records: &[JobStoreRecord]) -> Result<(), Error> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut writer = BufWriter::new(file);
+        for record in records {
+            serde_json::to_writer(&mut writer, record)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        self.record_number += records.len();
+        Ok(())
+    }
+
+    /// Replace the journal with one Add record per pending job
+    pub fn compact<'a>(&mut self, jobs: impl Iterator<Item = &'a Job>) -> Result<(), Error> {
+        let tmp_path = self.path.with_extension("tmp");
+        let mut record_number = 0;
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            for job in jobs {
+                serde_json::to_writer(&mut writer, &JobStoreRecord::Add(job.clone()))?;
+                writer.write_all(b"\n")?;
+                record_number += 1;
+            }
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+        }
+        fs::rename(&tmp_path, &self.path)?;
+        self.record_number = record_number;
+        Ok(())
+    }
+
+    pub fn need_compact(&self, job_number: usize) -> bool {
+        self.record_number > job_number + COMPACT_THRESHOLD
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+}
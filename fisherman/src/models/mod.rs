@@ -1 +1,3 @@
 pub mod job;
+pub mod job_store;
+pub mod outbox;
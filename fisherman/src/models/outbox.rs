@@ -0,0 +1,496 @@
+use anyhow::Error;
+use common::jobs::JobResult;
+use common::{JobId, Timestamp};
+use log::{debug, info, warn};
+use std::collections::{HashSet, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+type ResultKey = (JobId, Timestamp);
+
+//Number of results in a spill segment before a new segment is started
+const SPILL_SEGMENT_SIZE: usize = 1000;
+
+/*
+ * Bounded buffer of job results waiting for delivery to scheduler.
+ * When memory buffer is full, new results are spilled to disk (if spill file is configured)
+ * otherwise the oldest results are dropped.
+ * Results are identified by job_id and receive_timestamp, duplicated results are ignored.
+ */
+#[derive(Debug)]
+pub struct ResultOutbox {
+    results: VecDeque<JobResult>,
+    keys: HashSet<ResultKey>,
+    capacity: usize,
+    spill: Option<SpillSegments>,
+    spilled_number: usize,
+}
+
+impl ResultOutbox {
+    pub fn new(capacity: usize, spill_file: Option<PathBuf>) -> Result<Self, Error> {
+        let mut outbox = ResultOutbox {
+            results: VecDeque::new(),
+            keys: HashSet::new(),
+            capacity: capacity.max(1),
+            spill: None,
+            spilled_number: 0,
+        };
+        // Load results spilled before restart
+        if let Some(path) = spill_file {
+            let (spill, results) = SpillSegments::open(path)?;
+            for result in results.iter() {
+                outbox.keys.insert(Self::get_key(result));
+            }
+            outbox.spilled_number = results.len();
+            outbox.spill = Some(spill);
+            outbox.refill()?;
+        }
+        Ok(outbox)
+    }
+    fn get_key(result: &JobResult) -> ResultKey {
+        (result.job_id.clone(), result.receive_timestamp)
+    }
+    /// Return false if result is already in outbox
+    pub fn push(&mut self, result: JobResult) -> bool {
+        let key = Self::get_key(&result);
+        if self.keys.contains(&key) {
+            debug!("Ignore duplicated result {:?}", &key);
+            return false;
+        }
+        // Keep fifo order: once some results are spilled, all new results go to spill file
+        if self.results.len() >= self.capacity || self.spilled_number > 0 {
+            if let Some(spill) = self.spill.as_mut() {
+                match spill.append(&result) {
+                    Ok(_) => {
+                        self.spilled_number += 1;
+                        self.keys.insert(key);
+                        return true;
+                    }
+                    Err(err) => warn!("Cannot spill result to {:?}: {:?}", &spill.path, err),
+                }
+            }
+            if self.results.len() >= self.capacity {
+                if let Some(dropped) = self.results.pop_front() {
+                    warn!(
+                        "Outbox is full, drop result of job {} received at {}",
+                        &dropped.job_id, dropped.receive_timestamp
+                    );
+                    self.keys.remove(&Self::get_key(&dropped));
+                }
+            }
+        }
+        self.keys.insert(key);
+        self.results.push_back(result);
+        true
+    }
+    /// Get oldest results without removing them from outbox
+    pub fn front_batch(&self, max_size: usize) -> Vec<JobResult> {
+        self.results.iter().take(max_size).cloned().collect()
+    }
+    /// Remove first delivered results and move spilled results into memory
+    pub fn commit(&mut self, size: usize) -> Result<(), Error> {
+        for _ in 0..size {
+            match self.results.pop_front() {
+                Some(result) => {
+                    self.keys.remove(&Self::get_key(&result));
+                }
+                None => break,
+            }
+        }
+        self.refill()
+    }
+    fn refill(&mut self) -> Result<(), Error> {
+        let spill = match self.spill.as_mut() {
+            Some(spill) if self.spilled_number > 0 && self.results.len() < self.capacity => spill,
+            _ => return Ok(()),
+        };
+        let loaded = spill.read(self.capacity - self.results.len())?;
+        self.spilled_number = if spill.is_empty() {
+            0
+        } else {
+            self.spilled_number.saturating_sub(loaded.len())
+        };
+        self.results.extend(loaded);
+        Ok(())
+    }
+    /// Number of results waiting for delivery, including spilled results
+    pub fn len(&self) -> usize {
+        self.results.len() + self.spilled_number
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/*
+ * One outbox for each scheduler shard, so results of an unavailable shard do not block other shards.
+ * Capacity is shared evenly, with several shards the spill file of shard i is {spill_file}.shard{i}.
+ */
+#[derive(Debug)]
+pub struct ShardOutboxes {
+    outboxes: Vec<Mutex<ResultOutbox>>,
+}
+
+impl ShardOutboxes {
+    pub fn new(
+        shard_number: usize,
+        capacity: usize,
+        spill_file: Option<PathBuf>,
+    ) -> Result<Self, Error> {
+        let shard_number = shard_number.max(1);
+        let mut outboxes = Vec::with_capacity(shard_number);
+        for shard in 0..shard_number {
+            let shard_spill_file = spill_file.as_ref().map(|path| {
+                if shard_number == 1 {
+                    return path.clone();
+                }
+                let mut name = path.file_name().unwrap_or_default().to_os_string();
+                name.push(format!(".shard{}", shard));
+                path.with_file_name(name)
+            });
+            outboxes.push(Mutex::new(ResultOutbox::new(
+                capacity / shard_number,
+                shard_spill_file,
+            )?));
+        }
+        Ok(ShardOutboxes { outboxes })
+    }
+    pub fn shard_number(&self) -> usize {
+        self.outboxes.len()
+    }
+    /// Outbox of the shard, results of an unknown shard go to the first one
+    pub fn get(&self, shard: usize) -> &Mutex<ResultOutbox> {
+        self.outboxes.get(shard).unwrap_or(&self.outboxes[0])
+    }
+    /// Number of results waiting for delivery in all shards
+    pub async fn len(&self) -> usize {
+        let mut len = 0;
+        for outbox in self.outboxes.iter() {
+            len += outbox.lock().await.len();
+        }
+        len
+    }
+}
+
+/*
+ * Append-only spill segments {spill_file}.{seq}, oldest first.
+ * Read position in the oldest segment is kept in {spill_file}.offset,
+ * a segment is removed once all its results are moved into memory.
+ */
+#[derive(Debug)]
+struct SpillSegments {
+    path: PathBuf,
+    segments: VecDeque<u64>,
+    read_offset: u64,
+    //Segment being appended with its result number, a new segment is started after restart
+    write_segment: Option<(u64, usize)>,
+    next_seq: u64,
+}
+
+impl SpillSegments {
+    /// Open spill segments and return all results which are not read yet
+    fn open(path: PathBuf) -> Result<(Self, Vec<JobResult>), Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut spill = SpillSegments {
+            path,
+            segments: VecDeque::new(),
+            read_offset: 0,
+            write_segment: None,
+            next_seq: 0,
+        };
+        let mut segments = spill.list_segments()?;
+        // Single spill file of older versions becomes the first segment
+        if segments.is_empty() && spill.path.is_file() {
+            fs::rename(&spill.path, spill.get_segment_path(0))?;
+            segments.push(0);
+        }
+        let (offset_seq, offset) = spill.read_offset_file();
+        for seq in segments {
+            if seq < offset_seq {
+                // Segment was consumed but not removed before restart
+                fs::remove_file(spill.get_segment_path(seq))?;
+            } else {
+                spill.segments.push_back(seq);
+            }
+        }
+        if spill.segments.front() == Some(&offset_seq) {
+            spill.read_offset = offset;
+        }
+        spill.next_seq = spill
+            .segments
+            .back()
+            .map(|seq| seq + 1)
+            .unwrap_or(offset_seq);
+        let mut results = Vec::new();
+        for (ind, seq) in spill.segments.iter().enumerate() {
+            let offset = if ind == 0 { spill.read_offset } else { 0 };
+            let (mut segment_results, _, _) =
+                Self::read_segment(&spill.get_segment_path(*seq), offset, usize::MAX)?;
+            results.append(&mut segment_results);
+        }
+        info!(
+            "Found {} spilled results in {} segments of {:?}",
+            results.len(),
+            spill.segments.len(),
+            &spill.path
+        );
+        Ok((spill, results))
+    }
+    fn get_segment_path(&self, seq: u64) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", seq));
+        self.path.with_file_name(name)
+    }
+    fn get_offset_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".offset");
+        self.path.with_file_name(name)
+    }
+    fn list_segments(&self) -> Result<Vec<u64>, Error> {
+        let prefix = format!(
+            "{}.",
+            self.path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        let dir = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let mut segments = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .strip_prefix(&prefix)
+                    .and_then(|seq| seq.parse::<u64>().ok())
+            })
+            .collect::<Vec<u64>>();
+        segments.sort_unstable();
+        Ok(segments)
+    }
+    fn read_offset_file(&self) -> (u64, u64) {
+        let content = fs::read_to_string(self.get_offset_path()).unwrap_or_default();
+        let mut values = content
+            .split_whitespace()
+            .filter_map(|val| val.parse::<u64>().ok());
+        match (values.next(), values.next()) {
+            (Some(seq), Some(offset)) => (seq, offset),
+            _ => (0, 0),
+        }
+    }
+    fn write_offset_file(&self) -> Result<(), Error> {
+        let seq = self.segments.front().cloned().unwrap_or(self.next_seq);
+        let path = self.get_offset_path();
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, format!("{} {}", seq, self.read_offset))?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+    fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+    fn append(&mut self, result: &JobResult) -> Result<(), Error> {
+        let (seq, number) = match self.write_segment {
+            Some((seq, number)) if number < SPILL_SEGMENT_SIZE => (seq, number),
+            _ => (self.next_seq, 0),
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.get_segment_path(seq))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, result)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        if number == 0 {
+            self.segments.push_back(seq);
+            self.next_seq = seq + 1;
+        }
+        self.write_segment = Some((seq, number + 1));
+        Ok(())
+    }
+    /// Read at most max_size oldest results, fully read segments are removed
+    fn read(&mut self, max_size: usize) -> Result<Vec<JobResult>, Error> {
+        let mut results = Vec::new();
+        while results.len() < max_size {
+            let seq = match self.segments.front() {
+                Some(seq) => *seq,
+                None => break,
+            };
+            let path = self.get_segment_path(seq);
+            let (mut segment_results, offset, is_end) =
+                Self::read_segment(&path, self.read_offset, max_size - results.len())?;
+            results.append(&mut segment_results);
+            self.read_offset = offset;
+            if !is_end {
+                break;
+            }
+            self.segments.pop_front();
+            self.read_offset = 0;
+            if self.write_segment.map(|(write_seq, _)| write_seq) == Some(seq) {
+                self.write_segment = None;
+            }
+            // Offset points to the next segment before the read one is removed
+            self.write_offset_file()?;
+            fs::remove_file(&path)?;
+        }
+        self.write_offset_file()?;
+        Ok(results)
+    }
+    /// Return read results, offset after the last read result and whether end of segment is reached
+    fn read_segment(
+        path: &Path,
+        offset: u64,
+        max_size: usize,
+    ) -> Result<(Vec<JobResult>, u64, bool), Error> {
+        let mut reader = BufReader::new(File::open(path)?);
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut results = Vec::new();
+        let mut offset = offset;
+        let mut line = String::new();
+        while results.len() < max_size {
+            line.clear();
+            let size = reader.read_line(&mut line)?;
+            if size == 0 {
+                return Ok((results, offset, true));
+            }
+            offset += size as u64;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<JobResult>(&line) {
+                Ok(result) => results.push(result),
+                Err(err) => warn!("Skip invalid result in {:?}: {:?}", path, err),
+            }
+        }
+        // Segment may end right after the last read result
+        let is_end = reader.fill_buf()?.is_empty();
+        Ok((results, offset, is_end))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::outbox::{ResultOutbox, ShardOutboxes};
+    use common::job_manage::JobRole;
+    use common::jobs::JobResult;
+    use common::BlockChainType;
+    use std::fs;
+    use test_util::helper::{load_env, mock_job_result, JobName};
+    use uuid::Uuid;
+
+    fn new_test_result(job_id: &str, receive_timestamp: i64) -> JobResult {
+        load_env();
+        let mut result = mock_job_result(
+            &JobName::RoundTripTime,
+            BlockChainType::Eth,
+            job_id,
+            JobRole::Regular,
+        );
+        result.receive_timestamp = receive_timestamp;
+        result
+    }
+
+    #[test]
+    fn test_outbox_dedupe_and_drop() {
+        let mut outbox = ResultOutbox::new(2, None).unwrap();
+        assert!(outbox.push(new_test_result("job_1", 1)));
+        assert!(!outbox.push(new_test_result("job_1", 1)));
+        assert!(outbox.push(new_test_result("job_1", 2)));
+        assert!(outbox.push(new_test_result("job_2", 1)));
+        assert_eq!(outbox.len(), 2);
+        let batch = outbox.front_batch(10);
+        assert_eq!(batch[0].receive_timestamp, 2);
+        assert_eq!(batch[1].job_id, "job_2");
+        outbox.commit(1).unwrap();
+        assert_eq!(outbox.len(), 1);
+        // Delivered result can be pushed again
+        assert!(outbox.push(new_test_result("job_1", 2)));
+    }
+
+    #[test]
+    fn test_outbox_spill() {
+        let path = std::env::temp_dir().join(format!("outbox_{}.log", Uuid::new_v4()));
+        {
+            let mut outbox = ResultOutbox::new(2, Some(path.clone())).unwrap();
+            for ind in 0..5 {
+                outbox.push(new_test_result(&format!("job_{}", ind), ind));
+            }
+            assert_eq!(outbox.len(), 5);
+            assert_eq!(outbox.front_batch(10).len(), 2);
+        }
+        // Spilled results survive restart, results in memory are lost
+        let mut outbox = ResultOutbox::new(2, Some(path.clone())).unwrap();
+        assert_eq!(outbox.len(), 3);
+        assert!(!outbox.push(new_test_result("job_2", 2)));
+        let job_ids = outbox
+            .front_batch(10)
+            .into_iter()
+            .map(|res| res.job_id)
+            .collect::<Vec<_>>();
+        assert_eq!(job_ids, vec!["job_2", "job_3"]);
+        outbox.commit(2).unwrap();
+        assert_eq!(outbox.front_batch(10)[0].job_id, "job_4");
+        outbox.commit(1).unwrap();
+        assert!(outbox.is_empty());
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(path.with_extension("log.offset"));
+    }
+
+    #[test]
+    fn test_outbox_spill_offset() {
+        let path = std::env::temp_dir().join(format!("outbox_{}.log", Uuid::new_v4()));
+        {
+            let mut outbox = ResultOutbox::new(1, Some(path.clone())).unwrap();
+            for ind in 0..4 {
+                outbox.push(new_test_result(&format!("job_{}", ind), ind));
+            }
+            // job_1 is moved from spill segment into memory
+            outbox.commit(1).unwrap();
+            assert_eq!(outbox.front_batch(10)[0].job_id, "job_1");
+            assert_eq!(outbox.len(), 3);
+        }
+        // Results moved into memory are not read again after restart
+        let mut outbox = ResultOutbox::new(1, Some(path.clone())).unwrap();
+        assert_eq!(outbox.len(), 2);
+        assert_eq!(outbox.front_batch(10)[0].job_id, "job_2");
+        outbox.commit(1).unwrap();
+        outbox.commit(1).unwrap();
+        assert!(outbox.is_empty());
+        // Consumed segments are removed
+        assert!(!path.with_extension("log.0").exists());
+        let _ = fs::remove_file(path.with_extension("log.offset"));
+    }
+
+    #[tokio::test]
+    async fn test_shard_outboxes_spill() {
+        let path = std::env::temp_dir().join(format!("outbox_{}.log", Uuid::new_v4()));
+        let outboxes = ShardOutboxes::new(2, 2, Some(path.clone())).unwrap();
+        for ind in 0..3 {
+            outboxes
+                .get(1)
+                .lock()
+                .await
+                .push(new_test_result(&format!("job_{}", ind), ind));
+        }
+        // Each shard spills over its share of capacity into its own file
+        assert!(outboxes.get(0).lock().await.is_empty());
+        assert_eq!(outboxes.get(1).lock().await.front_batch(10).len(), 1);
+        assert_eq!(outboxes.len().await, 3);
+        assert!(path.with_extension("log.shard1.0").exists());
+        assert!(!path.with_extension("log.shard0.0").exists());
+        {
+            let mut outbox = outboxes.get(1).lock().await;
+            while !outbox.is_empty() {
+                outbox.commit(1).unwrap();
+            }
+        }
+        assert_eq!(outboxes.len().await, 0);
+        let _ = fs::remove_file(path.with_extension("log.shard0.offset"));
+        let _ = fs::remove_file(path.with_extension("log.shard1.offset"));
+    }
+}
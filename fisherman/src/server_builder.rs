@@ -1,6 +1,8 @@
 use crate::server_config::AccessControl;
+use common::auth::{HEADER_SIGNATURE, HEADER_TIMESTAMP};
 use common::jobs::Job;
-use log::{info, trace};
+use log::{info, trace, warn};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 use std::convert::Infallible;
@@ -8,23 +10,33 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use warp::http::{HeaderMap, Method};
 
-use crate::services::WebService;
+use crate::services::{RequestAuthenticator, WebService};
 use crate::state::WorkerState;
 use crate::BUILD_VERSION;
 use common::workers::{WorkerStateParam, WorkerStatus};
-use common::{JobId, PlanId};
+use common::{JobId, PlanId, TLS_CONFIG};
 use serde_json::json;
 use std::default::Default;
 use tokio::sync::RwLock;
-use warp::{http::StatusCode, Filter, Rejection, Reply};
+use warp::hyper::body::Bytes;
+use warp::{http::StatusCode, reject, Filter, Rejection, Reply};
 
 pub const MAX_JSON_BODY_SIZE: u64 = 1024 * 1024;
 
+#[derive(Debug)]
+pub struct UnAuthorization;
+impl reject::Reject for UnAuthorization {}
+
+#[derive(Debug)]
+pub struct InvalidBody(String);
+impl reject::Reject for InvalidBody {}
+
 #[derive(Default)]
 pub struct WebServerBuilder {
     entry_point: String,
     access_control: AccessControl,
     worker_state: Arc<WorkerState>,
+    authenticator: Arc<RequestAuthenticator>,
 }
 
 pub struct WorkerServer {
@@ -33,6 +45,7 @@ pub struct WorkerServer {
     pub web_service: Arc<WebService>,
     worker_state: Arc<WorkerState>,
     worker_status: Arc<RwLock<WorkerStatus>>,
+    authenticator: Arc<RequestAuthenticator>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -78,25 +91,40 @@ impl WorkerServer {
                 .create_get_status(self.worker_status.clone())
                 .with(&cors))
             .or(self
-                .create_route_handle_jobs(self.web_service.clone(), self.worker_state.clone())
+                .create_route_handle_jobs(
+                    self.web_service.clone(),
+                    self.worker_state.clone(),
+                    self.authenticator.clone(),
+                )
                 .with(&cors))
             .or(self
-                .create_route_cancel_jobs(self.web_service.clone(), self.worker_state.clone())
+                .create_route_cancel_jobs(
+                    self.web_service.clone(),
+                    self.worker_state.clone(),
+                    self.authenticator.clone(),
+                )
                 .with(&cors))
             .or(self
-                .create_route_cancel_plans(self.web_service.clone(), self.worker_state.clone())
+                .create_route_cancel_plans(
+                    self.web_service.clone(),
+                    self.worker_state.clone(),
+                    self.authenticator.clone(),
+                )
                 .with(&cors))
             .or(self.create_version().with(&cors))
             .or(self
-                .create_route_update_jobs(self.web_service.clone(), self.worker_state.clone())
+                .create_route_update_jobs(
+                    self.web_service.clone(),
+                    self.worker_state.clone(),
+                    self.authenticator.clone(),
+                )
                 .with(&cors))
             .or(self
                 .create_route_get_state(self.web_service.clone(), self.worker_state.clone())
                 .with(&cors))
             .recover(handle_rejection);
         let socket_addr: SocketAddr = self.entry_point.parse().unwrap();
-
-        warp::serve(router).run(socket_addr).await;
+        TLS_CONFIG.serve(router, socket_addr).await;
     }
 
     /// Ping API
@@ -147,11 +175,12 @@ impl WorkerServer {
         &self,
         service: Arc<WebService>,
         state: Arc<WorkerState>,
+        authenticator: Arc<RequestAuthenticator>,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         warp::path!("handle_jobs")
             .and(WorkerServer::log_headers())
             .and(warp::post())
-            .and(warp::body::content_length_limit(MAX_JSON_BODY_SIZE).and(warp::body::json()))
+            .and(WorkerServer::signed_json("handle_jobs", authenticator))
             .and_then(move |jobs: Vec<Job>| {
                 info!(
                     "#### Received {} handle_jobs request body {:?} ####",
@@ -167,11 +196,12 @@ impl WorkerServer {
         &self,
         service: Arc<WebService>,
         state: Arc<WorkerState>,
+        authenticator: Arc<RequestAuthenticator>,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         warp::path!("cancel_jobs")
             .and(WorkerServer::log_headers())
             .and(warp::post())
-            .and(warp::body::content_length_limit(MAX_JSON_BODY_SIZE).and(warp::body::json()))
+            .and(WorkerServer::signed_json("cancel_jobs", authenticator))
             .and_then(move |jobs: Vec<JobId>| {
                 info!(
                     "#### Received {} cancel_jobs request body {:?} ####",
@@ -188,11 +218,12 @@ impl WorkerServer {
         &self,
         service: Arc<WebService>,
         state: Arc<WorkerState>,
+        authenticator: Arc<RequestAuthenticator>,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         warp::path!("cancel_plans")
             .and(WorkerServer::log_headers())
             .and(warp::post())
-            .and(warp::body::content_length_limit(MAX_JSON_BODY_SIZE).and(warp::body::json()))
+            .and(WorkerServer::signed_json("cancel_plans", authenticator))
             .and_then(move |plans: Vec<PlanId>| {
                 info!(
                     "#### Received {} plans_cancel request body {:?} ####",
@@ -208,11 +239,12 @@ impl WorkerServer {
         &self,
         service: Arc<WebService>,
         state: Arc<WorkerState>,
+        authenticator: Arc<RequestAuthenticator>,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         warp::path!("jobs_update")
             .and(WorkerServer::log_headers())
             .and(warp::post())
-            .and(warp::body::content_length_limit(MAX_JSON_BODY_SIZE).and(warp::body::json()))
+            .and(WorkerServer::signed_json("jobs_update", authenticator))
             .and_then(move |jobs: Vec<Job>| {
                 info!("#### Received update_jobs request body {:?} ####", &jobs);
                 let clone_service = service.clone();
@@ -237,6 +269,34 @@ impl WorkerServer {
             })
     }
 
+    /*
+     * Verify scheduler signature over timestamp, path and raw body before parsing json body
+     */
+    fn signed_json<T: DeserializeOwned + Send>(
+        path: &'static str,
+        authenticator: Arc<RequestAuthenticator>,
+    ) -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+        warp::header::optional::<String>(HEADER_TIMESTAMP)
+            .and(warp::header::optional::<String>(HEADER_SIGNATURE))
+            .and(warp::body::content_length_limit(MAX_JSON_BODY_SIZE).and(warp::body::bytes()))
+            .and_then(
+                move |timestamp: Option<String>, signature: Option<String>, body: Bytes| {
+                    let authenticator = authenticator.clone();
+                    async move {
+                        if let Err(err) = authenticator
+                            .authenticate(path, timestamp.as_deref(), signature.as_deref(), &body)
+                            .await
+                        {
+                            warn!("Reject {} request: {:?}", path, err);
+                            return Err(warp::reject::custom(UnAuthorization));
+                        }
+                        serde_json::from_slice::<T>(&body)
+                            .map_err(|err| warp::reject::custom(InvalidBody(err.to_string())))
+                    }
+                },
+            )
+    }
+
     fn log_headers() -> impl Filter<Extract = (), Error = Infallible> + Copy {
         warp::header::headers_cloned()
             .map(|headers: HeaderMap| {
@@ -266,6 +326,10 @@ impl WebServerBuilder {
         self.worker_state = Arc::new(worker_state);
         self
     }
+    pub fn with_authenticator(mut self, authenticator: RequestAuthenticator) -> Self {
+        self.authenticator = Arc::new(authenticator);
+        self
+    }
 
     pub fn build(
         &self,
@@ -278,6 +342,7 @@ impl WebServerBuilder {
             web_service: Arc::new(service),
             worker_state: self.worker_state.clone(),
             worker_status,
+            authenticator: self.authenticator.clone(),
         }
     }
 }
@@ -287,6 +352,16 @@ async fn handle_rejection(err: Rejection) -> std::result::Result<impl Reply, Inf
         (StatusCode::NOT_FOUND, "Not Found".to_string())
     } else if err.find::<warp::reject::PayloadTooLarge>().is_some() {
         (StatusCode::BAD_REQUEST, "Payload too large".to_string())
+    } else if err.find::<UnAuthorization>().is_some() {
+        (
+            StatusCode::UNAUTHORIZED,
+            format!("Authorization error, {:?}", err),
+        )
+    } else if let Some(InvalidBody(message)) = err.find::<InvalidBody>() {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid request body, {}", message),
+        )
     } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
         (
             StatusCode::BAD_REQUEST,
@@ -312,14 +387,18 @@ mod tests {
     use reqwest::Client;
 
     use crate::models::job::JobBuffer;
+    use crate::models::outbox::ShardOutboxes;
     use crate::services::WebServiceBuilder;
 
     use std::time::Duration;
     use test_util::helper::load_env;
     use tokio::sync::mpsc::{channel, Receiver, Sender};
+    use tokio::sync::Mutex;
 
     use crate::services::service_status::WorkerStatusCheck;
+    use common::auth::WorkerSecret;
     use common::jobs::JobResult;
+    use common::util::get_current_time;
     use tokio::time::sleep;
 
     #[tokio::test]
@@ -340,14 +419,18 @@ mod tests {
         let (sender, receiver): (Sender<JobResult>, Receiver<JobResult>) = channel(1024);
         let job_buffer = Arc::new(Mutex::new(JobBuffer::new()));
         let service = WebServiceBuilder::new().build();
-        let worker_status_check = WorkerStatusCheck::new(sender, job_buffer.clone());
+        let outbox = Arc::new(ShardOutboxes::new(1, 1024, None)?);
+        let worker_status_check =
+            WorkerStatusCheck::new(sender, job_buffer.clone(), outbox, Default::default());
         let worker_status = worker_status_check.get_status();
         let access_control = AccessControl::default();
+        let secret = WorkerSecret::generate();
         // Create job process thread
         let server = WebServerBuilder::default()
             .with_entry_point(socket_addr.as_str())
             .with_access_control(access_control)
             .with_worker_state(WorkerState::new(job_buffer.clone()))
+            .with_authenticator(RequestAuthenticator::new(secret.clone(), 30000))
             .build(service, worker_status);
         info!("Start fisherman service ");
 
@@ -391,7 +474,21 @@ mod tests {
                     "###;
 
         let client = Client::new();
-        let resp = client.post(url).body(body).send().await?.text().await?;
+        // Unsigned request is rejected
+        let resp = client.post(url.as_str()).body(body).send().await?;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let timestamp = get_current_time();
+        let signature = secret.sign(timestamp, "handle_jobs", body.as_bytes())?;
+        let resp = client
+            .post(url.as_str())
+            .header(HEADER_TIMESTAMP, timestamp.to_string())
+            .header(HEADER_SIGNATURE, signature)
+            .body(body)
+            .send()
+            .await?
+            .text()
+            .await?;
         info!("res: {:#?}", resp);
 
         let resp: serde_json::value::Value = serde_json::from_str(&resp)?;
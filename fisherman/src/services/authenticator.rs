@@ -0,0 +1,125 @@
+use anyhow::anyhow;
+use common::auth::{WorkerSecret, HEADER_SIGNATURE, HEADER_TIMESTAMP};
+use common::util::get_current_time;
+use common::Timestamp;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+/*
+ * Verify signed control requests from scheduler.
+ * A request is accepted only if its timestamp is in the allowed clock skew window
+ * and its signature is not used before in that window (replay protection)
+ */
+#[derive(Debug, Default)]
+pub struct RequestAuthenticator {
+    secret: WorkerSecret,
+    max_clock_skew: Timestamp,
+    //Used signatures with their request timestamps
+    used_signatures: Mutex<HashMap<String, Timestamp>>,
+}
+
+impl RequestAuthenticator {
+    pub fn new(secret: WorkerSecret, max_clock_skew: Timestamp) -> Self {
+        RequestAuthenticator {
+            secret,
+            max_clock_skew,
+            used_signatures: Mutex::new(HashMap::new()),
+        }
+    }
+    pub async fn authenticate(
+        &self,
+        path: &str,
+        timestamp: Option<&str>,
+        signature: Option<&str>,
+        body: &[u8],
+    ) -> Result<(), anyhow::Error> {
+        let timestamp = timestamp
+            .ok_or(anyhow!("Missing header {}", HEADER_TIMESTAMP))?
+            .parse::<Timestamp>()
+            .map_err(|err| anyhow!("Invalid header {}: {:?}", HEADER_TIMESTAMP, err))?;
+        let signature = signature.ok_or(anyhow!("Missing header {}", HEADER_SIGNATURE))?;
+        let now = get_current_time();
+        if (now - timestamp).abs() > self.max_clock_skew {
+            return Err(anyhow!(
+                "Request timestamp {} is out of allowed window, current time {}",
+                timestamp,
+                now
+            ));
+        }
+        self.secret.verify(timestamp, path, body, signature)?;
+        let mut used_signatures = self.used_signatures.lock().await;
+        // Expired signatures are rejected by timestamp check
+        used_signatures.retain(|_, used_time| now - *used_time <= self.max_clock_skew);
+        // Hex decoding ignores case, so the same signature is not accepted again in another case
+        if used_signatures
+            .insert(signature.to_lowercase(), timestamp)
+            .is_some()
+        {
+            return Err(anyhow!("Request signature is already used"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_authenticate_request() {
+        let secret = WorkerSecret::generate();
+        let authenticator = RequestAuthenticator::new(secret.clone(), 30000);
+        let body = br#"["job_id"]"#;
+        let now = get_current_time();
+        let signature = secret.sign(now, "cancel_jobs", body).unwrap();
+        let timestamp = now.to_string();
+        assert!(authenticator
+            .authenticate("cancel_jobs", None, Some(&signature), body)
+            .await
+            .is_err());
+        assert!(authenticator
+            .authenticate("cancel_jobs", Some(&timestamp), None, body)
+            .await
+            .is_err());
+        assert!(authenticator
+            .authenticate("cancel_plans", Some(&timestamp), Some(&signature), body)
+            .await
+            .is_err());
+        assert!(authenticator
+            .authenticate("cancel_jobs", Some(&timestamp), Some(&signature), body)
+            .await
+            .is_ok());
+        // Replayed request
+        assert!(authenticator
+            .authenticate("cancel_jobs", Some(&timestamp), Some(&signature), body)
+            .await
+            .is_err());
+        // Replayed request with signature in upper case
+        assert!(authenticator
+            .authenticate(
+                "cancel_jobs",
+                Some(&timestamp),
+                Some(&signature.to_uppercase()),
+                body
+            )
+            .await
+            .is_err());
+        // Expired request
+        let expired = now - 60000;
+        let signature = secret.sign(expired, "cancel_jobs", body).unwrap();
+        assert!(authenticator
+            .authenticate(
+                "cancel_jobs",
+                Some(&expired.to_string()),
+                Some(&signature),
+                body
+            )
+            .await
+            .is_err());
+        // No secret issued
+        assert!(RequestAuthenticator::default()
+            .authenticate("cancel_jobs", Some(&timestamp), Some("00"), body)
+            .await
+            .is_err());
+    }
+}
@@ -1,23 +1,27 @@
 use crate::models::job::JobBuffer;
 use crate::tasks::get_executors;
-use crate::{
-    BENCHMARK_WRK_PATH, JOB_EXECUTOR_PERIOD, MAX_THREAD_COUNTER, WAITING_TIME_FOR_EXECUTING_THREAD,
-    WORKER_ID,
-};
+use crate::{MAX_BULK_JOBS, MAX_LATENCY_SENSITIVE_JOBS, MAX_THREAD_COUNTER, WORKER_ID};
 
-use common::jobs::JobResult;
+use common::jobs::{Job, JobResult, ResourceClass};
 use common::tasks::executor::TaskExecutor;
-use common::util::warning_if_error;
+use common::util::{get_current_time, warning_if_error};
+use common::workers::SchedulingLag;
 use log::{debug, trace};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::Duration;
 use tokio::runtime::{Builder, Runtime};
 use tokio::sync::mpsc::Sender;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Semaphore};
 use tokio::time::sleep;
+
+pub type SchedulingLags = Arc<StdMutex<HashMap<String, SchedulingLag>>>;
+//Each parallel job holds one permit while it is running, an exclusive job takes all of them
+const PARALLEL_JOB_PERMITS: u32 = 1 << 20;
 /*
  * For repeated jobs, after execution executor generate new job with new parameters and push back to JobBuffer
+ * Execution thread sleeps until expected runtime of the first job in buffer
+ * or until new jobs are pushed to buffer.
  */
 pub struct JobExecution {
     result_sender: Sender<JobResult>,
@@ -25,12 +29,15 @@ pub struct JobExecution {
     executors: Vec<Arc<dyn TaskExecutor>>,
     //Runtime for parallelable jobs
     runtime: Runtime,
-    thread_counter: Arc<AtomicUsize>,
+    running_jobs: Arc<Semaphore>,
+    //Concurrency cap for each parallel resource class
+    class_limits: HashMap<ResourceClass, Arc<Semaphore>>,
+    scheduling_lags: SchedulingLags,
 }
 
 impl JobExecution {
     pub fn new(result_sender: Sender<JobResult>, job_buffers: Arc<Mutex<JobBuffer>>) -> Self {
-        let executors = get_executors(WORKER_ID.as_str().to_string(), BENCHMARK_WRK_PATH.as_str());
+        let executors = get_executors(WORKER_ID.as_str().to_string());
 
         let runtime = Builder::new_multi_thread()
             .worker_threads(*MAX_THREAD_COUNTER)
@@ -43,56 +50,231 @@ impl JobExecution {
             job_buffers,
             executors,
             runtime,
-            thread_counter: Arc::new(AtomicUsize::new(0)),
+            running_jobs: Arc::new(Semaphore::new(PARALLEL_JOB_PERMITS as usize)),
+            class_limits: HashMap::from([
+                (
+                    ResourceClass::LatencySensitive,
+                    Arc::new(Semaphore::new(*MAX_LATENCY_SENSITIVE_JOBS)),
+                ),
+                (
+                    ResourceClass::Bulk,
+                    Arc::new(Semaphore::new(*MAX_BULK_JOBS)),
+                ),
+            ]),
+            scheduling_lags: Default::default(),
+        }
+    }
+    pub fn get_scheduling_lags(&self) -> SchedulingLags {
+        self.scheduling_lags.clone()
+    }
+    fn record_lag(scheduling_lags: &SchedulingLags, job: &Job) {
+        // Job without expected runtime is executed as soon as possible
+        if job.expected_runtime <= 0 {
+            return;
         }
+        let lag = (get_current_time() - job.expected_runtime).max(0);
+        trace!("Job {} is executed with lag {} ms", &job.job_id, lag);
+        let key = format!("{}_{}", job.job_type, job.job_name);
+        let mut lags = scheduling_lags.lock().unwrap();
+        lags.entry(key).or_default().add(lag);
     }
     pub async fn run(&mut self) {
+        let notifier = self.job_buffers.lock().await.get_notifier();
         //main thread
         loop {
             while let Some(next_job) = self.job_buffers.lock().await.pop_job() {
                 trace!("Execute job: {:?}", &next_job);
-                let rt_handle = self.runtime.handle();
-
-                if next_job.parallelable {
-                    for executor in self.executors.iter() {
-                        if !executor.can_apply(&next_job) {
-                            continue;
-                        }
-                        let result_sender = self.result_sender.clone();
-                        let clone_executor = executor.clone();
-                        let clone_job = next_job.clone();
-                        let counter = self.thread_counter.clone();
-                        counter.fetch_add(1, Ordering::SeqCst);
-                        rt_handle.spawn(async move {
-                            debug!("Execute job on a worker thread: {:?}", clone_job);
-                            let res = clone_executor.execute(&clone_job, result_sender).await;
-                            warning_if_error("executor.execute return error", res);
-                            //Fixme: Program will hang if it panic before fetch_sub is executed.
-                            counter.fetch_sub(1, Ordering::SeqCst);
-                        });
+                self.execute_job(next_job).await;
+            }
+            let next_runtime = self.job_buffers.lock().await.get_next_runtime();
+            match next_runtime {
+                Some(runtime) => {
+                    let waiting_time = (runtime - get_current_time()).max(0) as u64;
+                    trace!("Wait {} ms for next job", waiting_time);
+                    tokio::select! {
+                        _ = sleep(Duration::from_millis(waiting_time)) => {}
+                        _ = notifier.notified() => {}
                     }
-                } else {
-                    //wait until all task in  parallelable runtime pool is terminated
-                    while self.thread_counter.load(Ordering::Relaxed) > 0 {
-                        sleep(Duration::from_millis(*WAITING_TIME_FOR_EXECUTING_THREAD)).await;
+                }
+                None => notifier.notified().await,
+            }
+        }
+    }
+    async fn execute_job(&self, next_job: Job) {
+        let rt_handle = self.runtime.handle();
+        let resource_class = next_job.get_resource_class();
+
+        if let Some(limit) = self.class_limits.get(&resource_class) {
+            for executor in self.executors.iter() {
+                if !executor.can_apply(&next_job) {
+                    continue;
+                }
+                let result_sender = self.result_sender.clone();
+                let clone_executor = executor.clone();
+                let clone_job = next_job.clone();
+                let scheduling_lags = self.scheduling_lags.clone();
+                let limit = limit.clone();
+                let resource_class = resource_class.clone();
+                // Permit is taken before spawning, so a following exclusive job waits for this one
+                let running = match self.running_jobs.clone().acquire_owned().await {
+                    Ok(permit) => permit,
+                    Err(err) => {
+                        debug!("Cannot execute job {}: {:?}", &next_job.job_id, err);
+                        return;
                     }
-                    for executor in self.executors.iter() {
-                        let can_apply = executor.can_apply(&next_job);
-                        if !can_apply {
-                            continue;
-                        }
-                        let result_sender = self.result_sender.clone();
-                        debug!("Execute job {:?} on main execution thread", &next_job);
-                        match executor.execute(&next_job, result_sender).await {
-                            Ok(_) => {}
-                            Err(err) => {
-                                debug!("{:?}", &err)
-                            }
-                        }
+                };
+                rt_handle.spawn(async move {
+                    // Wait for a free slot of the job resource class without blocking main thread
+                    let _permit = limit.acquire_owned().await;
+                    debug!(
+                        "Execute {:?} job on a worker thread: {:?}",
+                        resource_class, clone_job
+                    );
+                    Self::record_lag(&scheduling_lags, &clone_job);
+                    let res = clone_executor.execute(&clone_job, result_sender).await;
+                    warning_if_error("executor.execute return error", res);
+                    // Permit is released even if the job panics
+                    drop(running);
+                });
+            }
+        } else {
+            //Exclusive job: wait until all task in  parallelable runtime pool is terminated
+            let _running = match self.running_jobs.acquire_many(PARALLEL_JOB_PERMITS).await {
+                Ok(permit) => permit,
+                Err(err) => {
+                    debug!("Cannot execute job {}: {:?}", &next_job.job_id, err);
+                    return;
+                }
+            };
+            for executor in self.executors.iter() {
+                let can_apply = executor.can_apply(&next_job);
+                if !can_apply {
+                    continue;
+                }
+                let result_sender = self.result_sender.clone();
+                debug!("Execute job {:?} on main execution thread", &next_job);
+                Self::record_lag(&self.scheduling_lags, &next_job);
+                match executor.execute(&next_job, result_sender).await {
+                    Ok(_) => {}
+                    Err(err) => {
+                        debug!("{:?}", &err)
                     }
                 }
             }
-            sleep(Duration::from_millis(JOB_EXECUTOR_PERIOD)).await;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use common::job_manage::JobRole;
+    use test_util::helper::{mock_job, JobName};
+    use tokio::sync::mpsc::channel;
+
+    //Running and max running jobs of each resource class, and whether an exclusive job ever overlaps
+    #[derive(Default)]
+    struct CountingExecutor {
+        counters: StdMutex<HashMap<ResourceClass, (usize, usize)>>,
+        overlapped: StdMutex<bool>,
+    }
+
+    #[async_trait]
+    impl TaskExecutor for CountingExecutor {
+        async fn execute(
+            &self,
+            job: &Job,
+            _result_sender: Sender<JobResult>,
+        ) -> Result<(), anyhow::Error> {
+            let resource_class = job.get_resource_class();
+            {
+                let mut counters = self.counters.lock().unwrap();
+                let running = counters.values().map(|(running, _)| running).sum::<usize>();
+                let exclusive_running = counters
+                    .get(&ResourceClass::Exclusive)
+                    .map(|(running, _)| *running > 0)
+                    .unwrap_or(false);
+                if exclusive_running || (resource_class == ResourceClass::Exclusive && running > 0)
+                {
+                    *self.overlapped.lock().unwrap() = true;
+                }
+                let counter = counters.entry(resource_class.clone()).or_default();
+                counter.0 += 1;
+                counter.1 = counter.1.max(counter.0);
+            }
+            sleep(Duration::from_millis(50)).await;
+            self.counters
+                .lock()
+                .unwrap()
+                .entry(resource_class)
+                .or_default()
+                .0 -= 1;
+            Ok(())
+        }
+        fn can_apply(&self, _job: &Job) -> bool {
+            true
+        }
+    }
+
+    fn mock_class_job(job_id: &str, resource_class: ResourceClass) -> Job {
+        let mut job = mock_job(&JobName::LatestBlock, "", job_id, &JobRole::Regular);
+        job.resource_class = Some(resource_class);
+        job
+    }
+
+    #[test]
+    fn test_class_limits() {
+        let executor = Arc::new(CountingExecutor::default());
+        let (result_sender, _receiver) = channel(10);
+        let execution = JobExecution {
+            result_sender,
+            job_buffers: Default::default(),
+            executors: vec![executor.clone() as Arc<dyn TaskExecutor>],
+            runtime: Builder::new_multi_thread()
+                .worker_threads(4)
+                .enable_time()
+                .build()
+                .unwrap(),
+            running_jobs: Arc::new(Semaphore::new(PARALLEL_JOB_PERMITS as usize)),
+            class_limits: HashMap::from([
+                (ResourceClass::LatencySensitive, Arc::new(Semaphore::new(2))),
+                (ResourceClass::Bulk, Arc::new(Semaphore::new(1))),
+            ]),
+            scheduling_lags: Default::default(),
+        };
+        let main_runtime = Builder::new_current_thread().enable_time().build().unwrap();
+        main_runtime.block_on(async {
+            for ind in 0..5 {
+                let job_id = format!("latency_{}", ind);
+                execution
+                    .execute_job(mock_class_job(&job_id, ResourceClass::LatencySensitive))
+                    .await;
+                let job_id = format!("bulk_{}", ind);
+                execution
+                    .execute_job(mock_class_job(&job_id, ResourceClass::Bulk))
+                    .await;
+            }
+            // Exclusive job waits for all parallel jobs, following jobs wait for it
+            execution
+                .execute_job(mock_class_job("exclusive", ResourceClass::Exclusive))
+                .await;
+            execution
+                .execute_job(mock_class_job(
+                    "latency_last",
+                    ResourceClass::LatencySensitive,
+                ))
+                .await;
+            let _all = execution
+                .running_jobs
+                .acquire_many(PARALLEL_JOB_PERMITS)
+                .await
+                .unwrap();
+        });
+        let counters = executor.counters.lock().unwrap();
+        assert_eq!(counters[&ResourceClass::LatencySensitive], (0, 2));
+        assert_eq!(counters[&ResourceClass::Bulk], (0, 1));
+        assert_eq!(counters[&ResourceClass::Exclusive], (0, 1));
+        assert!(!*executor.overlapped.lock().unwrap());
+    }
+}
@@ -1,8 +1,12 @@
+pub mod authenticator;
 pub mod execution;
+pub mod puller;
 pub mod reporter;
 pub mod service_status;
 pub mod webservice;
 
+pub use authenticator::RequestAuthenticator;
 pub use execution::JobExecution;
+pub use puller::JobPuller;
 pub use reporter::JobResultReporter;
 pub use webservice::{WebService, WebServiceBuilder};
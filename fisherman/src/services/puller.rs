@@ -0,0 +1,197 @@
+use crate::services::reporter::RetryBackoff;
+use crate::state::WorkerState;
+use crate::{PULL_JOBS_WAIT, REPORT_RETRY_MAX_DELAY, SCHEDULER_AUTHORIZATION};
+use anyhow::anyhow;
+use common::auth::{WorkerSecret, HEADER_SIGNATURE, HEADER_TIMESTAMP};
+use common::tls::get_tls_client;
+use common::util::get_current_time;
+use common::workers::WorkerJobsResult;
+use common::{JobId, WorkerId, COMMON_CONFIG};
+use log::{debug, info, warn};
+use reqwest::{Client, RequestBuilder};
+use std::time::Duration;
+use tokio::time::sleep;
+
+const PULL_RETRY_BASE_DELAY: u64 = 1000;
+
+/*
+ * Pull mode delivery: worker polls jobs and cancel plans from scheduler instead of receiving them
+ * on its own endpoints, so it works behind NAT or firewalls.
+ * Received jobs are acknowledged, otherwise scheduler delivers them again
+ */
+pub struct JobPuller {
+    scheduler_endpoint: String,
+    worker_id: WorkerId,
+    secret: WorkerSecret,
+    state: WorkerState,
+    backoff: RetryBackoff,
+}
+
+impl JobPuller {
+    pub fn new(
+        scheduler_endpoint: String,
+        worker_id: WorkerId,
+        secret: WorkerSecret,
+        state: WorkerState,
+    ) -> Self {
+        JobPuller {
+            scheduler_endpoint,
+            worker_id,
+            secret,
+            state,
+            backoff: RetryBackoff::new(PULL_RETRY_BASE_DELAY, *REPORT_RETRY_MAX_DELAY),
+        }
+    }
+    pub async fn run(&mut self) {
+        let client = match get_tls_client() {
+            Ok(client) => client,
+            Err(err) => {
+                warn!("Cannot create client for pulling jobs: {:?}", err);
+                return;
+            }
+        };
+        info!("Pull jobs from scheduler {}", &self.scheduler_endpoint);
+        loop {
+            match self.pull_jobs(&client).await {
+                Ok(()) => self.backoff.reset(),
+                Err(err) => {
+                    let delay = self.backoff.next_delay();
+                    warn!("Pull jobs error: {:?}, retry in {:.2?}", err, delay);
+                    sleep(delay).await;
+                }
+            }
+        }
+    }
+    pub async fn pull_jobs(&self, client: &Client) -> Result<(), anyhow::Error> {
+        let path = format!("worker/{}/jobs", &self.worker_id);
+        let url = format!(
+            "{}/{}?wait={}",
+            &self.scheduler_endpoint, &path, *PULL_JOBS_WAIT
+        );
+        let request =
+            self.sign_request(client.get(url), &path, "")?
+                .timeout(Duration::from_millis(
+                    *PULL_JOBS_WAIT * 1000 + COMMON_CONFIG.default_http_request_timeout_ms,
+                ));
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Scheduler response error: {:?} and body: {:?}",
+                response.status(),
+                response.text().await
+            ));
+        }
+        let WorkerJobsResult { jobs, cancel_plans } = response.json::<WorkerJobsResult>().await?;
+        if !cancel_plans.is_empty() {
+            let counter = self.state.cancel_plans(&cancel_plans).await;
+            debug!("Cancel {} jobs of {} plans", counter, cancel_plans.len());
+        }
+        if !jobs.is_empty() {
+            let job_ids = jobs
+                .iter()
+                .map(|job| job.job_id.clone())
+                .collect::<Vec<JobId>>();
+            let counter = self.state.push_jobs(jobs).await;
+            debug!("Add {} pulled jobs to queue", counter);
+            self.ack_jobs(client, &job_ids).await?;
+        }
+        Ok(())
+    }
+    async fn ack_jobs(&self, client: &Client, job_ids: &Vec<JobId>) -> Result<(), anyhow::Error> {
+        let path = format!("worker/{}/jobs/ack", &self.worker_id);
+        let url = format!("{}/{}", &self.scheduler_endpoint, &path);
+        let body = serde_json::to_string(job_ids)?;
+        let response = self
+            .sign_request(client.post(url), &path, &body)?
+            .header("content-type", "application/json")
+            .body(body)
+            .timeout(Duration::from_millis(
+                COMMON_CONFIG.default_http_request_timeout_ms,
+            ))
+            .send()
+            .await?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Ack {} jobs error: {:?}",
+                job_ids.len(),
+                response.status()
+            ))
+        }
+    }
+    fn sign_request(
+        &self,
+        builder: RequestBuilder,
+        path: &str,
+        body: &str,
+    ) -> Result<RequestBuilder, anyhow::Error> {
+        let timestamp = get_current_time();
+        let signature = self.secret.sign(timestamp, path, body.as_bytes())?;
+        Ok(builder
+            .header("authorization", &*SCHEDULER_AUTHORIZATION)
+            .header(HEADER_TIMESTAMP, timestamp.to_string())
+            .header(HEADER_SIGNATURE, signature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::job::JobBuffer;
+    use common::jobs::Job;
+    use httpmock::prelude::*;
+    use std::sync::Arc;
+    use test_util::helper::load_env;
+    use tokio::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_pull_jobs() {
+        load_env();
+        let server = MockServer::start();
+        let job = Job {
+            job_id: "job_id".to_string(),
+            plan_id: "plan_id".to_string(),
+            ..Default::default()
+        };
+        let result = WorkerJobsResult {
+            jobs: vec![job],
+            cancel_plans: vec![],
+        };
+        let poll = server.mock(|when, then| {
+            when.method(GET)
+                .path("/worker/worker_id/jobs")
+                .header_exists(HEADER_SIGNATURE)
+                .header_exists(HEADER_TIMESTAMP);
+            then.status(200).json_body_obj(&result);
+        });
+        let ack = server.mock(|when, then| {
+            when.method(POST)
+                .path("/worker/worker_id/jobs/ack")
+                .json_body_obj(&vec!["job_id"]);
+            then.status(200)
+                .body(r#"{"success":true,"acknowledged":1}"#);
+        });
+        let state = WorkerState::new(Arc::new(Mutex::new(JobBuffer::new())));
+        let puller = JobPuller::new(
+            server.base_url(),
+            "worker_id".to_string(),
+            WorkerSecret::generate(),
+            state.clone(),
+        );
+        let client = Client::new();
+        assert!(puller.pull_jobs(&client).await.is_ok());
+        poll.assert();
+        ack.assert();
+        assert_eq!(state.queue_len().await, 1);
+
+        // Worker without secret cannot sign requests
+        let puller = JobPuller::new(
+            server.base_url(),
+            "worker_id".to_string(),
+            WorkerSecret::default(),
+            state,
+        );
+        assert!(puller.pull_jobs(&client).await.is_err());
+    }
+}
@@ -1,42 +1,123 @@
-use crate::{JOB_RESULT_REPORTER_PERIOD, SCHEDULER_AUTHORIZATION};
+use crate::models::outbox::ShardOutboxes;
+use crate::{
+    JOB_RESULT_REPORTER_PERIOD, REPORT_BATCH_SIZE, REPORT_RETRY_MAX_DELAY, SCHEDULER_AUTHORIZATION,
+    WORKER_ID,
+};
 use anyhow::anyhow;
+use common::auth::{ResultSigner, HEADER_SIGNATURE, HEADER_WORKER_ID};
 use common::jobs::JobResult;
+use common::shard::ShardMap;
+use common::tls::get_tls_client;
+use common::util::warning_if_error;
 use common::COMMON_CONFIG;
-use log::{debug, info, trace};
+use log::{debug, error, info, trace, warn};
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Receiver;
 use tokio::time::sleep;
 
+#[derive(Debug, PartialEq)]
+pub enum SendResultStatus {
+    Delivered,
+    //Scheduler is overloaded or unavailable, retry after the duration in Retry-After header if any
+    Retry(Option<Duration>),
+    //Scheduler does not accept the results, retry does not help
+    Rejected(StatusCode),
+    //Scheduler processes results of its own providers and refuses the others, with its current shard map
+    Misdirected(ShardMap),
+}
+
+//Body of a misdirected request response
+#[derive(Deserialize)]
+struct MisdirectedResponse {
+    shard_map: ShardMap,
+}
+
+/*
+ * Exponential backoff with jitter, delay is picked randomly in [d/2, d]
+ * where d = base * 2^attempt capped by max
+ */
+#[derive(Debug)]
+pub struct RetryBackoff {
+    base: u64,
+    max: u64,
+    attempt: u32,
+}
+
+impl RetryBackoff {
+    pub fn new(base: u64, max: u64) -> Self {
+        RetryBackoff {
+            base,
+            max: max.max(base),
+            attempt: 0,
+        }
+    }
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self
+            .base
+            .saturating_mul(1u64 << self.attempt.min(32))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+        Duration::from_millis(rand::thread_rng().gen_range(delay / 2..=delay))
+    }
+}
+
+//Delivery state of a scheduler shard, each shard is retried with its own backoff
+struct ShardDelivery {
+    backoff: RetryBackoff,
+    retry_at: Option<Instant>,
+}
+
 pub struct JobResultReporter {
     receiver: Receiver<JobResult>,
     result_callback: String,
+    outboxes: Arc<ShardOutboxes>,
+    //Sign each result batch, so scheduler can verify that results are not forged
+    signer: Arc<ResultSigner>,
+    deliveries: Vec<ShardDelivery>,
+    //Results are sent to the scheduler shard of their provider
+    shard_map: ShardMap,
 }
 
 impl JobResultReporter {
-    pub fn new(receiver: Receiver<JobResult>, result_callback: String) -> Self {
+    pub fn new(
+        receiver: Receiver<JobResult>,
+        result_callback: String,
+        outboxes: Arc<ShardOutboxes>,
+        signer: Arc<ResultSigner>,
+    ) -> Self {
+        let deliveries = (0..outboxes.shard_number())
+            .map(|_| ShardDelivery {
+                backoff: RetryBackoff::new(*JOB_RESULT_REPORTER_PERIOD, *REPORT_RETRY_MAX_DELAY),
+                retry_at: None,
+            })
+            .collect();
         JobResultReporter {
             receiver,
             result_callback,
+            outboxes,
+            signer,
+            deliveries,
+            shard_map: ShardMap::default(),
         }
     }
+    pub fn with_shard_map(mut self, shard_map: ShardMap) -> Self {
+        self.shard_map = shard_map;
+        self
+    }
     pub async fn run(&mut self) {
         let mut loop_counter: u64 = 0;
         loop {
             loop_counter = loop_counter + 1;
-            let mut results = Vec::<JobResult>::new();
-            while let Ok(job_result) = self.receiver.try_recv() {
-                trace!("Received job result: {:?}", job_result);
-                results.push(job_result);
-            }
-            if !results.is_empty() {
-                let now = Instant::now();
-                let res = self.send_results(results).await;
-                info!(
-                    "Finished sending results in {:.2?} with res: {:?}",
-                    now.elapsed(),
-                    res
-                );
-            } else {
+            self.collect_results().await;
+            if !self.send_shard_batches().await {
                 //Print log for each 30 loops
                 if loop_counter % 30 == 0 {
                     debug!("No job result for report.");
@@ -45,17 +126,128 @@ impl JobResultReporter {
             }
         }
     }
-    pub async fn send_results(&self, results: Vec<JobResult>) -> Result<(), anyhow::Error> {
-        let call_back = self.result_callback.to_string();
+    /*
+     * Send the front batch of each shard which is not waiting for retry,
+     * return false if there is no batch to send
+     */
+    async fn send_shard_batches(&mut self) -> bool {
+        let mut sent = false;
+        for shard in 0..self.deliveries.len() {
+            if let Some(retry_at) = self.deliveries[shard].retry_at {
+                if retry_at > Instant::now() {
+                    continue;
+                }
+            }
+            let results = self
+                .outboxes
+                .get(shard)
+                .lock()
+                .await
+                .front_batch(*REPORT_BATCH_SIZE);
+            if results.is_empty() {
+                continue;
+            }
+            sent = true;
+            let now = Instant::now();
+            let res = self.send_results(&results).await;
+            info!(
+                "Finished sending results of shard {} in {:.2?} with res: {:?}",
+                shard,
+                now.elapsed(),
+                res
+            );
+            let mut misdirected = Vec::new();
+            let retry_after = match res {
+                Ok(SendResultStatus::Delivered) => None,
+                Ok(SendResultStatus::Misdirected(shard_map)) => {
+                    if shard_map.shards.len() != self.deliveries.len() {
+                        warn!(
+                            "Scheduler has {} shards but worker has {} shard outboxes",
+                            shard_map.shards.len(),
+                            self.deliveries.len()
+                        );
+                    }
+                    self.shard_map = shard_map;
+                    misdirected = results
+                        .iter()
+                        .filter(|result| self.shard_map.get_shard(&result.provider_id) != shard)
+                        .cloned()
+                        .collect();
+                    warn!(
+                        "Shard {} accepts {} results, move {} results to their shards",
+                        shard,
+                        results.len() - misdirected.len(),
+                        misdirected.len()
+                    );
+                    None
+                }
+                Ok(SendResultStatus::Rejected(status)) => {
+                    error!(
+                        "Scheduler rejects {} results with status {}, drop them",
+                        results.len(),
+                        status
+                    );
+                    None
+                }
+                Ok(SendResultStatus::Retry(retry_after)) => Some(retry_after.unwrap_or_default()),
+                Err(_) => Some(Duration::default()),
+            };
+            let delivery = &mut self.deliveries[shard];
+            match retry_after {
+                None => {
+                    delivery.backoff.reset();
+                    delivery.retry_at = None;
+                    let res = self.outboxes.get(shard).lock().await.commit(results.len());
+                    warning_if_error("Commit delivered results", res);
+                    for result in misdirected {
+                        let owner = self.shard_map.get_shard(&result.provider_id);
+                        self.outboxes.get(owner).lock().await.push(result);
+                    }
+                }
+                Some(retry_after) => {
+                    let delay = delivery.backoff.next_delay().max(retry_after);
+                    warn!(
+                        "Cannot deliver {} results to shard {}, retry in {:.2?}",
+                        results.len(),
+                        shard,
+                        delay
+                    );
+                    delivery.retry_at = Some(Instant::now() + delay);
+                }
+            }
+        }
+        sent
+    }
+    /// Move received results to the outbox of their shard
+    async fn collect_results(&mut self) -> usize {
+        let mut counter = 0;
+        while let Ok(job_result) = self.receiver.try_recv() {
+            trace!("Received job result: {:?}", job_result);
+            let shard = self.shard_map.get_shard(&job_result.provider_id);
+            self.outboxes.get(shard).lock().await.push(job_result);
+            counter += 1;
+        }
+        counter
+    }
+    pub async fn send_results(
+        &self,
+        results: &Vec<JobResult>,
+    ) -> Result<SendResultStatus, anyhow::Error> {
+        let call_back = results
+            .first()
+            .and_then(|result| self.shard_map.get_report_callback(&result.provider_id))
+            .unwrap_or_else(|| self.result_callback.to_string());
         info!("Send {} results to: {}", results.len(), call_back);
-        let client_builder = reqwest::ClientBuilder::new();
-        let client = client_builder.danger_accept_invalid_certs(true).build()?;
-        let body = serde_json::to_string(&results)?;
+        let client = get_tls_client()?;
+        let body = serde_json::to_string(results)?;
         trace!("Body content: {}", body);
+        let signature = self.signer.sign(body.as_bytes());
         let result = client
             .post(call_back)
             .header("content-type", "application/json")
             .header("authorization", &*SCHEDULER_AUTHORIZATION)
+            .header(HEADER_WORKER_ID, &*WORKER_ID)
+            .header(HEADER_SIGNATURE, signature)
             .body(body)
             .timeout(Duration::from_millis(
                 COMMON_CONFIG.default_http_request_timeout_ms,
@@ -64,8 +256,213 @@ impl JobResultReporter {
             .await;
         info!("Send response: {:?}", result);
         match result {
-            Ok(_res) => Ok(()),
+            Ok(res) => {
+                let status = res.status();
+                if status.is_success() {
+                    Ok(SendResultStatus::Delivered)
+                } else if status == StatusCode::TOO_MANY_REQUESTS
+                    || status == StatusCode::SERVICE_UNAVAILABLE
+                {
+                    let retry_after = res
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|val| val.to_str().ok())
+                        .and_then(|val| val.trim().parse::<u64>().ok())
+                        .map(Duration::from_secs);
+                    Ok(SendResultStatus::Retry(retry_after))
+                } else if status.is_server_error() {
+                    Ok(SendResultStatus::Retry(None))
+                } else if status == StatusCode::MISDIRECTED_REQUEST {
+                    match res.json::<MisdirectedResponse>().await {
+                        Ok(response) => Ok(SendResultStatus::Misdirected(response.shard_map)),
+                        Err(err) => {
+                            error!("Cannot get shard map of misdirected request: {:?}", err);
+                            Ok(SendResultStatus::Rejected(status))
+                        }
+                    }
+                } else {
+                    Ok(SendResultStatus::Rejected(status))
+                }
+            }
             Err(err) => Err(anyhow!(format!("{:?}", &err))),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::job_manage::JobRole;
+    use common::BlockChainType;
+    use httpmock::prelude::*;
+    use test_util::helper::{load_env, mock_job_result, JobName};
+    use tokio::sync::mpsc::channel;
+
+    #[test]
+    fn test_retry_backoff() {
+        let mut backoff = RetryBackoff::new(1000, 5000);
+        let delays = (0..5)
+            .map(|_| backoff.next_delay().as_millis() as u64)
+            .collect::<Vec<_>>();
+        assert!(delays[0] >= 500 && delays[0] <= 1000);
+        assert!(delays[1] >= 1000 && delays[1] <= 2000);
+        assert!(delays[2] >= 2000 && delays[2] <= 4000);
+        assert!(delays[3] >= 2500 && delays[3] <= 5000);
+        assert!(delays[4] >= 2500 && delays[4] <= 5000);
+        backoff.reset();
+        assert!(backoff.next_delay().as_millis() <= 1000);
+    }
+
+    #[tokio::test]
+    async fn test_send_results_status() {
+        load_env();
+        let server = MockServer::start();
+        let busy = server.mock(|when, then| {
+            when.method(POST).path("/busy");
+            then.status(429).header("Retry-After", "3");
+        });
+        let rejected = server.mock(|when, then| {
+            when.method(POST).path("/rejected");
+            then.status(400);
+        });
+        let (_sender, receiver) = channel(1);
+        let outbox = Arc::new(ShardOutboxes::new(1, 10, None).unwrap());
+        let signer = Arc::new(ResultSigner::generate());
+        let reporter = JobResultReporter::new(
+            receiver,
+            server.url("/busy"),
+            outbox.clone(),
+            signer.clone(),
+        );
+        let res = reporter.send_results(&vec![]).await.unwrap();
+        assert_eq!(res, SendResultStatus::Retry(Some(Duration::from_secs(3))));
+        busy.assert();
+
+        let (_sender, receiver) = channel(1);
+        let reporter = JobResultReporter::new(receiver, server.url("/rejected"), outbox, signer);
+        let res = reporter.send_results(&vec![]).await.unwrap();
+        assert_eq!(res, SendResultStatus::Rejected(StatusCode::BAD_REQUEST));
+        rejected.assert();
+    }
+
+    #[tokio::test]
+    async fn test_send_results_to_shard() {
+        load_env();
+        let server = MockServer::start();
+        let shard0_report = server.mock(|when, then| {
+            when.method(POST).path("/shard0/report");
+            then.status(503);
+        });
+        let shard1_report = server.mock(|when, then| {
+            when.method(POST).path("/shard1/report");
+            then.status(200);
+        });
+        let shard_map = ShardMap::new(vec![server.url("/shard0"), server.url("/shard1")]);
+        let provider_ids = (0..100)
+            .map(|ind| format!("provider-{}", ind))
+            .collect::<Vec<String>>();
+        let shard0 = provider_ids
+            .iter()
+            .find(|id| shard_map.get_shard(id) == 0)
+            .unwrap();
+        let shard1 = provider_ids
+            .iter()
+            .find(|id| shard_map.get_shard(id) == 1)
+            .unwrap();
+        let (sender, receiver) = channel(10);
+        for (ind, provider_id) in [shard0, shard1, shard0, shard1].iter().enumerate() {
+            let mut result = mock_job_result(
+                &JobName::LatestBlock,
+                BlockChainType::Eth,
+                &format!("job_{}", ind),
+                JobRole::Regular,
+            );
+            result.provider_id = provider_id.to_string();
+            sender.send(result).await.unwrap();
+        }
+        let outboxes = Arc::new(ShardOutboxes::new(2, 10, None).unwrap());
+        let mut reporter = JobResultReporter::new(
+            receiver,
+            server.url("/report"),
+            outboxes.clone(),
+            Arc::new(ResultSigner::generate()),
+        )
+        .with_shard_map(shard_map);
+        assert_eq!(reporter.collect_results().await, 4);
+        assert_eq!(outboxes.get(0).lock().await.len(), 2);
+        assert_eq!(outboxes.get(1).lock().await.len(), 2);
+        // Unavailable shard keeps its results and waits, other shard is delivered
+        assert!(reporter.send_shard_batches().await);
+        shard0_report.assert();
+        shard1_report.assert();
+        assert_eq!(outboxes.get(0).lock().await.len(), 2);
+        assert!(outboxes.get(1).lock().await.is_empty());
+        assert!(reporter.deliveries[0].retry_at.is_some());
+        assert!(reporter.deliveries[1].retry_at.is_none());
+        // Shard waiting for retry is not sent again before its backoff delay
+        assert!(!reporter.send_shard_batches().await);
+        shard0_report.assert_hits(1);
+    }
+
+    #[tokio::test]
+    async fn test_send_misdirected_results() {
+        load_env();
+        let server = MockServer::start();
+        let shard_map = ShardMap::new(vec![server.url("/shard0"), server.url("/shard1")]);
+        let shard0_report = server.mock(|when, then| {
+            when.method(POST).path("/shard0/report");
+            then.status(421).json_body(serde_json::json!({
+                "success": false,
+                "error": "2 results belong to other scheduler shards",
+                "shard_map": &shard_map
+            }));
+        });
+        let shard1_report = server.mock(|when, then| {
+            when.method(POST).path("/shard1/report");
+            then.status(200);
+        });
+        let provider_ids = (0..100)
+            .map(|ind| format!("provider-{}", ind))
+            .collect::<Vec<String>>();
+        let shard0 = provider_ids
+            .iter()
+            .find(|id| shard_map.get_shard(id) == 0)
+            .unwrap();
+        let shard1 = provider_ids
+            .iter()
+            .find(|id| shard_map.get_shard(id) == 1)
+            .unwrap();
+        let (sender, receiver) = channel(10);
+        for (ind, provider_id) in [shard0, shard1, shard0, shard1].iter().enumerate() {
+            let mut result = mock_job_result(
+                &JobName::LatestBlock,
+                BlockChainType::Eth,
+                &format!("job_{}", ind),
+                JobRole::Regular,
+            );
+            result.provider_id = provider_id.to_string();
+            sender.send(result).await.unwrap();
+        }
+        // Worker without shard map sends all results to the first shard
+        let outboxes = Arc::new(ShardOutboxes::new(2, 10, None).unwrap());
+        let mut reporter = JobResultReporter::new(
+            receiver,
+            server.url("/shard0/report"),
+            outboxes.clone(),
+            Arc::new(ResultSigner::generate()),
+        );
+        assert_eq!(reporter.collect_results().await, 4);
+        assert_eq!(outboxes.get(0).lock().await.len(), 4);
+        // Accepted results are committed, the others are moved to their shard and delivered there
+        assert!(reporter.send_shard_batches().await);
+        shard0_report.assert();
+        shard1_report.assert();
+        assert_eq!(reporter.shard_map, shard_map);
+        assert!(outboxes.get(0).lock().await.is_empty());
+        assert!(outboxes.get(1).lock().await.is_empty());
+        assert!(reporter
+            .deliveries
+            .iter()
+            .all(|delivery| delivery.retry_at.is_none()));
+    }
+}
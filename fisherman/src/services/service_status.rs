@@ -1,4 +1,6 @@
 use crate::models::job::JobBuffer;
+use crate::models::outbox::ShardOutboxes;
+use crate::services::execution::SchedulingLags;
 use common::jobs::JobResult;
 use common::workers::WorkerStatus;
 use common::COMMON_CONFIG;
@@ -13,14 +15,23 @@ pub struct WorkerStatusCheck {
     worker_status: Arc<RwLock<WorkerStatus>>,
     sender: Sender<JobResult>,
     job_buffer: Arc<Mutex<JobBuffer>>,
+    outbox: Arc<ShardOutboxes>,
+    scheduling_lags: SchedulingLags,
 }
 
 impl WorkerStatusCheck {
-    pub fn new(sender: Sender<JobResult>, job_buffer: Arc<Mutex<JobBuffer>>) -> Self {
+    pub fn new(
+        sender: Sender<JobResult>,
+        job_buffer: Arc<Mutex<JobBuffer>>,
+        outbox: Arc<ShardOutboxes>,
+        scheduling_lags: SchedulingLags,
+    ) -> Self {
         WorkerStatusCheck {
             worker_status: Arc::new(RwLock::new(WorkerStatus::default())),
             sender,
             job_buffer,
+            outbox,
+            scheduling_lags,
         }
     }
 }
@@ -39,9 +50,13 @@ impl WorkerStatusCheck {
         let jobs_number_in_queue = job_buffers.len();
         let jobs_stat = job_buffers.statistic_jobs();
         let reports_number_in_queue = self.sender.max_capacity() - self.sender.capacity();
+        let reports_number_in_outbox = self.outbox.len().await;
+        let scheduling_lags = self.scheduling_lags.lock().unwrap().clone();
         let worker_status = WorkerStatus {
             jobs_number_in_queue,
             reports_number_in_queue,
+            reports_number_in_outbox,
+            scheduling_lags,
             jobs_stat,
         };
         debug!("Worker status: {:?}", &worker_status);
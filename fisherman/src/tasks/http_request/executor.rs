@@ -2,9 +2,12 @@ use anyhow::Error;
 use async_trait::async_trait;
 use common::job_manage::{JobDetail, JobResultDetail};
 use common::jobs::{Job, JobResult};
+use common::models::{ResponseValues, ValuePath};
 use common::tasks::executor::TaskExecutor;
 use common::tasks::http_request::{
     HttpRequestError, HttpResponseValues, JobHttpResponse, JobHttpResponseDetail, JobHttpResult,
+    JsonRpcCall, BATCH_ERROR_NUMBER_KEY, BATCH_RESPONSE_NUMBER_KEY, BATCH_SIZE_KEY,
+    BATCH_UNKNOWN_IDS_KEY,
 };
 use common::util::{get_current_time, remove_break_line};
 use common::WorkerId;
@@ -66,9 +69,12 @@ impl HttpRequestExecutor {
             //     .await
             //     .map_err(|err| HttpRequestError::GetBodyError(format!("{}", err)))?;
 
-            let response_detail = self
-                .parse_response(resp, &request.response_type, &request.response_values)
-                .await;
+            let response_detail = if request.is_batch() {
+                self.parse_batch_response(resp, &request.batch).await
+            } else {
+                self.parse_response(resp, &request.response_type, &request.response_values)
+                    .await
+            };
             let response_duration = get_current_time() - request_time;
             match response_detail {
                 Ok(detail) => Ok(JobHttpResponse {
@@ -101,7 +107,7 @@ impl HttpRequestExecutor {
         &self,
         response: Response,
         response_type: &String,
-        values: &HashMap<String, Vec<Value>>,
+        values: &HashMap<String, ValuePath>,
     ) -> Result<JobHttpResponseDetail, HttpRequestError> {
         let response_detail = match response_type.as_str() {
             "json" => response
@@ -121,28 +127,102 @@ impl HttpRequestExecutor {
     fn extract_values(
         &self,
         content: String,
-        values: &HashMap<String, Vec<Value>>,
+        values: &HashMap<String, ValuePath>,
+    ) -> Result<JobHttpResponseDetail, HttpRequestError> {
+        let results = ResponseValues::extract_values(&content, values)
+            .map(|values| HttpResponseValues::new(values.into_inner()))
+            .map_err(|err| HttpRequestError::GetBodyError(format!("{}", err)))?;
+        debug!("Extracted result values {:?}", &results);
+        Ok(JobHttpResponseDetail::Values(results))
+    }
+    async fn parse_batch_response(
+        &self,
+        response: Response,
+        batch: &Vec<JsonRpcCall>,
+    ) -> Result<JobHttpResponseDetail, HttpRequestError> {
+        let response_detail = response
+            .text()
+            .await
+            .map_err(|err| HttpRequestError::SendError(format!("{}", err)))
+            .and_then(|content| self.extract_batch_values(content, batch));
+        log::trace!("Extracted batch response detail {:?}", response_detail);
+        response_detail
+    }
+    /*
+     * Match batch responses with calls by id.
+     * Values of each call are stored with key "{name}.{value}", error with key "{name}.error".
+     * A gateway may reject the whole batch with a single object, then all calls get its error.
+     */
+    fn extract_batch_values(
+        &self,
+        content: String,
+        batch: &Vec<JsonRpcCall>,
     ) -> Result<JobHttpResponseDetail, HttpRequestError> {
-        // get result
         let body: Value = serde_json::from_str(&content).map_err(|e| {
             HttpRequestError::GetBodyError(format!("Err {} when parsing response", e))
         })?;
-        let mut results = HttpResponseValues::default();
-        for (key, paths) in values.iter() {
-            let mut ind = 0_usize;
-            let mut tmp_value = &body;
-            while ind < paths.len() {
-                let field: &Value = paths.get(ind).unwrap();
-                if field.is_string() && tmp_value.is_object() {
-                    tmp_value = &tmp_value[field.as_str().unwrap()]
-                } else if field.is_number() && tmp_value.is_array() {
-                    tmp_value = &tmp_value[field.as_u64().unwrap() as usize]
+        let mut responses: HashMap<u64, &Value> = HashMap::new();
+        let mut unknown_ids = Vec::new();
+        let batch_error = match &body {
+            Value::Array(items) => {
+                for item in items.iter() {
+                    let id = item["id"]
+                        .as_u64()
+                        .filter(|id| batch.iter().any(|call| call.id == *id));
+                    match id {
+                        Some(id) if !responses.contains_key(&id) => {
+                            responses.insert(id, item);
+                        }
+                        _ => unknown_ids.push(item["id"].clone()),
+                    }
                 }
-                ind = ind + 1;
+                None
+            }
+            other => Some(
+                other
+                    .get("error")
+                    .cloned()
+                    .unwrap_or(Value::from("Batch response is not an array")),
+            ),
+        };
+        let mut results = HttpResponseValues::default();
+        let mut error_number = 0_u64;
+        for call in batch.iter() {
+            let error = match responses.get(&call.id) {
+                Some(response) => match response.get("error") {
+                    Some(error) if !error.is_null() => Some(error.clone()),
+                    _ => match ResponseValues::extract_from_value(response, &call.values) {
+                        Ok(values) => {
+                            for (key, value) in values.into_inner() {
+                                results.insert(call.get_value_key(&key), value);
+                            }
+                            None
+                        }
+                        Err(err) => Some(Value::from(err.to_string())),
+                    },
+                },
+                None => Some(
+                    batch_error
+                        .clone()
+                        .unwrap_or(Value::from("Missing response")),
+                ),
+            };
+            if let Some(error) = error {
+                error_number += 1;
+                results.insert(call.get_error_key(), error);
             }
-            results.insert(key.clone(), tmp_value.clone());
         }
-        debug!("Extracted result values {:?}", &results);
+        results.insert(BATCH_SIZE_KEY.to_string(), Value::from(batch.len()));
+        results.insert(
+            BATCH_RESPONSE_NUMBER_KEY.to_string(),
+            Value::from(responses.len()),
+        );
+        results.insert(
+            BATCH_ERROR_NUMBER_KEY.to_string(),
+            Value::from(error_number),
+        );
+        results.insert(BATCH_UNKNOWN_IDS_KEY.to_string(), Value::Array(unknown_ids));
+        debug!("Extracted batch values {:?}", &results);
         Ok(JobHttpResponseDetail::Values(results))
     }
 }
@@ -406,6 +486,75 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_extract_values_json_path() -> Result<(), Error> {
+        let executor = new_executor();
+        let content = r###"{"jsonrpc": "2.0", "id": 1, "result": {"number": "0xe2e63a", "transactions": ["0x4e3a", "0x00e0"]}}"###;
+        let values = serde_json::from_value(serde_json::json!({
+          "number": "$.result.number",
+          "tx_count": "$.result.transactions.length()"
+        }))?;
+        let res = executor.extract_values(content.to_string(), &values)?;
+        if let JobHttpResponseDetail::Values(res) = res {
+            assert_eq!(res["number"], "0xe2e63a");
+            assert_eq!(res["tx_count"], 2);
+        } else {
+            panic!("False extract_values");
+        }
+        // Unresolved path is an extraction error instead of null value
+        let values = serde_json::from_value(serde_json::json!({
+          "hash": ["result", "hash"]
+        }))?;
+        match executor.extract_values(content.to_string(), &values) {
+            Err(HttpRequestError::GetBodyError(message)) => assert_eq!(
+                message,
+                r#"Cannot extract value hash: Path ["result","hash"] does not resolve at ["result","hash"]"#
+            ),
+            res => panic!("Unexpected extract_values result {:?}", res),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_batch_values() -> Result<(), Error> {
+        let executor = new_executor();
+        let batch: Vec<JsonRpcCall> = serde_json::from_value(serde_json::json!([
+            {"id": 1, "name": "block_number", "method": "eth_blockNumber", "values": {"number": ["result"]}},
+            {"id": 2, "name": "chain_id", "method": "eth_chainId", "values": {"chain_id": ["result"]}},
+            {"id": 3, "name": "peer_count", "method": "net_peerCount", "values": {"peers": ["result"]}}
+        ]))?;
+        // Reordered responses, one failed call, one missing response and an unknown id
+        let content = r###"[
+            {"jsonrpc": "2.0", "id": 2, "error": {"code": -32601, "message": "Method not found"}},
+            {"jsonrpc": "2.0", "id": 1, "result": "0xe2e63a"},
+            {"jsonrpc": "2.0", "id": 9, "result": "0x1"}
+        ]"###;
+        let res = executor.extract_batch_values(content.to_string(), &batch)?;
+        let values = match res {
+            JobHttpResponseDetail::Values(values) => values,
+            _ => panic!("False extract_batch_values"),
+        };
+        assert_eq!(values["block_number.number"], "0xe2e63a");
+        assert_eq!(values["chain_id.error"]["code"], -32601);
+        assert_eq!(values["peer_count.error"], "Missing response");
+        assert_eq!(values.get_batch_size(), 3);
+        assert_eq!(values.get_batch_response_number(), 2);
+        assert_eq!(values.get_batch_error_number(), 2);
+        assert_eq!(values.get_batch_unknown_ids(), vec![serde_json::json!(9)]);
+        assert_eq!(values.get_batch_errors().len(), 2);
+
+        // Gateway rejects the whole batch
+        let content = r###"{"jsonrpc": "2.0", "id": null, "error": {"code": -32600, "message": "Batch too large"}}"###;
+        let res = executor.extract_batch_values(content.to_string(), &batch)?;
+        if let JobHttpResponseDetail::Values(values) = res {
+            assert_eq!(values.get_batch_error_number(), 3);
+            assert_eq!(values["block_number.error"]["message"], "Batch too large");
+        } else {
+            panic!("False extract_batch_values");
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_call_http_request() -> Result<(), Error> {
         let executor = new_executor();
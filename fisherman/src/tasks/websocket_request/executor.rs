@@ -11,7 +11,7 @@ use common::tasks::websocket_request::{
     JobWebsocket, JobWebsocketResponse, JobWebsocketResponseDetail,
 };
 use common::util::get_current_time;
-use common::WorkerId;
+use common::{Timestamp, WorkerId};
 use log::{debug, error, trace};
 
 use tokio::sync::mpsc::Sender;
@@ -32,6 +32,27 @@ impl WebsocketRequestExecutor {
         }
     }
 
+    /*
+     * A message without some configured response value is reported as a body error,
+     * the same way the http executor does, instead of an empty values map.
+     */
+    fn parse_text_message(
+        request: &JobWebsocket,
+        request_timestamp: Timestamp,
+        mess: &str,
+    ) -> Result<JobWebsocketResponse, HttpRequestError> {
+        let response_timestamp = get_current_time();
+        let response_values = ResponseValues::extract_values(mess, &request.response_values)
+            .map_err(|err| HttpRequestError::GetBodyError(format!("{}", err)))?;
+        Ok(JobWebsocketResponse {
+            request_timestamp,
+            response_duration: response_timestamp - request_timestamp,
+            detail: JobWebsocketResponseDetail::Values(response_values),
+            error_code: 0,
+            message: "".to_string(),
+        })
+    }
+
     pub async fn call_websocket_request(
         &self,
         request: &JobWebsocket,
@@ -129,17 +150,7 @@ impl WebsocketRequestExecutor {
             };
             let response = if let OwnedMessage::Text(mess) = received_message {
                 debug!("Received Socket message from provider {:?}", &request.url);
-                let response_timestamp = get_current_time();
-                let response_values =
-                    ResponseValues::extract_values(mess.as_str(), &request.response_values)
-                        .unwrap_or_default();
-                Ok(JobWebsocketResponse {
-                    request_timestamp,
-                    response_duration: response_timestamp - request_timestamp,
-                    detail: JobWebsocketResponseDetail::Values(response_values),
-                    error_code: 0,
-                    message: "".to_string(),
-                })
+                Self::parse_text_message(request, request_timestamp, mess.as_str())
             } else {
                 Err(HttpRequestError::GetBodyError(
                     "Invalid response data".to_string(),
@@ -220,6 +231,7 @@ mod tests {
     use crate::tasks::WebsocketRequestExecutor;
     use common::component::ComponentInfo;
 
+    use common::tasks::http_request::HttpRequestError;
     use common::tasks::websocket_request::{JobWebsocket, JobWebsocketResponseDetail};
     use common::BlockChainType;
     use serde_json::Value;
@@ -287,6 +299,26 @@ mod tests {
                 "hash": ["result", "hash"]
             }
         }"#;
+    #[test]
+    fn test_parse_text_message() {
+        let job_websocket = new_test_job(ETH_REQUEST);
+        let message = r#"{"jsonrpc":"2.0","id":1,"result":{"number":"0xa7e964","hash":"0x67de","timestamp":"0x62cbc6f3"}}"#;
+        let response =
+            WebsocketRequestExecutor::parse_text_message(&job_websocket, 0, message).unwrap();
+        match response.detail {
+            JobWebsocketResponseDetail::Values(values) => {
+                assert_eq!(values.get("number"), Some(&Value::from("0xa7e964")));
+            }
+            JobWebsocketResponseDetail::Body(_) => panic!("Expected extracted values"),
+        }
+        // Missing "hash" must fail the job instead of reporting partial values
+        let message =
+            r#"{"jsonrpc":"2.0","id":1,"result":{"number":"0xa7e964","timestamp":"0x62cbc6f3"}}"#;
+        let err =
+            WebsocketRequestExecutor::parse_text_message(&job_websocket, 0, message).unwrap_err();
+        assert!(matches!(err, HttpRequestError::GetBodyError(_)));
+        assert!(err.get_message().contains("hash"));
+    }
     #[ignore]
     #[tokio::test]
     async fn test_eth_gateway_websocket() {
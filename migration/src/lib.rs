@@ -2,6 +2,10 @@ pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
 mod m20220707_add_table_provider_latest_block;
+mod m20221018_add_column_worker_secret;
+mod m20221019_add_column_worker_public_key;
+mod m20221020_add_column_worker_delivery_mode;
+mod m20221021_add_column_job_resource_class;
 pub struct Migrator;
 
 #[async_trait::async_trait]
@@ -10,6 +14,10 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20220707_add_table_provider_latest_block::Migration),
+            Box::new(m20221018_add_column_worker_secret::Migration),
+            Box::new(m20221019_add_column_worker_public_key::Migration),
+            Box::new(m20221020_add_column_worker_delivery_mode::Migration),
+            Box::new(m20221021_add_column_job_resource_class::Migration),
         ]
     }
 }
@@ -1,57 +1,303 @@
-use crate::server_builder::SimpleResponse;
-use crate::service::ProcessorService;
-use crate::state::ProcessorState;
-use crate::SCHEDULER_AUTHORIZATION;
+use crate::server_builder::{PullJobsParam, SimpleResponse};
+use crate::service::config_reload::TaskConfigReloader;
+use crate::service::{ProcessorService, WebService};
+use crate::state::{ProcessorState, SchedulerState};
+use crate::{
+    PULL_JOBS_MAX_WAIT, REPORT_PROCESS_MAX_TASKS, SCHEDULER_AUTHORIZATION, SCHEDULER_SHARD_INDEX,
+    SHARD_MAP,
+};
 use common::jobs::JobResult;
 use common::task_spawn::spawn;
-use log::info;
+use common::workers::WorkerInfo;
+use common::{JobId, WorkerId};
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use serde_json::json;
 use std::convert::Infallible;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
 use tokio::time::Instant;
+use warp::http::header::RETRY_AFTER;
+use warp::hyper::body::Bytes;
 use warp::{http::StatusCode, reject, Rejection, Reply};
 
 #[derive(Debug)]
 pub struct UnAuthorization;
 impl reject::Reject for UnAuthorization {}
 
+#[derive(Debug)]
+pub struct InvalidSignature;
+impl reject::Reject for InvalidSignature {}
+
+#[derive(Debug)]
+pub struct InvalidBody(String);
+impl reject::Reject for InvalidBody {}
+
+#[derive(Debug)]
+pub struct NotLeader;
+impl reject::Reject for NotLeader {}
+
 pub static PROCESS_THREAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+//Seconds a worker waits before sending refused results again
+const REPORT_RETRY_AFTER: u64 = 5;
+lazy_static! {
+    static ref REPORT_PROCESS_PERMITS: Arc<Semaphore> =
+        Arc::new(Semaphore::new(*REPORT_PROCESS_MAX_TASKS));
+}
 pub async fn handle_route_reports(
     service: Arc<ProcessorService>,
     state: Arc<ProcessorState>,
-    job_results: Vec<JobResult>,
+    body: Bytes,
     authorization: String,
+    worker_id: Option<String>,
+    signature: Option<String>,
 ) -> Result<impl Reply, warp::Rejection> {
+    if authorization != *SCHEDULER_AUTHORIZATION {
+        return Err(warp::reject::custom(UnAuthorization));
+    }
+    let job_results: Vec<JobResult> = serde_json::from_slice(&body)
+        .map_err(|err| warp::reject::custom(InvalidBody(err.to_string())))?;
     info!(
         "#### Received {:?} reports request body  ####",
         &job_results.len()
     );
-    if authorization == *SCHEDULER_AUTHORIZATION {
-        let clone_service = service.clone();
-        let clone_state = state.clone();
-        spawn(async move {
-            PROCESS_THREAD_COUNT.fetch_add(1, Ordering::Relaxed);
-            let job_results_len = job_results.len();
-            let now = Instant::now();
-            let thread_id = PROCESS_THREAD_COUNT.load(Ordering::Relaxed);
-            info!(
-                "** Start {}th thread to process {} job results **",
-                thread_id, job_results_len
-            );
-            let res = clone_service.process_report(job_results, clone_state).await;
-            info!(
-                "** Finished {}th thread to process {} job results in {:.2?} with res: {:?} **",
-                thread_id,
-                job_results_len,
-                now.elapsed(),
-                res
+    let worker_id = worker_id.unwrap_or_default();
+    if let Err(err) = state
+        .verify_results(
+            &worker_id,
+            signature.as_deref().unwrap_or_default(),
+            &body,
+            &job_results,
+        )
+        .await
+    {
+        warn!(
+            "Reject {} results from worker {}: {:?}",
+            job_results.len(),
+            &worker_id,
+            err
+        );
+        return Err(warp::reject::custom(InvalidSignature));
+    }
+    //Results of other shards are sent by workers with an outdated shard map
+    let (job_results, foreign_results): (Vec<JobResult>, Vec<JobResult>) = job_results
+        .into_iter()
+        .partition(|result| SHARD_MAP.is_owned(&result.provider_id, *SCHEDULER_SHARD_INDEX));
+    if !foreign_results.is_empty() {
+        warn!(
+            "Refuse {} results of providers in other scheduler shards from worker {}",
+            foreign_results.len(),
+            &worker_id
+        );
+    }
+    let misdirected_error = format!(
+        "{} results belong to other scheduler shards",
+        foreign_results.len()
+    );
+    let misdirected_response = || {
+        warp::reply::with_status(
+            warp::reply::json(&json!({
+                "success": false,
+                "error": &misdirected_error,
+                "shard_map": &*SHARD_MAP
+            })),
+            StatusCode::MISDIRECTED_REQUEST,
+        )
+        .into_response()
+    };
+    if job_results.is_empty() && !foreign_results.is_empty() {
+        return Ok(misdirected_response());
+    }
+    let permit = match REPORT_PROCESS_PERMITS.clone().try_acquire_owned() {
+        Ok(permit) => permit,
+        Err(_) => {
+            warn!(
+                "Too many report processing tasks, refuse {} results from worker {}",
+                job_results.len(),
+                &worker_id
             );
-            PROCESS_THREAD_COUNT.fetch_sub(1, Ordering::Relaxed);
-        });
+            return Ok(warp::reply::with_header(
+                warp::reply::with_status(
+                    warp::reply::json(&SimpleResponse { success: false }),
+                    StatusCode::TOO_MANY_REQUESTS,
+                ),
+                RETRY_AFTER,
+                REPORT_RETRY_AFTER.to_string(),
+            )
+            .into_response());
+        }
+    };
+    let clone_service = service.clone();
+    let clone_state = state.clone();
+    spawn(async move {
+        let _permit = permit;
+        PROCESS_THREAD_COUNT.fetch_add(1, Ordering::Relaxed);
+        let job_results_len = job_results.len();
+        let now = Instant::now();
+        let thread_id = PROCESS_THREAD_COUNT.load(Ordering::Relaxed);
+        info!(
+            "** Start {}th thread to process {} job results **",
+            thread_id, job_results_len
+        );
+        let res = clone_service.process_report(job_results, clone_state).await;
+        info!(
+            "** Finished {}th thread to process {} job results in {:.2?} with res: {:?} **",
+            thread_id,
+            job_results_len,
+            now.elapsed(),
+            res
+        );
+        PROCESS_THREAD_COUNT.fetch_sub(1, Ordering::Relaxed);
+    });
 
-        Ok(warp::reply::json(&SimpleResponse { success: true }))
-    } else {
-        Err(warp::reject::custom(UnAuthorization))
+    //Owned results are processed, worker is told that the others are not
+    if !foreign_results.is_empty() {
+        return Ok(misdirected_response());
+    }
+    Ok(warp::reply::json(&SimpleResponse { success: true }).into_response())
+}
+/*
+ * Register a worker and issue a new secret.
+ * A registered worker is registered again only by a request signed with its current secret,
+ * so nobody else can take over its id and revoke its secret
+ */
+pub async fn handle_route_worker_register(
+    service: Arc<WebService>,
+    state: Arc<SchedulerState>,
+    body: Bytes,
+    authorization: String,
+    timestamp: Option<String>,
+    signature: Option<String>,
+) -> Result<impl Reply, warp::Rejection> {
+    if authorization != *SCHEDULER_AUTHORIZATION {
+        return Err(warp::reject::custom(UnAuthorization));
+    }
+    let worker_info: WorkerInfo = serde_json::from_slice(&body)
+        .map_err(|err| warp::reject::custom(InvalidBody(err.to_string())))?;
+    info!(
+        "#### Received register worker request body {:?} ####",
+        &worker_info
+    );
+    if state.is_registered_worker(&worker_info.worker_id).await {
+        if let Err(err) = state
+            .authenticate_worker(
+                &worker_info.worker_id,
+                "worker/register",
+                timestamp.as_deref(),
+                signature.as_deref(),
+                &body,
+            )
+            .await
+        {
+            warn!(
+                "Reject registration of registered worker {}: {:?}",
+                &worker_info.worker_id, err
+            );
+            return Err(warp::reject::custom(InvalidSignature));
+        }
+    }
+    service.register_worker(worker_info, state).await
+}
+pub async fn handle_route_worker_jobs(
+    state: Arc<SchedulerState>,
+    worker_id: WorkerId,
+    param: PullJobsParam,
+    authorization: String,
+    timestamp: Option<String>,
+    signature: Option<String>,
+) -> Result<impl Reply, warp::Rejection> {
+    if authorization != *SCHEDULER_AUTHORIZATION {
+        return Err(warp::reject::custom(UnAuthorization));
+    }
+    //Only the leader tracks polls and pending jobs of pull workers
+    if !state.is_leader() {
+        return Err(warp::reject::custom(NotLeader));
+    }
+    let path = format!("worker/{}/jobs", &worker_id);
+    if let Err(err) = state
+        .authenticate_worker(
+            &worker_id,
+            &path,
+            timestamp.as_deref(),
+            signature.as_deref(),
+            &[],
+        )
+        .await
+    {
+        warn!("Reject jobs poll from worker {}: {:?}", &worker_id, err);
+        return Err(warp::reject::custom(InvalidSignature));
+    }
+    let wait = param
+        .wait
+        .unwrap_or(*PULL_JOBS_MAX_WAIT)
+        .min(*PULL_JOBS_MAX_WAIT);
+    let result = state.poll_jobs(&worker_id, Duration::from_secs(wait)).await;
+    Ok(warp::reply::json(&result))
+}
+pub async fn handle_route_worker_jobs_ack(
+    state: Arc<SchedulerState>,
+    worker_id: WorkerId,
+    body: Bytes,
+    authorization: String,
+    timestamp: Option<String>,
+    signature: Option<String>,
+) -> Result<impl Reply, warp::Rejection> {
+    if authorization != *SCHEDULER_AUTHORIZATION {
+        return Err(warp::reject::custom(UnAuthorization));
+    }
+    //Only the leader tracks polls and pending jobs of pull workers
+    if !state.is_leader() {
+        return Err(warp::reject::custom(NotLeader));
+    }
+    let path = format!("worker/{}/jobs/ack", &worker_id);
+    if let Err(err) = state
+        .authenticate_worker(
+            &worker_id,
+            &path,
+            timestamp.as_deref(),
+            signature.as_deref(),
+            &body,
+        )
+        .await
+    {
+        warn!("Reject jobs ack from worker {}: {:?}", &worker_id, err);
+        return Err(warp::reject::custom(InvalidSignature));
+    }
+    let job_ids: Vec<JobId> = serde_json::from_slice(&body)
+        .map_err(|err| warp::reject::custom(InvalidBody(err.to_string())))?;
+    match state.ack_jobs(&worker_id, &job_ids).await {
+        Ok(acknowledged) => Ok(warp::reply::json(
+            &json!({ "success": true, "acknowledged": acknowledged }),
+        )),
+        Err(err) => {
+            error!("Ack jobs of worker {} error: {:?}", &worker_id, err);
+            Ok(warp::reply::json(
+                &json!({ "success": false, "error": err.to_string() }),
+            ))
+        }
+    }
+}
+pub async fn handle_route_reload_configs(
+    reloader: Arc<TaskConfigReloader>,
+    authorization: String,
+) -> Result<impl Reply, warp::Rejection> {
+    if authorization != *SCHEDULER_AUTHORIZATION {
+        return Err(warp::reject::custom(UnAuthorization));
+    }
+    match reloader.reload().await {
+        Ok(changes) => Ok(warp::reply::with_status(
+            warp::reply::json(&json!({ "success": true, "changes": changes })),
+            StatusCode::OK,
+        )),
+        Err(err) => {
+            error!("Reload task configs error: {:?}", err);
+            Ok(warp::reply::with_status(
+                warp::reply::json(&json!({ "success": false, "error": err.to_string() })),
+                StatusCode::BAD_REQUEST,
+            ))
+        }
     }
 }
 pub async fn handle_rejection(err: Rejection) -> std::result::Result<impl Reply, Infallible> {
@@ -69,6 +315,21 @@ pub async fn handle_rejection(err: Rejection) -> std::result::Result<impl Reply,
             StatusCode::UNAUTHORIZED,
             format!("Authorization error, {:?}", err),
         )
+    } else if err.find::<InvalidSignature>().is_some() {
+        (
+            StatusCode::UNAUTHORIZED,
+            format!("Signature error, {:?}", err),
+        )
+    } else if err.find::<NotLeader>().is_some() {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Jobs are served by the leader scheduler".to_string(),
+        )
+    } else if let Some(InvalidBody(message)) = err.find::<InvalidBody>() {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid request body, {}", message),
+        )
     } else {
         eprintln!("unhandled error: {:?}", err);
         (
@@ -1,7 +1,8 @@
 extern crate diesel;
 extern crate diesel_migrations;
 
-use common::Scheme;
+use common::shard::ShardMap;
+use common::{Scheme, Timestamp};
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 use std::env;
@@ -18,6 +19,8 @@ pub mod state;
 pub mod tasks;
 
 use crate::tasks::benchmark::generator::BenchmarkConfig;
+use crate::tasks::dot::generator::SubstrateConfig;
+use crate::tasks::eth::random_block::generator::RandomBlockConfig;
 use anyhow::anyhow;
 use common::tasks::http_request::HttpRequestJobConfig;
 use common::tasks::websocket_request::JobWebsocketConfig;
@@ -55,6 +58,8 @@ lazy_static! {
     pub static ref CONFIG_HTTP_REQUEST_DIR: String = String::from("http_request");
     pub static ref CONFIG_BENCHMARK_DIR: String = String::from("benchmark");
     pub static ref CONFIG_WEBSOCKET_DIR: String = String::from("websocket");
+    pub static ref CONFIG_RANDOM_BLOCK_DIR: String = String::from("random_block");
+    pub static ref CONFIG_SUBSTRATE_DIR: String = String::from("substrate");
     pub static ref PORTAL_AUTHORIZATION: String =
         env::var("PORTAL_AUTHORIZATION").expect("There is no env var PORTAL_AUTHORIZATION");
     pub static ref SCHEDULER_AUTHORIZATION: String =
@@ -83,6 +88,46 @@ lazy_static! {
         env::var("WORKER_PATH_JOBS_UPDATE").unwrap_or_else(|_| String::from("jobs_update"));
     pub static ref WORKER_PATH_JOB_UPDATE: String =
         env::var("WORKER_PATH_GET_STATE").unwrap_or_else(|_| String::from("get_state"));
+    //Failed deliveries to a worker before its job is reassigned to another worker
+    pub static ref JOB_DELIVERY_MAX_ATTEMPTS: u32 =
+        env::var("JOB_DELIVERY_MAX_ATTEMPTS").ok().and_then(|val| val.parse::<u32>().ok()).unwrap_or(3);
+    //Delay in milliseconds before the first retry of a failed delivery, it is doubled for each next retry
+    pub static ref JOB_DELIVERY_RETRY_DELAY: Timestamp =
+        env::var("JOB_DELIVERY_RETRY_DELAY").ok().and_then(|val| val.parse::<Timestamp>().ok()).unwrap_or(10000);
+    //Max benchmark jobs without result on a worker, workers over the cap do not get new benchmark jobs
+    pub static ref BENCHMARK_MAX_JOBS_PER_WORKER: usize =
+        env::var("BENCHMARK_MAX_JOBS_PER_WORKER").ok().and_then(|val| val.parse::<usize>().ok()).unwrap_or(1);
+    //Max time in seconds a pull mode worker waits for new jobs in a poll request,
+    //it must be shorter than update_worker_list_interval because polls are used as worker heartbeats
+    pub static ref PULL_JOBS_MAX_WAIT: u64 =
+        env::var("PULL_JOBS_MAX_WAIT").ok().and_then(|val| val.parse::<u64>().ok()).unwrap_or(20);
+    //Time in seconds before polled but unacknowledged jobs are returned to the pending list
+    pub static ref PULL_JOBS_ACK_TIMEOUT: u64 =
+        env::var("PULL_JOBS_ACK_TIMEOUT").ok().and_then(|val| val.parse::<u64>().ok()).unwrap_or(60);
+    //Max difference in milliseconds between timestamp of a signed worker request and local time
+    pub static ref REQUEST_MAX_CLOCK_SKEW: i64 =
+        env::var("REQUEST_MAX_CLOCK_SKEW").ok().and_then(|val| val.parse::<i64>().ok()).unwrap_or(30000);
+    //Seconds between leader lock attempts and between leader heartbeats, leader lock is released after 3 missed heartbeats
+    pub static ref LEADER_ELECTION_PERIOD: u64 =
+        env::var("LEADER_ELECTION_PERIOD").ok().and_then(|val| val.parse::<u64>().ok()).unwrap_or(5);
+    //Postgres advisory lock key of leader election, scheduler instances with the same key share one leader
+    pub static ref LEADER_LOCK_KEY: i64 =
+        env::var("LEADER_LOCK_KEY").ok().and_then(|val| val.parse::<i64>().ok()).unwrap_or(20221021);
+    //Base urls of scheduler shards in shard index order separated by comma, providers are partitioned by hash of their id.
+    //Each shard is a group of instances with its own leader, empty for a single shard
+    pub static ref SHARD_MAP: ShardMap = ShardMap::new(
+        env::var("SCHEDULER_SHARDS").unwrap_or_default().split(',').map(|url| url.trim().to_string()).filter(|url| !url.is_empty()).collect());
+    pub static ref SCHEDULER_SHARD_INDEX: usize =
+        env::var("SCHEDULER_SHARD_INDEX").ok().and_then(|val| val.parse::<usize>().ok()).unwrap_or(0);
+    //Max report batches processed at the same time, new reports are refused with 429 over the limit
+    pub static ref REPORT_PROCESS_MAX_TASKS: usize =
+        env::var("REPORT_PROCESS_MAX_TASKS").ok().and_then(|val| val.parse::<usize>().ok()).unwrap_or(64);
+    //Time in milliseconds judgments keep cached results of a provider task without new results
+    pub static ref JUDGMENT_CACHE_TTL: Timestamp =
+        env::var("JUDGMENT_CACHE_TTL").ok().and_then(|val| val.parse::<Timestamp>().ok()).unwrap_or(3600000);
+    //Interval for checking task config files, 0 for disable auto reload
+    pub static ref TASK_CONFIG_WATCH_PERIOD: u64 =
+        env::var("TASK_CONFIG_WATCH_PERIOD").ok().and_then(|val| val.parse::<u64>().ok()).unwrap_or(10);
     pub static ref IS_VERIFY_REPORT: bool =
         env::var("IS_VERIFY_REPORT").ok().and_then(|val|val.parse::<bool>().ok()).expect("There is no env var IS_VERIFY_REPORT, e.g. true");
     pub static ref IS_REGULAR_REPORT: bool =
@@ -179,3 +224,5 @@ pub trait TemplateRender {
 impl TemplateRender for JobWebsocketConfig {}
 impl TemplateRender for HttpRequestJobConfig {}
 impl TemplateRender for BenchmarkConfig {}
+impl TemplateRender for RandomBlockConfig {}
+impl TemplateRender for SubstrateConfig {}
@@ -1,22 +1,27 @@
+use clap::{Arg, Command};
 use common::logger::init_logger;
 //use diesel::r2d2::ConnectionManager;
 //use diesel::{r2d2, PgConnection};
 //use diesel_migrations::embed_migrations;
+use common::job_manage::JobRole;
 use futures_util::future::join5;
-use log::info;
+use log::{error, info};
 use scheduler::models::jobs::JobAssignmentBuffer;
 use scheduler::models::providers::ProviderStorage;
 use scheduler::models::workers::WorkerInfoStorage;
 use scheduler::provider::scanner::ProviderScanner;
 use scheduler::server_builder::ServerBuilder;
 use scheduler::server_config::AccessControl;
-use scheduler::service::delivery::{CancelPlanBuffer, JobDelivery};
+use scheduler::service::config_reload::{validate_task_configs, TaskConfigReloader};
+use scheduler::service::delivery::{CancelPlanBuffer, JobDelivery, PullJobBuffer};
 use scheduler::service::generator::JobGenerator;
+use scheduler::service::leader::LeaderElection;
+use scheduler::service::rebalancer::JobRebalancer;
 use scheduler::service::{ProcessorServiceBuilder, SchedulerServiceBuilder};
 use scheduler::state::{ProcessorState, SchedulerState};
 use scheduler::{
-    DATABASE_URL, LOG_CONFIG, SCHEDULER_ENDPOINT, SCHEDULER_MONITOR_OUTPUT, URL_GATEWAYS_LIST,
-    URL_NODES_LIST,
+    CONFIG_TASK_DIR, DATABASE_URL, LOG_CONFIG, SCHEDULER_ENDPOINT, SCHEDULER_MONITOR_OUTPUT,
+    URL_GATEWAYS_LIST, URL_NODES_LIST,
 };
 
 use migration::{Migrator, MigratorTrait};
@@ -30,7 +35,7 @@ use scheduler::service::check_worker_health::WorkerHealthService;
 use scheduler::service::service_monitor::ServiceMonitor;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tokio::task;
+use tokio::{select, task};
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
@@ -38,6 +43,28 @@ async fn main() -> Result<(), anyhow::Error> {
     if dotenv::dotenv().is_err() {
         println!("Warning: Cannot load .env file");
     }
+    let matches = Command::new("scheduler")
+        .subcommand(
+            Command::new("validate-configs")
+                .about("Validate task configs and print every rejected task")
+                .arg(Arg::new("dir").help("Task config dir, default is CONFIG_DIR/tasks")),
+        )
+        .get_matches();
+    if let Some(sub_matches) = matches.subcommand_matches("validate-configs") {
+        let config_dir = sub_matches
+            .get_one::<String>("dir")
+            .cloned()
+            .unwrap_or_else(|| CONFIG_TASK_DIR.to_string());
+        let rejected = validate_task_configs(&config_dir);
+        if rejected.is_empty() {
+            println!("All task configs in {} are valid", config_dir);
+            return Ok(());
+        }
+        for config in rejected.iter() {
+            println!("Rejected {}", config);
+        }
+        std::process::exit(1);
+    }
 
     // Init logger
     let _res = init_logger(&String::from("Fisherman Scheduler"), LOG_CONFIG.to_str());
@@ -74,6 +101,7 @@ async fn main() -> Result<(), anyhow::Error> {
     let assigment_buffer = Arc::new(Mutex::new(JobAssignmentBuffer::default()));
     let cancel_plans_buffer: Arc<Mutex<CancelPlanBuffer>> =
         Arc::new(Mutex::new(CancelPlanBuffer::default()));
+    let pull_buffer = Arc::new(PullJobBuffer::default());
 
     let scheduler_service = SchedulerServiceBuilder::default().build();
     let result_service = Arc::new(JobResultService::new(arc_conn.clone()));
@@ -94,6 +122,13 @@ async fn main() -> Result<(), anyhow::Error> {
         worker_infos.clone(),
         provider_service.clone(),
     );
+    // Move regular jobs when workers are added or removed
+    let rebalancer = Arc::new(
+        JobRebalancer::new(assigment_buffer.clone(), cancel_plans_buffer.clone())
+            .with_worker_pool(worker_infos.clone())
+            .with_providers(provider_storage.clone())
+            .with_job_service(job_service.clone()),
+    );
     let job_generator = JobGenerator::new(
         arc_conn.clone(),
         plan_service.clone(),
@@ -102,18 +137,37 @@ async fn main() -> Result<(), anyhow::Error> {
         job_service.clone(),
         assigment_buffer.clone(),
         result_cache.clone(),
+    )
+    .with_rebalancer(rebalancer.clone());
+    // Elect leader among scheduler instances sharing the database
+    let leader_election = Arc::new(
+        LeaderElection::new(arc_conn.clone())
+            .with_worker_service(worker_service.clone())
+            .with_worker_pool(worker_infos.clone())
+            .with_job_service(job_service.clone())
+            .with_pull_buffer(pull_buffer.clone()),
     );
     let scheduler_state = SchedulerState::new(
         arc_conn.clone(),
         plan_service.clone(),
-        worker_service,
+        worker_service.clone(),
         worker_infos.clone(),
         provider_storage.clone(),
-    );
-    let job_delivery = JobDelivery::new(assigment_buffer.clone(), cancel_plans_buffer.clone());
+    )
+    .with_job_service(job_service.clone())
+    .with_pull_buffer(pull_buffer.clone())
+    .with_leader_election(leader_election.clone());
+    let job_delivery = JobDelivery::new(assigment_buffer.clone(), cancel_plans_buffer.clone())
+        .with_pull_buffer(pull_buffer.clone())
+        .with_worker_pool(worker_infos.clone())
+        .with_providers(provider_storage.clone())
+        .with_job_service(job_service.clone())
+        .with_rebalancer(rebalancer.clone());
 
     // Check worker status task
-    let worker_health = WorkerHealthService::new(worker_infos.clone(), result_cache.clone());
+    let worker_health = WorkerHealthService::new(worker_infos.clone(), result_cache.clone())
+        .with_pull_buffer(pull_buffer)
+        .with_worker_service(worker_service.clone());
 
     // Check Service Info
     let service_monitor = ServiceMonitor::new(
@@ -125,12 +179,15 @@ async fn main() -> Result<(), anyhow::Error> {
         assigment_buffer.clone(),
     );
 
-    // Spawn tasks
-    let task_worker_health = task::spawn(async move { worker_health.run().await });
-    let task_provider_scanner = task::spawn(async move { provider_scanner.run().await });
-    let task_job_generator = task::spawn(async move { job_generator.run().await });
-    let task_job_delivery = task::spawn(async move { job_delivery.run().await });
-    let task_service_monitor = task::spawn(async move { service_monitor.run().await });
+    let election = leader_election.clone();
+    let _task_leader_election = task::spawn(async move { election.run().await });
+
+    // Spawn tasks of every instance
+    let job_generator_registry = job_generator.get_task_registry();
+    let job_delivery = Arc::new(job_delivery);
+    let cancel_delivery = job_delivery.clone();
+    let _task_cancel_plans = task::spawn(async move { cancel_delivery.run_cancel_plans().await });
+    let _task_service_monitor = task::spawn(async move { service_monitor.run().await });
 
     let processor_state = ProcessorState::new(
         arc_conn.clone(),
@@ -141,24 +198,50 @@ async fn main() -> Result<(), anyhow::Error> {
         worker_infos,
         cancel_plans_buffer,
     );
+    // Task configs hot reload, share task registry with generator and judgments with processors
+    let config_reloader = Arc::new(TaskConfigReloader::new(
+        CONFIG_TASK_DIR.as_str(),
+        job_generator_registry,
+        processor_state.get_judgments(&JobRole::Regular),
+        processor_state.get_judgments(&JobRole::Verification),
+    ));
+    let watch_reloader = config_reloader.clone();
+    let _task_config_watcher = task::spawn(async move { watch_reloader.watch().await });
     info!("Init http service ");
     let server = ServerBuilder::default()
         .with_entry_point(socket_addr)
         .with_access_control(access_control)
         .with_scheduler_state(scheduler_state)
         .with_processor_state(processor_state)
+        .with_config_reloader(config_reloader)
         .build(scheduler_service, processor_service);
 
     let _task_serve = tokio::spawn(async move { server.serve().await });
+
+    // Only the leader scans providers, generates and delivers jobs, followers serve reports and registrations
+    leader_election.wait_leadership(true).await;
+    info!("Scheduler becomes leader");
+    leader_election.take_over().await;
+    let task_worker_health = task::spawn(async move { worker_health.run().await });
+    let task_provider_scanner = task::spawn(async move { provider_scanner.run().await });
+    let task_job_generator = task::spawn(async move { job_generator.run().await });
+    let task_job_delivery = task::spawn(async move { job_delivery.run_assignments().await });
+    let task_rebalancer = task::spawn(async move { rebalancer.run().await });
     // Run all spawn task
-    let _res = join5(
-        task_provider_scanner,
-        task_job_generator,
-        task_job_delivery,
-        task_worker_health,
-        task_service_monitor,
-    )
-    .await;
+    select! {
+        _res = join5(
+            task_provider_scanner,
+            task_job_generator,
+            task_job_delivery,
+            task_worker_health,
+            task_rebalancer,
+        ) => {}
+        // In memory state of a former leader is stale, restart as a follower
+        _ = leader_election.wait_leadership(false) => {
+            error!("Scheduler loses leadership, exit");
+            std::process::exit(1);
+        }
+    }
 
     Ok(())
 }
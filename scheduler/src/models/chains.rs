@@ -0,0 +1,199 @@
+use crate::service::comparator::ComparatorKind;
+use anyhow::anyhow;
+use common::component::BlockChainFamily;
+use common::{BlockChainType, Timestamp};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+pub const CHAIN_REGISTRY_FILE: &str = "chain_type.json";
+
+/*
+ * Description of a supported chain, new chains are added to chain_type.json in task config dir
+ */
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ChainSpec {
+    pub id: String,
+    pub family: BlockChainFamily,
+    //Average block time in milliseconds
+    pub block_time: Timestamp,
+    pub comparator: ComparatorKind,
+    //Default rpc methods by usage, task configs use them as {{chain.rpc_methods.latest_block}}
+    #[serde(default)]
+    pub rpc_methods: HashMap<String, String>,
+}
+
+impl ChainSpec {
+    fn new(
+        id: &str,
+        family: BlockChainFamily,
+        block_time: Timestamp,
+        comparator: ComparatorKind,
+        rpc_methods: &[(&str, &str)],
+    ) -> Self {
+        ChainSpec {
+            id: id.to_string(),
+            family,
+            block_time,
+            comparator,
+            rpc_methods: rpc_methods
+                .iter()
+                .map(|(usage, method)| (usage.to_string(), method.to_string()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ChainRegistryConfig {
+    chains: Vec<ChainSpec>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ChainRegistry {
+    chains: HashMap<String, ChainSpec>,
+}
+
+impl ChainRegistry {
+    pub fn new(chains: Vec<ChainSpec>) -> Self {
+        ChainRegistry {
+            chains: chains
+                .into_iter()
+                .map(|chain| (chain.id.clone(), chain))
+                .collect(),
+        }
+    }
+    /*
+     * Chains supported before the registry, they are kept if chain_type.json does not describe them
+     */
+    pub fn builtin() -> Self {
+        let eth_methods = [
+            ("latest_block", "eth_getBlockByNumber"),
+            ("peer_count", "net_peerCount"),
+        ];
+        ChainRegistry::new(vec![
+            ChainSpec::new(
+                "eth",
+                BlockChainFamily::Ethereum,
+                12000,
+                ComparatorKind::BlockTimestamp,
+                &eth_methods,
+            ),
+            ChainSpec::new(
+                "bsc",
+                BlockChainFamily::Ethereum,
+                3000,
+                ComparatorKind::BlockTimestamp,
+                &eth_methods,
+            ),
+            ChainSpec::new(
+                "matic",
+                BlockChainFamily::Ethereum,
+                2000,
+                ComparatorKind::BlockTimestamp,
+                &eth_methods,
+            ),
+            ChainSpec::new(
+                "dot",
+                BlockChainFamily::Polkadot,
+                6000,
+                ComparatorKind::BlockNumber,
+                &[("latest_block", "chain_getBlock")],
+            ),
+        ])
+    }
+    pub fn load(config_dir: &str) -> Result<Self, anyhow::Error> {
+        let path = Path::new(config_dir).join(CHAIN_REGISTRY_FILE);
+        let json = std::fs::read_to_string(&path)
+            .map_err(|err| anyhow!("Error {:?}. Path not found {:?}", err, path))?;
+        let config: ChainRegistryConfig =
+            serde_json::from_str(&json).map_err(|err| anyhow!("Invalid {:?}: {}", path, err))?;
+        let mut registry = ChainRegistry::builtin();
+        for mut chain in config.chains {
+            chain.id = chain.id.to_lowercase();
+            if chain.id.is_empty() || chain.id.contains('.') {
+                return Err(anyhow!("Invalid chain id {:?} in {:?}", chain.id, path));
+            }
+            registry.chains.insert(chain.id.clone(), chain);
+        }
+        BlockChainType::register_chains(registry.chains.keys().cloned());
+        Ok(registry)
+    }
+    /*
+     * Load chain registry, built in chains are used if chain_type.json is invalid
+     */
+    pub fn load_or_builtin(config_dir: &str) -> Self {
+        ChainRegistry::load(config_dir).unwrap_or_else(|err| {
+            warn!("Cannot load chain registry: {:?}, use built in chains", err);
+            ChainRegistry::builtin()
+        })
+    }
+    pub fn get(&self, chain: &BlockChainType) -> Option<&ChainSpec> {
+        self.chains.get(&chain.to_string())
+    }
+    pub fn get_chains(&self) -> Vec<&ChainSpec> {
+        self.chains.values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CONFIG_TASK_DIR;
+    use std::str::FromStr;
+    use test_util::helper::load_env;
+
+    #[test]
+    fn test_load_chain_registry() -> Result<(), anyhow::Error> {
+        load_env();
+        let registry = ChainRegistry::load(CONFIG_TASK_DIR.as_str())?;
+        for (chain, family, comparator) in [
+            (
+                BlockChainType::Eth,
+                BlockChainFamily::Ethereum,
+                ComparatorKind::BlockTimestamp,
+            ),
+            (
+                BlockChainType::Bsc,
+                BlockChainFamily::Ethereum,
+                ComparatorKind::BlockTimestamp,
+            ),
+            (
+                BlockChainType::Matic,
+                BlockChainFamily::Ethereum,
+                ComparatorKind::BlockTimestamp,
+            ),
+            (
+                BlockChainType::Dot,
+                BlockChainFamily::Polkadot,
+                ComparatorKind::BlockNumber,
+            ),
+        ] {
+            let spec = registry.get(&chain).unwrap();
+            assert_eq!(spec.id, chain.to_string());
+            assert_eq!(spec.family, family);
+            assert_eq!(spec.comparator, comparator);
+        }
+        // Matic is not labelled as bsc
+        assert_eq!(BlockChainType::from_str("matic")?, BlockChainType::Matic);
+        assert_eq!(BlockChainType::Matic.to_string(), "matic");
+        // Chains out of the registry are not supported
+        assert!(BlockChainType::from_str("unknown").is_err());
+        let unknown = BlockChainType::Other("unknown".to_string());
+        assert!(registry.get(&unknown).is_none());
+        // Chains added to the registry are parsed by their id
+        let config_dir = std::env::temp_dir().join(format!("chains_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&config_dir)?;
+        std::fs::write(
+            config_dir.join(CHAIN_REGISTRY_FILE),
+            r#"{"chains": [{"id": "avax", "family": "ethereum", "block_time": 2000, "comparator": "block_timestamp"}]}"#,
+        )?;
+        let registry = ChainRegistry::load(&config_dir.to_string_lossy())?;
+        let avax = BlockChainType::from_str("avax")?;
+        assert_eq!(avax, BlockChainType::Other("avax".to_string()));
+        assert_eq!(registry.get(&avax).map(|spec| spec.block_time), Some(2000));
+        std::fs::remove_dir_all(&config_dir)?;
+        Ok(())
+    }
+}
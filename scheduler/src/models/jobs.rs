@@ -1,8 +1,10 @@
+use crate::BENCHMARK_MAX_JOBS_PER_WORKER;
+use common::job_manage::JobDetail;
 use common::jobs::{AssignmentConfig, Job, JobAssignment};
-use common::workers::MatchedWorkers;
+use common::workers::{MatchedWorkers, Worker};
 use log::{debug, warn};
-use rand::Rng;
 use std::collections::HashSet;
+use std::sync::Arc;
 
 #[derive(Clone, Debug, Default)]
 pub struct JobAssignmentBuffer {
@@ -17,12 +19,18 @@ impl JobAssignmentBuffer {
             list_assignments: vec![],
         }
     }
-    pub fn assign_job(
+    pub async fn assign_job(
         &mut self,
-        job: Job,
+        mut job: Job,
         workers: &MatchedWorkers,
         assignment_config: &Option<AssignmentConfig>,
     ) {
+        if let Some(resource_class) = assignment_config
+            .as_ref()
+            .and_then(|config| config.resource_class.clone())
+        {
+            job.resource_class = Some(resource_class);
+        }
         //Do assignment
         log::debug!(
             "Assign job {:?} to workers {:?} with config {:?}",
@@ -30,40 +38,29 @@ impl JobAssignmentBuffer {
             workers,
             assignment_config
         );
-        let mut rng = rand::thread_rng();
+        let first_assignment = self.list_assignments.len();
         match assignment_config {
             None => {
-                //without config, assign job for one random nearby worker
-                let worker = if !workers.nearby_workers.is_empty() {
-                    let ind = rng.gen_range(0..workers.nearby_workers.len());
-                    workers.get_nearby_worker(ind)
-                } else if !workers.measured_workers.is_empty() {
-                    let ind = rng.gen_range(0..workers.measured_workers.len());
-                    workers.get_best_worker(ind)
-                } else if !workers.remain_workers.is_empty() {
-                    workers.get_random_worker()
-                } else {
-                    warn!("No workers found for component {:?}", job.component_id);
-                    None
-                };
-                if let Some(worker) = worker {
-                    debug!(
-                        "Assign job {:?}.{:?} to one random worker {:?}",
-                        &job.job_name, &job.job_id, &worker.worker_info
-                    );
-                    let job_assignment = JobAssignment::new(worker, &job);
-                    self.list_assignments.push(job_assignment);
-                } else {
+                //without config, assign job for the least loaded nearby worker
+                let candidates = workers.get_eligible_workers(&None);
+                if !self
+                    .assign_to_least_loaded(&job, workers, &candidates)
+                    .await
+                {
                     warn!("No workers found for component {:?}", job.component_id);
                 }
             }
             Some(config) => {
-                self.assign_job_with_config(&job, workers, config);
+                self.assign_job_with_config(&job, workers, config).await;
             }
         }
+        //Keep config for reassignment when worker membership changes
+        for assignment in self.list_assignments[first_assignment..].iter_mut() {
+            assignment.assignment_config = assignment_config.clone();
+        }
         self.jobs.push(job);
     }
-    fn assign_job_with_config(
+    async fn assign_job_with_config(
         &mut self,
         job: &Job,
         workers: &MatchedWorkers,
@@ -73,80 +70,132 @@ impl JobAssignmentBuffer {
             "assign_job_with_config {:?} with config {:?} to workers {:?}",
             job, config, workers
         );
-        let mut rng = rand::thread_rng();
-        if let Some(true) = config.broadcast {
-            for worker in workers.measured_workers.iter() {
-                let job_assignment = JobAssignment::new(worker.clone(), &job);
-                self.list_assignments.push(job_assignment);
-                debug!(
-                    "Assign job {:?} to worker {:?}",
-                    job.job_name,
-                    worker.get_url("")
-                )
-            }
-            for worker in workers.remain_workers.iter() {
-                let job_assignment = JobAssignment::new(worker.clone(), &job);
-                self.list_assignments.push(job_assignment);
-                debug!(
-                    "Assign job {:?} on provider {:?} to worker {:?}",
-                    job.job_name,
-                    job.component_id,
-                    worker.get_url("")
-                )
+        if config.is_broadcast() {
+            for worker in Self::filter_capacity(job, workers, &workers.get_all_workers()).await {
+                self.push_assignment(job, workers, worker).await;
             }
         } else if let Some(val) = config.worker_number {
-            if let Some(true) = config.nearby_only {
-                debug!(
-                    "assign_job_with_config for job {:?} to {} of workers {:?}",
-                    job, val, workers.nearby_workers
-                );
-                if !workers.nearby_workers.is_empty() {
-                    for _i in 0..val {
-                        let ind = rng.gen_range(0..workers.nearby_workers.len());
-                        let worker = workers.get_nearby_worker(ind).unwrap();
-                        let job_assignment = JobAssignment::new(worker.clone(), &job);
-                        self.list_assignments.push(job_assignment);
+            // Candidates by nearby_only or by_distance config, or all workers
+            let candidates = workers.get_eligible_workers(&Some(config.clone()));
+            if config.use_rendezvous_hashing() {
+                self.assign_by_rendezvous_hashing(job, workers, &candidates, val)
+                    .await;
+                return;
+            }
+            log::debug!(
+                "Try to assign job {:?}.{:?}.{:?} to {} least loaded workers among {} workers",
+                &job.job_type,
+                &job.job_name,
+                &job.job_id,
+                val,
+                candidates.len()
+            );
+            let mut candidates = Self::filter_capacity(job, workers, &candidates).await;
+            for _ in 0..val {
+                //By distance config keeps round trip time order, load is compared among the nearest workers
+                let nearest_workers = if config.by_distance == Some(true) {
+                    workers.get_nearest_workers(&candidates)
+                } else {
+                    candidates.clone()
+                };
+                match workers.get_least_loaded_worker(&nearest_workers).await {
+                    Some(worker) => {
+                        //Each worker gets the job once
+                        candidates.retain(|candidate| candidate.get_id() != worker.get_id());
+                        self.push_assignment(job, workers, worker).await;
                     }
-                }
-            } else if let Some(true) = config.by_distance {
-                if !workers.measured_workers.is_empty() {
-                    for _i in 0..val {
-                        let ind = rng.gen_range(0..workers.measured_workers.len());
-                        let worker = workers.get_best_worker(ind).unwrap();
-                        let job_assignment = JobAssignment::new(worker.clone(), &job);
-                        self.list_assignments.push(job_assignment);
+                    None => {
+                        warn!(
+                            "No worker with free capacity for job {:?} on provider {:?}",
+                            job.job_name, job.component_id
+                        );
+                        break;
                     }
                 }
-            } else {
-                let all_workers = workers.get_all_workers();
-                log::debug!(
-                    "Try to assign job {:?}.{:?}.{:?} to {} random worker among {} workers",
-                    &job.job_type,
-                    &job.job_name,
-                    &job.job_id,
-                    val,
-                    all_workers.len()
-                );
-                if all_workers.len() > 0 {
-                    for _ in 0..val {
-                        let ind = rng.gen_range(0..all_workers.len());
-                        if let Some(worker) = all_workers.get(ind) {
-                            debug!(
-                                "Assign job {:?} on provider {:?} to worker {:?}",
-                                job.job_name,
-                                job.component_id,
-                                worker.get_url("")
-                            );
-                            let job_assignment = JobAssignment::new(worker.clone(), &job);
-                            self.list_assignments.push(job_assignment);
-                        }
+            }
+        }
+    }
+    /*
+     * Assign job to the top ranked workers for its provider, so the provider stays on the same workers
+     * while they are in the pool
+     */
+    async fn assign_by_rendezvous_hashing(
+        &mut self,
+        job: &Job,
+        workers: &MatchedWorkers,
+        candidates: &[Arc<Worker>],
+        worker_number: usize,
+    ) {
+        let candidates = Self::filter_capacity(job, workers, candidates).await;
+        let ranked_workers = workers.get_rendezvous_workers(&candidates, &job.component_id);
+        if ranked_workers.is_empty() {
+            warn!(
+                "No worker with free capacity for job {:?} on provider {:?}",
+                job.job_name, job.component_id
+            );
+        }
+        for worker in ranked_workers.into_iter().take(worker_number) {
+            self.push_assignment(job, workers, worker).await;
+        }
+    }
+    /*
+     * Assign job to the candidate with the lowest load per capacity unit
+     */
+    async fn assign_to_least_loaded(
+        &mut self,
+        job: &Job,
+        workers: &MatchedWorkers,
+        candidates: &[Arc<Worker>],
+    ) -> bool {
+        let candidates = Self::filter_capacity(job, workers, candidates).await;
+        match workers.get_least_loaded_worker(&candidates).await {
+            Some(worker) => {
+                self.push_assignment(job, workers, worker).await;
+                true
+            }
+            None => false,
+        }
+    }
+    /*
+     * Benchmark jobs are not assigned to workers which reach BENCHMARK_MAX_JOBS_PER_WORKER
+     */
+    async fn filter_capacity(
+        job: &Job,
+        workers: &MatchedWorkers,
+        candidates: &[Arc<Worker>],
+    ) -> Vec<Arc<Worker>> {
+        match job.job_detail {
+            JobDetail::Benchmark(_) => {
+                let mut free_workers = Vec::new();
+                for worker in candidates.iter() {
+                    if workers
+                        .loads
+                        .get_benchmark_jobs(&worker.worker_info.worker_id)
+                        .await
+                        < *BENCHMARK_MAX_JOBS_PER_WORKER
+                    {
+                        free_workers.push(worker.clone());
                     }
-                } else {
-                    log::debug!("No worker found in {:?}", &self);
                 }
+                free_workers
             }
+            _ => candidates.to_vec(),
         }
     }
+    async fn push_assignment(&mut self, job: &Job, workers: &MatchedWorkers, worker: Arc<Worker>) {
+        debug!(
+            "Assign job {:?} on provider {:?} to worker {:?}",
+            job.job_name,
+            job.component_id,
+            worker.get_url("")
+        );
+        //Count the job in worker load for the next assignments
+        workers
+            .loads
+            .add_job(&worker.worker_info.worker_id, job)
+            .await;
+        self.list_assignments.push(JobAssignment::new(worker, job));
+    }
     pub fn append(&mut self, other: Self) {
         let Self {
             mut jobs,
@@ -199,3 +248,229 @@ impl JobAssignmentBuffer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::component::Zone;
+    use common::job_manage::{JobBenchmark, JobRole};
+    use common::workers::WorkerSpec;
+    use common::ComponentInfo;
+    use std::collections::HashMap;
+    use test_util::helper::mock_worker;
+
+    fn mock_spec_worker(id: &str, cpus: u16) -> Arc<Worker> {
+        let mut info = mock_worker(id);
+        info.worker_spec = WorkerSpec::new(cpus, 8192, 100);
+        Arc::new(Worker::new(info))
+    }
+
+    fn count_assignments(buffer: &JobAssignmentBuffer, worker_id: &str) -> usize {
+        buffer
+            .list_assignments
+            .iter()
+            .filter(|assignment| assignment.worker.get_id() == worker_id)
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_capacity_aware_assignment() {
+        let workers = MatchedWorkers {
+            remain_workers: vec![mock_spec_worker("small", 1), mock_spec_worker("large", 4)],
+            ..Default::default()
+        };
+        let config = Some(AssignmentConfig {
+            worker_number: Some(1),
+            ..Default::default()
+        });
+        let mut buffer = JobAssignmentBuffer::default();
+        for ind in 0..5 {
+            let job = Job {
+                job_id: format!("job{}", ind),
+                ..Default::default()
+            };
+            buffer.assign_job(job, &workers, &config).await;
+        }
+        assert_eq!(count_assignments(&buffer, "large"), 4);
+        assert_eq!(count_assignments(&buffer, "small"), 1);
+
+        // Reported queue contains jobs assigned before the report
+        workers
+            .loads
+            .update_queue_length(&"large".to_string(), 2)
+            .await;
+        assert_eq!(workers.loads.get_load(&"large".to_string()).await, 4);
+        workers
+            .loads
+            .update_queue_length(&"large".to_string(), 100)
+            .await;
+        let mut buffer = JobAssignmentBuffer::default();
+        buffer.assign_job(Job::default(), &workers, &config).await;
+        assert_eq!(count_assignments(&buffer, "small"), 1);
+        // Finished jobs are not counted
+        workers
+            .loads
+            .update_queue_length(&"large".to_string(), 0)
+            .await;
+        workers
+            .loads
+            .finish_jobs(&"large".to_string(), &["job0".to_string()])
+            .await;
+        assert_eq!(workers.loads.get_load(&"large".to_string()).await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_by_distance_assignment() {
+        let workers = MatchedWorkers {
+            measured_workers: vec![
+                mock_spec_worker("near_loaded", 1),
+                mock_spec_worker("near", 1),
+                mock_spec_worker("far", 4),
+            ],
+            distances: HashMap::from([
+                ("near_loaded".to_string(), 10),
+                ("near".to_string(), 10),
+                ("far".to_string(), 100),
+            ]),
+            ..Default::default()
+        };
+        workers
+            .loads
+            .update_queue_length(&"near_loaded".to_string(), 10)
+            .await;
+        let config = Some(AssignmentConfig {
+            worker_number: Some(2),
+            by_distance: Some(true),
+            ..Default::default()
+        });
+        let mut buffer = JobAssignmentBuffer::default();
+        buffer.assign_job(Job::default(), &workers, &config).await;
+        // Far worker is not used while there are nearer ones, whatever its load
+        let assigned = buffer
+            .list_assignments
+            .iter()
+            .map(|assignment| assignment.worker.get_id())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            assigned,
+            vec!["near".to_string(), "near_loaded".to_string()]
+        );
+    }
+
+    async fn assigned_workers(
+        job: &Job,
+        workers: &MatchedWorkers,
+        config: &Option<AssignmentConfig>,
+    ) -> Vec<String> {
+        let mut buffer = JobAssignmentBuffer::default();
+        buffer.assign_job(job.clone(), workers, config).await;
+        buffer
+            .list_assignments
+            .iter()
+            .map(|assignment| assignment.worker.get_id())
+            .collect::<Vec<String>>()
+    }
+
+    #[tokio::test]
+    async fn test_rendezvous_assignment() {
+        let mut far_worker = mock_worker("far");
+        far_worker.zone = Zone::EU;
+        let mut workers = MatchedWorkers {
+            provider: ComponentInfo {
+                id: "provider".to_string(),
+                zone: Zone::AS,
+                ..Default::default()
+            },
+            remain_workers: (0..6)
+                .map(|ind| mock_spec_worker(&format!("worker{}", ind), 1))
+                .chain(std::iter::once(Arc::new(Worker::new(far_worker))))
+                .collect(),
+            ..Default::default()
+        };
+        let config = Some(AssignmentConfig {
+            worker_number: Some(2),
+            rendezvous_hashing: Some(true),
+            ..Default::default()
+        });
+        let job = Job {
+            component_id: "provider".to_string(),
+            phase: JobRole::Regular,
+            ..Default::default()
+        };
+        let selected = assigned_workers(&job, &workers, &config).await;
+        assert_eq!(selected.len(), 2);
+        assert!(!selected.contains(&"far".to_string()));
+        // Same workers regardless of load
+        assert_eq!(assigned_workers(&job, &workers, &config).await, selected);
+
+        // Removing another worker does not move the provider
+        let other = workers
+            .remain_workers
+            .iter()
+            .map(|worker| worker.get_id())
+            .find(|id| !selected.contains(id) && id != "far")
+            .unwrap();
+        workers = workers.exclude(&[other]);
+        assert_eq!(assigned_workers(&job, &workers, &config).await, selected);
+
+        // Only the removed worker is replaced
+        workers = workers.exclude(&[selected[0].clone()]);
+        let reassigned = assigned_workers(&job, &workers, &config).await;
+        assert!(reassigned.contains(&selected[1]));
+        assert!(!reassigned.contains(&selected[0]));
+
+        // Worker out of provider zone fills the remaining slot
+        let nearby = workers
+            .remain_workers
+            .iter()
+            .map(|worker| worker.get_id())
+            .filter(|id| id != &selected[1] && id != "far")
+            .collect::<Vec<String>>();
+        workers = workers.exclude(&nearby);
+        let reassigned = assigned_workers(&job, &workers, &config).await;
+        assert_eq!(reassigned, vec![selected[1].clone(), "far".to_string()]);
+
+        // Rendezvous hashing is enabled by task config only
+        let config = AssignmentConfig {
+            worker_number: Some(2),
+            ..Default::default()
+        };
+        assert!(!config.use_rendezvous_hashing());
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_cap() {
+        let workers = MatchedWorkers {
+            remain_workers: vec![mock_spec_worker("small", 1), mock_spec_worker("large", 4)],
+            ..Default::default()
+        };
+        let benchmark = Job {
+            job_detail: JobDetail::Benchmark(JobBenchmark::default()),
+            timeout: 60000,
+            ..Default::default()
+        };
+        let config = Some(AssignmentConfig {
+            worker_number: Some(3),
+            ..Default::default()
+        });
+        let mut buffer = JobAssignmentBuffer::default();
+        buffer
+            .assign_job(benchmark.clone(), &workers, &config)
+            .await;
+        // One benchmark job per worker
+        assert_eq!(buffer.list_assignments.len(), 2);
+        assert_eq!(
+            workers.loads.get_benchmark_jobs(&"large".to_string()).await,
+            *BENCHMARK_MAX_JOBS_PER_WORKER
+        );
+        // Finished benchmark frees the worker
+        workers
+            .loads
+            .finish_jobs(&"large".to_string(), &[benchmark.job_id.clone()])
+            .await;
+        let mut buffer = JobAssignmentBuffer::default();
+        buffer.assign_job(benchmark, &workers, &config).await;
+        assert_eq!(count_assignments(&buffer, "large"), 1);
+        assert_eq!(buffer.list_assignments.len(), 1);
+    }
+}
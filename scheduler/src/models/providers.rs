@@ -109,6 +109,17 @@ impl ProviderStorage {
         }
         providers
     }
+    pub async fn get_active_provider(&self, id: &str) -> Option<ComponentInfo> {
+        if let Some(node) = self.nodes.lock().await.iter().find(|comp| comp.id == id) {
+            return Some(node.clone());
+        }
+        self.gateways
+            .lock()
+            .await
+            .iter()
+            .find(|comp| comp.id == id)
+            .cloned()
+    }
     pub async fn get_number_active_providers(&self) -> (usize,usize) {
         let nodes = self.nodes.lock().await.len();
         let gateways = self.gateways.lock().await.len();
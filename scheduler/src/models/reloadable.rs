@@ -0,0 +1,46 @@
+use std::sync::{Arc, RwLock};
+
+/*
+ * Value which can be replaced at runtime (e.g. task configs on hot reload).
+ * Readers get a snapshot, so anything started with old value keeps it until finished
+ */
+#[derive(Debug)]
+pub struct Reloadable<T> {
+    inner: RwLock<Arc<T>>,
+}
+
+impl<T> Reloadable<T> {
+    pub fn new(value: T) -> Self {
+        Reloadable {
+            inner: RwLock::new(Arc::new(value)),
+        }
+    }
+    pub fn get(&self) -> Arc<T> {
+        self.inner.read().unwrap().clone()
+    }
+    pub fn set(&self, value: T) -> Arc<T> {
+        let mut inner = self.inner.write().unwrap();
+        std::mem::replace(&mut *inner, Arc::new(value))
+    }
+}
+
+impl<T: Default> Default for Reloadable<T> {
+    fn default() -> Self {
+        Reloadable::new(T::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reloadable_snapshot() {
+        let value = Reloadable::new(vec![1, 2]);
+        let snapshot = value.get();
+        let old = value.set(vec![3]);
+        assert_eq!(*old, vec![1, 2]);
+        assert_eq!(*snapshot, vec![1, 2]);
+        assert_eq!(*value.get(), vec![3]);
+    }
+}
@@ -1,11 +1,11 @@
 use crate::persistence::ProviderMapModel;
 use common::component::ComponentInfo;
-use common::workers::{MatchedWorkers, Worker, WorkerInfo};
+use common::workers::{MatchedWorkers, Worker, WorkerLoads};
 use common::{ComponentId, WorkerId};
 use log::{debug, info};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
 
 /*
  * Todo: Rename to WorkerStorage
@@ -14,27 +14,34 @@ use tokio::sync::Mutex;
 pub struct WorkerInfoStorage {
     workers: Mutex<Vec<Arc<Worker>>>,
     map_worker_provider: Mutex<Vec<ProviderMapModel>>,
+    //Notified when a worker is added to or removed from the pool
+    membership_changed: Notify,
+    worker_loads: WorkerLoads,
 }
 
 impl WorkerInfoStorage {
-    pub fn new(workers: Vec<WorkerInfo>) -> Self {
+    pub fn new(workers: Vec<Worker>) -> Self {
         WorkerInfoStorage {
-            workers: Mutex::new(
-                workers
-                    .into_iter()
-                    .map(|info| Arc::new(Worker::new(info)))
-                    .collect(),
-            ),
+            workers: Mutex::new(workers.into_iter().map(Arc::new).collect()),
             map_worker_provider: Mutex::new(vec![]),
+            membership_changed: Notify::new(),
+            worker_loads: Default::default(),
         }
     }
-    pub async fn add_worker(&self, info: WorkerInfo) {
+    /*
+     * Replace the stored worker if it already exists, so newly issued secret is used
+     */
+    pub async fn add_worker(&self, worker: Worker) {
         let mut workers = self.workers.lock().await;
-        if !workers
-            .iter()
-            .any(|worker| worker.worker_info.worker_id == info.worker_id)
+        match workers
+            .iter_mut()
+            .find(|item| item.worker_info.worker_id == worker.worker_info.worker_id)
         {
-            workers.push(Arc::new(Worker::new(info)));
+            Some(item) => *item = Arc::new(worker),
+            None => {
+                workers.push(Arc::new(worker));
+                self.membership_changed.notify_one();
+            }
         }
     }
     pub async fn remove_workers(&self, worker_ids: &[&WorkerId]) {
@@ -43,8 +50,19 @@ impl WorkerInfoStorage {
             "Workers list before remove: {:?}, worker_ids:{:?}",
             workers, worker_ids
         );
+        let size = workers.len();
         workers.retain(|worker| !worker_ids.contains(&&worker.worker_info.worker_id));
         info!("Workers list after remove: {:?}", workers);
+        self.worker_loads.remove_workers(worker_ids).await;
+        if workers.len() < size {
+            self.membership_changed.notify_one();
+        }
+    }
+    /*
+     * Wait until a worker is added or removed, a change before waiting is not lost
+     */
+    pub async fn wait_membership_change(&self) {
+        self.membership_changed.notified().await
     }
 
     pub async fn get_workers(&self) -> Vec<Arc<Worker>> {
@@ -56,6 +74,9 @@ impl WorkerInfoStorage {
             .map(|worker| worker.clone())
             .collect()
     }
+    pub fn get_worker_loads(&self) -> WorkerLoads {
+        self.worker_loads.clone()
+    }
     pub async fn get_workers_number(&self) -> usize {
         self.workers.lock().await.len()
     }
@@ -125,6 +146,8 @@ impl WorkerInfoStorage {
             nearby_workers,
             measured_workers,
             remain_workers,
+            distances,
+            loads: self.worker_loads.clone(),
         };
         debug!(
             "matched workers for provider {:?} {:?}",
@@ -338,6 +338,8 @@ impl FromDb<job_result_benchmarks::Model> for JobBenchmarkResult {
             error_code: model.error_code as u32,
             message: model.message.clone(),
             histograms,
+            //Request counters are not stored
+            ..Default::default()
         };
         JobBenchmarkResult {
             job: job.clone(),
@@ -1,14 +1,23 @@
 use crate::persistence::JobAssignmentActiveModel;
 use anyhow::anyhow;
-use common::jobs::{Job, JobAssignment};
-use common::{ComponentId, JobId};
+use common::job_manage::JobRole;
+use common::jobs::{Job, JobAssignment, JobStatus};
+use common::util::get_current_time;
+use common::{ComponentId, JobId, PlanId, WorkerId};
 use entity::seaorm::{job_assignments, jobs};
 use log::debug;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, FromQueryResult, QueryFilter, QuerySelect};
 use sea_orm::{Condition, DatabaseConnection};
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+#[derive(FromQueryResult)]
+struct PlanWorker {
+    plan_id: PlanId,
+    worker_id: WorkerId,
+}
+
 #[derive(Default)]
 pub struct JobService {
     db: Arc<DatabaseConnection>,
@@ -67,12 +76,94 @@ impl JobService {
         Ok(0)
     }
 
+    pub async fn update_assignment_status(
+        &self,
+        worker_id: &WorkerId,
+        job_ids: &[JobId],
+        status: JobStatus,
+    ) -> Result<u64, anyhow::Error> {
+        if job_ids.is_empty() {
+            return Ok(0);
+        }
+        match job_assignments::Entity::update_many()
+            .col_expr(
+                job_assignments::Column::Status,
+                Expr::value(status.to_string()),
+            )
+            .filter(job_assignments::Column::WorkerId.eq(worker_id.as_str()))
+            .filter(job_assignments::Column::JobId.is_in(job_ids.iter().map(|id| id.as_str())))
+            .exec(self.db.as_ref())
+            .await
+        {
+            Ok(res) => Ok(res.rows_affected),
+            Err(err) => Err(anyhow!("{:?}", &err)),
+        }
+    }
+
+    /*
+     * Move job assignment to another worker, after delivery failures or worker membership changes
+     */
+    pub async fn reassign_job(
+        &self,
+        job_id: &JobId,
+        from_worker: &WorkerId,
+        to_worker: &WorkerId,
+    ) -> Result<u64, anyhow::Error> {
+        match job_assignments::Entity::update_many()
+            .col_expr(
+                job_assignments::Column::WorkerId,
+                Expr::value(to_worker.clone()),
+            )
+            .col_expr(
+                job_assignments::Column::Status,
+                Expr::value(JobStatus::ASSIGNED.to_string()),
+            )
+            .col_expr(
+                job_assignments::Column::AssignTime,
+                Expr::value(get_current_time()),
+            )
+            .filter(job_assignments::Column::JobId.eq(job_id.as_str()))
+            .filter(job_assignments::Column::WorkerId.eq(from_worker.as_str()))
+            .exec(self.db.as_ref())
+            .await
+        {
+            Ok(res) => Ok(res.rows_affected),
+            Err(err) => Err(anyhow!("{:?}", &err)),
+        }
+    }
+
     pub async fn get_job_assignments(
         &self,
     ) -> Result<HashMap<ComponentId, JobAssignment>, anyhow::Error> {
         Ok(Default::default())
     }
 
+    /*
+     * Distinct plans and workers of regular job assignments
+     */
+    pub async fn get_regular_plan_workers(&self) -> Result<Vec<(PlanId, WorkerId)>, anyhow::Error> {
+        match job_assignments::Entity::find()
+            .select_only()
+            .column(job_assignments::Column::PlanId)
+            .column(job_assignments::Column::WorkerId)
+            .filter(
+                job_assignments::Column::PlanId
+                    .starts_with(&format!("{}-", JobRole::Regular.to_string())),
+            )
+            .group_by(job_assignments::Column::PlanId)
+            .group_by(job_assignments::Column::WorkerId)
+            .into_model::<PlanWorker>()
+            .all(self.db.as_ref())
+            .await
+        {
+            Ok(models) => Ok(models
+                .into_iter()
+                .map(|model| (model.plan_id, model.worker_id))
+                .collect()),
+            Err(err) => Err(anyhow!("{:?}", &err)),
+        }
+    }
+
     pub async fn get_job_by_ids(
         &self,
         job_ids: &HashSet<JobId>,
@@ -1,8 +1,9 @@
 use anyhow::anyhow;
 
-use common::workers::WorkerInfo;
+use common::workers::{Worker, WorkerInfo};
 use entity::seaorm::workers;
 
+use sea_orm::sea_query::Expr;
 use sea_orm::DatabaseConnection;
 use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter};
 
@@ -16,7 +17,7 @@ impl WorkerService {
     pub fn new(db: Arc<DatabaseConnection>) -> Self {
         WorkerService { db }
     }
-    pub async fn get_active(&self) -> Vec<WorkerInfo> {
+    pub async fn get_active(&self) -> Vec<Worker> {
         let mut res = Vec::new();
         if let Ok(workers) = workers::Entity::find()
             .filter(workers::Column::Active.eq(1))
@@ -24,7 +25,7 @@ impl WorkerService {
             .await
         {
             for model in workers.iter() {
-                res.push(WorkerInfo::from(model))
+                res.push(Worker::from(model))
             }
         }
         res
@@ -45,11 +46,50 @@ impl WorkerService {
             Err(_) => None,
         }
     }
-    pub async fn store_worker(&self, worker: &WorkerInfo) -> Result<workers::Model, anyhow::Error> {
+    pub async fn get_worker(&self, worker_id: &str) -> Option<Worker> {
+        match workers::Entity::find()
+            .filter(workers::Column::WorkerId.eq(worker_id))
+            .one(self.db.as_ref())
+            .await
+        {
+            Ok(model) => model.as_ref().map(Worker::from),
+            Err(_) => None,
+        }
+    }
+    pub async fn store_worker(&self, worker: &Worker) -> Result<workers::Model, anyhow::Error> {
         let worker = workers::ActiveModel::from(worker);
         match worker.insert(self.db.as_ref()).await {
             Ok(res) => Ok(res),
             Err(err) => Err(anyhow!("{:?}", &err)),
         }
     }
+    /*
+     * Update credentials, delivery mode and spec issued or registered at the latest worker registration
+     */
+    pub async fn update_worker_credentials(&self, worker: &Worker) -> Result<u64, anyhow::Error> {
+        match workers::Entity::update_many()
+            .col_expr(
+                workers::Column::Secret,
+                Expr::value(worker.secret.as_str().to_string()),
+            )
+            .col_expr(
+                workers::Column::PublicKey,
+                Expr::value(worker.worker_info.public_key.clone()),
+            )
+            .col_expr(
+                workers::Column::DeliveryMode,
+                Expr::value(worker.worker_info.delivery_mode.to_string()),
+            )
+            .col_expr(
+                workers::Column::Specification,
+                Expr::value(serde_json::to_value(&worker.worker_info.worker_spec)?),
+            )
+            .filter(workers::Column::WorkerId.eq(worker.worker_info.worker_id.as_str()))
+            .exec(self.db.as_ref())
+            .await
+        {
+            Ok(res) => Ok(res.rows_affected),
+            Err(err) => Err(anyhow!("{:?}", &err)),
+        }
+    }
 }
@@ -1,7 +1,7 @@
 use crate::models::providers::ProviderStorage;
 use crate::models::workers::WorkerInfoStorage;
 use crate::persistence::services::provider_service::ProviderService;
-use crate::{CONFIG, PORTAL_AUTHORIZATION};
+use crate::{CONFIG, PORTAL_AUTHORIZATION, SCHEDULER_SHARD_INDEX, SHARD_MAP};
 use anyhow::Error;
 use common::component::{ComponentInfo, ComponentType, Zone};
 use log::{debug, error, trace};
@@ -146,6 +146,9 @@ impl ProviderScanner {
             components.retain(|component| component.get_chain_id() == *chain_id);
         }
 
+        //Keep providers of this scheduler shard
+        components.retain(|component| SHARD_MAP.is_owned(&component.id, *SCHEDULER_SHARD_INDEX));
+
         Ok(components)
     }
     /*
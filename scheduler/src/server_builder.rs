@@ -11,15 +11,21 @@ use std::sync::Arc;
 use warp::http::{HeaderMap, Method};
 
 use crate::service::{ProcessorService, WebService};
+use common::auth::{HEADER_SIGNATURE, HEADER_TIMESTAMP, HEADER_WORKER_ID};
 use common::component::ComponentInfo;
-use common::jobs::JobResult;
+use common::TLS_CONFIG;
 
+use warp::hyper::body::Bytes;
 use warp::{Filter, Rejection};
 
-use crate::handler::{handle_rejection, handle_route_reports, UnAuthorization};
+use crate::handler::{
+    handle_rejection, handle_route_reload_configs, handle_route_reports, handle_route_worker_jobs,
+    handle_route_worker_jobs_ack, handle_route_worker_register, UnAuthorization,
+};
+use crate::service::config_reload::TaskConfigReloader;
 use crate::state::{ProcessorState, SchedulerState};
-use crate::{BUILD_VERSION, SCHEDULER_AUTHORIZATION};
-use common::workers::WorkerInfo;
+use crate::BUILD_VERSION;
+use common::WorkerId;
 
 pub const MAX_JSON_BODY_SIZE: u64 = 1024 * 1024;
 
@@ -31,6 +37,7 @@ pub struct ServerBuilder {
     processor_service: ProcessorService,
     scheduler_state: Arc<SchedulerState>,
     processor_state: Arc<ProcessorState>,
+    config_reloader: Arc<TaskConfigReloader>,
 }
 
 pub struct SchedulerServer {
@@ -40,6 +47,7 @@ pub struct SchedulerServer {
     processor_service: Arc<ProcessorService>,
     scheduler_state: Arc<SchedulerState>,
     processor_state: Arc<ProcessorState>,
+    config_reloader: Arc<TaskConfigReloader>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -47,6 +55,12 @@ pub struct DeployParam {
     pub id: String,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PullJobsParam {
+    //Max time in seconds to wait for new jobs, capped by PULL_JOBS_MAX_WAIT
+    pub wait: Option<u64>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct SimpleResponse {
     pub success: bool,
@@ -61,6 +75,7 @@ impl SchedulerServer {
             processor_service: Default::default(),
             scheduler_state: Arc::new(Default::default()),
             processor_state: Arc::new(Default::default()),
+            config_reloader: Arc::new(Default::default()),
         }
     }
     pub async fn serve(&self) {
@@ -103,10 +118,21 @@ impl SchedulerServer {
                     self.scheduler_state.clone(),
                 )
                 .with(&cors))
+            //For pull mode workers
+            .or(self
+                .create_route_worker_jobs(self.scheduler_state.clone())
+                .with(&cors))
+            .or(self
+                .create_route_worker_jobs_ack(self.scheduler_state.clone())
+                .with(&cors))
             //For report processor
             .or(self
                 .create_route_reports(self.processor_service.clone(), self.processor_state.clone())
                 .with(&cors))
+            //For task config hot reload
+            .or(self
+                .create_route_reload_configs(self.config_reloader.clone())
+                .with(&cors))
             // .or(self
             //     .create_route_worker_pause(
             //         self.scheduler_service.clone(),
@@ -122,8 +148,7 @@ impl SchedulerServer {
             .recover(handle_rejection);
 
         let socket_addr: SocketAddr = self.entry_point.parse().unwrap();
-
-        warp::serve(router).run(socket_addr).await;
+        TLS_CONFIG.serve(router, socket_addr).await;
     }
     /// Ping API
     fn create_ping(&self) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
@@ -151,26 +176,27 @@ impl SchedulerServer {
         warp::path!("worker" / "register")
             .and(SchedulerServer::log_headers())
             .and(warp::post())
-            .and(warp::body::content_length_limit(MAX_JSON_BODY_SIZE).and(warp::body::json()))
+            .and(warp::body::content_length_limit(MAX_JSON_BODY_SIZE).and(warp::body::bytes()))
             .and(warp::header::<String>("authorization"))
-            .and_then(move |worker_info: WorkerInfo, authorization: String| {
-                let clone_service = service.clone();
-                let clone_state = state.clone();
-                async move {
-                    if authorization == *SCHEDULER_AUTHORIZATION {
-                        info!(
-                            "#### Received register worker request body {:?} ####",
-                            &worker_info
-                        );
-
-                        clone_service
-                            .register_worker(worker_info, clone_state)
-                            .await
-                    } else {
-                        Err(warp::reject::custom(UnAuthorization))
-                    }
-                }
-            })
+            .and(warp::header::optional::<String>(HEADER_TIMESTAMP))
+            .and(warp::header::optional::<String>(HEADER_SIGNATURE))
+            .map(
+                move |body: Bytes,
+                      authorization: String,
+                      timestamp: Option<String>,
+                      signature: Option<String>| {
+                    (
+                        service.clone(),
+                        state.clone(),
+                        body,
+                        authorization,
+                        timestamp,
+                        signature,
+                    )
+                },
+            )
+            .untuple_one()
+            .and_then(handle_route_worker_register)
     }
     // fn create_route_worker_pause(
     //     &self,
@@ -231,6 +257,66 @@ impl SchedulerServer {
                 }
             })
     }
+    fn create_route_worker_jobs(
+        &self,
+        state: Arc<SchedulerState>,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("worker" / WorkerId / "jobs")
+            .and(SchedulerServer::log_headers())
+            .and(warp::get())
+            .and(warp::query::<PullJobsParam>())
+            .and(warp::header::<String>("authorization"))
+            .and(warp::header::optional::<String>(HEADER_TIMESTAMP))
+            .and(warp::header::optional::<String>(HEADER_SIGNATURE))
+            .map(
+                move |worker_id: WorkerId,
+                      param: PullJobsParam,
+                      authorization: String,
+                      timestamp: Option<String>,
+                      signature: Option<String>| {
+                    (
+                        state.clone(),
+                        worker_id,
+                        param,
+                        authorization,
+                        timestamp,
+                        signature,
+                    )
+                },
+            )
+            .untuple_one()
+            .and_then(handle_route_worker_jobs)
+    }
+    fn create_route_worker_jobs_ack(
+        &self,
+        state: Arc<SchedulerState>,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("worker" / WorkerId / "jobs" / "ack")
+            .and(SchedulerServer::log_headers())
+            .and(warp::post())
+            .and(warp::body::content_length_limit(MAX_JSON_BODY_SIZE).and(warp::body::bytes()))
+            .and(warp::header::<String>("authorization"))
+            .and(warp::header::optional::<String>(HEADER_TIMESTAMP))
+            .and(warp::header::optional::<String>(HEADER_SIGNATURE))
+            .map(
+                move |worker_id: WorkerId,
+                      body: Bytes,
+                      authorization: String,
+                      timestamp: Option<String>,
+                      signature: Option<String>| {
+                    (
+                        state.clone(),
+                        worker_id,
+                        body,
+                        authorization,
+                        timestamp,
+                        signature,
+                    )
+                },
+            )
+            .untuple_one()
+            .and_then(handle_route_worker_jobs_ack)
+    }
     fn create_route_reports(
         &self,
         service: Arc<ProcessorService>,
@@ -239,15 +325,42 @@ impl SchedulerServer {
         warp::path!("report")
             .and(SchedulerServer::log_headers())
             .and(warp::post())
-            .and(warp::body::content_length_limit(MAX_JSON_BODY_SIZE).and(warp::body::json()))
+            .and(warp::body::content_length_limit(MAX_JSON_BODY_SIZE).and(warp::body::bytes()))
             .and(warp::header::<String>("authorization"))
-            .map(move |job_results: Vec<JobResult>, authorization: String| {
-                (service.clone(), state.clone(), job_results, authorization)
-            })
+            .and(warp::header::optional::<String>(HEADER_WORKER_ID))
+            .and(warp::header::optional::<String>(HEADER_SIGNATURE))
+            .map(
+                move |body: Bytes,
+                      authorization: String,
+                      worker_id: Option<String>,
+                      signature: Option<String>| {
+                    (
+                        service.clone(),
+                        state.clone(),
+                        body,
+                        authorization,
+                        worker_id,
+                        signature,
+                    )
+                },
+            )
             .untuple_one()
             .and_then(handle_route_reports)
     }
 
+    fn create_route_reload_configs(
+        &self,
+        reloader: Arc<TaskConfigReloader>,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("admin" / "reload_configs")
+            .and(SchedulerServer::log_headers())
+            .and(warp::post())
+            .and(warp::header::<String>("authorization"))
+            .map(move |authorization: String| (reloader.clone(), authorization))
+            .untuple_one()
+            .and_then(handle_route_reload_configs)
+    }
+
     fn log_headers() -> impl Filter<Extract = (), Error = Infallible> + Copy {
         warp::header::headers_cloned()
             .map(|headers: HeaderMap| {
@@ -289,6 +402,10 @@ impl ServerBuilder {
         self.processor_state = Arc::new(processor_state);
         self
     }
+    pub fn with_config_reloader(mut self, config_reloader: Arc<TaskConfigReloader>) -> Self {
+        self.config_reloader = config_reloader;
+        self
+    }
     pub fn build(&self, scheduler: WebService, processor: ProcessorService) -> SchedulerServer {
         SchedulerServer {
             entry_point: self.entry_point.clone(),
@@ -297,6 +414,7 @@ impl ServerBuilder {
             processor_service: Arc::new(processor),
             scheduler_state: self.scheduler_state.clone(),
             processor_state: self.processor_state.clone(),
+            config_reloader: self.config_reloader.clone(),
         }
     }
 }
@@ -308,10 +426,12 @@ mod tests {
     use crate::models::workers::WorkerInfoStorage;
     use crate::persistence::services::{JobResultService, JobService, PlanService, WorkerService};
     use crate::service::{ProcessorServiceBuilder, SchedulerServiceBuilder};
+    use crate::SCHEDULER_AUTHORIZATION;
 
     use anyhow::Error;
 
     use common::task_spawn;
+    use common::util::get_current_time;
     use reqwest::Client;
     use sea_orm::{DatabaseBackend, MockDatabase};
     use std::env;
@@ -320,10 +440,12 @@ mod tests {
     use crate::service::delivery::CancelPlanBuffer;
     use crate::service::report_portal::ReportRecord;
     use chrono::FixedOffset;
+    use common::auth::{ResultSigner, WorkerSecret};
     use common::logger::init_logger;
-    use serde_json::json;
+    use common::workers::{Worker, WorkerRegisterResult};
+    use reqwest::StatusCode;
     use std::time::Duration;
-    use test_util::helper::{load_env, mock_db_connection};
+    use test_util::helper::{load_env, mock_db_connection, mock_worker};
     use tokio::fs;
     use tokio::sync::Mutex;
     use tokio::time::{sleep, Instant};
@@ -408,6 +530,7 @@ mod tests {
         let body = r###"
 {
     "worker_id":"worker_id",
+    "public_key":"PUBLIC_KEY",
     "app_key":"lSP1lFN9I_izEzRi_jBapA",
     "worker_ip":"192.168.1.30",
     "url":"http://192.168.1.30:3030/handle_jobs",
@@ -419,6 +542,9 @@ mod tests {
     }
 }
         "###;
+        let signer = ResultSigner::generate();
+        let body = body.replace("PUBLIC_KEY", &signer.get_public_key());
+        let body = body.as_str();
 
         let client = Client::new();
         let url = format!("http://localhost:{}/worker/register", local_port);
@@ -446,6 +572,19 @@ mod tests {
         info!("had wrong author header res: {:#?}", resp);
         assert_eq!(resp, "Authorization error, Rejection(UnAuthorization)");
 
+        // Test case: worker does not register a valid public key
+        let resp = client
+            .post(url.clone())
+            .body(body.replace(&signer.get_public_key(), "invalid_key"))
+            .header("authorization", &*SCHEDULER_AUTHORIZATION)
+            .send()
+            .await?
+            .text()
+            .await?;
+        let resp = serde_json::from_str::<WorkerRegisterResult>(&resp)?;
+        assert!(resp.worker_id.is_empty());
+        assert!(resp.worker_secret.is_empty());
+
         // Test case: had correct author header
         let resp = client
             .post(url)
@@ -457,13 +596,57 @@ mod tests {
             .await?;
         info!("had correct author header res: {:#?}", resp);
 
-        let resp = serde_json::from_str::<serde_json::value::Value>(&resp)?;
-        let expect_resp = json!({
-            "worker_id": "worker_id",
-            "report_callback": callback_url
-        });
-        println!("{} == {}", resp, expect_resp);
-        assert_eq!(resp, expect_resp);
+        let resp = serde_json::from_str::<WorkerRegisterResult>(&resp)?;
+        println!("{:?}", resp);
+        assert_eq!(resp.worker_id, "worker_id");
+        assert_eq!(resp.report_callback, callback_url);
+        // New secret is issued for each registration
+        assert_eq!(resp.worker_secret.as_str().len(), 64);
+
+        // Test case: live worker is registered again without signature
+        let resp_status = client
+            .post(url.clone())
+            .body(body)
+            .header("authorization", &*SCHEDULER_AUTHORIZATION)
+            .send()
+            .await?
+            .status();
+        assert_eq!(resp_status, StatusCode::UNAUTHORIZED);
+
+        // Test case: live worker is registered again with another secret
+        let timestamp = get_current_time();
+        let resp_status = client
+            .post(url.clone())
+            .body(body)
+            .header("authorization", &*SCHEDULER_AUTHORIZATION)
+            .header(HEADER_TIMESTAMP, timestamp.to_string())
+            .header(
+                HEADER_SIGNATURE,
+                WorkerSecret::generate().sign(timestamp, "worker/register", body.as_bytes())?,
+            )
+            .send()
+            .await?
+            .status();
+        assert_eq!(resp_status, StatusCode::UNAUTHORIZED);
+
+        // Test case: live worker is registered again with its current secret
+        let resp = client
+            .post(url)
+            .body(body)
+            .header("authorization", &*SCHEDULER_AUTHORIZATION)
+            .header(HEADER_TIMESTAMP, timestamp.to_string())
+            .header(
+                HEADER_SIGNATURE,
+                resp.worker_secret
+                    .sign(timestamp, "worker/register", body.as_bytes())?,
+            )
+            .send()
+            .await?
+            .text()
+            .await?;
+        let resp = serde_json::from_str::<WorkerRegisterResult>(&resp)?;
+        assert_eq!(resp.worker_id, "worker_id");
+        assert_eq!(resp.worker_secret.as_str().len(), 64);
 
         Ok(())
     }
@@ -487,7 +670,12 @@ mod tests {
         let scheduler_service = SchedulerServiceBuilder::default().build();
         let processor_service = ProcessorServiceBuilder::default().build();
         let access_control = AccessControl::default();
-        let worker_infos = Arc::new(WorkerInfoStorage::new(vec![]));
+        let signer = ResultSigner::generate();
+        let worker = Worker::new(
+            mock_worker("7c7da61c-aec7-45b1-9e32-7436d4721ce0")
+                .with_public_key(signer.get_public_key()),
+        );
+        let worker_infos = Arc::new(WorkerInfoStorage::new(vec![worker]));
         let provider_storage = Arc::new(ProviderStorage::default());
         let cancel_plans_buffer: Arc<Mutex<CancelPlanBuffer>> =
             Arc::new(Mutex::new(CancelPlanBuffer::default()));
@@ -543,10 +731,26 @@ mod tests {
 
         let client = Client::new();
         let url = format!("http://localhost:{}/report", local_port);
+        // Results signed by another key are rejected
+        let resp = client
+            .post(url.clone())
+            .body(body)
+            .header("authorization", &authorization)
+            .header(HEADER_WORKER_ID, "7c7da61c-aec7-45b1-9e32-7436d4721ce0")
+            .header(
+                HEADER_SIGNATURE,
+                ResultSigner::generate().sign(body.as_bytes()),
+            )
+            .send()
+            .await?;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
         let resp = client
             .post(url)
             .body(body)
             .header("authorization", &authorization)
+            .header(HEADER_WORKER_ID, "7c7da61c-aec7-45b1-9e32-7436d4721ce0")
+            .header(HEADER_SIGNATURE, signer.sign(body.as_bytes()))
             .send()
             .await;
         info!("send resp: {:?}", resp);
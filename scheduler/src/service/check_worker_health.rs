@@ -1,9 +1,12 @@
 use crate::models::job_result_cache::JobResultCache;
 use crate::models::workers::WorkerInfoStorage;
+use crate::persistence::services::WorkerService;
 
 use crate::server_builder::SimpleResponse;
+use crate::service::delivery::PullJobBuffer;
 use crate::CONFIG;
 use anyhow::{anyhow, Error};
+use common::tls::get_tls_client;
 use common::util::get_current_time;
 use common::workers::Worker;
 use common::{Timestamp, WorkerId, COMMON_CONFIG};
@@ -40,6 +43,10 @@ pub struct WorkerHealthService {
     workers: Arc<WorkerInfoStorage>,
     result_cache: Arc<JobResultCache>,
     workers_status: HashMap<WorkerId, WorkerStatus>,
+    //Pull mode workers are not reachable, their polls are used as heartbeats
+    pull_buffer: Arc<PullJobBuffer>,
+    //Workers may register on any scheduler instance, the leader adds them from database
+    worker_service: Arc<WorkerService>,
 }
 
 impl WorkerHealthService {
@@ -48,10 +55,40 @@ impl WorkerHealthService {
             workers,
             result_cache,
             workers_status: HashMap::new(),
+            pull_buffer: Default::default(),
+            worker_service: Default::default(),
+        }
+    }
+    pub fn with_pull_buffer(mut self, pull_buffer: Arc<PullJobBuffer>) -> Self {
+        self.pull_buffer = pull_buffer;
+        self
+    }
+    pub fn with_worker_service(mut self, worker_service: Arc<WorkerService>) -> Self {
+        self.worker_service = worker_service;
+        self
+    }
+    /*
+     * Add workers registered on other scheduler instances, a new registration issues a new secret
+     */
+    async fn add_registered_workers(&self) {
+        for worker in self.worker_service.get_active().await {
+            let worker_id = worker.get_id();
+            let known_secret = match self.workers.get_worker(worker_id.clone()).await {
+                Some(known_worker) => Some(known_worker.secret.clone()),
+                None => self
+                    .workers_status
+                    .get(&worker_id)
+                    .map(|status| status.worker.secret.clone()),
+            };
+            if known_secret.as_ref().map(|secret| secret.as_str()) != Some(worker.secret.as_str()) {
+                info!("Add worker {} registered on another instance", worker_id);
+                self.workers.add_worker(worker).await;
+            }
         }
     }
     pub async fn run(mut self) {
         loop {
+            self.add_registered_workers().await;
             let workers = self.workers.get_workers().await;
             info!("Get {} workers from list", workers.len());
             for worker in workers {
@@ -59,6 +96,8 @@ impl WorkerHealthService {
                     .workers_status
                     .entry(worker.worker_info.worker_id.to_string())
                     .or_insert_with(|| WorkerStatus::new(&*worker, WorkerHealth::Good));
+                // Keep the latest registered worker (with its current secret)
+                worker_status.worker = (*worker).clone();
                 // If worker exist in self.workers list and it heal is bad -> it have been restart.
                 if worker_status.health == WorkerHealth::Bad {
                     worker_status.health = WorkerHealth::Good;
@@ -95,11 +134,28 @@ impl WorkerHealthService {
             }
         }
 
+        // Update pull mode worker.update_time by its latest poll
+        for (id, status) in self.workers_status.iter_mut() {
+            if !status.worker.is_pull_mode() {
+                continue;
+            }
+            //Polls reach only the leader, so its last poll is the latest heartbeat of the worker
+            if let Some(last_poll) = self.pull_buffer.get_last_poll(id).await {
+                if status.update_time < last_poll {
+                    status.update_time = last_poll;
+                }
+            }
+        }
+
         // Judge worker status
         for (_id, status) in self.workers_status.iter_mut() {
             let now = get_current_time();
             if now - status.update_time > CONFIG.update_worker_list_interval * 1000 {
-                let res = Self::ping_worker(&*status.worker.worker_info.url).await;
+                let res = if status.worker.is_pull_mode() {
+                    Err(anyhow!("Pull mode worker does not poll for jobs"))
+                } else {
+                    Self::ping_worker(&*status.worker.worker_info.url).await
+                };
                 status.update_time = now;
                 if res.is_err() {
                     if status.health == WorkerHealth::Good {
@@ -111,6 +167,9 @@ impl WorkerHealthService {
                         self.workers
                             .remove_workers(&[&status.worker.worker_info.worker_id])
                             .await;
+                        self.pull_buffer
+                            .remove_mailboxes(&[&status.worker.worker_info.worker_id])
+                            .await;
                     }
                     status.health = WorkerHealth::Bad;
                 } else {
@@ -120,9 +179,7 @@ impl WorkerHealthService {
                             "Ping ok, add worker {} {} from working list.",
                             status.worker.worker_info.worker_id, status.worker.worker_info.url
                         );
-                        self.workers
-                            .add_worker(status.worker.worker_info.clone())
-                            .await;
+                        self.workers.add_worker(status.worker.clone()).await;
                     }
                     status.health = WorkerHealth::Good;
                 }
@@ -133,9 +190,7 @@ impl WorkerHealthService {
                         "Update new result, add worker {} {} from working list.",
                         status.worker.worker_info.worker_id, status.worker.worker_info.url
                     );
-                    self.workers
-                        .add_worker(status.worker.worker_info.clone())
-                        .await;
+                    self.workers.add_worker(status.worker.clone()).await;
                 }
                 status.health = WorkerHealth::Good;
             }
@@ -143,10 +198,7 @@ impl WorkerHealthService {
     }
     async fn ping_worker(worker_url: &str) -> Result<(), Error> {
         let url = format!("{}/ping", worker_url);
-        let client = reqwest::Client::builder()
-            .danger_accept_invalid_certs(true)
-            .timeout(Duration::from_millis(4000))
-            .build()?;
+        let client = get_tls_client()?;
         let resp = client
             .get(&url)
             .timeout(Duration::from_millis(
@@ -1,4 +1,4 @@
-use crate::service::comparator::Comparator;
+use crate::service::comparator::{Comparator, ComparatorKind};
 use anyhow::{anyhow, Error};
 use common::tasks::http_request::HttpResponseValues;
 use serde::{Deserialize, Serialize};
@@ -18,6 +18,9 @@ impl LatestBlockDotComparator {
     }
 }
 impl Comparator for LatestBlockDotComparator {
+    fn get_kind(&self) -> ComparatorKind {
+        ComparatorKind::BlockNumber
+    }
     fn get_latest_value(&self, value: &HttpResponseValues) -> Option<i64> {
         self.get_number_value(value, "number").ok()
     }
@@ -1,6 +1,7 @@
 mod default_comparator;
 mod dot_comparator;
 mod eth_comparator;
+use crate::models::chains::ChainRegistry;
 use anyhow::{anyhow, Error};
 use async_trait::async_trait;
 use common::tasks::http_request::HttpResponseValues;
@@ -10,10 +11,30 @@ pub use default_comparator::LatestBlockDefaultComparator;
 pub use dot_comparator::LatestBlockDotComparator;
 pub use eth_comparator::LatestBlockEthComparator;
 use log::trace;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
 
+/*
+ * How latest blocks of a chain are compared, declared by each chain in chain registry
+ */
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparatorKind {
+    BlockTimestamp,
+    BlockNumber,
+}
+
+impl ComparatorKind {
+    pub fn create_comparator(&self) -> Arc<dyn Comparator> {
+        match self {
+            ComparatorKind::BlockTimestamp => Arc::new(LatestBlockEthComparator::default()),
+            ComparatorKind::BlockNumber => Arc::new(LatestBlockDotComparator::default()),
+        }
+    }
+}
+
 #[async_trait]
 pub trait Comparator: Sync + Send + Debug {
     fn get_number_value(&self, value: &HttpResponseValues, field: &str) -> Result<i64, Error> {
@@ -25,6 +46,9 @@ pub trait Comparator: Sync + Send + Debug {
         trace!("Get field {} from {:?} return {:?}", field, value, &res);
         res
     }
+    fn get_kind(&self) -> ComparatorKind {
+        ComparatorKind::BlockTimestamp
+    }
     fn get_latest_value(&self, value: &HttpResponseValues) -> Option<i64>;
     fn compare(
         &self,
@@ -33,23 +57,15 @@ pub trait Comparator: Sync + Send + Debug {
     ) -> Result<i64, Error>;
 }
 
-pub fn get_comparators() -> HashMap<BlockChainType, Arc<dyn Comparator>> {
-    let mut comparators = HashMap::<BlockChainType, Arc<dyn Comparator>>::new();
-    comparators.insert(
-        BlockChainType::Eth,
-        Arc::new(LatestBlockEthComparator::default()),
-    );
-    comparators.insert(
-        BlockChainType::Matic,
-        Arc::new(LatestBlockEthComparator::default()),
-    );
-    comparators.insert(
-        BlockChainType::Bsc,
-        Arc::new(LatestBlockEthComparator::default()),
-    );
-    comparators.insert(
-        BlockChainType::Dot,
-        Arc::new(LatestBlockDotComparator::default()),
-    );
-    comparators
+pub fn get_comparators(chains: &ChainRegistry) -> HashMap<BlockChainType, Arc<dyn Comparator>> {
+    chains
+        .get_chains()
+        .into_iter()
+        .filter_map(|spec| {
+            spec.id
+                .parse::<BlockChainType>()
+                .ok()
+                .map(|chain| (chain, spec.comparator.create_comparator()))
+        })
+        .collect()
 }
@@ -0,0 +1,367 @@
+use crate::models::chains::{ChainRegistry, CHAIN_REGISTRY_FILE};
+use crate::service::generator::TaskConfig;
+use crate::service::judgment::ReportCheck;
+use crate::tasks::benchmark::generator::BenchmarkConfig;
+use crate::tasks::dot::generator::SubstrateConfig;
+use crate::tasks::eth::random_block::generator::RandomBlockConfig;
+use crate::tasks::generator::TaskRegistry;
+use crate::{
+    CONFIG_BENCHMARK_DIR, CONFIG_HTTP_REQUEST_DIR, CONFIG_RANDOM_BLOCK_DIR, CONFIG_SUBSTRATE_DIR,
+    CONFIG_WEBSOCKET_DIR, TASK_CONFIG_WATCH_PERIOD,
+};
+use anyhow::{anyhow, Error};
+use common::job_manage::JobRole;
+use common::tasks::http_request::HttpRequestJobConfig;
+use common::tasks::websocket_request::JobWebsocketConfig;
+use common::tasks::{
+    get_config_files, merge_config, read_json_file, LoadConfigs, RejectedConfig, DEFAULT_KEY,
+};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+
+const TASK_MASTER_FILE: &str = "task_master.json";
+const NAME_KEY: &str = "name";
+
+/*
+ * Summary of a reload, task key has format {task_dir}/{task_name}
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskConfigChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    pub task_master_changed: bool,
+}
+
+impl TaskConfigChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && !self.task_master_changed
+    }
+}
+
+/*
+ * Raw content of task config dir, each task keeps its config entries (merged with default.json)
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaskConfigSnapshot {
+    task_master: Value,
+    tasks: BTreeMap<String, Vec<Value>>,
+}
+
+impl TaskConfigSnapshot {
+    /*
+     * Read and validate whole task config dir, any invalid file fails the load
+     */
+    pub fn load(config_dir: &str) -> Result<Self, Error> {
+        let task_master = read_json_file(&Path::new(config_dir).join(TASK_MASTER_FILE))
+            .map_err(|err| anyhow!("Cannot load {}: {}", TASK_MASTER_FILE, err))?;
+        serde_json::from_value::<TaskConfig>(task_master.clone())
+            .map_err(|err| anyhow!("Invalid {}: {}", TASK_MASTER_FILE, err))?;
+        let mut tasks = BTreeMap::<String, Vec<Value>>::new();
+        for task_dir in get_task_dirs() {
+            let path = Path::new(config_dir).join(task_dir);
+            let default_path = path.join(format!("{}.json", DEFAULT_KEY));
+            let default_config = if default_path.exists() {
+                read_json_file(&default_path)
+                    .map_err(|err| anyhow!("Cannot load {:?}: {}", default_path, err))?
+                    .as_object()
+                    .cloned()
+                    .ok_or(anyhow!("{:?} is not a json object", default_path))?
+            } else {
+                Map::new()
+            };
+            for file in get_config_files(&path) {
+                let config = read_json_file(&file)
+                    .map_err(|err| anyhow!("Cannot load {:?}: {}", file, err))?;
+                let entries = match config {
+                    Value::Array(values) => values,
+                    value @ Value::Object(_) => vec![value],
+                    _ => return Err(anyhow!("{:?} must contain an object or array", file)),
+                };
+                for entry in entries.iter() {
+                    let entry = entry
+                        .as_object()
+                        .ok_or(anyhow!("Task config in {:?} is not a json object", file))?;
+                    let name = entry
+                        .get(NAME_KEY)
+                        .and_then(|name| name.as_str())
+                        .ok_or(anyhow!("Task config in {:?} has no name", file))?;
+                    let mut config = default_config.clone();
+                    merge_config(&mut config, entry);
+                    tasks
+                        .entry(format!("{}/{}", task_dir, name))
+                        .or_default()
+                        .push(Value::Object(config));
+                }
+            }
+        }
+        Ok(TaskConfigSnapshot { task_master, tasks })
+    }
+    pub fn diff(&self, new: &TaskConfigSnapshot) -> TaskConfigChanges {
+        let mut changes = TaskConfigChanges {
+            task_master_changed: self.task_master != new.task_master,
+            ..Default::default()
+        };
+        for (key, configs) in new.tasks.iter() {
+            match self.tasks.get(key) {
+                None => changes.added.push(key.clone()),
+                Some(old_configs) if old_configs != configs => changes.changed.push(key.clone()),
+                _ => {}
+            }
+        }
+        for key in self.tasks.keys() {
+            if !new.tasks.contains_key(key) {
+                changes.removed.push(key.clone());
+            }
+        }
+        changes
+    }
+}
+
+/*
+ * Reload task configs at runtime: task generators and judgment thresholds are swapped,
+ * running plans and result caches are kept. Scheduler config (scheduler.json) is not reloadable.
+ */
+#[derive(Default)]
+pub struct TaskConfigReloader {
+    config_dir: String,
+    task_registry: Arc<TaskRegistry>,
+    regular_judgments: Vec<Arc<dyn ReportCheck>>,
+    verification_judgments: Vec<Arc<dyn ReportCheck>>,
+    snapshot: Mutex<TaskConfigSnapshot>,
+}
+
+impl TaskConfigReloader {
+    pub fn new(
+        config_dir: &str,
+        task_registry: Arc<TaskRegistry>,
+        regular_judgments: Vec<Arc<dyn ReportCheck>>,
+        verification_judgments: Vec<Arc<dyn ReportCheck>>,
+    ) -> Self {
+        let snapshot = TaskConfigSnapshot::load(config_dir).unwrap_or_else(|err| {
+            warn!("Cannot load task config snapshot: {:?}", err);
+            TaskConfigSnapshot::default()
+        });
+        TaskConfigReloader {
+            config_dir: config_dir.to_string(),
+            task_registry,
+            regular_judgments,
+            verification_judgments,
+            snapshot: Mutex::new(snapshot),
+        }
+    }
+    pub async fn reload(&self) -> Result<TaskConfigChanges, Error> {
+        // Only one reload at a time
+        let mut snapshot = self.snapshot.lock().await;
+        // Validate before touching anything, current configs stay active on error
+        let rejected = validate_task_configs(&self.config_dir);
+        if !rejected.is_empty() {
+            return Err(anyhow!(
+                "Rejected task configs: {}",
+                rejected
+                    .iter()
+                    .map(|config| config.to_string())
+                    .collect::<Vec<String>>()
+                    .join("; ")
+            ));
+        }
+        let new_snapshot = TaskConfigSnapshot::load(&self.config_dir)?;
+        let task_config = TaskConfig::load(&self.config_dir)?;
+        self.task_registry.reload(&self.config_dir, &task_config);
+        for (phase, judgments) in [
+            (JobRole::Regular, &self.regular_judgments),
+            (JobRole::Verification, &self.verification_judgments),
+        ] {
+            for judgment in judgments.iter() {
+                if let Err(err) = judgment.reload_configs(&self.config_dir, &phase) {
+                    error!(
+                        "Reload configs of judgment {} error: {:?}",
+                        judgment.get_name(),
+                        err
+                    );
+                }
+            }
+        }
+        let changes = snapshot.diff(&new_snapshot);
+        *snapshot = new_snapshot;
+        info!("Task configs reloaded with changes {:?}", &changes);
+        Ok(changes)
+    }
+    /*
+     * Poll modified time of config files and reload when any file is changed, added or removed
+     */
+    pub async fn watch(&self) {
+        if *TASK_CONFIG_WATCH_PERIOD == 0 {
+            info!("Task config watcher is disabled");
+            return;
+        }
+        let mut modified_times = get_modified_times(&self.config_dir);
+        loop {
+            sleep(Duration::from_secs(*TASK_CONFIG_WATCH_PERIOD)).await;
+            let current_times = get_modified_times(&self.config_dir);
+            if current_times == modified_times {
+                continue;
+            }
+            debug!("Task config files changed, reload configs");
+            modified_times = current_times;
+            if let Err(err) = self.reload().await {
+                error!("Reload task configs error: {:?}", err);
+            }
+        }
+    }
+}
+
+/*
+ * Typed validation of all task configs, regardless of phase and active flag
+ */
+pub fn validate_task_configs(config_dir: &str) -> Vec<RejectedConfig> {
+    let task_master_path = Path::new(config_dir).join(TASK_MASTER_FILE);
+    if let Err(err) = TaskConfig::load(config_dir) {
+        return vec![RejectedConfig::new(&task_master_path, "", err.to_string())];
+    }
+    let mut rejected = Vec::new();
+    let chain_registry_path = Path::new(config_dir).join(CHAIN_REGISTRY_FILE);
+    if chain_registry_path.exists() {
+        if let Err(err) = ChainRegistry::load(config_dir) {
+            rejected.push(RejectedConfig::new(
+                &chain_registry_path,
+                "",
+                err.to_string(),
+            ));
+        }
+    }
+    for task_dir in get_task_dirs() {
+        let path = Path::new(config_dir).join(task_dir);
+        if !path.is_dir() {
+            rejected.push(RejectedConfig::new(
+                &path,
+                "",
+                "task config dir not found".to_string(),
+            ));
+            continue;
+        }
+        let mut dir_rejected = match task_dir {
+            dir if dir == CONFIG_WEBSOCKET_DIR.as_str() => {
+                JobWebsocketConfig::validate_configs(&path)
+            }
+            dir if dir == CONFIG_BENCHMARK_DIR.as_str() => BenchmarkConfig::validate_configs(&path),
+            dir if dir == CONFIG_RANDOM_BLOCK_DIR.as_str() => {
+                RandomBlockConfig::validate_configs(&path)
+            }
+            dir if dir == CONFIG_SUBSTRATE_DIR.as_str() => SubstrateConfig::validate_configs(&path),
+            _ => HttpRequestJobConfig::validate_configs(&path),
+        };
+        rejected.append(&mut dir_rejected);
+    }
+    rejected
+}
+
+fn get_task_dirs() -> Vec<&'static str> {
+    vec![
+        CONFIG_HTTP_REQUEST_DIR.as_str(),
+        CONFIG_WEBSOCKET_DIR.as_str(),
+        CONFIG_BENCHMARK_DIR.as_str(),
+        CONFIG_RANDOM_BLOCK_DIR.as_str(),
+        CONFIG_SUBSTRATE_DIR.as_str(),
+    ]
+}
+
+fn get_modified_times(config_dir: &str) -> BTreeMap<PathBuf, SystemTime> {
+    let mut files = vec![
+        Path::new(config_dir).join(TASK_MASTER_FILE),
+        Path::new(config_dir).join(CHAIN_REGISTRY_FILE),
+    ];
+    for task_dir in get_task_dirs() {
+        let path = Path::new(config_dir).join(task_dir);
+        files.push(path.join(format!("{}.json", DEFAULT_KEY)));
+        files.append(&mut get_config_files(&path));
+    }
+    files
+        .into_iter()
+        .filter_map(|file| {
+            let modified = std::fs::metadata(&file).and_then(|md| md.modified()).ok()?;
+            Some((file, modified))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CONFIG_TASK_DIR;
+    use serde_json::json;
+    use test_util::helper::load_env;
+
+    #[test]
+    fn test_snapshot_diff() -> Result<(), Error> {
+        load_env();
+        let snapshot = TaskConfigSnapshot::load(CONFIG_TASK_DIR.as_str())?;
+        assert!(snapshot.diff(&snapshot).is_empty());
+        assert!(snapshot.tasks.contains_key("http_request/RoundTripTime"));
+
+        let mut new_snapshot = snapshot.clone();
+        new_snapshot.tasks.remove("websocket/EthWebsocket");
+        new_snapshot.tasks.insert(
+            "http_request/NewTask".to_string(),
+            vec![json!({"name": "NewTask"})],
+        );
+        if let Some(configs) = new_snapshot.tasks.get_mut("http_request/RoundTripTime") {
+            configs[0]["active"] = json!(false);
+        }
+        let changes = snapshot.diff(&new_snapshot);
+        assert_eq!(
+            changes,
+            TaskConfigChanges {
+                added: vec!["http_request/NewTask".to_string()],
+                removed: vec!["websocket/EthWebsocket".to_string()],
+                changed: vec!["http_request/RoundTripTime".to_string()],
+                task_master_changed: false,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_task_configs() {
+        load_env();
+        let rejected = validate_task_configs(CONFIG_TASK_DIR.as_str());
+        assert!(rejected.is_empty(), "Rejected configs {:?}", rejected);
+        let rejected = validate_task_configs("not_existed_dir");
+        assert_eq!(rejected.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reload_unchanged_configs() -> Result<(), Error> {
+        load_env();
+        let config_dir = CONFIG_TASK_DIR.as_str();
+        let task_config = TaskConfig::load(config_dir)?;
+        let task_registry = Arc::new(TaskRegistry::new(config_dir, &task_config));
+        let tasks = task_registry.get_tasks(&JobRole::Regular);
+        let chains = task_registry.get_chains();
+        let reloader = TaskConfigReloader::new(config_dir, task_registry.clone(), vec![], vec![]);
+        let changes = reloader.reload().await?;
+        assert!(changes.is_empty());
+        // Running generators keep their snapshot while registry serves new tasks
+        assert!(!Arc::ptr_eq(
+            &tasks,
+            &task_registry.get_tasks(&JobRole::Regular)
+        ));
+        assert_eq!(
+            tasks.len(),
+            task_registry.get_tasks(&JobRole::Regular).len()
+        );
+        // Chain registry is reloaded with the tasks
+        assert!(!Arc::ptr_eq(&chains, &task_registry.get_chains()));
+        Ok(())
+    }
+}
@@ -1,23 +1,47 @@
 use crate::models::jobs::JobAssignmentBuffer;
+use crate::models::providers::ProviderStorage;
+use crate::models::workers::WorkerInfoStorage;
+use crate::persistence::services::JobService;
+use crate::service::rebalancer::JobRebalancer;
 
-use crate::DELIVERY_PERIOD;
-use common::jobs::{Job, JobAssignment};
-use common::workers::Worker;
-use common::{PlanId, WorkerId};
+use crate::{
+    DELIVERY_PERIOD, JOB_DELIVERY_MAX_ATTEMPTS, JOB_DELIVERY_RETRY_DELAY, PULL_JOBS_ACK_TIMEOUT,
+};
+use common::jobs::{Job, JobAssignment, JobStatus};
+use common::util::{get_current_time, warning_if_error};
+use common::workers::{MatchedWorkers, Worker, WorkerJobsResult};
+use common::{JobId, PlanId, Timestamp, WorkerId};
 use futures_util::future::{join, join_all};
-use log::error;
-use std::collections::HashMap;
+use log::{debug, error, info, warn};
+use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
 use tokio::task;
-use tokio::time::sleep;
+use tokio::time::{sleep, timeout};
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct JobDelivery {
     assignment_buffer: Arc<Mutex<JobAssignmentBuffer>>,
     cancel_plans_buffer: Arc<Mutex<CancelPlanBuffer>>,
+    //Jobs and cancel plans for pull mode workers
+    pull_buffer: Arc<PullJobBuffer>,
+    worker_pool: Arc<WorkerInfoStorage>,
+    providers: Arc<ProviderStorage>,
+    job_service: Arc<JobService>,
+    //Regular jobs may be moved by rebalancer while they are waiting for retry
+    rebalancer: Arc<JobRebalancer>,
+    //Failed assignments waiting for retry, purged when their plans are cancelled
+    pending_assignments: Arc<Mutex<Vec<JobAssignment>>>,
+}
+
+/*
+ * Delay in milliseconds before retry a failed delivery, doubled for each attempt
+ */
+fn get_retry_delay(attempts: u32) -> Timestamp {
+    let factor = 1 << attempts.saturating_sub(1).min(16);
+    JOB_DELIVERY_RETRY_DELAY.saturating_mul(factor)
 }
 
 #[derive(Default, Clone)]
@@ -51,6 +75,140 @@ impl DerefMut for CancelPlanBuffer {
     }
 }
 
+#[derive(Default)]
+struct WorkerMailbox {
+    jobs: Vec<Job>,
+    cancel_plans: Vec<PlanId>,
+    //Polled jobs waiting for acknowledgement with their poll time
+    unacked_jobs: HashMap<JobId, (Job, Timestamp)>,
+    last_poll: Option<Timestamp>,
+    notify: Arc<Notify>,
+}
+
+impl WorkerMailbox {
+    fn take(&mut self, now: Timestamp, ack_timeout: Timestamp) -> WorkerJobsResult {
+        //Return jobs which are not acknowledged in time to pending list
+        let expired_ids = self
+            .unacked_jobs
+            .iter()
+            .filter(|(_, (_, poll_time))| now - *poll_time > ack_timeout)
+            .map(|(job_id, _)| job_id.clone())
+            .collect::<Vec<JobId>>();
+        for job_id in expired_ids {
+            if let Some((job, _)) = self.unacked_jobs.remove(&job_id) {
+                self.jobs.push(job);
+            }
+        }
+        let jobs = std::mem::take(&mut self.jobs);
+        for job in jobs.iter() {
+            self.unacked_jobs
+                .insert(job.job_id.clone(), (job.clone(), now));
+        }
+        WorkerJobsResult {
+            jobs,
+            cancel_plans: std::mem::take(&mut self.cancel_plans),
+        }
+    }
+}
+
+/*
+ * Jobs and cancel plans waiting for pull mode workers.
+ * A worker polls its mailbox with a long poll request, polled jobs stay unacknowledged until worker acks them,
+ * jobs which are not acknowledged in PULL_JOBS_ACK_TIMEOUT are delivered again in the next poll
+ */
+#[derive(Default)]
+pub struct PullJobBuffer {
+    mailboxes: Mutex<HashMap<WorkerId, WorkerMailbox>>,
+}
+
+impl PullJobBuffer {
+    pub async fn push_jobs(&self, worker_id: &WorkerId, jobs: Vec<Job>) {
+        let mut mailboxes = self.mailboxes.lock().await;
+        let mailbox = mailboxes.entry(worker_id.clone()).or_default();
+        mailbox.jobs.extend(jobs);
+        mailbox.notify.notify_one();
+    }
+    pub async fn push_cancel_plans(&self, worker_id: &WorkerId, plans: Vec<PlanId>) {
+        let mut mailboxes = self.mailboxes.lock().await;
+        let mailbox = mailboxes.entry(worker_id.clone()).or_default();
+        //Jobs of cancelled plans are not delivered any more
+        mailbox.jobs.retain(|job| !plans.contains(&job.plan_id));
+        mailbox
+            .unacked_jobs
+            .retain(|_, (job, _)| !plans.contains(&job.plan_id));
+        mailbox.cancel_plans.extend(plans);
+        mailbox.notify.notify_one();
+    }
+    /*
+     * Wait until there are jobs or cancel plans for the worker or wait time is over
+     */
+    pub async fn poll(&self, worker_id: &WorkerId, wait: Duration) -> WorkerJobsResult {
+        let ack_timeout = *PULL_JOBS_ACK_TIMEOUT as Timestamp * 1000;
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            let notify = {
+                let mut mailboxes = self.mailboxes.lock().await;
+                let mailbox = mailboxes.entry(worker_id.clone()).or_default();
+                let now = get_current_time();
+                mailbox.last_poll = Some(now);
+                let result = mailbox.take(now, ack_timeout);
+                if !result.is_empty() {
+                    debug!(
+                        "Worker {} polls {} jobs and {} cancel plans",
+                        worker_id,
+                        result.jobs.len(),
+                        result.cancel_plans.len()
+                    );
+                    return result;
+                }
+                mailbox.notify.clone()
+            };
+            let remain = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if remain.is_zero() || timeout(remain, notify.notified()).await.is_err() {
+                return WorkerJobsResult::default();
+            }
+        }
+    }
+    /*
+     * Remove acknowledged jobs from unacknowledged list, return ids of jobs which are really polled by the worker
+     */
+    pub async fn ack(&self, worker_id: &WorkerId, job_ids: &[JobId]) -> Vec<JobId> {
+        let mut mailboxes = self.mailboxes.lock().await;
+        match mailboxes.get_mut(worker_id) {
+            Some(mailbox) => job_ids
+                .iter()
+                .filter(|job_id| mailbox.unacked_jobs.remove(*job_id).is_some())
+                .cloned()
+                .collect(),
+            None => vec![],
+        }
+    }
+    /*
+     * Drop mailboxes of workers removed from working pool, their jobs are reassigned by rebalancer.
+     * A worker polling again gets a new mailbox
+     */
+    pub async fn remove_mailboxes(&self, worker_ids: &[&WorkerId]) {
+        let mut mailboxes = self.mailboxes.lock().await;
+        for worker_id in worker_ids {
+            if let Some(mailbox) = mailboxes.remove(*worker_id) {
+                debug!(
+                    "Remove mailbox of worker {} with {} pending and {} unacknowledged jobs",
+                    worker_id,
+                    mailbox.jobs.len(),
+                    mailbox.unacked_jobs.len()
+                );
+            }
+        }
+    }
+    pub async fn get_last_poll(&self, worker_id: &WorkerId) -> Option<Timestamp> {
+        self.mailboxes
+            .lock()
+            .await
+            .get(worker_id)
+            .and_then(|mailbox| mailbox.last_poll)
+    }
+}
+
 impl JobDelivery {
     pub fn new(
         assignment_buffer: Arc<Mutex<JobAssignmentBuffer>>,
@@ -61,50 +219,290 @@ impl JobDelivery {
             assignment_buffer,
 
             cancel_plans_buffer,
+            pull_buffer: Default::default(),
+            worker_pool: Default::default(),
+            providers: Default::default(),
+            job_service: Default::default(),
+            rebalancer: Default::default(),
+            pending_assignments: Default::default(),
+        }
+    }
+    pub fn with_pull_buffer(mut self, pull_buffer: Arc<PullJobBuffer>) -> Self {
+        self.pull_buffer = pull_buffer;
+        self
+    }
+    pub fn with_worker_pool(mut self, worker_pool: Arc<WorkerInfoStorage>) -> Self {
+        self.worker_pool = worker_pool;
+        self
+    }
+    pub fn with_providers(mut self, providers: Arc<ProviderStorage>) -> Self {
+        self.providers = providers;
+        self
+    }
+    pub fn with_job_service(mut self, job_service: Arc<JobService>) -> Self {
+        self.job_service = job_service;
+        self
+    }
+    pub fn with_rebalancer(mut self, rebalancer: Arc<JobRebalancer>) -> Self {
+        self.rebalancer = rebalancer;
+        self
+    }
+    /*
+     * Send jobs to current registered workers, return assignments which cannot be delivered
+     */
+    async fn deliver_assignments(&self, assignments: Vec<JobAssignment>) -> Vec<JobAssignment> {
+        let mut failed_assignments = Vec::new();
+        let mut worker_assignments = HashMap::<WorkerId, Vec<JobAssignment>>::default();
+        for assignment in assignments.into_iter() {
+            worker_assignments
+                .entry(assignment.worker.get_id())
+                .or_default()
+                .push(assignment);
+        }
+        let mut handlers = Vec::new();
+        for (id, mut assignments) in worker_assignments.into_iter() {
+            // Worker may register again with a new secret or be removed by health check
+            let worker = match self.worker_pool.get_worker(id.clone()).await {
+                Some(worker) => worker,
+                None => {
+                    warn!("Worker {} is not in working pool", &id);
+                    failed_assignments.append(&mut assignments);
+                    continue;
+                }
+            };
+            let jobs = assignments
+                .iter()
+                .map(|assignment| assignment.job.clone())
+                .collect::<Vec<Job>>();
+            if worker.is_pull_mode() {
+                //Pull mode jobs are marked as delivered when worker acknowledges them
+                self.pull_buffer.push_jobs(&id, jobs).await;
+                continue;
+            }
+            let handler = tokio::spawn(async move {
+                // Process each socket concurrently.
+                let res = worker.send_jobs(&jobs).await;
+                (id, assignments, res)
+            });
+            handlers.push(handler);
+        }
+        for res in join_all(handlers).await {
+            match res {
+                Ok((worker_id, assignments, Ok(()))) => {
+                    let job_ids = assignments
+                        .iter()
+                        .map(|assignment| assignment.job.job_id.clone())
+                        .collect::<Vec<JobId>>();
+                    let res = self
+                        .job_service
+                        .update_assignment_status(&worker_id, &job_ids, JobStatus::DELIVERED)
+                        .await;
+                    warning_if_error("update_assignment_status return error", res);
+                }
+                Ok((worker_id, mut assignments, Err(err))) => {
+                    warn!(
+                        "Cannot deliver {} jobs to worker {}: {:?}",
+                        assignments.len(),
+                        &worker_id,
+                        err
+                    );
+                    failed_assignments.append(&mut assignments);
+                }
+                Err(err) => {
+                    error!("Delivery task error: {:?}", err);
+                }
+            }
+        }
+        failed_assignments
+    }
+    /*
+     * Retry failed delivery with backoff, after JOB_DELIVERY_MAX_ATTEMPTS failures
+     * job is reassigned to another matched worker
+     */
+    async fn handle_failed_assignment(
+        &self,
+        mut assignment: JobAssignment,
+    ) -> Option<JobAssignment> {
+        assignment.delivery_attempts += 1;
+        if assignment.delivery_attempts < *JOB_DELIVERY_MAX_ATTEMPTS {
+            assignment.next_delivery =
+                get_current_time() + get_retry_delay(assignment.delivery_attempts);
+            return Some(assignment);
+        }
+        let mut excluded = assignment.failed_workers.clone();
+        excluded.push(assignment.worker.get_id());
+        let matched_workers = match self
+            .providers
+            .get_active_provider(&assignment.job.component_id)
+            .await
+        {
+            Some(provider) => self
+                .worker_pool
+                .match_workers(&provider)
+                .await
+                .unwrap_or_default(),
+            //Provider of verification job is not in active list
+            None => MatchedWorkers {
+                remain_workers: self.worker_pool.get_workers().await,
+                loads: self.worker_pool.get_worker_loads(),
+                ..Default::default()
+            },
+        };
+        match matched_workers.get_candidate(&excluded) {
+            Some(worker) => {
+                if !self
+                    .rebalancer
+                    .move_job(
+                        &assignment.job.job_id,
+                        &assignment.worker.get_id(),
+                        &worker.get_id(),
+                    )
+                    .await
+                {
+                    info!(
+                        "Job {} is already moved from worker {} by rebalancer",
+                        &assignment.job.job_id,
+                        assignment.worker.get_id()
+                    );
+                    return None;
+                }
+                info!(
+                    "Reassign job {} from worker {} to worker {}",
+                    &assignment.job.job_id,
+                    assignment.worker.get_id(),
+                    worker.get_id()
+                );
+                let res = self
+                    .job_service
+                    .reassign_job(
+                        &assignment.job.job_id,
+                        &assignment.worker.get_id(),
+                        &worker.get_id(),
+                    )
+                    .await;
+                warning_if_error("reassign_job return error", res);
+                let worker_loads = self.worker_pool.get_worker_loads();
+                worker_loads
+                    .finish_jobs(
+                        &assignment.worker.get_id(),
+                        &[assignment.job.job_id.clone()],
+                    )
+                    .await;
+                worker_loads
+                    .add_job(&worker.get_id(), &assignment.job)
+                    .await;
+                Some(assignment.reassign(worker))
+            }
+            None => {
+                warn!(
+                    "Drop job {} of provider {}, there is no worker for reassignment",
+                    &assignment.job.job_id, &assignment.job.component_id
+                );
+                let res = self
+                    .job_service
+                    .update_assignment_status(
+                        &assignment.worker.get_id(),
+                        &[assignment.job.job_id.clone()],
+                        JobStatus::FAILED,
+                    )
+                    .await;
+                warning_if_error("update_assignment_status return error", res);
+                self.worker_pool
+                    .get_worker_loads()
+                    .finish_jobs(
+                        &assignment.worker.get_id(),
+                        &[assignment.job.job_id.clone()],
+                    )
+                    .await;
+                None
+            }
         }
     }
     pub async fn run(&self) {
-        let cancel_plans_buffer = self.cancel_plans_buffer.clone();
-        let assignment_buffer = self.assignment_buffer.clone();
+        let res = join(self.run_assignments(), self.run_cancel_plans()).await;
+        error!("JobDelivery stop with error: {:?}", res);
+    }
+    /*
+     * Deliver job assignments, only the leader scheduler generates assignments
+     */
+    pub async fn run_assignments(&self) {
+        let delivery = self.clone();
         let task_assignment_buffer = task::spawn(async move {
-            let mut worker_pool: HashMap<WorkerId, Arc<Worker>> = HashMap::default();
             loop {
-                let assignments = assignment_buffer.lock().await.pop_all();
-                log::debug!("Run delivery for {} jobs", assignments.len());
-                let _undelivered = Vec::<JobAssignment>::default();
-                let mut handlers = Vec::new();
-                let mut worker_jobs = HashMap::<WorkerId, Vec<Job>>::default();
-                for job_assign in assignments.into_iter() {
-                    let JobAssignment { worker, job, .. } = job_assign;
-                    let worker_id = worker.get_id();
-                    if let Some(jobs) = worker_jobs.get_mut(&worker_id) {
-                        jobs.push(job)
-                    } else {
-                        worker_jobs.insert(worker_id.clone(), vec![job]);
+                let mut assignments = delivery.assignment_buffer.lock().await.pop_all();
+                assignments.extend(delivery.pop_retries(get_current_time()).await);
+                log::debug!(
+                    "Run delivery for {} jobs, {} jobs are waiting for retry",
+                    assignments.len(),
+                    delivery.pending_assignments.lock().await.len()
+                );
+                let failed_assignments = delivery.deliver_assignments(assignments).await;
+                let mut retries = Vec::new();
+                for assignment in failed_assignments {
+                    if let Some(assignment) = delivery.handle_failed_assignment(assignment).await {
+                        retries.push(assignment);
                     }
-                    worker_pool.insert(worker_id, worker);
-                }
-                for (id, jobs) in worker_jobs.into_iter() {
-                    if let Some(worker) = worker_pool.get(&id) {
-                        let worker_cloned = worker.clone();
-                        let handler = tokio::spawn(async move {
-                            // Process each socket concurrently.
-                            worker_cloned.send_jobs(&jobs).await
-                        });
-                        handlers.push(handler);
-                    }
-                }
-                if !handlers.is_empty() {
-                    join_all(handlers).await;
                 }
+                delivery.pending_assignments.lock().await.extend(retries);
                 sleep(Duration::from_secs(DELIVERY_PERIOD)).await;
             }
         });
-
+        let res = task_assignment_buffer.await;
+        error!("Job assignment delivery stop with error: {:?}", res);
+    }
+    /*
+     * Take pending assignments which are due for retry
+     */
+    async fn pop_retries(&self, now: Timestamp) -> Vec<JobAssignment> {
+        let mut pending_assignments = self.pending_assignments.lock().await;
+        let (retries, waiting): (Vec<JobAssignment>, Vec<JobAssignment>) =
+            std::mem::take(&mut *pending_assignments)
+                .into_iter()
+                .partition(|assignment| assignment.next_delivery <= now);
+        *pending_assignments = waiting;
+        retries
+    }
+    /*
+     * Jobs of cancelled plans are not delivered any more
+     */
+    async fn purge_cancelled_plans(&self, plans: &HashSet<PlanId>) {
+        let mut pending_assignments = self.pending_assignments.lock().await;
+        let length = pending_assignments.len();
+        pending_assignments.retain(|assignment| !plans.contains(&assignment.job.plan_id));
+        if pending_assignments.len() < length {
+            debug!(
+                "Purge {} pending assignments of cancelled plans",
+                length - pending_assignments.len()
+            );
+        }
+    }
+    /*
+     * Send cancel plans, every scheduler instance cancels plans finished by its report processors
+     */
+    pub async fn run_cancel_plans(&self) {
+        let delivery = self.clone();
         let task_cancel_plans_buffer = task::spawn(async move {
             loop {
-                let cancel_plans = cancel_plans_buffer.lock().await.pop_all();
+                let cancel_plans = delivery.cancel_plans_buffer.lock().await.pop_all();
+                let plans = cancel_plans
+                    .values()
+                    .flatten()
+                    .cloned()
+                    .collect::<HashSet<PlanId>>();
+                delivery.purge_cancelled_plans(&plans).await;
+                delivery
+                    .worker_pool
+                    .get_worker_loads()
+                    .cancel_plans(&plans)
+                    .await;
                 for (worker, plans) in cancel_plans.into_iter() {
+                    if worker.is_pull_mode() {
+                        delivery
+                            .pull_buffer
+                            .push_cancel_plans(&worker.get_id(), plans)
+                            .await;
+                        continue;
+                    }
                     let res = worker.send_cancel_plans(&plans).await;
                     if let Err(err) = res {
                         error!("send_cancel_plans error: {:?}", err);
@@ -113,9 +511,8 @@ impl JobDelivery {
                 sleep(Duration::from_secs(DELIVERY_PERIOD)).await;
             }
         });
-
-        let res = join(task_assignment_buffer, task_cancel_plans_buffer).await;
-        error!("JobDelivery stop with error: {:?}", res);
+        let res = task_cancel_plans_buffer.await;
+        error!("Cancel plans delivery stop with error: {:?}", res);
     }
     // fn get_worker(&mut self, worker_id: WorkerId) -> Arc<Worker> {
     //     if let Some(worker) = self.worker_pool.get(&worker_id) {
@@ -128,3 +525,150 @@ impl JobDelivery {
     //     }
     // }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::auth::WorkerSecret;
+    use test_util::helper::{load_env, mock_db_connection, mock_worker};
+
+    fn mock_job(job_id: &str, plan_id: &str) -> Job {
+        Job {
+            job_id: job_id.to_string(),
+            plan_id: plan_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pull_job_buffer() {
+        let buffer = Arc::new(PullJobBuffer::default());
+        let worker_id = "worker_id".to_string();
+        assert!(buffer.get_last_poll(&worker_id).await.is_none());
+        // No jobs before wait time is over
+        let result = buffer.poll(&worker_id, Duration::from_millis(100)).await;
+        assert!(result.is_empty());
+        assert!(buffer.get_last_poll(&worker_id).await.is_some());
+
+        // Waiting poll is woken up by new jobs
+        let cloned_buffer = buffer.clone();
+        let cloned_id = worker_id.clone();
+        let handle = tokio::spawn(async move {
+            cloned_buffer
+                .poll(&cloned_id, Duration::from_secs(10))
+                .await
+        });
+        sleep(Duration::from_millis(100)).await;
+        buffer
+            .push_jobs(
+                &worker_id,
+                vec![mock_job("job1", "plan1"), mock_job("job2", "plan2")],
+            )
+            .await;
+        let result = handle.await.unwrap();
+        assert_eq!(result.jobs.len(), 2);
+
+        // Jobs of cancelled plan are not acknowledged
+        buffer
+            .push_cancel_plans(&worker_id, vec!["plan2".to_string()])
+            .await;
+        let acked = buffer
+            .ack(
+                &worker_id,
+                &["job1".to_string(), "job2".to_string(), "job3".to_string()],
+            )
+            .await;
+        assert_eq!(acked, vec!["job1".to_string()]);
+        let result = buffer.poll(&worker_id, Duration::from_millis(100)).await;
+        assert!(result.jobs.is_empty());
+        assert_eq!(result.cancel_plans, vec!["plan2".to_string()]);
+        assert!(buffer
+            .ack(&"other".to_string(), &["job1".to_string()])
+            .await
+            .is_empty());
+
+        // Mailbox of removed worker is dropped
+        buffer
+            .push_jobs(&worker_id, vec![mock_job("job3", "plan3")])
+            .await;
+        buffer.remove_mailboxes(&[&worker_id]).await;
+        assert!(buffer.get_last_poll(&worker_id).await.is_none());
+        let result = buffer.poll(&worker_id, Duration::from_millis(100)).await;
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_redeliver_unacked_jobs() {
+        let mut mailbox = WorkerMailbox::default();
+        mailbox.jobs.push(mock_job("job1", "plan1"));
+        assert_eq!(mailbox.take(1000, 500).jobs.len(), 1);
+        assert!(mailbox.take(1400, 500).is_empty());
+        // Not acknowledged in time
+        let result = mailbox.take(1600, 500);
+        assert_eq!(result.jobs, vec![mock_job("job1", "plan1")]);
+        assert_eq!(
+            mailbox.unacked_jobs.get("job1").map(|item| item.1),
+            Some(1600)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redeliver_failed_assignments() {
+        load_env();
+        let mut unreachable = mock_worker("unreachable");
+        unreachable.url = "http://127.0.0.1:1".to_string();
+        let unreachable = Worker::new(unreachable).with_secret(WorkerSecret::generate());
+        let other = Worker::new(mock_worker("other"));
+        let worker_pool = Arc::new(WorkerInfoStorage::new(vec![unreachable.clone(), other]));
+        let job_service = Arc::new(JobService::new(Arc::new(mock_db_connection())));
+        let delivery = JobDelivery::new(Default::default(), Default::default())
+            .with_worker_pool(worker_pool.clone())
+            .with_job_service(job_service);
+
+        let assignment = JobAssignment::new(Arc::new(unreachable), &mock_job("job1", "plan1"));
+        assert_eq!(assignment.status, JobStatus::ASSIGNED);
+        let mut failed = delivery.deliver_assignments(vec![assignment]).await;
+        assert_eq!(failed.len(), 1);
+        let mut assignment = failed.pop().unwrap();
+        // Retry with the same worker before reaching max attempts
+        for attempt in 1..*JOB_DELIVERY_MAX_ATTEMPTS {
+            assignment = delivery.handle_failed_assignment(assignment).await.unwrap();
+            assert_eq!(assignment.delivery_attempts, attempt);
+            assert!(assignment.next_delivery > get_current_time());
+            assert_eq!(assignment.worker.get_id(), "unreachable");
+        }
+        let assignment = delivery.handle_failed_assignment(assignment).await.unwrap();
+        assert_eq!(assignment.worker.get_id(), "other");
+        assert_eq!(assignment.delivery_attempts, 0);
+        assert_eq!(assignment.failed_workers, vec!["unreachable".to_string()]);
+
+        // Worker removed from pool
+        worker_pool.remove_workers(&[&"other".to_string()]).await;
+        let failed = delivery.deliver_assignments(vec![assignment]).await;
+        assert_eq!(failed.len(), 1);
+        assert_eq!(get_retry_delay(1) * 2, get_retry_delay(2));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_plan_waiting_for_retry() {
+        let worker = Arc::new(Worker::new(mock_worker("worker")));
+        let delivery = JobDelivery::new(Default::default(), Default::default());
+        let mut waiting = JobAssignment::new(worker.clone(), &mock_job("job1", "plan1"));
+        waiting.next_delivery = get_current_time() + 60000;
+        let other = JobAssignment::new(worker, &mock_job("job2", "plan2"));
+        delivery
+            .pending_assignments
+            .lock()
+            .await
+            .extend(vec![waiting, other]);
+
+        delivery
+            .purge_cancelled_plans(&HashSet::from(["plan1".to_string()]))
+            .await;
+        // Job of cancelled plan is not retried even after its retry delay
+        let retries = delivery.pop_retries(get_current_time() + 120000).await;
+        assert_eq!(retries.len(), 1);
+        assert_eq!(retries[0].job.job_id, "job2");
+        assert!(delivery.pending_assignments.lock().await.is_empty());
+    }
+}
@@ -6,9 +6,10 @@ use crate::models::jobs::JobAssignmentBuffer;
 use crate::models::providers::ProviderStorage;
 use crate::models::workers::WorkerInfoStorage;
 use crate::persistence::services::{JobService, PlanService};
-use crate::tasks::generator::get_tasks;
+use crate::service::rebalancer::JobRebalancer;
+use crate::tasks::generator::TaskRegistry;
 use crate::{CONFIG, CONFIG_TASK_DIR, JOB_VERIFICATION_GENERATOR_PERIOD};
-use common::job_manage::JobRole;
+use anyhow::anyhow;
 use common::task_spawn;
 use futures_util::future::join;
 use log::{error, info};
@@ -46,10 +47,20 @@ pub struct TaskConfig {
     pub verification: Vec<String>,
 }
 
+impl TaskConfig {
+    pub fn load(config_dir: &str) -> Result<TaskConfig, anyhow::Error> {
+        let path = Path::new(config_dir).join("task_master.json");
+        let json = std::fs::read_to_string(&path)
+            .map_err(|err| anyhow!("Error {:?}. Path not found {:?}", err, path))?;
+        serde_json::from_str(&*json).map_err(|err| anyhow!("Invalid {:?}: {}", path, err))
+    }
+}
+
 #[derive(Default)]
 pub struct JobGenerator {
     verification: VerificationJobGenerator,
     regular: RegularJobGenerator,
+    task_registry: Arc<TaskRegistry>,
 }
 
 impl JobGenerator {
@@ -64,18 +75,14 @@ impl JobGenerator {
     ) -> Self {
         //Load config
         let config_dir = &*CONFIG_TASK_DIR;
-        // let path = format!("{}/task_master.json", config_dir);
-        let path = Path::new(config_dir).join("task_master.json");
-        let json = std::fs::read_to_string(&path).unwrap_or_else(|err| {
-            panic!("Error {:?}. Path not found {:?}", err, path);
-        });
-        let task_config: TaskConfig = serde_json::from_str(&*json).unwrap();
+        let task_config = TaskConfig::load(config_dir).unwrap_or_else(|err| panic!("{}", err));
+        let task_registry = Arc::new(TaskRegistry::new(config_dir, &task_config));
         let verification = VerificationJobGenerator {
             db_conn: db_conn.clone(),
             plan_service: plan_service.clone(),
             providers: providers.clone(),
             worker_infos: worker_infos.clone(),
-            tasks: get_tasks(config_dir, JobRole::Verification, &task_config.verification),
+            task_registry: task_registry.clone(),
             job_service: job_service.clone(),
             assignments: assignments.clone(),
             result_cache: result_cache.clone(),
@@ -88,21 +95,31 @@ impl JobGenerator {
             plan_service,
             providers,
             worker_infos,
-            tasks: get_tasks(config_dir, JobRole::Regular, &task_config.regular),
+            task_registry: task_registry.clone(),
             job_service,
             assignments,
             result_cache: result_cache.clone(),
+            rebalancer: Default::default(),
         };
 
         JobGenerator {
             verification,
             regular,
+            task_registry,
         }
     }
+    pub fn with_rebalancer(mut self, rebalancer: Arc<JobRebalancer>) -> Self {
+        self.regular.rebalancer = rebalancer;
+        self
+    }
+    pub fn get_task_registry(&self) -> Arc<TaskRegistry> {
+        self.task_registry.clone()
+    }
     pub async fn run(self) {
         let JobGenerator {
             mut verification,
             mut regular,
+            ..
         } = self;
         // Run Verification task
         let verification_task = task_spawn::spawn(async move {
@@ -155,6 +172,7 @@ pub mod tests {
     use crate::persistence::PlanModel;
 
     use common::component::ComponentType;
+    use common::workers::Worker;
     use common::BlockChainType;
     use itertools::Itertools;
     use log::info;
@@ -178,7 +196,7 @@ pub mod tests {
         let worker_service = Arc::new(WorkerService::new(arc_conn.clone()));
         let job_service = Arc::new(JobService::new(arc_conn.clone()));
         let mut all_workers = worker_service.clone().get_active().await;
-        all_workers.push(mock_worker("worker_id"));
+        all_workers.push(Worker::new(mock_worker("worker_id")));
 
         // Keep the list of node and gateway
         let provider_storage = Arc::new(ProviderStorage::default());
@@ -282,7 +300,7 @@ pub mod tests {
         let worker_service = Arc::new(WorkerService::new(arc_conn.clone()));
         let job_service = Arc::new(JobService::new(arc_conn.clone()));
         let mut all_workers = worker_service.clone().get_active().await;
-        all_workers.push(mock_worker("worker_id"));
+        all_workers.push(Worker::new(mock_worker("worker_id")));
 
         // Keep the list of node and gateway
         let provider_storage = Arc::new(ProviderStorage::default());
@@ -381,7 +399,7 @@ pub mod tests {
         let worker_service = Arc::new(WorkerService::new(arc_conn.clone()));
         let job_service = Arc::new(JobService::new(arc_conn.clone()));
         let mut all_workers = worker_service.clone().get_active().await;
-        all_workers.push(mock_worker("worker_id"));
+        all_workers.push(Worker::new(mock_worker("worker_id")));
 
         // Keep the list of node and gateway
         let provider_storage = Arc::new(ProviderStorage::default());
@@ -471,7 +489,7 @@ pub mod tests {
         let worker_service = Arc::new(WorkerService::new(arc_conn.clone()));
         let job_service = Arc::new(JobService::new(arc_conn.clone()));
         let mut all_workers = worker_service.clone().get_active().await;
-        all_workers.push(mock_worker("worker_id"));
+        all_workers.push(Worker::new(mock_worker("worker_id")));
 
         // Keep the list of node and gateway
         let provider_storage = Arc::new(ProviderStorage::default());
@@ -5,8 +5,9 @@ use crate::models::providers::ProviderStorage;
 use crate::models::workers::WorkerInfoStorage;
 use crate::persistence::services::{JobService, PlanService};
 use crate::persistence::PlanModel;
+use crate::service::rebalancer::JobRebalancer;
 
-use crate::tasks::generator::TaskApplicant;
+use crate::tasks::generator::TaskRegistry;
 use anyhow::{anyhow, Error};
 use common::component::ComponentInfo;
 use common::job_manage::JobRole;
@@ -26,10 +27,11 @@ pub struct RegularJobGenerator {
     pub plan_service: Arc<PlanService>,
     pub providers: Arc<ProviderStorage>,
     pub worker_infos: Arc<WorkerInfoStorage>,
-    pub tasks: Vec<Arc<dyn TaskApplicant>>,
+    pub task_registry: Arc<TaskRegistry>,
     pub job_service: Arc<JobService>,
     pub assignments: Arc<Mutex<JobAssignmentBuffer>>,
     pub result_cache: Arc<JobResultCache>,
+    pub rebalancer: Arc<JobRebalancer>,
 }
 
 impl RegularJobGenerator {
@@ -96,6 +98,9 @@ impl RegularJobGenerator {
                 .save_job_assignments(&list_assignments)
                 .await;
             warning_if_error("save_job_assignments return error", res);
+            self.rebalancer
+                .register_assignments(&list_assignments)
+                .await;
             self.assignments
                 .lock()
                 .await
@@ -139,7 +144,8 @@ impl RegularJobGenerator {
             .await
             .unwrap_or(MatchedWorkers::default());
 
-        for task in self.tasks.iter() {
+        let tasks = self.task_registry.get_tasks(&JobRole::Regular);
+        for task in tasks.iter() {
             if !task.can_apply(provider) {
                 continue;
             }
@@ -158,13 +164,16 @@ impl RegularJobGenerator {
                 latest_task_update
             );
             let plan_id = format!("{}-{}", JobRole::Regular.to_string(), provider.id);
-            if let Ok(applied_jobs) = task.apply_with_cache(
-                &plan_id,
-                &provider,
-                JobRole::Regular,
-                &matched_workers,
-                latest_task_update,
-            ) {
+            if let Ok(applied_jobs) = task
+                .apply_with_cache(
+                    &plan_id,
+                    &provider,
+                    JobRole::Regular,
+                    &matched_workers,
+                    latest_task_update,
+                )
+                .await
+            {
                 if applied_jobs.jobs.len() > 0 {
                     debug!(
                         "Generated {} regular jobs for provider {}, {:?}",
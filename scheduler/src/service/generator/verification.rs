@@ -8,7 +8,7 @@ use crate::persistence::services::{JobService, PlanService};
 use crate::persistence::PlanModel;
 
 use crate::service::judgment::JudgmentsResult;
-use crate::tasks::generator::TaskApplicant;
+use crate::tasks::generator::{TaskApplicant, TaskRegistry};
 
 use anyhow::anyhow;
 use common::component::ComponentInfo;
@@ -35,7 +35,7 @@ pub struct VerificationJobGenerator {
     pub plan_service: Arc<PlanService>,
     pub providers: Arc<ProviderStorage>,
     pub worker_infos: Arc<WorkerInfoStorage>,
-    pub tasks: Vec<Arc<dyn TaskApplicant>>,
+    pub task_registry: Arc<TaskRegistry>,
     pub job_service: Arc<JobService>,
     pub assignments: Arc<Mutex<JobAssignmentBuffer>>,
     pub result_cache: Arc<JobResultCache>,
@@ -87,10 +87,11 @@ impl VerificationJobGenerator {
 
         let mut providers = self.providers.pop_components_for_verifications().await;
         if !providers.is_empty() {
+            let tasks = self.task_registry.get_tasks(&JobRole::Verification);
             log::debug!(
                 "Generate verification jobs for {} providers with {} tasks",
                 providers.len(),
-                self.tasks.len()
+                tasks.len()
             );
             let mut total_assignment_buffer = JobAssignmentBuffer::default();
             for provider_plan in providers.iter() {
@@ -103,7 +104,7 @@ impl VerificationJobGenerator {
                     debug!("matched workers {:?}", &matched_workers);
                     let plan_task = WaitingProviderPlanTask {
                         provider_plan: provider_plan.clone(),
-                        tasks: self.tasks.clone(),
+                        tasks: tasks.as_ref().clone(),
                     };
                     let waiting_task = self
                         .generate_provider_plan_jobs(
@@ -218,13 +219,15 @@ impl VerificationJobGenerator {
                 .map(|(key, value)| (key.task_name.clone(), value.clone()))
                 .collect::<HashMap<String, JudgmentsResult>>();
             log::debug!("Generate jobs for task {}", task.get_type());
-            let applied_jobs = task.apply(
-                &provider_plan.plan.plan_id,
-                &provider_plan.provider,
-                JobRole::Verification,
-                &matched_workers,
-                &sub_task_results,
-            );
+            let applied_jobs = task
+                .apply(
+                    &provider_plan.plan.plan_id,
+                    &provider_plan.provider,
+                    JobRole::Verification,
+                    &matched_workers,
+                    &sub_task_results,
+                )
+                .await;
             match applied_jobs {
                 Ok(mut applied_jobs) => {
                     trace!("applied_jobs: {:?}", applied_jobs);
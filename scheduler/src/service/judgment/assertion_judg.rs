@@ -0,0 +1,782 @@
+use crate::models::job_result::ProviderTask;
+use crate::models::reloadable::Reloadable;
+use crate::service::judgment::{JudgmentsResult, ReportCheck};
+use crate::service::report_portal::{ReportErrorCode, ReportFailedReason, ReportFailedReasons};
+use crate::{CONFIG_HTTP_REQUEST_DIR, JUDGMENT_CACHE_TTL};
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use common::job_manage::{JobResultDetail, JobRole};
+use common::jobs::JobResult;
+use common::tasks::http_request::{HttpRequestJobConfig, JobHttpResponseDetail, JobHttpResult};
+use common::tasks::{LoadConfigs, TaskConfigTrait};
+use common::util::get_current_time;
+use common::{PlanId, Timestamp};
+use log::{debug, error, trace};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fmt::Formatter;
+use std::path::Path;
+use tokio::sync::Mutex;
+
+const ASSERTIONS_KEY: &str = "assertions";
+const ERROR_CODE_KEY: &str = "error_code";
+const WINDOW_SEPARATOR: &str = " over last ";
+const SUCCESS_RATE: &str = "success_rate";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompareOperator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl CompareOperator {
+    //Two characters operators go first so "<=" is not split by "<"
+    const SYMBOLS: [(&'static str, CompareOperator); 6] = [
+        ("==", CompareOperator::Equal),
+        ("!=", CompareOperator::NotEqual),
+        ("<=", CompareOperator::LessOrEqual),
+        (">=", CompareOperator::GreaterOrEqual),
+        ("<", CompareOperator::Less),
+        (">", CompareOperator::Greater),
+    ];
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AggregateFunction {
+    Percentile(f64),
+    Avg,
+    Min,
+    Max,
+    Sum,
+    Count,
+}
+
+impl AggregateFunction {
+    fn parse(name: &str) -> Result<Self, Error> {
+        match name {
+            "avg" => Ok(AggregateFunction::Avg),
+            "min" => Ok(AggregateFunction::Min),
+            "max" => Ok(AggregateFunction::Max),
+            "sum" => Ok(AggregateFunction::Sum),
+            "count" => Ok(AggregateFunction::Count),
+            _ => name
+                .strip_prefix('p')
+                .and_then(|percent| percent.parse::<f64>().ok())
+                .filter(|percent| *percent > 0.0 && *percent <= 100.0)
+                .map(AggregateFunction::Percentile)
+                .ok_or_else(|| anyhow!("Unknown function {}", name)),
+        }
+    }
+    fn apply(&self, mut values: Vec<f64>) -> Option<f64> {
+        if values.is_empty() {
+            return match self {
+                AggregateFunction::Count | AggregateFunction::Sum => Some(0.0),
+                _ => None,
+            };
+        }
+        let size = values.len() as f64;
+        match self {
+            AggregateFunction::Avg => Some(values.iter().sum::<f64>() / size),
+            AggregateFunction::Min => values.into_iter().reduce(f64::min),
+            AggregateFunction::Max => values.into_iter().reduce(f64::max),
+            AggregateFunction::Sum => Some(values.iter().sum()),
+            AggregateFunction::Count => Some(size),
+            AggregateFunction::Percentile(percent) => {
+                // Nearest rank percentile
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+                let rank = (percent / 100.0 * size).ceil() as usize;
+                values.get(rank.max(1) - 1).cloned()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operand {
+    Number(f64),
+    Text(String),
+    //Field of one result: http_code, error_code, response_duration, success, body, values.<name>
+    Field(String),
+    //Function over the field of all results in window
+    Aggregate(AggregateFunction, String),
+}
+
+impl Operand {
+    fn parse(input: &str) -> Result<Self, Error> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(anyhow!("Missing operand"));
+        }
+        for quote in ['\'', '"'] {
+            if input.len() >= 2 && input.starts_with(quote) && input.ends_with(quote) {
+                return Ok(Operand::Text(input[1..input.len() - 1].to_string()));
+            }
+        }
+        if let Ok(number) = input.parse::<f64>() {
+            return Ok(Operand::Number(number));
+        }
+        if input == SUCCESS_RATE {
+            return Ok(Operand::Aggregate(
+                AggregateFunction::Avg,
+                String::from("success"),
+            ));
+        }
+        if let Some((name, field)) = input
+            .strip_suffix(')')
+            .and_then(|input| input.split_once('('))
+        {
+            let field = field.trim();
+            Self::check_field(field)?;
+            return Ok(Operand::Aggregate(
+                AggregateFunction::parse(name.trim())?,
+                field.to_string(),
+            ));
+        }
+        Self::check_field(input)?;
+        Ok(Operand::Field(input.to_string()))
+    }
+    fn check_field(field: &str) -> Result<(), Error> {
+        let valid = field
+            .chars()
+            .next()
+            .map(|first| first.is_ascii_alphabetic() || first == '_')
+            .unwrap_or(false)
+            && field
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+        if valid {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid field name {}", field))
+        }
+    }
+    fn is_field(&self) -> bool {
+        matches!(self, Operand::Field(_))
+    }
+}
+
+/*
+ * One comparison of the form "<operand> <operator> <operand> [over last <N>]"
+ * Fields are checked on each of the last N results, aggregates are computed over them.
+ * Window is 1 (the newest result) if not specified.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assertion {
+    pub expression: String,
+    pub left: Operand,
+    pub operator: CompareOperator,
+    pub right: Operand,
+    pub window: usize,
+    pub error_code: ReportErrorCode,
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum AssertionConfig {
+    Expression(String),
+    Detail {
+        expr: String,
+        #[serde(default)]
+        error_code: Option<ReportErrorCode>,
+    },
+}
+
+impl Assertion {
+    pub fn parse(expression: &str, error_code: ReportErrorCode) -> Result<Self, Error> {
+        let (comparison, window) = match expression.rsplit_once(WINDOW_SEPARATOR) {
+            Some((comparison, window)) => {
+                let window = window
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|window| *window > 0)
+                    .ok_or_else(|| anyhow!("Invalid window {} in {}", window, expression))?;
+                (comparison, window)
+            }
+            None => (expression, 1),
+        };
+        let (left, operator, right) = Self::split_comparison(comparison)
+            .ok_or_else(|| anyhow!("Missing compare operator in {}", expression))?;
+        Ok(Assertion {
+            expression: expression.trim().to_string(),
+            left: Operand::parse(left).map_err(|err| anyhow!("{} in {}", err, expression))?,
+            operator,
+            right: Operand::parse(right).map_err(|err| anyhow!("{} in {}", err, expression))?,
+            window,
+            error_code,
+        })
+    }
+    pub fn from_config(
+        config: &AssertionConfig,
+        default_code: &ReportErrorCode,
+    ) -> Result<Self, Error> {
+        match config {
+            AssertionConfig::Expression(expr) => Self::parse(expr, default_code.clone()),
+            AssertionConfig::Detail { expr, error_code } => Self::parse(
+                expr,
+                error_code.clone().unwrap_or_else(|| default_code.clone()),
+            ),
+        }
+    }
+    //Find the first operator outside of quoted text
+    fn split_comparison(comparison: &str) -> Option<(&str, CompareOperator, &str)> {
+        let mut quote: Option<char> = None;
+        for (ind, c) in comparison.char_indices() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if c == '\'' || c == '"' => quote = Some(c),
+                None => {
+                    for (symbol, operator) in CompareOperator::SYMBOLS.iter() {
+                        if comparison[ind..].starts_with(symbol) {
+                            return Some((
+                                &comparison[..ind],
+                                operator.clone(),
+                                &comparison[ind + symbol.len()..],
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+    /// Return Ok(None) if the assertion holds, otherwise the failed detail
+    pub fn check(&self, samples: &[AssertionSample]) -> Result<Option<String>, Error> {
+        let window = &samples[samples.len().saturating_sub(self.window)..];
+        if self.left.is_field() || self.right.is_field() {
+            for sample in window.iter().rev() {
+                let left = Self::evaluate(&self.left, window, Some(sample))?;
+                let right = Self::evaluate(&self.right, window, Some(sample))?;
+                if !Self::compare(&left, &self.operator, &right)? {
+                    return Ok(Some(format!(
+                        "{} is false with values {} and {}",
+                        self, left, right
+                    )));
+                }
+            }
+            Ok(None)
+        } else {
+            let left = Self::evaluate(&self.left, window, None)?;
+            let right = Self::evaluate(&self.right, window, None)?;
+            if Self::compare(&left, &self.operator, &right)? {
+                Ok(None)
+            } else {
+                Ok(Some(format!(
+                    "{} is false with values {} and {}",
+                    self, left, right
+                )))
+            }
+        }
+    }
+    fn evaluate(
+        operand: &Operand,
+        window: &[AssertionSample],
+        sample: Option<&AssertionSample>,
+    ) -> Result<Value, Error> {
+        match operand {
+            Operand::Number(number) => Ok(Value::from(*number)),
+            Operand::Text(text) => Ok(Value::from(text.clone())),
+            Operand::Field(field) => sample
+                .and_then(|sample| sample.get(field))
+                .cloned()
+                .ok_or_else(|| anyhow!("Missing value {}", field)),
+            Operand::Aggregate(function, field) => {
+                let values = window
+                    .iter()
+                    .filter_map(|sample| sample.get(field).and_then(to_number))
+                    .collect::<Vec<f64>>();
+                function
+                    .apply(values)
+                    .map(Value::from)
+                    .ok_or_else(|| anyhow!("No numeric value {} to aggregate", field))
+            }
+        }
+    }
+    fn compare(left: &Value, operator: &CompareOperator, right: &Value) -> Result<bool, Error> {
+        let ordering = match (to_number(left), to_number(right)) {
+            (Some(left), Some(right)) => left.partial_cmp(&right),
+            _ => match (left, right) {
+                (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+                _ => None,
+            },
+        };
+        match (operator, ordering) {
+            (CompareOperator::Equal, None) => Ok(left == right),
+            (CompareOperator::NotEqual, None) => Ok(left != right),
+            (_, None) => Err(anyhow!("Cannot compare {} with {}", left, right)),
+            (CompareOperator::Equal, Some(ordering)) => Ok(ordering.is_eq()),
+            (CompareOperator::NotEqual, Some(ordering)) => Ok(ordering.is_ne()),
+            (CompareOperator::Less, Some(ordering)) => Ok(ordering.is_lt()),
+            (CompareOperator::LessOrEqual, Some(ordering)) => Ok(ordering.is_le()),
+            (CompareOperator::Greater, Some(ordering)) => Ok(ordering.is_gt()),
+            (CompareOperator::GreaterOrEqual, Some(ordering)) => Ok(ordering.is_ge()),
+        }
+    }
+}
+
+//Numbers, booleans and numeric strings (decimal or 0x hex) can be compared as number
+fn to_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::Bool(val) => Some(if *val { 1.0 } else { 0.0 }),
+        Value::String(text) => {
+            let text = text.trim();
+            match text.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16).ok().map(|val| val as f64),
+                None => text.parse::<f64>().ok(),
+            }
+        }
+        _ => None,
+    }
+}
+
+/*
+ * Fields of one http request result used in assertions
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AssertionSample {
+    fields: HashMap<String, Value>,
+}
+
+impl AssertionSample {
+    pub fn from_job_result(job_result: &JobResult) -> Option<Self> {
+        let response = match &job_result.result_detail {
+            JobResultDetail::HttpRequest(JobHttpResult { response, .. }) => response,
+            _ => return None,
+        };
+        let success = response.error_code == 0 && (200..300).contains(&response.http_code);
+        let mut fields = HashMap::from([
+            ("http_code".to_string(), Value::from(response.http_code)),
+            ("error_code".to_string(), Value::from(response.error_code)),
+            (
+                "response_duration".to_string(),
+                Value::from(response.response_duration),
+            ),
+            ("success".to_string(), Value::from(success)),
+            ("message".to_string(), Value::from(response.message.clone())),
+        ]);
+        match &response.detail {
+            JobHttpResponseDetail::Body(body) => {
+                fields.insert("body".to_string(), Value::from(body.clone()));
+            }
+            JobHttpResponseDetail::Values(values) => {
+                for (key, value) in values.iter() {
+                    fields.insert(format!("values.{}", key), value.clone());
+                }
+            }
+        }
+        Some(AssertionSample { fields })
+    }
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        self.fields.get(field)
+    }
+}
+
+/*
+ * Samples of one provider task in the current plan
+ */
+#[derive(Debug, Default)]
+struct CachedSamples {
+    plan_id: PlanId,
+    samples: VecDeque<AssertionSample>,
+    updated_time: Timestamp,
+}
+
+#[derive(Clone, Debug)]
+struct TaskAssertions {
+    config: HttpRequestJobConfig,
+    assertions: Vec<Assertion>,
+}
+
+impl TaskAssertions {
+    fn get_window(&self) -> usize {
+        self.assertions
+            .iter()
+            .map(|assertion| assertion.window)
+            .max()
+            .unwrap_or(1)
+    }
+}
+
+/*
+ * Generic judgment for http request tasks with "assertions" in thresholds, for example
+ * "thresholds": {
+ *   "error_code": "JudgementFailed",
+ *   "assertions": [
+ *     "http_code == 200",
+ *     {"expr": "p95(response_duration) < 500 over last 10", "error_code": "RoundTripTimeResponseTimeFailed"},
+ *     "success_rate >= 0.8 over last 10"
+ *   ]
+ * }
+ * Task passes when all assertions hold, it is unfinished until there are enough results for the largest window.
+ */
+#[derive(Debug)]
+pub struct AssertionJudgment {
+    task_assertions: Reloadable<Vec<TaskAssertions>>,
+    result_cache: Mutex<HashMap<ProviderTask, CachedSamples>>,
+}
+
+impl AssertionJudgment {
+    pub fn new(config_dir: &str, phase: &JobRole) -> Self {
+        AssertionJudgment {
+            task_assertions: Reloadable::new(Self::load_assertions(config_dir, phase)),
+            result_cache: Default::default(),
+        }
+    }
+    fn load_assertions(config_dir: &str, phase: &JobRole) -> Vec<TaskAssertions> {
+        let path = Path::new(config_dir).join(&*CONFIG_HTTP_REQUEST_DIR);
+        HttpRequestJobConfig::read_configs(&path, phase)
+            .into_iter()
+            .filter_map(|config| {
+                let assertions = Self::parse_assertions(&config);
+                if assertions.is_empty() {
+                    None
+                } else {
+                    Some(TaskAssertions { config, assertions })
+                }
+            })
+            .collect::<Vec<TaskAssertions>>()
+    }
+    fn parse_assertions(config: &HttpRequestJobConfig) -> Vec<Assertion> {
+        let configs = match config.thresholds.get(ASSERTIONS_KEY) {
+            Some(value) => value.clone(),
+            None => return Vec::new(),
+        };
+        let default_code = config
+            .thresholds
+            .get(ERROR_CODE_KEY)
+            .and_then(|value| serde_json::from_value::<ReportErrorCode>(value.clone()).ok())
+            .unwrap_or(ReportErrorCode::AssertionFailed);
+        let configs = match serde_json::from_value::<Vec<AssertionConfig>>(configs) {
+            Ok(configs) => configs,
+            Err(err) => {
+                error!("Invalid assertions of task {}: {:?}", &config.name, err);
+                return Vec::new();
+            }
+        };
+        configs
+            .iter()
+            .filter_map(
+                |assertion| match Assertion::from_config(assertion, &default_code) {
+                    Ok(assertion) => Some(assertion),
+                    Err(err) => {
+                        error!("Skip assertion of task {}: {:?}", &config.name, err);
+                        None
+                    }
+                },
+            )
+            .collect()
+    }
+    fn get_task_assertions(&self, task_name: &str, phase: &JobRole) -> Option<TaskAssertions> {
+        self.task_assertions
+            .get()
+            .iter()
+            .find(|task| task.config.name.as_str() == task_name && task.config.match_phase(phase))
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl ReportCheck for AssertionJudgment {
+    fn get_name(&self) -> String {
+        String::from("Assertion")
+    }
+    fn get_error_code(&self) -> ReportErrorCode {
+        ReportErrorCode::JudgementFailed
+    }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), Error> {
+        // Cached samples are kept, windows of changed assertions apply from next results
+        self.task_assertions
+            .set(Self::load_assertions(config_dir, phase));
+        Ok(())
+    }
+    fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
+        task.task_type.as_str() == "HttpRequest"
+            && self
+                .task_assertions
+                .get()
+                .iter()
+                .any(|assertions| assertions.config.name == task.task_name)
+    }
+    async fn apply_for_results(
+        &self,
+        provider_task: &ProviderTask,
+        results: &Vec<JobResult>,
+    ) -> Result<JudgmentsResult, Error> {
+        let phase = match results.first() {
+            Some(result) => result.phase.clone(),
+            None => return Ok(JudgmentsResult::Unfinished),
+        };
+        let task = self
+            .get_task_assertions(&provider_task.task_name, &phase)
+            .ok_or_else(|| anyhow!("No assertion for task {}", &provider_task.task_name))?;
+        let window = task.get_window();
+        let plan_id = results[0].plan_id.clone();
+        let samples = {
+            let now = get_current_time();
+            let mut cache = self.result_cache.lock().await;
+            // Drop samples of providers without results for a long time, their plans are finished
+            cache.retain(|_, cached| now - cached.updated_time <= *JUDGMENT_CACHE_TTL);
+            let cached = cache.entry(provider_task.clone()).or_default();
+            // Samples of a finished plan do not count for the new plan
+            if cached.plan_id != plan_id {
+                cached.plan_id = plan_id;
+                cached.samples.clear();
+            }
+            cached.updated_time = now;
+            let samples = &mut cached.samples;
+            let mut results = results.iter().collect::<Vec<&JobResult>>();
+            results.sort_by_key(|result| result.receive_timestamp);
+            for result in results {
+                if let Some(sample) = AssertionSample::from_job_result(result) {
+                    samples.push_back(sample);
+                }
+            }
+            while samples.len() > window {
+                samples.pop_front();
+            }
+            samples.iter().cloned().collect::<Vec<AssertionSample>>()
+        };
+        trace!("Assertion samples of {:?}: {:?}", provider_task, &samples);
+        if samples.len() < window {
+            return Ok(JudgmentsResult::Unfinished);
+        }
+        let mut reasons = Vec::new();
+        for assertion in task.assertions.iter() {
+            let failed_detail = match assertion.check(&samples) {
+                Ok(detail) => detail,
+                Err(err) => Some(format!("{} cannot be checked: {}", assertion, err)),
+            };
+            if let Some(detail) = failed_detail {
+                reasons.push(ReportFailedReason::new(
+                    provider_task.task_name.clone(),
+                    detail,
+                    assertion.error_code.clone(),
+                ));
+            }
+        }
+        debug!(
+            "Assertion judgment for {:?} has {} failed assertions",
+            provider_task,
+            reasons.len()
+        );
+        if reasons.is_empty() {
+            Ok(JudgmentsResult::Pass)
+        } else {
+            Ok(JudgmentsResult::Failed(ReportFailedReasons::new(reasons)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CONFIG_TASK_DIR;
+    use common::component::ComponentType;
+    use common::BlockChainType;
+    use serde_json::json;
+    use test_util::helper::{load_env, mock_http_values_detail, mock_job_result_with_detail};
+
+    fn mock_sample(fields: Value) -> AssertionSample {
+        AssertionSample {
+            fields: serde_json::from_value(fields).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_assertion() {
+        let assertion = Assertion::parse(
+            "p95(response_duration) <= 500 over last 10",
+            ReportErrorCode::AssertionFailed,
+        )
+        .unwrap();
+        assert_eq!(
+            assertion.left,
+            Operand::Aggregate(
+                AggregateFunction::Percentile(95.0),
+                "response_duration".to_string()
+            )
+        );
+        assert_eq!(assertion.operator, CompareOperator::LessOrEqual);
+        assert_eq!(assertion.right, Operand::Number(500.0));
+        assert_eq!(assertion.window, 10);
+
+        let assertion =
+            Assertion::parse("values.chain_id == '0x1'", ReportErrorCode::AssertionFailed).unwrap();
+        assert_eq!(
+            assertion.left,
+            Operand::Field("values.chain_id".to_string())
+        );
+        assert_eq!(assertion.right, Operand::Text("0x1".to_string()));
+        assert_eq!(assertion.window, 1);
+
+        assert!(Assertion::parse("http_code 200", ReportErrorCode::AssertionFailed).is_err());
+        assert!(Assertion::parse("p101(x) > 1", ReportErrorCode::AssertionFailed).is_err());
+        assert!(Assertion::parse(
+            "success_rate > 0.5 over last 0",
+            ReportErrorCode::AssertionFailed
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_check_assertion() {
+        let samples = (1..=10)
+            .map(|ind| {
+                mock_sample(json!({
+                    "http_code": 200,
+                    "response_duration": ind * 100,
+                    "success": ind != 3,
+                    "values.peer_count": "0x5"
+                }))
+            })
+            .collect::<Vec<AssertionSample>>();
+        let check = |expression: &str| {
+            Assertion::parse(expression, ReportErrorCode::AssertionFailed)
+                .unwrap()
+                .check(&samples)
+                .unwrap()
+        };
+        assert_eq!(check("http_code == 200 over last 10"), None);
+        assert_eq!(check("values.peer_count >= 5"), None);
+        assert_eq!(check("p90(response_duration) <= 900 over last 10"), None);
+        assert_eq!(check("avg(response_duration) == 550 over last 10"), None);
+        assert_eq!(check("success_rate >= 0.9 over last 10"), None);
+        assert_eq!(
+            check("success_rate >= 0.95 over last 10"),
+            Some("success_rate >= 0.95 over last 10 is false with values 0.9 and 0.95".to_string())
+        );
+        // Newest results are at the end
+        assert_eq!(
+            check("response_duration < 200"),
+            Some("response_duration < 200 is false with values 1000 and 200.0".to_string())
+        );
+        assert!(
+            Assertion::parse("values.missing == 1", ReportErrorCode::AssertionFailed)
+                .unwrap()
+                .check(&samples)
+                .is_err()
+        );
+    }
+
+    fn mock_result(http_code: u16, peer_count: &str) -> JobResult {
+        let mut detail = mock_http_values_detail(HashMap::from([(
+            "peer_count".to_string(),
+            Value::from(peer_count),
+        )]));
+        if let JobResultDetail::HttpRequest(result) = &mut detail {
+            result.response.http_code = http_code;
+        }
+        mock_job_result_with_detail("EthPeerCount", BlockChainType::Eth, "", detail)
+    }
+
+    #[tokio::test]
+    async fn test_assertion_judgment() -> Result<(), Error> {
+        load_env();
+        let judge = AssertionJudgment::new(CONFIG_TASK_DIR.as_str(), &JobRole::Regular);
+        let task = ProviderTask::new(
+            "provider_id".to_string(),
+            ComponentType::Node,
+            "HttpRequest".to_string(),
+            "EthPeerCount".to_string(),
+        );
+        let task_rtt = ProviderTask::new(
+            "provider_id".to_string(),
+            ComponentType::Node,
+            "HttpRequest".to_string(),
+            "RoundTripTime".to_string(),
+        );
+        assert!(judge.can_apply_for_result(&task));
+        assert!(!judge.can_apply_for_result(&task_rtt));
+
+        let window = judge
+            .get_task_assertions("EthPeerCount", &JobRole::Regular)
+            .unwrap()
+            .get_window();
+        let results = (0..window - 1)
+            .map(|_| mock_result(200, "0x8"))
+            .collect::<Vec<JobResult>>();
+        assert_eq!(
+            judge.apply_for_results(&task, &results).await?,
+            JudgmentsResult::Unfinished
+        );
+        assert_eq!(
+            judge
+                .apply_for_results(&task, &vec![mock_result(200, "0x8")])
+                .await?,
+            JudgmentsResult::Pass
+        );
+        let res = judge
+            .apply_for_results(&task, &vec![mock_result(200, "0x1")])
+            .await?;
+        match res {
+            JudgmentsResult::Failed(reasons) => {
+                assert_eq!(reasons.len(), 1);
+                assert_eq!(
+                    reasons[0],
+                    ReportFailedReason::new(
+                        "EthPeerCount".to_string(),
+                        "values.peer_count >= 3 is false with values \"0x1\" and 3.0".to_string(),
+                        ReportErrorCode::AssertionFailed
+                    )
+                );
+            }
+            _ => panic!("Unexpected judgment result {:?}", res),
+        }
+
+        // A new plan starts with an empty window
+        let mut result = mock_result(200, "0x8");
+        result.plan_id = "new_plan_id".to_string();
+        assert_eq!(
+            judge.apply_for_results(&task, &vec![result]).await?,
+            JudgmentsResult::Unfinished
+        );
+        assert_eq!(
+            judge
+                .result_cache
+                .lock()
+                .await
+                .get(&task)
+                .unwrap()
+                .samples
+                .len(),
+            1
+        );
+
+        // Samples of providers without new results are evicted after the ttl
+        let stale_task = ProviderTask::new(
+            "stale_provider_id".to_string(),
+            ComponentType::Node,
+            "HttpRequest".to_string(),
+            "EthPeerCount".to_string(),
+        );
+        judge
+            .apply_for_results(&stale_task, &vec![mock_result(200, "0x8")])
+            .await?;
+        judge
+            .result_cache
+            .lock()
+            .await
+            .get_mut(&stale_task)
+            .unwrap()
+            .updated_time -= *JUDGMENT_CACHE_TTL + 1;
+        judge
+            .apply_for_results(&task, &vec![mock_result(200, "0x8")])
+            .await?;
+        assert!(!judge.result_cache.lock().await.contains_key(&stale_task));
+        Ok(())
+    }
+}
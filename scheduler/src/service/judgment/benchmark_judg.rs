@@ -1,4 +1,5 @@
 use crate::models::job_result::ProviderTask;
+use crate::models::reloadable::Reloadable;
 use crate::persistence::services::job_result_service::JobResultService;
 use crate::service::judgment::{JudgmentsResult, ReportCheck};
 use crate::tasks::benchmark::generator::BenchmarkConfig;
@@ -115,26 +116,28 @@ impl BenchmarkResultCache {
 #[derive()]
 pub struct BenchmarkJudgment {
     _result_service: Arc<JobResultService>,
-    task_configs: Vec<BenchmarkConfig>,
+    task_configs: Reloadable<Vec<BenchmarkConfig>>,
     result_cache: BenchmarkResultCache,
 }
 
 impl BenchmarkJudgment {
     pub fn new(config_dir: &str, result_service: Arc<JobResultService>) -> Self {
-        let path = Path::new(config_dir).join(&*CONFIG_BENCHMARK_DIR);
-
-        let task_configs: Vec<BenchmarkConfig> =
-            BenchmarkConfig::read_configs(&path, &JobRole::Verification);
         BenchmarkJudgment {
             _result_service: result_service,
-            task_configs,
+            task_configs: Reloadable::new(Self::load_configs(config_dir)),
             result_cache: BenchmarkResultCache::default(),
         }
     }
-    fn get_config(&self, task_name: &str) -> Option<&BenchmarkConfig> {
+    fn load_configs(config_dir: &str) -> Vec<BenchmarkConfig> {
+        let path = Path::new(config_dir).join(&*CONFIG_BENCHMARK_DIR);
+        BenchmarkConfig::read_configs(&path, &JobRole::Verification)
+    }
+    fn get_config(&self, task_name: &str) -> Option<BenchmarkConfig> {
         self.task_configs
+            .get()
             .iter()
             .find(|config| config.name.as_str() == task_name)
+            .cloned()
     }
 }
 
@@ -146,6 +149,10 @@ impl ReportCheck for BenchmarkJudgment {
     fn get_error_code(&self) -> ReportErrorCode {
         ReportErrorCode::BenchmarkCallFailed
     }
+    fn reload_configs(&self, config_dir: &str, _phase: &JobRole) -> Result<(), Error> {
+        self.task_configs.set(Self::load_configs(config_dir));
+        Ok(())
+    }
     fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
         return task.task_type.as_str() == "Benchmark";
     }
@@ -223,7 +230,24 @@ impl ReportCheck for BenchmarkJudgment {
                     &config.judge_histogram_percentile, res, config.response_threshold
                 );
                 if *res <= config.response_threshold as f32 {
-                    JudgmentsResult::Pass
+                    match best_benchmark.response.get_success_percent() {
+                        Some(percent) if percent < config.success_percent_threshold => {
+                            let failed_reason = format!(
+                                "Success percent {}% < {}% ({} non-2xx, {} errors in {} requests)",
+                                percent,
+                                config.success_percent_threshold,
+                                best_benchmark.response.non_success_number,
+                                best_benchmark.response.error_number,
+                                best_benchmark.response.request_number
+                            );
+                            JudgmentsResult::new_failed(
+                                self.get_name(),
+                                failed_reason,
+                                ReportErrorCode::BenchmarkThreadHoldFailed,
+                            )
+                        }
+                        _ => JudgmentsResult::Pass,
+                    }
                 } else {
                     let failed_reason = format!(
                         "Histogram value at {}% is {} > {}",
@@ -345,6 +369,30 @@ pub mod tests {
         info!("Judge Dot res: {:?}", res);
         assert_eq!(res, JudgmentsResult::Pass);
 
+        // Fast responses but too many failed requests
+        let task_failed = ProviderTask::new(
+            "failed_provider_id".to_string(),
+            ComponentType::Node,
+            "Benchmark".to_string(),
+            "VerifyEthNode".to_string(),
+        );
+        let mut job_result = mock_job_result(
+            &JobName::Benchmark,
+            BlockChainType::Eth,
+            "",
+            Default::default(),
+        );
+        if let JobResultDetail::Benchmark(result) = &mut job_result.result_detail {
+            result.response.request_number = 20;
+            result.response.non_success_number = 5;
+            result.response.error_number = 10;
+        }
+        let res = judge
+            .apply_for_results(&task_failed, &vec![job_result])
+            .await?;
+        info!("Judge failed requests res: {:?}", res);
+        assert!(matches!(res, JudgmentsResult::Failed(_)));
+
         Ok(())
     }
 }
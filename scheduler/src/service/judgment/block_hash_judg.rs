@@ -0,0 +1,418 @@
+use crate::models::job_result::ProviderTask;
+use crate::models::reloadable::Reloadable;
+use crate::persistence::services::JobResultService;
+use crate::service::judgment::http_latestblock_judg::CacheKey;
+use crate::service::judgment::{latest_result, JudgmentsResult, ReportCheck};
+use crate::service::report_portal::ReportErrorCode;
+use crate::CONFIG_HTTP_REQUEST_DIR;
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use common::component::ChainInfo;
+use common::job_manage::{JobResultDetail, JobRole};
+use common::jobs::JobResult;
+use common::tasks::http_request::{
+    HttpRequestJobConfig, HttpResponseValues, JobHttpResponseDetail, JobHttpResult,
+};
+use common::tasks::{LoadConfigs, TaskConfigTrait};
+use common::util::from_str_radix16;
+use common::ComponentId;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const CONSENSUS_KEY: &str = "consensus";
+//Number of latest sampled blocks kept for each chain
+const MAX_SAMPLED_BLOCKS: usize = 32;
+const REFERENCE_TIMEOUT: u64 = 5000;
+
+/*
+ * Thresholds of a consensus task, for example
+ * "thresholds": {
+ *   "consensus": {
+ *     "min_providers": 3,
+ *     "references": {"eth.mainnet": "https://trusted-eth-endpoint"}
+ *   }
+ * }
+ */
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ConsensusConfig {
+    #[serde(default = "default_min_providers")]
+    pub min_providers: usize,
+    //Trusted endpoints by {blockchain}.{network}, queried with eth_getBlockByNumber
+    #[serde(default)]
+    pub references: HashMap<String, String>,
+}
+
+fn default_min_providers() -> usize {
+    3
+}
+
+#[derive(Clone, Debug, Default)]
+struct BlockSamples {
+    hashes: HashMap<ComponentId, String>,
+    reference: Option<String>,
+}
+
+impl BlockSamples {
+    /*
+     * Hash returned by more than half of the providers
+     */
+    fn get_majority_hash(&self) -> Option<(&String, usize)> {
+        let mut counters = HashMap::<&String, usize>::new();
+        for hash in self.hashes.values() {
+            *counters.entry(hash).or_default() += 1;
+        }
+        counters
+            .into_iter()
+            .max_by_key(|(_, counter)| *counter)
+            .filter(|(_, counter)| 2 * counter > self.hashes.len())
+    }
+}
+
+/*
+ * Check that providers of the same chain return the same hash for the same block number.
+ * Tasks with "consensus" in thresholds request a block which all providers agree on (e.g. "finalized"),
+ * a provider fails if its hash differs from the trusted reference endpoint if any, otherwise from the majority hash.
+ */
+#[derive(Debug)]
+pub struct BlockHashJudgment {
+    task_configs: Reloadable<Vec<HttpRequestJobConfig>>,
+    _result_service: Arc<JobResultService>,
+    samples: Mutex<HashMap<CacheKey, BTreeMap<i64, BlockSamples>>>,
+}
+
+impl BlockHashJudgment {
+    pub fn new(config_dir: &str, phase: &JobRole, result_service: Arc<JobResultService>) -> Self {
+        BlockHashJudgment {
+            task_configs: Reloadable::new(Self::load_configs(config_dir, phase)),
+            _result_service: result_service,
+            samples: Default::default(),
+        }
+    }
+    fn load_configs(config_dir: &str, phase: &JobRole) -> Vec<HttpRequestJobConfig> {
+        let path = Path::new(config_dir).join(&*CONFIG_HTTP_REQUEST_DIR);
+        HttpRequestJobConfig::read_configs(&path, phase)
+            .into_iter()
+            .filter(|config| config.thresholds.contains_key(CONSENSUS_KEY))
+            .collect::<Vec<HttpRequestJobConfig>>()
+    }
+    pub fn get_consensus_config(
+        &self,
+        task_name: &str,
+        phase: &JobRole,
+    ) -> Result<ConsensusConfig, Error> {
+        let value = self
+            .task_configs
+            .get()
+            .iter()
+            .find(|config| config.name.as_str() == task_name && config.match_phase(phase))
+            .and_then(|config| config.thresholds.get(CONSENSUS_KEY).cloned())
+            .ok_or_else(|| anyhow!("No consensus config for task {}", task_name))?;
+        serde_json::from_value::<ConsensusConfig>(value)
+            .map_err(|err| anyhow!("Invalid consensus config of task {}: {}", task_name, err))
+    }
+    fn get_block(values: &HttpResponseValues) -> Result<(i64, String), Error> {
+        let number = match values.get("number") {
+            Some(Value::String(number)) => from_str_radix16(number)?,
+            Some(Value::Number(number)) => number
+                .as_i64()
+                .ok_or_else(|| anyhow!("Invalid block number {}", number))?,
+            _ => return Err(anyhow!("Missing block number in {:?}", values)),
+        };
+        let hash = values
+            .get("hash")
+            .and_then(|hash| hash.as_str())
+            .ok_or_else(|| anyhow!("Missing block hash in {:?}", values))?;
+        Ok((number, hash.to_lowercase()))
+    }
+    /*
+     * Put sample of a provider and return all samples of the same block
+     */
+    fn add_sample(
+        &self,
+        cache_key: &CacheKey,
+        number: i64,
+        provider_id: &ComponentId,
+        hash: String,
+    ) -> BlockSamples {
+        let mut samples = self.samples.lock().unwrap();
+        let blocks = samples.entry(cache_key.clone()).or_default();
+        let block = blocks.entry(number).or_default();
+        block.hashes.insert(provider_id.clone(), hash);
+        let block = block.clone();
+        while blocks.len() > MAX_SAMPLED_BLOCKS {
+            let oldest = *blocks.keys().next().unwrap();
+            blocks.remove(&oldest);
+        }
+        block
+    }
+    fn set_reference(&self, cache_key: &CacheKey, number: i64, hash: String) {
+        let mut samples = self.samples.lock().unwrap();
+        if let Some(block) = samples
+            .get_mut(cache_key)
+            .and_then(|blocks| blocks.get_mut(&number))
+        {
+            block.reference = Some(hash);
+        }
+    }
+    async fn get_reference_hash(url: &str, number: i64) -> Result<String, Error> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "method": "eth_getBlockByNumber",
+            "params": [format!("0x{:x}", number), false],
+            "id": 1
+        });
+        let response = reqwest::Client::builder()
+            .timeout(Duration::from_millis(REFERENCE_TIMEOUT))
+            .build()?
+            .post(url)
+            .header("content-type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await?
+            .text()
+            .await?;
+        let response = serde_json::from_str::<Value>(&response)?;
+        response["result"]["hash"]
+            .as_str()
+            .map(|hash| hash.to_lowercase())
+            .ok_or_else(|| anyhow!("Missing block hash in reference response {}", response))
+    }
+    fn check_hash(
+        &self,
+        provider_id: &ComponentId,
+        number: i64,
+        hash: &str,
+        block: &BlockSamples,
+        config: &ConsensusConfig,
+    ) -> JudgmentsResult {
+        if let Some(reference) = &block.reference {
+            return if reference == hash {
+                JudgmentsResult::Pass
+            } else {
+                JudgmentsResult::new_failed(
+                    self.get_name(),
+                    format!(
+                        "Hash {} of block {} differs from reference hash {}",
+                        hash, number, reference
+                    ),
+                    ReportErrorCode::BlockHashReferenceMismatched,
+                )
+            };
+        }
+        if block.hashes.len() < config.min_providers {
+            debug!(
+                "Block {} is sampled from {} of {} providers, wait for more samples",
+                number,
+                block.hashes.len(),
+                config.min_providers
+            );
+            return JudgmentsResult::Unfinished;
+        }
+        match block.get_majority_hash() {
+            Some((majority, _)) if majority == hash => JudgmentsResult::Pass,
+            Some((majority, counter)) => JudgmentsResult::new_failed(
+                self.get_name(),
+                format!(
+                    "Hash {} of block {} differs from majority hash {} of {}/{} providers",
+                    hash,
+                    number,
+                    majority,
+                    counter,
+                    block.hashes.len()
+                ),
+                ReportErrorCode::BlockHashMismatched,
+            ),
+            None => {
+                debug!(
+                    "No majority hash for block {} of provider {}: {:?}",
+                    number, provider_id, &block.hashes
+                );
+                JudgmentsResult::Unfinished
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ReportCheck for BlockHashJudgment {
+    fn get_name(&self) -> String {
+        String::from("BlockHash")
+    }
+    fn get_error_code(&self) -> ReportErrorCode {
+        ReportErrorCode::DataCorrectnessJudgementFailed
+    }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), Error> {
+        // Sampled hashes are kept
+        self.task_configs.set(Self::load_configs(config_dir, phase));
+        Ok(())
+    }
+    fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
+        task.task_type.as_str() == "HttpRequest"
+            && self
+                .task_configs
+                .get()
+                .iter()
+                .any(|config| config.name == task.task_name)
+    }
+    async fn apply_for_results(
+        &self,
+        provider_task: &ProviderTask,
+        results: &Vec<JobResult>,
+    ) -> Result<JudgmentsResult, Error> {
+        let latest_result = match latest_result(provider_task, results) {
+            Some(result) => result,
+            None => return Ok(JudgmentsResult::Unfinished),
+        };
+        let response = match &latest_result.result_detail {
+            JobResultDetail::HttpRequest(JobHttpResult { response, .. }) => response,
+            _ => return Err(anyhow!("Unexpected result detail of {:?}", provider_task)),
+        };
+        if response.error_code != 0 {
+            return Ok(JudgmentsResult::new_call_failed(
+                self.get_name(),
+                &format!("http code {}: {}", response.http_code, &response.message),
+                ReportErrorCode::DataCorrectnessCallFailed,
+            ));
+        }
+        let (number, hash) = match &response.detail {
+            JobHttpResponseDetail::Values(values) => Self::get_block(values),
+            JobHttpResponseDetail::Body(body) => {
+                Err(anyhow!("Block response is not parsed: {}", body))
+            }
+        }
+        .map_err(|err| anyhow!("Cannot get block of {:?}: {}", provider_task, err))?;
+        let config = self.get_consensus_config(&latest_result.job_name, &latest_result.phase)?;
+        let ChainInfo { chain, network } = latest_result
+            .chain_info
+            .clone()
+            .ok_or_else(|| anyhow!("Missing chain_info"))?;
+        let reference_url = config
+            .references
+            .get(&format!("{}.{}", chain, network))
+            .cloned();
+        // Providers are not distinguished in the key, samples of a chain are shared
+        let cache_key = CacheKey::new(String::new(), chain, network);
+        let mut block =
+            self.add_sample(&cache_key, number, &provider_task.provider_id, hash.clone());
+        if let (Some(url), None) = (reference_url, &block.reference) {
+            match Self::get_reference_hash(&url, number).await {
+                Ok(reference) => {
+                    self.set_reference(&cache_key, number, reference.clone());
+                    block.reference = Some(reference);
+                }
+                Err(err) => warn!(
+                    "Cannot get hash of block {} from reference {}: {:?}",
+                    number, url, err
+                ),
+            }
+        }
+        let res = self.check_hash(&provider_task.provider_id, number, &hash, &block, &config);
+        debug!("Block hash judgment for {:?}: {:?}", provider_task, &res);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CONFIG_TASK_DIR;
+    use common::component::ComponentType;
+    use common::BlockChainType;
+    use test_util::helper::{
+        load_env, mock_db_connection, mock_http_values_detail, mock_job_result_with_detail,
+    };
+
+    fn mock_block_result(provider_id: &str, number: &str, hash: &str) -> JobResult {
+        let values = HashMap::from([
+            ("number".to_string(), Value::from(number)),
+            ("hash".to_string(), Value::from(hash)),
+        ]);
+        mock_job_result_with_detail(
+            "BlockHash",
+            BlockChainType::Eth,
+            provider_id,
+            mock_http_values_detail(values),
+        )
+    }
+
+    fn mock_block_hash_task(provider_id: &str) -> ProviderTask {
+        ProviderTask::new(
+            provider_id.to_string(),
+            ComponentType::Node,
+            "HttpRequest".to_string(),
+            "BlockHash".to_string(),
+        )
+    }
+
+    async fn judge_block(
+        judge: &BlockHashJudgment,
+        provider_id: &str,
+        number: &str,
+        hash: &str,
+    ) -> Result<JudgmentsResult, Error> {
+        let results = vec![mock_block_result(provider_id, number, hash)];
+        judge
+            .apply_for_results(&mock_block_hash_task(provider_id), &results)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_block_hash_consensus() -> Result<(), Error> {
+        load_env();
+        let result_service = JobResultService::new(Arc::new(mock_db_connection()));
+        let judge = BlockHashJudgment::new(
+            CONFIG_TASK_DIR.as_str(),
+            &JobRole::Regular,
+            Arc::new(result_service),
+        );
+        assert!(judge.can_apply_for_result(&mock_block_hash_task("provider_1")));
+        assert!(!judge.can_apply_for_result(&ProviderTask::new(
+            "provider_1".to_string(),
+            ComponentType::Node,
+            "HttpRequest".to_string(),
+            "LatestBlock".to_string(),
+        )));
+        // Not enough providers sampled block 0x10
+        assert_eq!(
+            judge_block(&judge, "provider_1", "0x10", "0xAAA").await?,
+            JudgmentsResult::Unfinished
+        );
+        assert_eq!(
+            judge_block(&judge, "provider_2", "0x10", "0xfff").await?,
+            JudgmentsResult::Unfinished
+        );
+        // Another block does not count
+        assert_eq!(
+            judge_block(&judge, "provider_3", "0x11", "0xaaa").await?,
+            JudgmentsResult::Unfinished
+        );
+        assert_eq!(
+            judge_block(&judge, "provider_3", "0x10", "0xaaa").await?,
+            JudgmentsResult::Pass
+        );
+        assert_eq!(
+            judge_block(&judge, "provider_1", "0x10", "0xaaa").await?,
+            JudgmentsResult::Pass
+        );
+        let res = judge_block(&judge, "provider_2", "0x10", "0xfff").await?;
+        assert!(
+            matches!(&res, JudgmentsResult::Failed(reasons) if reasons.to_string().contains("majority")),
+            "{:?}",
+            res
+        );
+        // No majority on a split block
+        judge_block(&judge, "provider_1", "0x12", "0x1").await?;
+        judge_block(&judge, "provider_2", "0x12", "0x2").await?;
+        judge_block(&judge, "provider_3", "0x12", "0x1").await?;
+        assert_eq!(
+            judge_block(&judge, "provider_4", "0x12", "0x2").await?,
+            JudgmentsResult::Unfinished
+        );
+        Ok(())
+    }
+}
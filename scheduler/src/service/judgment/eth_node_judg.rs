@@ -0,0 +1,510 @@
+use crate::models::job_result::ProviderTask;
+use crate::models::reloadable::Reloadable;
+use crate::persistence::services::JobResultService;
+use crate::service::judgment::{latest_result, JudgmentsResult, ReportCheck};
+use crate::service::report_portal::ReportErrorCode;
+use crate::CONFIG_HTTP_REQUEST_DIR;
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use common::job_manage::{JobResultDetail, JobRole};
+use common::jobs::JobResult;
+use common::tasks::http_request::{
+    HttpRequestJobConfig, JobHttpResponse, JobHttpResponseDetail, JobHttpResult,
+};
+use common::tasks::{LoadConfigs, TaskConfigTrait};
+use common::util::from_str_radix16;
+use common::ComponentId;
+use log::{debug, info};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+const SYNCING_KEY: &str = "syncing";
+const CHAIN_ID_KEY: &str = "chain_id";
+const CLIENT_VERSION_KEY: &str = "client_version";
+
+/*
+ * "thresholds": {"syncing": {"max_sync_lag": 10}}, value "syncing" is the result of eth_syncing
+ */
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SyncingConfig {
+    #[serde(default = "default_max_sync_lag")]
+    pub max_sync_lag: u64,
+}
+
+fn default_max_sync_lag() -> u64 {
+    10
+}
+
+/*
+ * "thresholds": {"chain_id": {"expected": {"eth.mainnet": 1}}}, value "chain_id" is the result of eth_chainId or net_version
+ */
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ChainIdConfig {
+    //Expected ids by {blockchain}.{network}
+    #[serde(default)]
+    pub expected: HashMap<String, u64>,
+}
+
+/*
+ * "thresholds": {"client_version": {"allowed_clients": ["geth"]}}, value "client_version" is the result of web3_clientVersion.
+ * Empty allowed_clients allows any client
+ */
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ClientVersionConfig {
+    #[serde(default)]
+    pub allowed_clients: Vec<String>,
+}
+
+/*
+ * Http request task configs with a threshold key, judgments apply only for their own key
+ */
+#[derive(Debug)]
+struct EthNodeTaskConfigs {
+    key: &'static str,
+    configs: Reloadable<Vec<HttpRequestJobConfig>>,
+}
+
+impl EthNodeTaskConfigs {
+    fn new(config_dir: &str, phase: &JobRole, key: &'static str) -> Self {
+        EthNodeTaskConfigs {
+            key,
+            configs: Reloadable::new(Self::load_configs(config_dir, phase, key)),
+        }
+    }
+    fn load_configs(config_dir: &str, phase: &JobRole, key: &str) -> Vec<HttpRequestJobConfig> {
+        let path = Path::new(config_dir).join(&*CONFIG_HTTP_REQUEST_DIR);
+        HttpRequestJobConfig::read_configs(&path, phase)
+            .into_iter()
+            .filter(|config| config.thresholds.contains_key(key))
+            .collect()
+    }
+    fn reload(&self, config_dir: &str, phase: &JobRole) {
+        self.configs
+            .set(Self::load_configs(config_dir, phase, self.key));
+    }
+    fn can_apply(&self, task: &ProviderTask) -> bool {
+        task.task_type.as_str() == "HttpRequest"
+            && self
+                .configs
+                .get()
+                .iter()
+                .any(|config| config.name == task.task_name)
+    }
+    fn get_thresholds<T: DeserializeOwned>(
+        &self,
+        task_name: &str,
+        phase: &JobRole,
+    ) -> Result<T, Error> {
+        let value = self
+            .configs
+            .get()
+            .iter()
+            .find(|config| config.name.as_str() == task_name && config.match_phase(phase))
+            .and_then(|config| config.thresholds.get(self.key).cloned())
+            .ok_or_else(|| anyhow!("No {} config for task {}", self.key, task_name))?;
+        serde_json::from_value::<T>(value)
+            .map_err(|err| anyhow!("Invalid {} config of task {}: {}", self.key, task_name, err))
+    }
+}
+
+fn get_http_response<'a>(
+    provider_task: &ProviderTask,
+    result: &'a JobResult,
+) -> Result<&'a JobHttpResponse, Error> {
+    match &result.result_detail {
+        JobResultDetail::HttpRequest(JobHttpResult { response, .. }) => Ok(response),
+        _ => Err(anyhow!("Unexpected result detail of {:?}", provider_task)),
+    }
+}
+
+fn get_value<'a>(response: &'a JobHttpResponse, key: &str) -> Result<&'a Value, Error> {
+    match &response.detail {
+        JobHttpResponseDetail::Values(values) => values
+            .get(key)
+            .ok_or_else(|| anyhow!("Missing {} in {:?}", key, values)),
+        JobHttpResponseDetail::Body(body) => Err(anyhow!("Response is not parsed: {}", body)),
+    }
+}
+
+/*
+ * Parse hex quantity ("0x5", eth_chainId) or decimal string ("5", net_version)
+ */
+fn parse_number(value: &Value) -> Result<u64, Error> {
+    match value {
+        Value::String(number) if number.starts_with("0x") => Ok(from_str_radix16(number)? as u64),
+        Value::String(number) => number
+            .parse::<u64>()
+            .map_err(|err| anyhow!("Invalid number {}: {}", number, err)),
+        Value::Number(number) => number
+            .as_u64()
+            .ok_or_else(|| anyhow!("Invalid number {}", number)),
+        _ => Err(anyhow!("Invalid number {}", value)),
+    }
+}
+
+/*
+ * eth_syncing: a node returning a recent latest block may still be syncing
+ */
+#[derive(Debug)]
+pub struct EthSyncingJudgment {
+    task_configs: EthNodeTaskConfigs,
+    _result_service: Arc<JobResultService>,
+}
+
+impl EthSyncingJudgment {
+    pub fn new(config_dir: &str, phase: &JobRole, result_service: Arc<JobResultService>) -> Self {
+        EthSyncingJudgment {
+            task_configs: EthNodeTaskConfigs::new(config_dir, phase, SYNCING_KEY),
+            _result_service: result_service,
+        }
+    }
+}
+
+#[async_trait]
+impl ReportCheck for EthSyncingJudgment {
+    fn get_name(&self) -> String {
+        String::from("EthSyncing")
+    }
+    fn get_error_code(&self) -> ReportErrorCode {
+        ReportErrorCode::EthNodeJudgementFailed
+    }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), Error> {
+        self.task_configs.reload(config_dir, phase);
+        Ok(())
+    }
+    fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
+        self.task_configs.can_apply(task)
+    }
+    async fn apply_for_results(
+        &self,
+        provider_task: &ProviderTask,
+        results: &Vec<JobResult>,
+    ) -> Result<JudgmentsResult, Error> {
+        let result = match latest_result(provider_task, results) {
+            Some(result) => result,
+            None => return Ok(JudgmentsResult::Unfinished),
+        };
+        let response = get_http_response(provider_task, result)?;
+        if response.error_code != 0 {
+            return Ok(JudgmentsResult::new_call_failed(
+                self.get_name(),
+                &format!("http code {}: {}", response.http_code, &response.message),
+                ReportErrorCode::EthNodeCallFailed,
+            ));
+        }
+        let config: SyncingConfig = self
+            .task_configs
+            .get_thresholds(&result.job_name, &result.phase)?;
+        let syncing = get_value(response, SYNCING_KEY)?;
+        let res = match syncing {
+            Value::Bool(false) => JudgmentsResult::Pass,
+            Value::Object(_) => {
+                let current_block = parse_number(&syncing["currentBlock"])?;
+                let highest_block = parse_number(&syncing["highestBlock"])?;
+                let lag = highest_block.saturating_sub(current_block);
+                if lag > config.max_sync_lag {
+                    JudgmentsResult::new_failed(
+                        self.get_name(),
+                        format!(
+                            "Node is syncing, current block {} is {} blocks behind highest block {}, max {}",
+                            current_block, lag, highest_block, config.max_sync_lag
+                        ),
+                        ReportErrorCode::EthNodeSyncing,
+                    )
+                } else {
+                    JudgmentsResult::Pass
+                }
+            }
+            _ => return Err(anyhow!("Invalid eth_syncing result {}", syncing)),
+        };
+        debug!("Eth syncing judgment for {:?}: {:?}", provider_task, &res);
+        Ok(res)
+    }
+}
+
+/*
+ * eth_chainId / net_version: catch providers serving another network than the registered one
+ */
+#[derive(Debug)]
+pub struct EthChainIdJudgment {
+    task_configs: EthNodeTaskConfigs,
+    _result_service: Arc<JobResultService>,
+}
+
+impl EthChainIdJudgment {
+    pub fn new(config_dir: &str, phase: &JobRole, result_service: Arc<JobResultService>) -> Self {
+        EthChainIdJudgment {
+            task_configs: EthNodeTaskConfigs::new(config_dir, phase, CHAIN_ID_KEY),
+            _result_service: result_service,
+        }
+    }
+}
+
+#[async_trait]
+impl ReportCheck for EthChainIdJudgment {
+    fn get_name(&self) -> String {
+        String::from("EthChainId")
+    }
+    fn get_error_code(&self) -> ReportErrorCode {
+        ReportErrorCode::EthNodeJudgementFailed
+    }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), Error> {
+        self.task_configs.reload(config_dir, phase);
+        Ok(())
+    }
+    fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
+        self.task_configs.can_apply(task)
+    }
+    async fn apply_for_results(
+        &self,
+        provider_task: &ProviderTask,
+        results: &Vec<JobResult>,
+    ) -> Result<JudgmentsResult, Error> {
+        let result = match latest_result(provider_task, results) {
+            Some(result) => result,
+            None => return Ok(JudgmentsResult::Unfinished),
+        };
+        let response = get_http_response(provider_task, result)?;
+        if response.error_code != 0 {
+            return Ok(JudgmentsResult::new_call_failed(
+                self.get_name(),
+                &format!("http code {}: {}", response.http_code, &response.message),
+                ReportErrorCode::EthNodeCallFailed,
+            ));
+        }
+        let config: ChainIdConfig = self
+            .task_configs
+            .get_thresholds(&result.job_name, &result.phase)?;
+        let chain_info = result
+            .chain_info
+            .as_ref()
+            .ok_or_else(|| anyhow!("Missing chain_info"))?
+            .to_string();
+        let chain_id = parse_number(get_value(response, CHAIN_ID_KEY)?)?;
+        let res = match config.expected.get(&chain_info) {
+            Some(expected) if *expected != chain_id => JudgmentsResult::new_failed(
+                self.get_name(),
+                format!(
+                    "Provider of {} returns chain id {}, expected {}",
+                    chain_info, chain_id, expected
+                ),
+                ReportErrorCode::ChainIdMismatched,
+            ),
+            Some(_) => JudgmentsResult::Pass,
+            None => {
+                debug!("No expected chain id for {}", chain_info);
+                JudgmentsResult::Pass
+            }
+        };
+        debug!("Eth chain id judgment for {:?}: {:?}", provider_task, &res);
+        Ok(res)
+    }
+}
+
+/*
+ * web3_clientVersion: record client type of each provider, e.g. "geth" of "Geth/v1.10.23-stable/linux-amd64/go1.18.5"
+ */
+#[derive(Debug)]
+pub struct EthClientVersionJudgment {
+    task_configs: EthNodeTaskConfigs,
+    _result_service: Arc<JobResultService>,
+    clients: Mutex<HashMap<ComponentId, String>>,
+}
+
+impl EthClientVersionJudgment {
+    pub fn new(config_dir: &str, phase: &JobRole, result_service: Arc<JobResultService>) -> Self {
+        EthClientVersionJudgment {
+            task_configs: EthNodeTaskConfigs::new(config_dir, phase, CLIENT_VERSION_KEY),
+            _result_service: result_service,
+            clients: Default::default(),
+        }
+    }
+    pub fn get_client(&self, provider_id: &ComponentId) -> Option<String> {
+        self.clients.lock().unwrap().get(provider_id).cloned()
+    }
+    pub fn parse_client(client_version: &str) -> String {
+        client_version
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+    }
+}
+
+#[async_trait]
+impl ReportCheck for EthClientVersionJudgment {
+    fn get_name(&self) -> String {
+        String::from("EthClientVersion")
+    }
+    fn get_error_code(&self) -> ReportErrorCode {
+        ReportErrorCode::EthNodeJudgementFailed
+    }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), Error> {
+        // Recorded clients are kept
+        self.task_configs.reload(config_dir, phase);
+        Ok(())
+    }
+    fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
+        self.task_configs.can_apply(task)
+    }
+    async fn apply_for_results(
+        &self,
+        provider_task: &ProviderTask,
+        results: &Vec<JobResult>,
+    ) -> Result<JudgmentsResult, Error> {
+        let result = match latest_result(provider_task, results) {
+            Some(result) => result,
+            None => return Ok(JudgmentsResult::Unfinished),
+        };
+        let response = get_http_response(provider_task, result)?;
+        if response.error_code != 0 {
+            return Ok(JudgmentsResult::new_call_failed(
+                self.get_name(),
+                &format!("http code {}: {}", response.http_code, &response.message),
+                ReportErrorCode::EthNodeCallFailed,
+            ));
+        }
+        let config: ClientVersionConfig = self
+            .task_configs
+            .get_thresholds(&result.job_name, &result.phase)?;
+        let client_version = get_value(response, CLIENT_VERSION_KEY)?
+            .as_str()
+            .ok_or_else(|| anyhow!("Invalid web3_clientVersion result"))?;
+        let client = Self::parse_client(client_version);
+        let previous = self
+            .clients
+            .lock()
+            .unwrap()
+            .insert(provider_task.provider_id.clone(), client.clone());
+        if previous.as_ref() != Some(&client) {
+            info!(
+                "Provider {} runs client {}",
+                &provider_task.provider_id, client_version
+            );
+        }
+        let res = if config.allowed_clients.is_empty()
+            || config
+                .allowed_clients
+                .iter()
+                .any(|allowed| allowed.to_lowercase() == client)
+        {
+            JudgmentsResult::Pass
+        } else {
+            JudgmentsResult::new_failed(
+                self.get_name(),
+                format!(
+                    "Client {} is not in allowed clients {:?}",
+                    client_version, &config.allowed_clients
+                ),
+                ReportErrorCode::ClientNotAllowed,
+            )
+        };
+        debug!(
+            "Eth client version judgment for {:?}: {:?}",
+            provider_task, &res
+        );
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CONFIG_TASK_DIR;
+    use common::component::{ChainInfo, ComponentType};
+    use common::BlockChainType;
+    use serde_json::json;
+    use test_util::helper::{
+        load_env, mock_db_connection, mock_http_values_detail, mock_job_result_with_detail,
+    };
+
+    fn mock_eth_node_result(task_name: &str, key: &str, value: Value) -> Vec<JobResult> {
+        let mut job_result = mock_job_result_with_detail(
+            task_name,
+            BlockChainType::Eth,
+            "",
+            mock_http_values_detail(HashMap::from([(key.to_string(), value)])),
+        );
+        job_result.chain_info = Some(ChainInfo::new(BlockChainType::Eth, "mainnet".to_string()));
+        vec![job_result]
+    }
+
+    fn mock_eth_node_task(task_name: &str) -> ProviderTask {
+        ProviderTask::new(
+            "provider_id".to_string(),
+            ComponentType::Node,
+            "HttpRequest".to_string(),
+            task_name.to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_eth_node_judgments() -> Result<(), Error> {
+        load_env();
+        let config_dir = CONFIG_TASK_DIR.as_str();
+        let phase = JobRole::Regular;
+        let result_service = Arc::new(JobResultService::new(Arc::new(mock_db_connection())));
+        let syncing = EthSyncingJudgment::new(config_dir, &phase, result_service.clone());
+        let chain_id = EthChainIdJudgment::new(config_dir, &phase, result_service.clone());
+        let client = EthClientVersionJudgment::new(config_dir, &phase, result_service.clone());
+
+        let task = mock_eth_node_task("EthSyncing");
+        assert!(syncing.can_apply_for_result(&task));
+        assert!(!chain_id.can_apply_for_result(&task));
+        let check = |value| mock_eth_node_result("EthSyncing", SYNCING_KEY, value);
+        assert!(syncing
+            .apply_for_results(&task, &check(json!(false)))
+            .await?
+            .is_pass());
+        let stuck =
+            json!({"startingBlock": "0x0", "currentBlock": "0x100", "highestBlock": "0x200"});
+        assert!(syncing
+            .apply_for_results(&task, &check(stuck))
+            .await?
+            .is_failed());
+
+        // Provider of eth.mainnet answering goerli chain id
+        for task_name in ["EthChainId", "EthNetVersion"] {
+            let task = mock_eth_node_task(task_name);
+            assert!(chain_id.can_apply_for_result(&task));
+            let check = |value| mock_eth_node_result(task_name, CHAIN_ID_KEY, value);
+            assert!(chain_id
+                .apply_for_results(&task, &check(json!("0x1")))
+                .await?
+                .is_pass());
+            assert!(chain_id
+                .apply_for_results(&task, &check(json!("1")))
+                .await?
+                .is_pass());
+            assert_eq!(
+                chain_id
+                    .apply_for_results(&task, &check(json!("0x5")))
+                    .await?,
+                JudgmentsResult::new_failed(
+                    "EthChainId".to_string(),
+                    "Provider of eth.mainnet returns chain id 5, expected 1".to_string(),
+                    ReportErrorCode::ChainIdMismatched
+                )
+            );
+        }
+
+        let task = mock_eth_node_task("EthClientVersion");
+        assert!(client.can_apply_for_result(&task));
+        let results = mock_eth_node_result(
+            "EthClientVersion",
+            CLIENT_VERSION_KEY,
+            json!("Geth/v1.10.23-stable-d901d853/linux-amd64/go1.18.5"),
+        );
+        assert!(client.apply_for_results(&task, &results).await?.is_pass());
+        assert_eq!(
+            client.get_client(&"provider_id".to_string()),
+            Some("geth".to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use crate::models::job_result::ProviderTask;
+use crate::models::reloadable::Reloadable;
+use crate::service::judgment::{latest_result, JudgmentsResult, ReportCheck};
+use crate::service::report_portal::ReportErrorCode;
+use crate::CONFIG_HTTP_REQUEST_DIR;
+use async_trait::async_trait;
+use common::job_manage::{JobResultDetail, JobRole};
+use common::jobs::JobResult;
+use common::tasks::http_request::{HttpRequestJobConfig, JobHttpResponseDetail, JobHttpResult};
+use common::tasks::{LoadConfigs, TaskConfigTrait};
+use log::debug;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/*
+ * Check json-rpc batch requests: every call must get a response with its own id
+ * and number of failed calls must not exceed threshold max_error_calls
+ */
+#[derive(Debug)]
+pub struct HttpBatchJudgment {
+    task_configs: Reloadable<Vec<HttpRequestJobConfig>>,
+}
+
+impl HttpBatchJudgment {
+    pub fn new(config_dir: &str, phase: &JobRole) -> Self {
+        HttpBatchJudgment {
+            task_configs: Reloadable::new(Self::load_configs(config_dir, phase)),
+        }
+    }
+    fn load_configs(config_dir: &str, phase: &JobRole) -> Vec<HttpRequestJobConfig> {
+        let path = Path::new(config_dir).join(&*CONFIG_HTTP_REQUEST_DIR);
+        HttpRequestJobConfig::read_configs(&path, phase)
+            .into_iter()
+            .filter(|config| !config.batch.is_empty())
+            .collect::<Vec<HttpRequestJobConfig>>()
+    }
+    pub fn get_judgment_thresholds(&self, task_name: &str, phase: &JobRole) -> Map<String, Value> {
+        self.task_configs
+            .get()
+            .iter()
+            .find(|config| config.name.as_str() == task_name && config.match_phase(phase))
+            .map(|config| config.thresholds.clone())
+            .unwrap_or_default()
+    }
+    fn check_result(&self, result: &JobResult) -> JudgmentsResult {
+        let response = match &result.result_detail {
+            JobResultDetail::HttpRequest(JobHttpResult { response, .. }) => response,
+            _ => {
+                return JudgmentsResult::new_failed(
+                    self.get_name(),
+                    format!("Unexpected result detail of job {}", &result.job_id),
+                    ReportErrorCode::BatchRequestJudgementFailed,
+                )
+            }
+        };
+        if response.error_code != 0 {
+            return JudgmentsResult::new_call_failed(
+                self.get_name(),
+                &format!("http code {}: {}", response.http_code, &response.message),
+                ReportErrorCode::BatchRequestCallFailed,
+            );
+        }
+        let values = match &response.detail {
+            JobHttpResponseDetail::Values(values) => values,
+            JobHttpResponseDetail::Body(body) => {
+                return JudgmentsResult::new_failed(
+                    self.get_name(),
+                    format!("Batch response is not parsed: {}", body),
+                    ReportErrorCode::BatchRequestJudgementFailed,
+                )
+            }
+        };
+        let unknown_ids = values.get_batch_unknown_ids();
+        if !unknown_ids.is_empty() {
+            return JudgmentsResult::new_failed(
+                self.get_name(),
+                format!(
+                    "Batch response contains unknown or duplicated ids {:?}",
+                    unknown_ids
+                ),
+                ReportErrorCode::BatchRequestIdMismatched,
+            );
+        }
+        let thresholds = self.get_judgment_thresholds(&result.job_name, &result.phase);
+        let max_error_calls = thresholds
+            .get("max_error_calls")
+            .and_then(|val| val.as_u64())
+            .unwrap_or_default();
+        if values.get_batch_error_number() > max_error_calls {
+            return JudgmentsResult::new_failed(
+                self.get_name(),
+                format!(
+                    "{} of {} calls failed ({} responses), errors: {:?}",
+                    values.get_batch_error_number(),
+                    values.get_batch_size(),
+                    values.get_batch_response_number(),
+                    values.get_batch_errors()
+                ),
+                ReportErrorCode::BatchRequestPartialFailed,
+            );
+        }
+        JudgmentsResult::Pass
+    }
+}
+
+#[async_trait]
+impl ReportCheck for HttpBatchJudgment {
+    fn get_name(&self) -> String {
+        String::from("HttpBatch")
+    }
+    fn get_error_code(&self) -> ReportErrorCode {
+        ReportErrorCode::BatchRequestJudgementFailed
+    }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), anyhow::Error> {
+        self.task_configs.set(Self::load_configs(config_dir, phase));
+        Ok(())
+    }
+    fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
+        task.task_type.as_str() == "HttpRequest"
+            && self
+                .task_configs
+                .get()
+                .iter()
+                .any(|config| config.name == task.task_name)
+    }
+    async fn apply_for_results(
+        &self,
+        provider_task: &ProviderTask,
+        results: &Vec<JobResult>,
+    ) -> Result<JudgmentsResult, anyhow::Error> {
+        let latest_result = match latest_result(provider_task, results) {
+            Some(result) => result,
+            None => return Ok(JudgmentsResult::Unfinished),
+        };
+        let res = self.check_result(latest_result);
+        debug!("Batch judgment for {:?}: {:?}", provider_task, &res);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CONFIG_TASK_DIR;
+    use anyhow::Error;
+    use common::component::ComponentType;
+    use common::BlockChainType;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use test_util::helper::{load_env, mock_http_values_detail, mock_job_result_with_detail};
+
+    fn mock_batch_result(values: Value) -> JobResult {
+        let values: HashMap<String, Value> = serde_json::from_value(values).unwrap();
+        mock_job_result_with_detail(
+            "BatchCall",
+            BlockChainType::Eth,
+            "",
+            mock_http_values_detail(values),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_http_batch_judgment() -> Result<(), Error> {
+        load_env();
+        let judge = HttpBatchJudgment::new(CONFIG_TASK_DIR.as_str(), &JobRole::Regular);
+        let task_batch = ProviderTask::new(
+            "provider_id".to_string(),
+            ComponentType::Node,
+            "HttpRequest".to_string(),
+            "BatchCall".to_string(),
+        );
+        let task_latest_block = ProviderTask::new(
+            "provider_id".to_string(),
+            ComponentType::Node,
+            "HttpRequest".to_string(),
+            "LatestBlock".to_string(),
+        );
+        assert!(judge.can_apply_for_result(&task_batch));
+        assert!(!judge.can_apply_for_result(&task_latest_block));
+        assert_eq!(
+            judge.apply_for_results(&task_batch, &vec![]).await?,
+            JudgmentsResult::Unfinished
+        );
+
+        let passed = mock_batch_result(json!({
+            "block_number.number": "0xe2e63a",
+            "batch_size": 2, "batch_response_number": 2, "batch_error_number": 0,
+            "batch_unknown_ids": []
+        }));
+        assert_eq!(
+            judge.apply_for_results(&task_batch, &vec![passed]).await?,
+            JudgmentsResult::Pass
+        );
+
+        let partial = mock_batch_result(json!({
+            "chain_id.error": {"code": -32601, "message": "Method not found"},
+            "batch_size": 2, "batch_response_number": 2, "batch_error_number": 1,
+            "batch_unknown_ids": []
+        }));
+        let res = judge.apply_for_results(&task_batch, &vec![partial]).await?;
+        assert!(res.is_failed());
+        assert!(res.to_string().contains("1 of 2 calls failed"));
+
+        let mismatched = mock_batch_result(json!({
+            "batch_size": 2, "batch_response_number": 2, "batch_error_number": 0,
+            "batch_unknown_ids": [5]
+        }));
+        let res = judge
+            .apply_for_results(&task_batch, &vec![mismatched])
+            .await?;
+        assert!(res.is_failed());
+        Ok(())
+    }
+}
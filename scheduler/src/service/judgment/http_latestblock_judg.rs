@@ -1,6 +1,10 @@
+use crate::models::chains::ChainRegistry;
 use crate::models::job_result::ProviderTask;
+use crate::models::reloadable::Reloadable;
 use crate::persistence::services::job_result_service::JobResultService;
-use crate::service::comparator::{get_comparators, Comparator, LatestBlockDefaultComparator};
+use crate::service::comparator::{
+    get_comparators, Comparator, ComparatorKind, LatestBlockDefaultComparator,
+};
 use crate::service::judgment::{JudgmentsResult, ReportCheck};
 use anyhow::{anyhow, Error};
 use async_trait::async_trait;
@@ -22,6 +26,9 @@ use std::collections::HashMap;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
+//Number of block times a node may lag behind when late_duration is not configured
+const DEFAULT_LATE_BLOCKS: i64 = 100;
+
 #[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Hash, Default)]
 pub struct CacheKey {
     pub blockchain: BlockChainType,
@@ -94,12 +101,12 @@ impl LatestBlockResultCache {
             "Check latest block for blockchain {:?} with values {:?} and thresholds {:?}",
             &cache_key.blockchain, &result_value, &thresholds
         );
-        match &cache_key.blockchain {
-            BlockChainType::Dot => {
+        match comparator.get_kind() {
+            ComparatorKind::BlockNumber => {
                 self.check_latest_dot_block(cache_key, result_value, comparator, thresholds)
             }
             //Default use eth check
-            BlockChainType::Eth | BlockChainType::Bsc | BlockChainType::Matic => {
+            ComparatorKind::BlockTimestamp => {
                 self.check_latest_eth_block(cache_key, result_value, comparator, thresholds)
             }
         }
@@ -201,28 +208,38 @@ impl LatestBlockResultCache {
 
 #[derive()]
 pub struct HttpLatestBlockJudgment {
-    task_configs: Vec<HttpRequestJobConfig>,
+    task_configs: Reloadable<Vec<HttpRequestJobConfig>>,
     _result_service: Arc<JobResultService>,
     cache_values: LatestBlockResultCache,
-    comparators: HashMap<BlockChainType, Arc<dyn Comparator>>,
+    comparators: Reloadable<HashMap<BlockChainType, Arc<dyn Comparator>>>,
+    chains: Reloadable<ChainRegistry>,
 }
 
 impl HttpLatestBlockJudgment {
     pub fn new(config_dir: &str, phase: &JobRole, result_service: Arc<JobResultService>) -> Self {
+        let judgment = HttpLatestBlockJudgment {
+            task_configs: Reloadable::new(Self::load_configs(config_dir, phase)),
+            _result_service: result_service,
+            cache_values: LatestBlockResultCache::default(),
+            comparators: Default::default(),
+            chains: Default::default(),
+        };
+        judgment.load_chains(config_dir);
+        judgment
+    }
+    fn load_chains(&self, config_dir: &str) {
+        let chains = ChainRegistry::load_or_builtin(config_dir);
+        self.comparators.set(get_comparators(&chains));
+        self.chains.set(chains);
+    }
+    fn load_configs(config_dir: &str, phase: &JobRole) -> Vec<HttpRequestJobConfig> {
         //let path = format!("{}/http_request", config_dir);
         let path = Path::new(config_dir).join(&*CONFIG_HTTP_REQUEST_DIR);
         // let task_configs = HttpRequestJobConfig::read_config(path.as_str(), phase);
-        let task_configs = HttpRequestJobConfig::read_configs(&path, phase)
+        HttpRequestJobConfig::read_configs(&path, phase)
             .into_iter()
             .filter(|config| config.name.as_str() == "LatestBlock")
-            .collect::<Vec<HttpRequestJobConfig>>();
-        let comparators = get_comparators();
-        HttpLatestBlockJudgment {
-            task_configs,
-            _result_service: result_service,
-            cache_values: LatestBlockResultCache::default(),
-            comparators,
-        }
+            .collect::<Vec<HttpRequestJobConfig>>()
     }
     pub fn get_task_config(
         &self,
@@ -232,6 +249,7 @@ impl HttpLatestBlockJudgment {
         provider_type: &ComponentType,
     ) -> Map<String, Value> {
         self.task_configs
+            .get()
             .iter()
             .filter(|config| {
                 config.match_phase(phase)
@@ -246,6 +264,7 @@ impl HttpLatestBlockJudgment {
     }
     pub fn get_comparator(&self, chain_id: &BlockChainType) -> Arc<dyn Comparator> {
         self.comparators
+            .get()
             .get(chain_id)
             .cloned()
             .unwrap_or_else(|| Arc::new(LatestBlockDefaultComparator::default()))
@@ -261,6 +280,11 @@ impl ReportCheck for HttpLatestBlockJudgment {
     fn get_error_code(&self) -> ReportErrorCode {
         ReportErrorCode::LatestBlockCallFailed
     }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), Error> {
+        self.task_configs.set(Self::load_configs(config_dir, phase));
+        self.load_chains(config_dir);
+        Ok(())
+    }
 
     fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
         return task.task_type.as_str() == "HttpRequest"
@@ -334,12 +358,21 @@ impl ReportCheck for HttpLatestBlockJudgment {
             "cache_key: {:?}, latest_job_result: {:?}",
             cache_key, latest_job_result
         );
-        let thresholds = self.get_task_config(
+        let mut thresholds = self.get_task_config(
             &latest_job_result.phase,
             &cache_key.blockchain,
             &cache_key.network,
             &latest_job_result.provider_type,
         );
+        //Without late_duration a node is late after DEFAULT_LATE_BLOCKS block times of its chain
+        if !thresholds.contains_key("late_duration") {
+            if let Some(spec) = self.chains.get().get(&cache_key.blockchain) {
+                thresholds.insert(
+                    "late_duration".to_string(),
+                    Value::from(spec.block_time * DEFAULT_LATE_BLOCKS / 1000),
+                );
+            }
+        }
 
         trace!("get_task_config thresholds: {:?}", thresholds);
 
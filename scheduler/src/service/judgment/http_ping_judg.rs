@@ -1,4 +1,5 @@
 use crate::models::job_result::ProviderTask;
+use crate::models::reloadable::Reloadable;
 use crate::persistence::services::JobResultService;
 use crate::service::judgment::{JudgmentsResult, ReportCheck};
 
@@ -142,27 +143,29 @@ impl HttpPingResultCache {
 }
 #[derive(Debug)]
 pub struct HttpPingJudgment {
-    task_configs: Vec<HttpRequestJobConfig>,
+    task_configs: Reloadable<Vec<HttpRequestJobConfig>>,
     _result_service: Arc<JobResultService>,
     result_cache: HttpPingResultCache,
 }
 
 impl HttpPingJudgment {
     pub fn new(config_dir: &str, phase: &JobRole, result_service: Arc<JobResultService>) -> Self {
+        HttpPingJudgment {
+            task_configs: Reloadable::new(Self::load_configs(config_dir, phase)),
+            _result_service: result_service,
+            result_cache: HttpPingResultCache::default(),
+        }
+    }
+    fn load_configs(config_dir: &str, phase: &JobRole) -> Vec<HttpRequestJobConfig> {
         // let path = format!("{}/http_request", config_dir);
         let path = Path::new(config_dir).join(&*CONFIG_HTTP_REQUEST_DIR);
         //let task_configs = HttpRequestJobConfig::read_config(path.as_str(), phase);
-        let task_configs = HttpRequestJobConfig::read_configs(&path, phase)
+        HttpRequestJobConfig::read_configs(&path, phase)
             .into_iter()
             .filter(|config| {
                 config.name.as_str() == "RoundTripTime" || config.name.as_str() == "Ping"
             })
-            .collect::<Vec<HttpRequestJobConfig>>();
-        HttpPingJudgment {
-            task_configs,
-            _result_service: result_service,
-            result_cache: HttpPingResultCache::default(),
-        }
+            .collect::<Vec<HttpRequestJobConfig>>()
     }
     pub fn get_judgment_thresholds(&self, phase: &JobRole) -> Map<String, Value> {
         trace!(
@@ -170,6 +173,7 @@ impl HttpPingJudgment {
             self.task_configs
         );
         self.task_configs
+            .get()
             .iter()
             .filter(|config| config.match_phase(phase))
             .collect::<Vec<&HttpRequestJobConfig>>()
@@ -201,6 +205,10 @@ impl ReportCheck for HttpPingJudgment {
     fn get_error_code(&self) -> ReportErrorCode {
         ReportErrorCode::RoundTripTimeCallFailed
     }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), anyhow::Error> {
+        self.task_configs.set(Self::load_configs(config_dir, phase));
+        Ok(())
+    }
     async fn apply_for_results(
         &self,
         provider_task: &ProviderTask,
@@ -64,6 +64,9 @@ impl MainJudgment {
             judgment_result_cache: Default::default(),
         }
     }
+    pub fn get_judgments(&self) -> Vec<Arc<dyn ReportCheck>> {
+        self.judgments.clone()
+    }
     pub fn put_judgment_result(&self, plan: &PlanEntity, job_id: JobId, result: JudgmentsResult) {
         self.judgment_result_cache
             .insert_value(plan.plan_id.clone(), job_id.clone(), result);
@@ -1,9 +1,15 @@
+pub mod assertion_judg;
 pub mod benchmark_judg;
+pub mod block_hash_judg;
+pub mod eth_node_judg;
+pub mod http_batch_judg;
 pub mod http_latestblock_judg;
 pub mod http_ping_judg;
 //pub mod latestblock_judg;
 pub mod main_judg;
 //pub mod ping_judg;
+pub mod random_block_judg;
+pub mod substrate_judg;
 pub mod websocket_judg;
 
 use crate::persistence::services::job_result_service::JobResultService;
@@ -18,13 +24,25 @@ use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Display, Formatter};
 
 use crate::models::job_result::ProviderTask;
+use crate::service::judgment::assertion_judg::AssertionJudgment;
+use crate::service::judgment::block_hash_judg::BlockHashJudgment;
+use crate::service::judgment::eth_node_judg::{
+    EthChainIdJudgment, EthClientVersionJudgment, EthSyncingJudgment,
+};
+use crate::service::judgment::http_batch_judg::HttpBatchJudgment;
 use crate::service::judgment::http_latestblock_judg::HttpLatestBlockJudgment;
 use crate::service::judgment::http_ping_judg::HttpPingJudgment;
+use crate::service::judgment::random_block_judg::RandomBlockJudgment;
+use crate::service::judgment::substrate_judg::{
+    SubstrateFinalityJudgment, SubstrateHealthJudgment, SubstrateRuntimeVersionJudgment,
+    SubstrateSyncStateJudgment,
+};
 use common::jobs::{Job, JobResult};
 
 use crate::service::judgment::JudgmentsResult::Failed;
 use crate::service::report_portal::{ReportErrorCode, ReportFailedReasons};
 use common::job_manage::JobRole;
+use log::trace;
 use std::sync::Arc;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -75,6 +93,28 @@ impl JudgmentsResult {
             ReportFailedReasons::new_with_single_reason(job_name, failed_detail, error_code);
         JudgmentsResult::Failed(reasons)
     }
+    /*
+     * Provider did not answer the request of the judgment
+     */
+    pub fn new_call_failed(job_name: String, message: &str, error_code: ReportErrorCode) -> Self {
+        Self::new_failed(job_name, format!("Request failed: {}", message), error_code)
+    }
+}
+
+/*
+ * Only the newest result decides, None if there is no result yet
+ */
+pub fn latest_result<'a>(
+    provider_task: &ProviderTask,
+    results: &'a [JobResult],
+) -> Option<&'a JobResult> {
+    let latest_result = results.iter().max_by_key(|res| res.receive_timestamp);
+    trace!(
+        "Judge {:?} latest result {:?}",
+        provider_task,
+        latest_result
+    );
+    latest_result
 }
 
 #[async_trait]
@@ -96,6 +136,12 @@ pub trait ReportCheck: Sync + Send {
         _provider_task: &ProviderTask,
         _result: &Vec<JobResult>,
     ) -> Result<JudgmentsResult, anyhow::Error>;
+    /*
+     * Replace task configs (thresholds) on hot reload, cached results must be kept
+     */
+    fn reload_configs(&self, _config_dir: &str, _phase: &JobRole) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
 }
 
 pub fn get_report_judgments(
@@ -104,6 +150,8 @@ pub fn get_report_judgments(
     phase: &JobRole,
 ) -> Vec<Arc<dyn ReportCheck>> {
     let result: Vec<Arc<dyn ReportCheck>> = vec![
+        //Assertions in task config take precedence over built-in judgments
+        Arc::new(AssertionJudgment::new(config_dir, phase)),
         Arc::new(BenchmarkJudgment::new(config_dir, result_service.clone())),
         Arc::new(HttpPingJudgment::new(
             config_dir,
@@ -115,6 +163,48 @@ pub fn get_report_judgments(
             phase,
             result_service.clone(),
         )),
+        Arc::new(BlockHashJudgment::new(
+            config_dir,
+            phase,
+            result_service.clone(),
+        )),
+        Arc::new(EthSyncingJudgment::new(
+            config_dir,
+            phase,
+            result_service.clone(),
+        )),
+        Arc::new(EthChainIdJudgment::new(
+            config_dir,
+            phase,
+            result_service.clone(),
+        )),
+        Arc::new(EthClientVersionJudgment::new(
+            config_dir,
+            phase,
+            result_service.clone(),
+        )),
+        Arc::new(RandomBlockJudgment::new(config_dir, phase)),
+        Arc::new(SubstrateHealthJudgment::new(
+            config_dir,
+            phase,
+            result_service.clone(),
+        )),
+        Arc::new(SubstrateSyncStateJudgment::new(
+            config_dir,
+            phase,
+            result_service.clone(),
+        )),
+        Arc::new(SubstrateFinalityJudgment::new(
+            config_dir,
+            phase,
+            result_service.clone(),
+        )),
+        Arc::new(SubstrateRuntimeVersionJudgment::new(
+            config_dir,
+            phase,
+            result_service.clone(),
+        )),
+        Arc::new(HttpBatchJudgment::new(config_dir, phase)),
         Arc::new(WebsocketJudgment::new(
             config_dir,
             phase,
@@ -0,0 +1,193 @@
+use crate::models::job_result::ProviderTask;
+use crate::models::reloadable::Reloadable;
+use crate::service::judgment::{latest_result, JudgmentsResult, ReportCheck};
+use crate::service::report_portal::ReportErrorCode;
+use crate::tasks::eth::random_block::generator::RandomBlockConfig;
+use crate::CONFIG_RANDOM_BLOCK_DIR;
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use common::job_manage::{JobResultDetail, JobRole};
+use common::jobs::JobResult;
+use common::tasks::eth::random_block::JobRandomBlockResult;
+use common::tasks::LoadConfigs;
+use log::debug;
+use std::path::Path;
+
+/*
+ * A provider fails if a random historical block differs from the same block of the reference endpoint
+ */
+#[derive(Debug)]
+pub struct RandomBlockJudgment {
+    task_configs: Reloadable<Vec<RandomBlockConfig>>,
+}
+
+impl RandomBlockJudgment {
+    pub fn new(config_dir: &str, phase: &JobRole) -> Self {
+        RandomBlockJudgment {
+            task_configs: Reloadable::new(Self::load_configs(config_dir, phase)),
+        }
+    }
+    fn load_configs(config_dir: &str, phase: &JobRole) -> Vec<RandomBlockConfig> {
+        let path = Path::new(config_dir).join(&*CONFIG_RANDOM_BLOCK_DIR);
+        RandomBlockConfig::read_configs(&path, phase)
+    }
+}
+
+#[async_trait]
+impl ReportCheck for RandomBlockJudgment {
+    fn get_name(&self) -> String {
+        String::from("RandomBlock")
+    }
+    fn get_error_code(&self) -> ReportErrorCode {
+        ReportErrorCode::DataCorrectnessJudgementFailed
+    }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), Error> {
+        self.task_configs.set(Self::load_configs(config_dir, phase));
+        Ok(())
+    }
+    fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
+        task.task_type.as_str() == "RandomBlock"
+            && self
+                .task_configs
+                .get()
+                .iter()
+                .any(|config| config.name == task.task_name)
+    }
+    async fn apply_for_results(
+        &self,
+        provider_task: &ProviderTask,
+        results: &Vec<JobResult>,
+    ) -> Result<JudgmentsResult, Error> {
+        let latest_result = match latest_result(provider_task, results) {
+            Some(result) => result,
+            None => return Ok(JudgmentsResult::Unfinished),
+        };
+        let response = match &latest_result.result_detail {
+            JobResultDetail::RandomBlock(JobRandomBlockResult { response, .. }) => response,
+            _ => return Err(anyhow!("Unexpected result detail of {:?}", provider_task)),
+        };
+        // Reference endpoint failure is not provider's fault
+        if response.is_reference_error() {
+            debug!(
+                "Reference error for {:?}: {}",
+                provider_task, &response.message
+            );
+            return Ok(JudgmentsResult::Unfinished);
+        }
+        if response.error_code != 0 {
+            return Ok(JudgmentsResult::new_call_failed(
+                self.get_name(),
+                &response.message,
+                ReportErrorCode::DataCorrectnessCallFailed,
+            ));
+        }
+        let (provider_block, reference_block) =
+            match (&response.provider_block, &response.reference_block) {
+                (Some(provider_block), Some(reference_block)) => (provider_block, reference_block),
+                _ => return Err(anyhow!("Missing blocks in result of {:?}", provider_task)),
+            };
+        let mismatched_fields = provider_block.get_mismatched_fields(reference_block);
+        let res = if mismatched_fields.is_empty() {
+            JudgmentsResult::Pass
+        } else {
+            JudgmentsResult::new_failed(
+                self.get_name(),
+                format!(
+                    "Block {} differs from reference in {}: provider {:?}, reference {:?}",
+                    response.block_number,
+                    mismatched_fields.join(", "),
+                    provider_block,
+                    reference_block
+                ),
+                ReportErrorCode::RandomBlockMismatched,
+            )
+        };
+        debug!("Random block judgment for {:?}: {:?}", provider_task, &res);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CONFIG_TASK_DIR;
+    use common::component::ComponentType;
+    use common::tasks::eth::random_block::{
+        BlockSummary, CallRandomBlockError, RandomBlockResponse,
+    };
+    use common::BlockChainType;
+    use test_util::helper::{load_env, mock_job_result_with_detail};
+
+    fn mock_random_block_result(response: RandomBlockResponse) -> JobResult {
+        let detail = JobResultDetail::RandomBlock(JobRandomBlockResult {
+            job: Default::default(),
+            worker_id: "".to_string(),
+            response,
+        });
+        mock_job_result_with_detail("RandomBlock", BlockChainType::Eth, "", detail)
+    }
+
+    #[tokio::test]
+    async fn test_random_block_judgment() -> Result<(), Error> {
+        load_env();
+        let judge = RandomBlockJudgment::new(CONFIG_TASK_DIR.as_str(), &JobRole::Regular);
+        let task = ProviderTask::new(
+            "provider".to_string(),
+            ComponentType::Node,
+            "RandomBlock".to_string(),
+            "RandomBlock".to_string(),
+        );
+        assert!(judge.can_apply_for_result(&task));
+        judge.reload_configs(CONFIG_TASK_DIR.as_str(), &JobRole::Regular)?;
+        assert!(judge.can_apply_for_result(&task));
+        let block = BlockSummary {
+            number: 100,
+            hash: "0xaaa".to_string(),
+            parent_hash: "0xbbb".to_string(),
+            state_root: "0xccc".to_string(),
+            transaction_count: 3,
+        };
+        let mut response = RandomBlockResponse {
+            block_number: 100,
+            provider_block: Some(block.clone()),
+            reference_block: Some(block.clone()),
+            ..Default::default()
+        };
+        let judge_response =
+            |response: &RandomBlockResponse| vec![mock_random_block_result(response.clone())];
+        assert_eq!(
+            judge
+                .apply_for_results(&task, &judge_response(&response))
+                .await?,
+            JudgmentsResult::Pass
+        );
+        response.provider_block = Some(BlockSummary {
+            state_root: "0xddd".to_string(),
+            ..block
+        });
+        let res = judge
+            .apply_for_results(&task, &judge_response(&response))
+            .await?;
+        assert!(
+            matches!(&res, JudgmentsResult::Failed(reasons) if reasons.to_string().contains("stateRoot")),
+            "{:?}",
+            res
+        );
+        // Reference failure does not fail the provider
+        let reference_error = CallRandomBlockError::ReferenceError("timeout".to_string());
+        let response = RandomBlockResponse::new_error(0, reference_error.get_code(), "timeout");
+        assert_eq!(
+            judge
+                .apply_for_results(&task, &judge_response(&response))
+                .await?,
+            JudgmentsResult::Unfinished
+        );
+        let provider_error = CallRandomBlockError::ProviderError("timeout".to_string());
+        let response = RandomBlockResponse::new_error(0, provider_error.get_code(), "timeout");
+        assert!(judge
+            .apply_for_results(&task, &judge_response(&response))
+            .await?
+            .is_failed());
+        Ok(())
+    }
+}
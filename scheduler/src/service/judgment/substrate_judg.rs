@@ -0,0 +1,669 @@
+use crate::models::job_result::ProviderTask;
+use crate::models::reloadable::Reloadable;
+use crate::persistence::services::JobResultService;
+use crate::service::judgment::http_latestblock_judg::CacheKey;
+use crate::service::judgment::{latest_result, JudgmentsResult, ReportCheck};
+use crate::service::report_portal::ReportErrorCode;
+use crate::tasks::dot::generator::{SubstrateConfig, SubstrateThresholds};
+use crate::CONFIG_SUBSTRATE_DIR;
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use common::component::ChainInfo;
+use common::job_manage::{JobResultDetail, JobRole};
+use common::jobs::JobResult;
+use common::tasks::dot::{
+    JobSubstrateResult, RuntimeVersion, SubstrateCheck, SubstrateResponse, SubstrateValues,
+};
+use common::tasks::{LoadConfigs, TaskConfigTrait};
+use common::ComponentId;
+use log::debug;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/*
+ * Substrate task configs of a check, judgments apply only for their own check
+ */
+#[derive(Debug)]
+struct SubstrateTaskConfigs {
+    check: SubstrateCheck,
+    configs: Reloadable<Vec<SubstrateConfig>>,
+}
+
+impl SubstrateTaskConfigs {
+    fn new(config_dir: &str, phase: &JobRole, check: SubstrateCheck) -> Self {
+        SubstrateTaskConfigs {
+            check,
+            configs: Reloadable::new(Self::load_configs(config_dir, phase, check)),
+        }
+    }
+    fn load_configs(
+        config_dir: &str,
+        phase: &JobRole,
+        check: SubstrateCheck,
+    ) -> Vec<SubstrateConfig> {
+        let path = Path::new(config_dir).join(&*CONFIG_SUBSTRATE_DIR);
+        SubstrateConfig::read_configs(&path, phase)
+            .into_iter()
+            .filter(|config| config.check == check)
+            .collect()
+    }
+    fn reload(&self, config_dir: &str, phase: &JobRole) {
+        self.configs
+            .set(Self::load_configs(config_dir, phase, self.check));
+    }
+    fn can_apply(&self, task: &ProviderTask) -> bool {
+        task.task_type.as_str() == "Substrate"
+            && self
+                .configs
+                .get()
+                .iter()
+                .any(|config| config.name == task.task_name)
+    }
+    fn get_thresholds(
+        &self,
+        task_name: &str,
+        phase: &JobRole,
+    ) -> Result<SubstrateThresholds, Error> {
+        self.configs
+            .get()
+            .iter()
+            .find(|config| config.name.as_str() == task_name && config.match_phase(phase))
+            .map(|config| config.thresholds.clone())
+            .ok_or_else(|| anyhow!("No {} config for task {}", self.check, task_name))
+    }
+}
+
+fn get_substrate_response<'a>(
+    provider_task: &ProviderTask,
+    result: &'a JobResult,
+) -> Result<&'a SubstrateResponse, Error> {
+    match &result.result_detail {
+        JobResultDetail::Substrate(JobSubstrateResult { response, .. }) => Ok(response),
+        _ => Err(anyhow!("Unexpected result detail of {:?}", provider_task)),
+    }
+}
+
+/*
+ * system_health: node must not be syncing and must have enough peers
+ */
+#[derive(Debug)]
+pub struct SubstrateHealthJudgment {
+    task_configs: SubstrateTaskConfigs,
+    _result_service: Arc<JobResultService>,
+}
+
+impl SubstrateHealthJudgment {
+    pub fn new(config_dir: &str, phase: &JobRole, result_service: Arc<JobResultService>) -> Self {
+        SubstrateHealthJudgment {
+            task_configs: SubstrateTaskConfigs::new(config_dir, phase, SubstrateCheck::Health),
+            _result_service: result_service,
+        }
+    }
+}
+
+#[async_trait]
+impl ReportCheck for SubstrateHealthJudgment {
+    fn get_name(&self) -> String {
+        String::from("SubstrateHealth")
+    }
+    fn get_error_code(&self) -> ReportErrorCode {
+        ReportErrorCode::SubstrateJudgementFailed
+    }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), Error> {
+        self.task_configs.reload(config_dir, phase);
+        Ok(())
+    }
+    fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
+        self.task_configs.can_apply(task)
+    }
+    async fn apply_for_results(
+        &self,
+        provider_task: &ProviderTask,
+        results: &Vec<JobResult>,
+    ) -> Result<JudgmentsResult, Error> {
+        let result = match latest_result(provider_task, results) {
+            Some(result) => result,
+            None => return Ok(JudgmentsResult::Unfinished),
+        };
+        let response = get_substrate_response(provider_task, result)?;
+        if response.error_code != 0 {
+            return Ok(JudgmentsResult::new_call_failed(
+                self.get_name(),
+                &response.message,
+                ReportErrorCode::SubstrateCallFailed,
+            ));
+        }
+        let thresholds = self
+            .task_configs
+            .get_thresholds(&result.job_name, &result.phase)?;
+        let res = match &response.values {
+            SubstrateValues::Health {
+                is_syncing: true, ..
+            } => JudgmentsResult::new_failed(
+                self.get_name(),
+                String::from("Node is syncing"),
+                ReportErrorCode::SubstrateNodeSyncing,
+            ),
+            SubstrateValues::Health {
+                peers,
+                should_have_peers: true,
+                ..
+            } if *peers < thresholds.min_peers => JudgmentsResult::new_failed(
+                self.get_name(),
+                format!(
+                    "Node has {} peers, expected at least {}",
+                    peers, thresholds.min_peers
+                ),
+                ReportErrorCode::SubstratePeersTooFew,
+            ),
+            SubstrateValues::Health { .. } => JudgmentsResult::Pass,
+            values => return Err(anyhow!("Unexpected health values {:?}", values)),
+        };
+        debug!(
+            "Substrate health judgment for {:?}: {:?}",
+            provider_task, &res
+        );
+        Ok(res)
+    }
+}
+
+/*
+ * system_syncState: current block must be close to highest known block
+ */
+#[derive(Debug)]
+pub struct SubstrateSyncStateJudgment {
+    task_configs: SubstrateTaskConfigs,
+    _result_service: Arc<JobResultService>,
+}
+
+impl SubstrateSyncStateJudgment {
+    pub fn new(config_dir: &str, phase: &JobRole, result_service: Arc<JobResultService>) -> Self {
+        SubstrateSyncStateJudgment {
+            task_configs: SubstrateTaskConfigs::new(config_dir, phase, SubstrateCheck::SyncState),
+            _result_service: result_service,
+        }
+    }
+}
+
+#[async_trait]
+impl ReportCheck for SubstrateSyncStateJudgment {
+    fn get_name(&self) -> String {
+        String::from("SubstrateSyncState")
+    }
+    fn get_error_code(&self) -> ReportErrorCode {
+        ReportErrorCode::SubstrateJudgementFailed
+    }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), Error> {
+        self.task_configs.reload(config_dir, phase);
+        Ok(())
+    }
+    fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
+        self.task_configs.can_apply(task)
+    }
+    async fn apply_for_results(
+        &self,
+        provider_task: &ProviderTask,
+        results: &Vec<JobResult>,
+    ) -> Result<JudgmentsResult, Error> {
+        let result = match latest_result(provider_task, results) {
+            Some(result) => result,
+            None => return Ok(JudgmentsResult::Unfinished),
+        };
+        let response = get_substrate_response(provider_task, result)?;
+        if response.error_code != 0 {
+            return Ok(JudgmentsResult::new_call_failed(
+                self.get_name(),
+                &response.message,
+                ReportErrorCode::SubstrateCallFailed,
+            ));
+        }
+        let thresholds = self
+            .task_configs
+            .get_thresholds(&result.job_name, &result.phase)?;
+        let (current_block, highest_block) = match &response.values {
+            SubstrateValues::SyncState {
+                current_block,
+                highest_block,
+            } => (*current_block, *highest_block),
+            values => return Err(anyhow!("Unexpected sync state values {:?}", values)),
+        };
+        let lag = highest_block.saturating_sub(current_block);
+        let res = if lag > thresholds.max_sync_lag {
+            JudgmentsResult::new_failed(
+                self.get_name(),
+                format!(
+                    "Current block {} is {} blocks behind highest block {}, max {}",
+                    current_block, lag, highest_block, thresholds.max_sync_lag
+                ),
+                ReportErrorCode::SubstrateSyncLagging,
+            )
+        } else {
+            JudgmentsResult::Pass
+        };
+        debug!(
+            "Substrate sync state judgment for {:?}: {:?}",
+            provider_task, &res
+        );
+        Ok(res)
+    }
+}
+
+/*
+ * chain_getFinalizedHead: finalized head must be close to best head
+ */
+#[derive(Debug)]
+pub struct SubstrateFinalityJudgment {
+    task_configs: SubstrateTaskConfigs,
+    _result_service: Arc<JobResultService>,
+}
+
+impl SubstrateFinalityJudgment {
+    pub fn new(config_dir: &str, phase: &JobRole, result_service: Arc<JobResultService>) -> Self {
+        SubstrateFinalityJudgment {
+            task_configs: SubstrateTaskConfigs::new(
+                config_dir,
+                phase,
+                SubstrateCheck::FinalizedHead,
+            ),
+            _result_service: result_service,
+        }
+    }
+}
+
+#[async_trait]
+impl ReportCheck for SubstrateFinalityJudgment {
+    fn get_name(&self) -> String {
+        String::from("SubstrateFinality")
+    }
+    fn get_error_code(&self) -> ReportErrorCode {
+        ReportErrorCode::SubstrateJudgementFailed
+    }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), Error> {
+        self.task_configs.reload(config_dir, phase);
+        Ok(())
+    }
+    fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
+        self.task_configs.can_apply(task)
+    }
+    async fn apply_for_results(
+        &self,
+        provider_task: &ProviderTask,
+        results: &Vec<JobResult>,
+    ) -> Result<JudgmentsResult, Error> {
+        let result = match latest_result(provider_task, results) {
+            Some(result) => result,
+            None => return Ok(JudgmentsResult::Unfinished),
+        };
+        let response = get_substrate_response(provider_task, result)?;
+        if response.error_code != 0 {
+            return Ok(JudgmentsResult::new_call_failed(
+                self.get_name(),
+                &response.message,
+                ReportErrorCode::SubstrateCallFailed,
+            ));
+        }
+        let thresholds = self
+            .task_configs
+            .get_thresholds(&result.job_name, &result.phase)?;
+        let (best_number, finalized_number) = match &response.values {
+            SubstrateValues::FinalizedHead {
+                best_number,
+                finalized_number,
+            } => (*best_number, *finalized_number),
+            values => return Err(anyhow!("Unexpected finalized head values {:?}", values)),
+        };
+        let lag = best_number.saturating_sub(finalized_number);
+        let res = if lag > thresholds.max_finality_lag {
+            JudgmentsResult::new_failed(
+                self.get_name(),
+                format!(
+                    "Finalized block {} is {} blocks behind best block {}, max {}",
+                    finalized_number, lag, best_number, thresholds.max_finality_lag
+                ),
+                ReportErrorCode::SubstrateFinalityLagging,
+            )
+        } else {
+            JudgmentsResult::Pass
+        };
+        debug!(
+            "Substrate finality judgment for {:?}: {:?}",
+            provider_task, &res
+        );
+        Ok(res)
+    }
+}
+
+/*
+ * state_getRuntimeVersion: providers of the same chain and network must run the same runtime.
+ * Latest version of each provider is kept, a provider fails if its version differs from the majority version.
+ */
+#[derive(Debug)]
+pub struct SubstrateRuntimeVersionJudgment {
+    task_configs: SubstrateTaskConfigs,
+    _result_service: Arc<JobResultService>,
+    versions: Mutex<HashMap<CacheKey, HashMap<ComponentId, RuntimeVersion>>>,
+}
+
+impl SubstrateRuntimeVersionJudgment {
+    pub fn new(config_dir: &str, phase: &JobRole, result_service: Arc<JobResultService>) -> Self {
+        SubstrateRuntimeVersionJudgment {
+            task_configs: SubstrateTaskConfigs::new(
+                config_dir,
+                phase,
+                SubstrateCheck::RuntimeVersion,
+            ),
+            _result_service: result_service,
+            versions: Default::default(),
+        }
+    }
+    /*
+     * Put version of a provider and return versions of all providers of the chain
+     */
+    fn add_version(
+        &self,
+        cache_key: &CacheKey,
+        provider_id: &ComponentId,
+        version: &RuntimeVersion,
+    ) -> HashMap<ComponentId, RuntimeVersion> {
+        let mut versions = self.versions.lock().unwrap();
+        let chain_versions = versions.entry(cache_key.clone()).or_default();
+        chain_versions.insert(provider_id.clone(), version.clone());
+        chain_versions.clone()
+    }
+    /*
+     * Version returned by more than half of the providers
+     */
+    fn get_majority_version(
+        versions: &HashMap<ComponentId, RuntimeVersion>,
+    ) -> Option<(&RuntimeVersion, usize)> {
+        let mut counters = HashMap::<&RuntimeVersion, usize>::new();
+        for version in versions.values() {
+            *counters.entry(version).or_default() += 1;
+        }
+        counters
+            .into_iter()
+            .max_by_key(|(_, counter)| *counter)
+            .filter(|(_, counter)| 2 * counter > versions.len())
+    }
+}
+
+#[async_trait]
+impl ReportCheck for SubstrateRuntimeVersionJudgment {
+    fn get_name(&self) -> String {
+        String::from("SubstrateRuntimeVersion")
+    }
+    fn get_error_code(&self) -> ReportErrorCode {
+        ReportErrorCode::SubstrateJudgementFailed
+    }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), Error> {
+        // Sampled versions are kept
+        self.task_configs.reload(config_dir, phase);
+        Ok(())
+    }
+    fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
+        self.task_configs.can_apply(task)
+    }
+    async fn apply_for_results(
+        &self,
+        provider_task: &ProviderTask,
+        results: &Vec<JobResult>,
+    ) -> Result<JudgmentsResult, Error> {
+        let result = match latest_result(provider_task, results) {
+            Some(result) => result,
+            None => return Ok(JudgmentsResult::Unfinished),
+        };
+        let response = get_substrate_response(provider_task, result)?;
+        if response.error_code != 0 {
+            return Ok(JudgmentsResult::new_call_failed(
+                self.get_name(),
+                &response.message,
+                ReportErrorCode::SubstrateCallFailed,
+            ));
+        }
+        let thresholds = self
+            .task_configs
+            .get_thresholds(&result.job_name, &result.phase)?;
+        let version = match &response.values {
+            SubstrateValues::RuntimeVersion(version) => version,
+            values => return Err(anyhow!("Unexpected runtime version values {:?}", values)),
+        };
+        let ChainInfo { chain, network } = result
+            .chain_info
+            .clone()
+            .ok_or_else(|| anyhow!("Missing chain_info"))?;
+        // Providers are not distinguished in the key, versions of a chain are shared
+        let cache_key = CacheKey::new(String::new(), chain, network);
+        let versions = self.add_version(&cache_key, &provider_task.provider_id, version);
+        if versions.len() < thresholds.min_providers {
+            debug!(
+                "Runtime version is sampled from {} of {} providers, wait for more samples",
+                versions.len(),
+                thresholds.min_providers
+            );
+            return Ok(JudgmentsResult::Unfinished);
+        }
+        let res = match Self::get_majority_version(&versions) {
+            Some((majority, _)) if majority == version => JudgmentsResult::Pass,
+            Some((majority, counter)) => JudgmentsResult::new_failed(
+                self.get_name(),
+                format!(
+                    "Runtime version {} differs from majority version {} of {}/{} providers",
+                    version,
+                    majority,
+                    counter,
+                    versions.len()
+                ),
+                ReportErrorCode::SubstrateRuntimeVersionMismatched,
+            ),
+            None => {
+                debug!(
+                    "No majority runtime version for provider {}: {:?}",
+                    &provider_task.provider_id, &versions
+                );
+                JudgmentsResult::Unfinished
+            }
+        };
+        debug!(
+            "Substrate runtime version judgment for {:?}: {:?}",
+            provider_task, &res
+        );
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CONFIG_TASK_DIR;
+    use common::component::ComponentType;
+    use common::BlockChainType;
+    use test_util::helper::{load_env, mock_db_connection, mock_job_result_with_detail};
+
+    fn mock_substrate_result(
+        provider_id: &str,
+        task_name: &str,
+        values: SubstrateValues,
+    ) -> Vec<JobResult> {
+        let detail = JobResultDetail::Substrate(JobSubstrateResult {
+            job: Default::default(),
+            worker_id: "".to_string(),
+            response: SubstrateResponse {
+                values,
+                ..Default::default()
+            },
+        });
+        vec![mock_job_result_with_detail(
+            task_name,
+            BlockChainType::Dot,
+            provider_id,
+            detail,
+        )]
+    }
+
+    fn mock_substrate_task(provider_id: &str, task_name: &str) -> ProviderTask {
+        ProviderTask::new(
+            provider_id.to_string(),
+            ComponentType::Node,
+            "Substrate".to_string(),
+            task_name.to_string(),
+        )
+    }
+
+    fn mock_runtime_version(spec_version: u64) -> SubstrateValues {
+        SubstrateValues::RuntimeVersion(RuntimeVersion {
+            spec_name: "polkadot".to_string(),
+            spec_version,
+            transaction_version: 13,
+        })
+    }
+
+    fn get_failed_detail(res: &JudgmentsResult) -> Option<String> {
+        match res {
+            JudgmentsResult::Failed(reasons) => Some(reasons.to_string()),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_substrate_judgments() -> Result<(), Error> {
+        load_env();
+        let config_dir = CONFIG_TASK_DIR.as_str();
+        let phase = JobRole::Regular;
+        let result_service = Arc::new(JobResultService::new(Arc::new(mock_db_connection())));
+        let health = SubstrateHealthJudgment::new(config_dir, &phase, result_service.clone());
+        let sync_state =
+            SubstrateSyncStateJudgment::new(config_dir, &phase, result_service.clone());
+        let finality = SubstrateFinalityJudgment::new(config_dir, &phase, result_service.clone());
+        // Each judgment applies only for its own check
+        let task = mock_substrate_task("provider_1", "DotHealth");
+        assert!(health.can_apply_for_result(&task));
+        assert!(!sync_state.can_apply_for_result(&task));
+        let check = |values| mock_substrate_result("provider_1", "DotHealth", values);
+        let res = health
+            .apply_for_results(
+                &task,
+                &check(SubstrateValues::Health {
+                    is_syncing: false,
+                    peers: 40,
+                    should_have_peers: true,
+                }),
+            )
+            .await?;
+        assert_eq!(res, JudgmentsResult::Pass);
+        let res = health
+            .apply_for_results(
+                &task,
+                &check(SubstrateValues::Health {
+                    is_syncing: true,
+                    peers: 40,
+                    should_have_peers: true,
+                }),
+            )
+            .await?;
+        assert!(get_failed_detail(&res).unwrap().contains("syncing"));
+        let res = health
+            .apply_for_results(
+                &task,
+                &check(SubstrateValues::Health {
+                    is_syncing: false,
+                    peers: 1,
+                    should_have_peers: true,
+                }),
+            )
+            .await?;
+        assert!(get_failed_detail(&res).unwrap().contains("peers"));
+
+        let task = mock_substrate_task("provider_1", "DotSyncState");
+        assert!(sync_state.can_apply_for_result(&task));
+        let check = |current_block, highest_block| {
+            mock_substrate_result(
+                "provider_1",
+                "DotSyncState",
+                SubstrateValues::SyncState {
+                    current_block,
+                    highest_block,
+                },
+            )
+        };
+        assert!(sync_state
+            .apply_for_results(&task, &check(1000, 1002))
+            .await?
+            .is_pass());
+        assert!(sync_state
+            .apply_for_results(&task, &check(900, 1000))
+            .await?
+            .is_failed());
+
+        let task = mock_substrate_task("provider_1", "DotFinalizedHead");
+        assert!(finality.can_apply_for_result(&task));
+        let check = |best_number, finalized_number| {
+            mock_substrate_result(
+                "provider_1",
+                "DotFinalizedHead",
+                SubstrateValues::FinalizedHead {
+                    best_number,
+                    finalized_number,
+                },
+            )
+        };
+        assert!(finality
+            .apply_for_results(&task, &check(1000, 998))
+            .await?
+            .is_pass());
+        assert!(finality
+            .apply_for_results(&task, &check(1000, 500))
+            .await?
+            .is_failed());
+        // Failed call
+        let mut results = check(1000, 998);
+        if let JobResultDetail::Substrate(result) = &mut results[0].result_detail {
+            result.response = SubstrateResponse::new_error(0, 1, "timeout");
+        }
+        assert!(finality
+            .apply_for_results(&task, &results)
+            .await?
+            .is_failed());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_substrate_runtime_version() -> Result<(), Error> {
+        load_env();
+        let result_service = Arc::new(JobResultService::new(Arc::new(mock_db_connection())));
+        let judge = SubstrateRuntimeVersionJudgment::new(
+            CONFIG_TASK_DIR.as_str(),
+            &JobRole::Regular,
+            result_service,
+        );
+        let judge_version = |provider_id: &'static str, spec_version| {
+            let task = mock_substrate_task(provider_id, "DotRuntimeVersion");
+            let results = mock_substrate_result(
+                provider_id,
+                "DotRuntimeVersion",
+                mock_runtime_version(spec_version),
+            );
+            (task, results)
+        };
+        let (task, results) = judge_version("provider_1", 9270);
+        assert!(judge.can_apply_for_result(&task));
+        assert_eq!(
+            judge.apply_for_results(&task, &results).await?,
+            JudgmentsResult::Unfinished
+        );
+        let (task, results) = judge_version("provider_2", 9260);
+        assert_eq!(
+            judge.apply_for_results(&task, &results).await?,
+            JudgmentsResult::Unfinished
+        );
+        let (task, results) = judge_version("provider_3", 9270);
+        assert!(judge.apply_for_results(&task, &results).await?.is_pass());
+        let (task, results) = judge_version("provider_2", 9260);
+        let res = judge.apply_for_results(&task, &results).await?;
+        assert!(get_failed_detail(&res).unwrap().contains("majority"));
+        // Upgraded provider passes
+        let (task, results) = judge_version("provider_2", 9270);
+        assert!(judge.apply_for_results(&task, &results).await?.is_pass());
+        Ok(())
+    }
+}
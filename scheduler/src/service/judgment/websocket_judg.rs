@@ -1,4 +1,5 @@
 use crate::models::job_result::ProviderTask;
+use crate::models::reloadable::Reloadable;
 use crate::persistence::services::job_result_service::JobResultService;
 use crate::service::judgment::{JudgmentsResult, ReportCheck};
 use std::path::Path;
@@ -16,7 +17,7 @@ use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct WebsocketJudgment {
-    _job_configs: Vec<JobWebsocketConfig>,
+    _job_configs: Reloadable<Vec<JobWebsocketConfig>>,
     _result_service: Arc<JobResultService>,
 }
 
@@ -26,19 +27,21 @@ impl WebsocketJudgment {
         //     format!("{}/websocket.json", config_dir).as_str(),
         //     phase,
         // );
-        //let path = format!("{}/websocket", config_dir);
-        let path = Path::new(config_dir).join(&*CONFIG_WEBSOCKET_DIR);
-        let job_configs = JobWebsocketConfig::read_configs(&path, phase);
         WebsocketJudgment {
-            _job_configs: job_configs,
+            _job_configs: Reloadable::new(Self::load_configs(config_dir, phase)),
             _result_service: result_service,
         }
     }
-    pub fn get_config(&self, name: &TaskName) -> Option<&JobWebsocketConfig> {
-        for config in &self._job_configs {
+    fn load_configs(config_dir: &str, phase: &JobRole) -> Vec<JobWebsocketConfig> {
+        //let path = format!("{}/websocket", config_dir);
+        let path = Path::new(config_dir).join(&*CONFIG_WEBSOCKET_DIR);
+        JobWebsocketConfig::read_configs(&path, phase)
+    }
+    pub fn get_config(&self, name: &TaskName) -> Option<JobWebsocketConfig> {
+        for config in self._job_configs.get().iter() {
             if &config.name == name {
                 info!("&config.name: {}, &config.name: {}", config.name, name);
-                return Some(config);
+                return Some(config.clone());
             }
         }
         None
@@ -53,6 +56,10 @@ impl ReportCheck for WebsocketJudgment {
     fn get_error_code(&self) -> ReportErrorCode {
         ReportErrorCode::WebsocketCallFailed
     }
+    fn reload_configs(&self, config_dir: &str, phase: &JobRole) -> Result<(), anyhow::Error> {
+        self._job_configs.set(Self::load_configs(config_dir, phase));
+        Ok(())
+    }
     fn can_apply_for_result(&self, task: &ProviderTask) -> bool {
         return task.task_type.as_str() == "Websocket";
     }
@@ -0,0 +1,247 @@
+use crate::models::workers::WorkerInfoStorage;
+use crate::persistence::services::{JobService, WorkerService};
+use crate::service::delivery::PullJobBuffer;
+use crate::{LEADER_ELECTION_PERIOD, LEADER_LOCK_KEY, SCHEDULER_SHARD_INDEX, SHARD_MAP};
+use anyhow::anyhow;
+use common::job_manage::JobRole;
+use common::workers::Worker;
+use common::PlanId;
+use log::{error, info, warn};
+use sea_orm::{
+    ConnectionTrait, DatabaseBackend, DatabaseConnection, DatabaseTransaction, Statement,
+    TransactionTrait,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{sleep, timeout};
+
+/*
+ * Elect one leader among scheduler instances sharing a database with a Postgres advisory lock.
+ * The lock is held by an open transaction, it is released when the transaction ends or its connection dies,
+ * an idle leader transaction is terminated by Postgres after 3 missed heartbeats.
+ */
+pub struct LeaderElection {
+    db: Arc<DatabaseConnection>,
+    lock_key: i64,
+    leader: watch::Sender<bool>,
+    worker_service: Arc<WorkerService>,
+    worker_pool: Arc<WorkerInfoStorage>,
+    job_service: Arc<JobService>,
+    pull_buffer: Arc<PullJobBuffer>,
+}
+
+impl LeaderElection {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        let (leader, _) = watch::channel(false);
+        LeaderElection {
+            db,
+            //Each scheduler shard has its own leader
+            lock_key: *LEADER_LOCK_KEY + *SCHEDULER_SHARD_INDEX as i64,
+            leader,
+            worker_service: Default::default(),
+            worker_pool: Default::default(),
+            job_service: Default::default(),
+            pull_buffer: Default::default(),
+        }
+    }
+    pub fn with_lock_key(mut self, lock_key: i64) -> Self {
+        self.lock_key = lock_key;
+        self
+    }
+    pub fn with_worker_service(mut self, worker_service: Arc<WorkerService>) -> Self {
+        self.worker_service = worker_service;
+        self
+    }
+    pub fn with_worker_pool(mut self, worker_pool: Arc<WorkerInfoStorage>) -> Self {
+        self.worker_pool = worker_pool;
+        self
+    }
+    pub fn with_job_service(mut self, job_service: Arc<JobService>) -> Self {
+        self.job_service = job_service;
+        self
+    }
+    pub fn with_pull_buffer(mut self, pull_buffer: Arc<PullJobBuffer>) -> Self {
+        self.pull_buffer = pull_buffer;
+        self
+    }
+    pub fn is_leader(&self) -> bool {
+        *self.leader.borrow()
+    }
+    /*
+     * Wait until this instance becomes leader (true) or loses its leadership (false)
+     */
+    pub async fn wait_leadership(&self, leader: bool) {
+        let mut receiver = self.leader.subscribe();
+        while *receiver.borrow() != leader {
+            if receiver.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+    /*
+     * Open a transaction holding the leader lock, None if another instance holds it
+     */
+    pub async fn try_acquire(&self) -> Result<Option<DatabaseTransaction>, anyhow::Error> {
+        let txn = self.db.begin().await?;
+        let idle_timeout = format!(
+            "SET LOCAL idle_in_transaction_session_timeout = '{}s'",
+            3 * *LEADER_ELECTION_PERIOD
+        );
+        txn.execute(Statement::from_string(
+            DatabaseBackend::Postgres,
+            idle_timeout,
+        ))
+        .await?;
+        let locked = txn
+            .query_one(Statement::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                "SELECT pg_try_advisory_xact_lock($1) AS locked",
+                vec![self.lock_key.into()],
+            ))
+            .await?
+            .ok_or(anyhow!("Leader lock query returns no row"))?
+            .try_get::<bool>("", "locked")?;
+        if locked {
+            Ok(Some(txn))
+        } else {
+            txn.rollback().await?;
+            Ok(None)
+        }
+    }
+    async fn heartbeat(txn: &DatabaseTransaction) -> Result<(), anyhow::Error> {
+        let period = Duration::from_secs(*LEADER_ELECTION_PERIOD);
+        timeout(
+            period,
+            txn.execute(Statement::from_string(
+                DatabaseBackend::Postgres,
+                "SELECT 1".to_string(),
+            )),
+        )
+        .await
+        .map_err(|_| anyhow!("Leader heartbeat timeout"))??;
+        Ok(())
+    }
+    pub async fn run(&self) {
+        loop {
+            match self.try_acquire().await {
+                Ok(Some(txn)) => {
+                    info!("Acquired leader lock {}", self.lock_key);
+                    self.leader.send_replace(true);
+                    loop {
+                        sleep(Duration::from_secs(*LEADER_ELECTION_PERIOD)).await;
+                        if let Err(err) = Self::heartbeat(&txn).await {
+                            error!("Lost leader lock {}: {:?}", self.lock_key, err);
+                            break;
+                        }
+                    }
+                    self.leader.send_replace(false);
+                }
+                Ok(None) => {
+                    log::debug!("Leader lock {} is held by another instance", self.lock_key);
+                }
+                Err(err) => {
+                    warn!("Cannot acquire leader lock {}: {:?}", self.lock_key, err);
+                }
+            }
+            sleep(Duration::from_secs(*LEADER_ELECTION_PERIOD)).await;
+        }
+    }
+    /*
+     * Prepare in memory state before running leader tasks:
+     * reload workers registered on any instance and cancel regular plans assigned by the previous leader,
+     * they are generated again by this instance
+     */
+    pub async fn take_over(&self) {
+        for worker in self.worker_service.get_active().await {
+            self.worker_pool.add_worker(worker).await;
+        }
+        let plan_workers = match self.job_service.get_regular_plan_workers().await {
+            Ok(plan_workers) => plan_workers,
+            Err(err) => {
+                error!("get_regular_plan_workers error: {:?}", err);
+                return;
+            }
+        };
+        let mut cancel_plans = HashMap::<Arc<Worker>, Vec<PlanId>>::new();
+        let plan_prefix = format!("{}-", JobRole::Regular.to_string());
+        for (plan_id, worker_id) in plan_workers {
+            //Other shards keep their regular plans
+            let provider_id = plan_id.trim_start_matches(&plan_prefix);
+            if !SHARD_MAP.is_owned(provider_id, *SCHEDULER_SHARD_INDEX) {
+                continue;
+            }
+            if let Some(worker) = self.worker_pool.get_worker(worker_id).await {
+                cancel_plans.entry(worker).or_default().push(plan_id);
+            }
+        }
+        info!(
+            "Cancel regular plans of previous leader on {} workers",
+            cancel_plans.len()
+        );
+        for (worker, plans) in cancel_plans {
+            if worker.is_pull_mode() {
+                self.pull_buffer
+                    .push_cancel_plans(&worker.get_id(), plans)
+                    .await;
+            } else if let Err(err) = worker.send_cancel_plans(&plans).await {
+                warn!(
+                    "Cannot cancel plans on worker {}: {:?}",
+                    worker.get_id(),
+                    err
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{MockDatabase, MockExecResult, Value};
+    use std::collections::BTreeMap;
+    use test_util::helper::load_env;
+
+    fn mock_lock_connection(locked: bool) -> Arc<DatabaseConnection> {
+        let exec_res = MockExecResult {
+            last_insert_id: 0,
+            rows_affected: 0,
+        };
+        let row = BTreeMap::from([("locked", Value::from(locked))]);
+        Arc::new(
+            MockDatabase::new(DatabaseBackend::Postgres)
+                .append_exec_results(vec![exec_res])
+                .append_query_results(vec![vec![row]])
+                .into_connection(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_try_acquire() -> Result<(), anyhow::Error> {
+        load_env();
+        let election = LeaderElection::new(mock_lock_connection(true)).with_lock_key(1);
+        assert!(election.try_acquire().await?.is_some());
+        let election = LeaderElection::new(mock_lock_connection(false)).with_lock_key(1);
+        assert!(election.try_acquire().await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wait_leadership() {
+        let election = Arc::new(LeaderElection::new(Default::default()));
+        assert!(!election.is_leader());
+        let waiting = election.clone();
+        let task = tokio::spawn(async move { waiting.wait_leadership(true).await });
+        election.leader.send_replace(true);
+        timeout(Duration::from_secs(1), task)
+            .await
+            .expect("Leadership is not notified")
+            .unwrap();
+        assert!(election.is_leader());
+        // Already leader
+        timeout(Duration::from_secs(1), election.wait_leadership(true))
+            .await
+            .unwrap();
+    }
+}
@@ -1,9 +1,12 @@
 pub mod check_worker_health;
 pub mod comparator;
+pub mod config_reload;
 pub mod delivery;
 pub mod generator;
 pub mod judgment;
+pub mod leader;
 pub mod processor;
+pub mod rebalancer;
 pub mod report_portal;
 pub mod service_monitor;
 pub mod webservice;
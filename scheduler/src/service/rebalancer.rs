@@ -0,0 +1,656 @@
+use crate::models::job_result_cache::TaskKey;
+use crate::models::jobs::JobAssignmentBuffer;
+use crate::models::providers::ProviderStorage;
+use crate::models::workers::WorkerInfoStorage;
+use crate::persistence::services::JobService;
+use crate::service::delivery::CancelPlanBuffer;
+use common::jobs::{AssignmentConfig, Job, JobAssignment};
+use common::util::warning_if_error;
+use common::workers::{MatchedWorkers, Worker};
+use common::{JobId, PlanId, WorkerId};
+use log::{debug, info, warn};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/*
+ * Regular job with the workers which currently run it
+ */
+struct ActiveJob {
+    job: Job,
+    config: Option<AssignmentConfig>,
+    workers: Vec<WorkerId>,
+}
+
+impl ActiveJob {
+    fn new(assignment: &JobAssignment) -> Self {
+        ActiveJob {
+            job: assignment.job.clone(),
+            config: assignment.assignment_config.clone(),
+            workers: vec![],
+        }
+    }
+    fn is_broadcast(&self) -> bool {
+        self.config
+            .as_ref()
+            .map(|config| config.is_broadcast())
+            .unwrap_or(false)
+    }
+    fn is_rendezvous(&self) -> bool {
+        self.config
+            .as_ref()
+            .map(|config| config.use_rendezvous_hashing())
+            .unwrap_or(false)
+    }
+    /*
+     * Pick an eligible worker: the next one in rendezvous ranking or the least loaded one
+     */
+    async fn select_worker(&self, matched_workers: &MatchedWorkers) -> Option<Arc<Worker>> {
+        let eligible_workers = matched_workers.get_eligible_workers(&self.config);
+        if self.is_rendezvous() {
+            matched_workers
+                .get_rendezvous_workers(&eligible_workers, &self.job.component_id)
+                .into_iter()
+                .next()
+        } else {
+            matched_workers
+                .get_least_loaded_worker(&eligible_workers)
+                .await
+        }
+    }
+    /*
+     * New worker takes the place of the holder in the top ranked workers of the job
+     */
+    fn is_displaced(
+        &self,
+        matched_workers: &MatchedWorkers,
+        holder: &WorkerId,
+        new_worker: &WorkerId,
+    ) -> bool {
+        let worker_number = self
+            .config
+            .as_ref()
+            .and_then(|config| config.worker_number)
+            .unwrap_or(1);
+        let top_workers = matched_workers
+            .get_rendezvous_workers(
+                &matched_workers.get_eligible_workers(&self.config),
+                &self.job.component_id,
+            )
+            .into_iter()
+            .take(worker_number)
+            .map(|worker| worker.get_id())
+            .collect::<Vec<WorkerId>>();
+        top_workers.contains(new_worker) && !top_workers.contains(holder)
+    }
+    fn create_assignment(&self, worker: Arc<Worker>) -> JobAssignment {
+        let mut assignment = JobAssignment::new(worker, &self.job);
+        assignment.assignment_config = self.config.clone();
+        assignment
+    }
+}
+
+#[derive(Default)]
+struct RebalanceResult {
+    //Moved assignments with the worker which lost the job
+    moved_assignments: Vec<(WorkerId, JobAssignment)>,
+    new_assignments: Vec<JobAssignment>,
+    cancel_plans: Vec<(PlanId, Arc<Worker>)>,
+}
+
+/*
+ * Keep regular jobs on working workers when worker pool changes:
+ * jobs of removed workers are reassigned with the task's assignment config,
+ * new workers take broadcast jobs, jobs without worker, rendezvous jobs ranking them higher
+ * and plans of the most loaded workers.
+ * Workers which lost jobs receive cancel plans
+ */
+#[derive(Default)]
+pub struct JobRebalancer {
+    assignment_buffer: Arc<Mutex<JobAssignmentBuffer>>,
+    cancel_plans_buffer: Arc<Mutex<CancelPlanBuffer>>,
+    worker_pool: Arc<WorkerInfoStorage>,
+    providers: Arc<ProviderStorage>,
+    job_service: Arc<JobService>,
+    active_jobs: Mutex<HashMap<PlanId, HashMap<TaskKey, ActiveJob>>>,
+}
+
+impl JobRebalancer {
+    pub fn new(
+        assignment_buffer: Arc<Mutex<JobAssignmentBuffer>>,
+        cancel_plans_buffer: Arc<Mutex<CancelPlanBuffer>>,
+    ) -> Self {
+        JobRebalancer {
+            assignment_buffer,
+            cancel_plans_buffer,
+            ..Default::default()
+        }
+    }
+    pub fn with_worker_pool(mut self, worker_pool: Arc<WorkerInfoStorage>) -> Self {
+        self.worker_pool = worker_pool;
+        self
+    }
+    pub fn with_providers(mut self, providers: Arc<ProviderStorage>) -> Self {
+        self.providers = providers;
+        self
+    }
+    pub fn with_job_service(mut self, job_service: Arc<JobService>) -> Self {
+        self.job_service = job_service;
+        self
+    }
+    /*
+     * Track generated regular assignments, a regenerated job replaces the old job of the same task
+     */
+    pub async fn register_assignments(&self, assignments: &[JobAssignment]) {
+        let mut active_jobs = self.active_jobs.lock().await;
+        for assignment in assignments.iter() {
+            let key = TaskKey {
+                task_type: assignment.job.job_type.clone(),
+                task_name: assignment.job.job_name.clone(),
+            };
+            let active_job = active_jobs
+                .entry(assignment.job.plan_id.clone())
+                .or_default()
+                .entry(key)
+                .or_insert_with(|| ActiveJob::new(assignment));
+            if active_job.job.job_id != assignment.job.job_id {
+                *active_job = ActiveJob::new(assignment);
+            }
+            active_job.workers.push(assignment.worker.get_id());
+        }
+    }
+    /*
+     * Move tracked job to another worker, return false if the job is not on from_worker any more
+     */
+    pub async fn move_job(
+        &self,
+        job_id: &JobId,
+        from_worker: &WorkerId,
+        to_worker: &WorkerId,
+    ) -> bool {
+        let mut active_jobs = self.active_jobs.lock().await;
+        let active_job = active_jobs
+            .values_mut()
+            .flat_map(|plan_jobs| plan_jobs.values_mut())
+            .find(|active_job| &active_job.job.job_id == job_id);
+        match active_job {
+            Some(active_job) => match active_job.workers.iter_mut().find(|id| *id == from_worker) {
+                Some(id) => {
+                    *id = to_worker.clone();
+                    true
+                }
+                None => false,
+            },
+            None => true,
+        }
+    }
+    pub async fn run(&self) {
+        let mut known_workers = self
+            .worker_pool
+            .get_workers()
+            .await
+            .into_iter()
+            .map(|worker| (worker.get_id(), worker))
+            .collect::<HashMap<WorkerId, Arc<Worker>>>();
+        loop {
+            self.worker_pool.wait_membership_change().await;
+            self.rebalance(&mut known_workers).await;
+        }
+    }
+    pub async fn rebalance(&self, known_workers: &mut HashMap<WorkerId, Arc<Worker>>) {
+        let workers = self.worker_pool.get_workers().await;
+        let removed_workers = known_workers
+            .values()
+            .filter(|worker| !workers.iter().any(|item| item.has_id(&worker.get_id())))
+            .cloned()
+            .collect::<Vec<Arc<Worker>>>();
+        let added_workers = workers
+            .iter()
+            .filter(|worker| !known_workers.contains_key(&worker.get_id()))
+            .cloned()
+            .collect::<Vec<Arc<Worker>>>();
+        *known_workers = workers
+            .into_iter()
+            .map(|worker| (worker.get_id(), worker))
+            .collect();
+        if !removed_workers.is_empty() {
+            info!(
+                "Rebalance jobs of {} removed workers",
+                removed_workers.len()
+            );
+            let result = self.handle_removed_workers(&removed_workers).await;
+            self.apply(result).await;
+        }
+        for worker in added_workers {
+            info!("Rebalance jobs for new worker {}", worker.get_id());
+            let result = self.handle_added_worker(worker).await;
+            self.apply(result).await;
+        }
+    }
+    async fn match_plan_workers(
+        &self,
+        plan_jobs: &HashMap<TaskKey, ActiveJob>,
+    ) -> Option<MatchedWorkers> {
+        let provider_id = plan_jobs.values().next()?.job.component_id.clone();
+        let provider = self.providers.get_active_provider(&provider_id).await?;
+        self.worker_pool.match_workers(&provider).await.ok()
+    }
+    async fn handle_removed_workers(&self, removed_workers: &[Arc<Worker>]) -> RebalanceResult {
+        let removed_ids = removed_workers
+            .iter()
+            .map(|worker| worker.get_id())
+            .collect::<Vec<WorkerId>>();
+        let mut result = RebalanceResult::default();
+        let mut inactive_plans = Vec::new();
+        let mut active_jobs = self.active_jobs.lock().await;
+        for (plan_id, plan_jobs) in active_jobs.iter_mut() {
+            let lost_workers = removed_workers
+                .iter()
+                .filter(|worker| {
+                    plan_jobs
+                        .values()
+                        .any(|active_job| active_job.workers.contains(&worker.get_id()))
+                })
+                .cloned()
+                .collect::<Vec<Arc<Worker>>>();
+            if lost_workers.is_empty() {
+                continue;
+            }
+            // Removed worker may be still alive, stop its jobs of the plan
+            for worker in lost_workers {
+                result.cancel_plans.push((plan_id.clone(), worker));
+            }
+            let matched_workers = match self.match_plan_workers(plan_jobs).await {
+                Some(matched_workers) => matched_workers,
+                None => {
+                    inactive_plans.push(plan_id.clone());
+                    continue;
+                }
+            };
+            for active_job in plan_jobs.values_mut() {
+                let (lost, mut holders): (Vec<WorkerId>, Vec<WorkerId>) = active_job
+                    .workers
+                    .drain(..)
+                    .partition(|id| removed_ids.contains(id));
+                // Broadcast job is still run by the other workers
+                if !active_job.is_broadcast() {
+                    for from_worker in lost {
+                        match active_job
+                            .select_worker(&matched_workers.exclude(&holders))
+                            .await
+                        {
+                            Some(worker) => {
+                                debug!(
+                                    "Move job {} from removed worker {} to worker {}",
+                                    &active_job.job.job_id,
+                                    &from_worker,
+                                    worker.get_id()
+                                );
+                                holders.push(worker.get_id());
+                                result
+                                    .moved_assignments
+                                    .push((from_worker, active_job.create_assignment(worker)));
+                            }
+                            None => {
+                                warn!(
+                                    "No worker for job {} of plan {}, it waits for a new worker",
+                                    &active_job.job.job_id, plan_id
+                                );
+                            }
+                        }
+                    }
+                }
+                active_job.workers = holders;
+            }
+        }
+        active_jobs.retain(|plan_id, _| !inactive_plans.contains(plan_id));
+        result
+    }
+    async fn handle_added_worker(&self, new_worker: Arc<Worker>) -> RebalanceResult {
+        let new_id = new_worker.get_id();
+        let mut result = RebalanceResult::default();
+        let mut inactive_plans = Vec::new();
+        let mut active_jobs = self.active_jobs.lock().await;
+        let mut loads = HashMap::<WorkerId, usize>::new();
+        for active_job in active_jobs
+            .values()
+            .flat_map(|plan_jobs| plan_jobs.values())
+        {
+            for id in active_job.workers.iter() {
+                *loads.entry(id.clone()).or_default() += 1;
+            }
+        }
+        for (plan_id, plan_jobs) in active_jobs.iter_mut() {
+            let matched_workers = match self.match_plan_workers(plan_jobs).await {
+                Some(matched_workers) => matched_workers,
+                None => {
+                    inactive_plans.push(plan_id.clone());
+                    continue;
+                }
+            };
+            let eligible_jobs = plan_jobs
+                .iter()
+                .filter(|(_, active_job)| {
+                    !active_job.workers.contains(&new_id)
+                        && matched_workers
+                            .get_eligible_workers(&active_job.config)
+                            .iter()
+                            .any(|worker| worker.has_id(&new_id))
+                })
+                .map(|(key, _)| key.clone())
+                .collect::<HashSet<TaskKey>>();
+            // Broadcast jobs run on every worker, jobs without worker are taken by the new one
+            for (key, active_job) in plan_jobs.iter_mut() {
+                if eligible_jobs.contains(key)
+                    && (active_job.is_broadcast() || active_job.workers.is_empty())
+                {
+                    active_job.workers.push(new_id.clone());
+                    *loads.entry(new_id.clone()).or_default() += 1;
+                    result
+                        .new_assignments
+                        .push(active_job.create_assignment(new_worker.clone()));
+                }
+            }
+            // Move the plan from the most loaded worker if the load becomes more balanced
+            let mut holders = plan_jobs
+                .values()
+                .flat_map(|active_job| active_job.workers.iter())
+                .filter(|id| **id != new_id)
+                .cloned()
+                .collect::<HashSet<WorkerId>>()
+                .into_iter()
+                .collect::<Vec<WorkerId>>();
+            holders.sort_by_key(|id| Reverse(loads.get(id).cloned().unwrap_or_default()));
+            for holder in holders {
+                let holder_jobs = plan_jobs
+                    .iter()
+                    .filter(|(_, active_job)| active_job.workers.contains(&holder))
+                    .map(|(key, _)| key.clone())
+                    .collect::<Vec<TaskKey>>();
+                // Cancel plan stops all jobs of the plan on the worker, so all of them must be moved
+                if holder_jobs
+                    .iter()
+                    .any(|key| !eligible_jobs.contains(key) || plan_jobs[key].is_broadcast())
+                {
+                    continue;
+                }
+                let size = holder_jobs.len();
+                let holder_load = loads.get(&holder).cloned().unwrap_or_default();
+                let new_load = loads.get(&new_id).cloned().unwrap_or_default();
+                let rendezvous_jobs = holder_jobs
+                    .iter()
+                    .filter(|key| plan_jobs[*key].is_rendezvous())
+                    .count();
+                if rendezvous_jobs > 0 {
+                    // Rendezvous jobs move only if the new worker takes the holder place in their ranking
+                    if rendezvous_jobs < size
+                        || !holder_jobs.iter().all(|key| {
+                            plan_jobs[key].is_displaced(&matched_workers, &holder, &new_id)
+                        })
+                    {
+                        continue;
+                    }
+                } else if holder_load < new_load + 2 * size {
+                    continue;
+                }
+                let worker = match self.worker_pool.get_worker(holder.clone()).await {
+                    Some(worker) => worker,
+                    None => continue,
+                };
+                for key in holder_jobs.iter() {
+                    if let Some(active_job) = plan_jobs.get_mut(key) {
+                        active_job.workers.retain(|id| *id != holder);
+                        active_job.workers.push(new_id.clone());
+                        result.moved_assignments.push((
+                            holder.clone(),
+                            active_job.create_assignment(new_worker.clone()),
+                        ));
+                    }
+                }
+                debug!(
+                    "Move {} jobs of plan {} from worker {} to new worker {}",
+                    size, plan_id, &holder, &new_id
+                );
+                loads.insert(holder.clone(), holder_load - size);
+                loads.insert(new_id.clone(), new_load + size);
+                result.cancel_plans.push((plan_id.clone(), worker));
+                if rendezvous_jobs == 0 {
+                    break;
+                }
+            }
+        }
+        active_jobs.retain(|plan_id, _| !inactive_plans.contains(plan_id));
+        result
+    }
+    async fn apply(&self, result: RebalanceResult) {
+        let RebalanceResult {
+            moved_assignments,
+            new_assignments,
+            cancel_plans,
+        } = result;
+        if !cancel_plans.is_empty() {
+            let mut cancel_plans_buffer = self.cancel_plans_buffer.lock().await;
+            for (plan_id, worker) in cancel_plans {
+                cancel_plans_buffer.insert_plan(plan_id, worker);
+            }
+        }
+        let mut assignments = Vec::new();
+        let worker_loads = self.worker_pool.get_worker_loads();
+        for (from_worker, assignment) in moved_assignments {
+            worker_loads
+                .finish_jobs(&from_worker, &[assignment.job.job_id.clone()])
+                .await;
+            worker_loads
+                .add_job(&assignment.worker.get_id(), &assignment.job)
+                .await;
+            let res = self
+                .job_service
+                .reassign_job(
+                    &assignment.job.job_id,
+                    &from_worker,
+                    &assignment.worker.get_id(),
+                )
+                .await;
+            warning_if_error("reassign_job return error", res);
+            assignments.push(assignment);
+        }
+        if !new_assignments.is_empty() {
+            for assignment in new_assignments.iter() {
+                worker_loads
+                    .add_job(&assignment.worker.get_id(), &assignment.job)
+                    .await;
+            }
+            let res = self
+                .job_service
+                .save_job_assignments(&new_assignments)
+                .await;
+            warning_if_error("save_job_assignments return error", res);
+            assignments.extend(new_assignments);
+        }
+        info!("Rebalancer delivers {} job assignments", assignments.len());
+        if !assignments.is_empty() {
+            self.assignment_buffer
+                .lock()
+                .await
+                .add_assignments(assignments);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::component::ComponentType;
+    use common::job_manage::JobRole;
+    use common::BlockChainType;
+    use test_util::helper::{load_env, mock_component_info, mock_worker};
+
+    fn mock_assignment(
+        worker: &Arc<Worker>,
+        job_id: &str,
+        plan_id: &str,
+        config: Option<AssignmentConfig>,
+    ) -> JobAssignment {
+        let job = Job {
+            job_id: job_id.to_string(),
+            job_type: "HttpRequest".to_string(),
+            job_name: job_id.to_string(),
+            plan_id: plan_id.to_string(),
+            component_id: "provider".to_string(),
+            ..Default::default()
+        };
+        let mut assignment = JobAssignment::new(worker.clone(), &job);
+        assignment.assignment_config = config;
+        assignment
+    }
+
+    #[tokio::test]
+    async fn test_rebalance_jobs() {
+        load_env();
+        let worker1 = Arc::new(Worker::new(mock_worker("worker1")));
+        let worker2 = Arc::new(Worker::new(mock_worker("worker2")));
+        let worker3 = Arc::new(Worker::new(mock_worker("worker3")));
+        let worker_pool = Arc::new(WorkerInfoStorage::new(vec![
+            (*worker1).clone(),
+            (*worker2).clone(),
+        ]));
+        let providers = Arc::new(ProviderStorage::default());
+        providers
+            .update_components_list(
+                ComponentType::Node,
+                vec![mock_component_info(
+                    "provider",
+                    &BlockChainType::Eth,
+                    &ComponentType::Node,
+                )],
+            )
+            .await;
+        let rebalancer = JobRebalancer::new(Default::default(), Default::default())
+            .with_worker_pool(worker_pool.clone())
+            .with_providers(providers);
+        let broadcast = Some(AssignmentConfig {
+            broadcast: Some(true),
+            ..Default::default()
+        });
+        rebalancer
+            .register_assignments(&[
+                mock_assignment(&worker1, "job1", "plan1", None),
+                mock_assignment(&worker1, "job2", "plan1", broadcast.clone()),
+                mock_assignment(&worker2, "job2", "plan1", broadcast),
+                mock_assignment(&worker2, "job3", "plan2", None),
+                mock_assignment(&worker2, "job4", "plan2", None),
+                mock_assignment(&worker2, "job5", "plan3", None),
+                mock_assignment(&worker2, "job6", "plan3", None),
+            ])
+            .await;
+
+        // Job of removed worker is moved, broadcast job stays on the other worker
+        worker_pool.remove_workers(&[&"worker1".to_string()]).await;
+        let result = rebalancer.handle_removed_workers(&[worker1.clone()]).await;
+        assert_eq!(result.moved_assignments.len(), 1);
+        let (from_worker, assignment) = &result.moved_assignments[0];
+        assert_eq!(from_worker, "worker1");
+        assert_eq!(assignment.job.job_id, "job1");
+        assert_eq!(assignment.worker.get_id(), "worker2");
+        assert_eq!(result.cancel_plans.len(), 1);
+        assert_eq!(result.cancel_plans[0].0, "plan1");
+        assert!(result.new_assignments.is_empty());
+        assert!(
+            !rebalancer
+                .move_job(
+                    &"job1".to_string(),
+                    &"worker1".to_string(),
+                    &"worker3".to_string()
+                )
+                .await
+        );
+        // Untracked job can be moved
+        assert!(
+            rebalancer
+                .move_job(
+                    &"job7".to_string(),
+                    &"worker1".to_string(),
+                    &"worker3".to_string()
+                )
+                .await
+        );
+
+        // New worker takes broadcast job and one plan of the loaded worker
+        worker_pool.add_worker((*worker3).clone()).await;
+        let result = rebalancer.handle_added_worker(worker3.clone()).await;
+        assert_eq!(result.new_assignments.len(), 1);
+        assert_eq!(result.new_assignments[0].job.job_id, "job2");
+        assert_eq!(result.moved_assignments.len(), 2);
+        assert!(result
+            .moved_assignments
+            .iter()
+            .all(|(from_worker, assignment)| from_worker == "worker2"
+                && assignment.worker.get_id() == "worker3"));
+        assert_eq!(result.cancel_plans.len(), 1);
+        assert_eq!(result.cancel_plans[0].1.get_id(), "worker2");
+        assert_ne!(result.cancel_plans[0].0, "plan1");
+    }
+
+    #[tokio::test]
+    async fn test_rebalance_rendezvous_jobs() {
+        load_env();
+        let worker_pool = Arc::new(WorkerInfoStorage::new(vec![]));
+        let providers = Arc::new(ProviderStorage::default());
+        let provider = mock_component_info("provider", &BlockChainType::Eth, &ComponentType::Node);
+        providers
+            .update_components_list(ComponentType::Node, vec![provider.clone()])
+            .await;
+        let rebalancer = JobRebalancer::new(Default::default(), Default::default())
+            .with_worker_pool(worker_pool.clone())
+            .with_providers(providers);
+        let config = Some(AssignmentConfig {
+            worker_number: Some(1),
+            rendezvous_hashing: Some(true),
+            ..Default::default()
+        });
+        let worker = Arc::new(Worker::new(mock_worker("worker0")));
+        worker_pool.add_worker((*worker).clone()).await;
+        let mut assignment = mock_assignment(&worker, "job1", "plan1", config);
+        assignment.job.phase = JobRole::Regular;
+        rebalancer.register_assignments(&[assignment]).await;
+        let mut holder = worker.get_id();
+        // Job is always on the top ranked worker
+        for ind in 1..8 {
+            let worker = Arc::new(Worker::new(mock_worker(&format!("worker{}", ind))));
+            worker_pool.add_worker((*worker).clone()).await;
+            let result = rebalancer.handle_added_worker(worker).await;
+            if let Some((from_worker, assignment)) = result.moved_assignments.first() {
+                assert_eq!(from_worker, &holder);
+                assert_eq!(result.cancel_plans[0].1.get_id(), holder);
+                holder = assignment.worker.get_id();
+            }
+            let matched_workers = worker_pool.match_workers(&provider).await.unwrap();
+            let top_worker = matched_workers
+                .get_rendezvous_workers(&matched_workers.get_all_workers(), "provider")
+                .first()
+                .map(|worker| worker.get_id())
+                .unwrap();
+            assert_eq!(top_worker, holder);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wait_membership_change() {
+        let worker_pool = Arc::new(WorkerInfoStorage::new(vec![]));
+        worker_pool
+            .add_worker(Worker::new(mock_worker("worker1")))
+            .await;
+        // Change before waiting is not lost
+        tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            worker_pool.wait_membership_change(),
+        )
+        .await
+        .unwrap();
+        let rebalancer = JobRebalancer::default().with_worker_pool(worker_pool.clone());
+        let mut known_workers = HashMap::new();
+        rebalancer.rebalance(&mut known_workers).await;
+        assert!(known_workers.contains_key("worker1"));
+    }
+}
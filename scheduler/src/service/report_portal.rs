@@ -3,6 +3,7 @@ use crate::{URL_PORTAL_PROVIDER_REPORT, URL_PORTAL_PROVIDER_VERIFY};
 use anyhow::{anyhow, Error};
 use common::component::ComponentType;
 use common::job_manage::JobRole;
+use common::tls::get_tls_client;
 use common::{ComponentId, Deserialize, PlanId, Serialize, COMMON_CONFIG};
 use log::{debug, info};
 use reqwest::Response;
@@ -80,8 +81,52 @@ pub enum ReportErrorCode {
     BenchmarkResponseTimeFailed = 402,
     //Bandwidth is too narrow
     BenchmarkThreadHoldFailed = 403,
+    // Cannot call batch request
+    BatchRequestCallFailed = 500,
+    // Batch judgement error
+    BatchRequestJudgementFailed = 501,
+    // Too many calls in batch failed or missing
+    BatchRequestPartialFailed = 502,
+    // Batch response ids do not match request ids
+    BatchRequestIdMismatched = 503,
+    // Cannot get data to check correctness
+    DataCorrectnessCallFailed = 600,
+    // Data correctness judgement error
+    DataCorrectnessJudgementFailed = 601,
+    // Block hash differs from majority hash of other providers
+    BlockHashMismatched = 602,
+    // Block hash differs from trusted reference endpoint
+    BlockHashReferenceMismatched = 603,
+    // Historical block differs from trusted reference endpoint
+    RandomBlockMismatched = 604,
+    // Cannot call substrate check
+    SubstrateCallFailed = 700,
+    // Substrate check judgement error
+    SubstrateJudgementFailed = 701,
+    // system_health reports node is syncing
+    SubstrateNodeSyncing = 702,
+    // system_health reports too few peers
+    SubstratePeersTooFew = 703,
+    // system_syncState current block too far from highest block
+    SubstrateSyncLagging = 704,
+    // Finalized head too far from best head
+    SubstrateFinalityLagging = 705,
+    // Runtime version differs from majority version of other providers
+    SubstrateRuntimeVersionMismatched = 706,
+    // Cannot call EVM node state method
+    EthNodeCallFailed = 800,
+    // EVM node state judgement error
+    EthNodeJudgementFailed = 801,
+    // eth_syncing reports node too far behind highest block
+    EthNodeSyncing = 802,
+    // eth_chainId or net_version differs from chain and network of the provider
+    ChainIdMismatched = 803,
+    // web3_clientVersion reports a client out of allowed clients
+    ClientNotAllowed = 804,
     //Judgement Failed
     JudgementFailed = 900,
+    //Assertion in task config is false
+    AssertionFailed = 901,
 }
 
 impl Default for ReportErrorCode {
@@ -264,8 +309,7 @@ impl StoreReport {
     }
 
     pub async fn send_data(&self) -> Result<Response, Error> {
-        let client_builder = reqwest::ClientBuilder::new();
-        let client = client_builder.danger_accept_invalid_certs(true).build()?;
+        let client = get_tls_client()?;
         // create body
         let body = self.create_body()?;
         // get url
@@ -38,6 +38,8 @@ impl CheckMkReport for WorkerMonitor {
             Box::new(self.response_time.clone()),
             Box::new(self.jobs_number_in_queue.clone()),
             Box::new(self.reports_number_in_queue.clone()),
+            Box::new(self.reports_number_in_outbox.clone()),
+            Box::new(self.max_scheduling_lag.clone()),
         ];
         // Create metric string
         let metrics_string =
@@ -114,6 +116,8 @@ pub struct WorkerMonitor {
     response_time: Metric<Timestamp>,
     jobs_number_in_queue: Metric<usize>,
     reports_number_in_queue: Metric<usize>,
+    reports_number_in_outbox: Metric<usize>,
+    max_scheduling_lag: Metric<Timestamp>,
     status_detail: String,
 }
 
@@ -170,11 +174,26 @@ impl WorkerMonitor {
             "reports_number_in_queue",
             worker_status.reports_number_in_queue,
         );
+        let reports_number_in_outbox = Metric::new(
+            "reports_number_in_outbox",
+            worker_status.reports_number_in_outbox,
+        );
+        let max_scheduling_lag = Metric::new(
+            "max_scheduling_lag",
+            worker_status
+                .scheduling_lags
+                .values()
+                .map(|lag| lag.last)
+                .max()
+                .unwrap_or_default(),
+        );
         WorkerMonitor {
             name,
             response_time,
             jobs_number_in_queue,
             reports_number_in_queue,
+            reports_number_in_outbox,
+            max_scheduling_lag,
             status_detail,
         }
     }
@@ -285,9 +304,17 @@ impl ServiceMonitor {
             return res;
         }
         let client = client.unwrap();
+        let worker_loads = self.workers.get_worker_loads();
         for worker in self.workers.get_workers().await {
             // get url
+            let worker_id = worker.get_id();
             let worker_status = Self::get_worker_status(worker, &client).await;
+            // Queue length is used for capacity aware assignment
+            if let Ok(monitor) = &worker_status {
+                worker_loads
+                    .update_queue_length(&worker_id, monitor.jobs_number_in_queue.value)
+                    .await;
+            }
             res.push(worker_status)
         }
         res
@@ -24,10 +24,7 @@ impl WebService {
         match scheduler_state.register_worker(worker_info).await {
             Ok(result) => Ok(warp::reply::json(&result)),
             Err(_err) => {
-                let result = WorkerRegisterResult {
-                    worker_id: "".to_string(),
-                    report_callback: "".to_string(),
-                };
+                let result = WorkerRegisterResult::default();
                 Ok(warp::reply::json(&result))
             }
         }
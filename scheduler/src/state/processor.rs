@@ -1,18 +1,24 @@
 use crate::models::job_result_cache::JobResultCache;
-use crate::persistence::services::{JobResultService, JobService, PlanService};
+use crate::persistence::services::{JobResultService, JobService, PlanService, WorkerService};
 use crate::report_processors::adapters::get_report_adapters;
 use crate::report_processors::adapters::result_cache_appender::ResultCacheAppender;
 use crate::report_processors::regular_processor::RegularReportProcessor;
 use crate::report_processors::verification_processor::VerificationReportProcessor;
 use crate::report_processors::ReportProcessor;
-use crate::service::judgment::MainJudgment;
+use crate::service::judgment::{MainJudgment, ReportCheck};
+use anyhow::anyhow;
+use common::auth::verify_signature;
 use common::job_manage::JobRole;
-use common::jobs::JobResult;
+use common::jobs::{JobResult, JobStatus};
+use common::util::warning_if_error;
+use common::{JobId, WorkerId};
+use log::warn;
 
 use sea_orm::DatabaseConnection;
 
 use crate::models::workers::WorkerInfoStorage;
 use crate::service::delivery::CancelPlanBuffer;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -21,9 +27,13 @@ pub struct ProcessorState {
     connection: Arc<DatabaseConnection>,
     regular_processor: Arc<dyn ReportProcessor>,
     verification_processor: Arc<dyn ReportProcessor>,
+    regular_judgments: Vec<Arc<dyn ReportCheck>>,
+    verification_judgments: Vec<Arc<dyn ReportCheck>>,
+    worker_pool: Arc<WorkerInfoStorage>,
+    worker_service: Arc<WorkerService>,
     _result_service: Arc<JobResultService>,
     _plan_service: Arc<PlanService>,
-    _job_service: Arc<JobService>,
+    job_service: Arc<JobService>,
 }
 
 impl ProcessorState {
@@ -39,31 +49,40 @@ impl ProcessorState {
         //For verification processor
         let mut report_adapters = get_report_adapters(connection.clone());
         report_adapters.push(Arc::new(ResultCacheAppender::new(result_cache.clone())));
+        let verification_judgment =
+            MainJudgment::new(result_service.clone(), &JobRole::Verification);
+        let verification_judgments = verification_judgment.get_judgments();
         let verification_processor = VerificationReportProcessor::new(
             report_adapters.clone(),
             plan_service.clone(),
             job_service.clone(),
             result_service.clone(),
             result_cache,
-            MainJudgment::new(result_service.clone(), &JobRole::Verification),
+            verification_judgment,
             worker_pool.clone(),
             cancel_plans_buffer.clone(),
         );
         //For regular processor
         let judgment = MainJudgment::new(result_service.clone(), &JobRole::Regular);
+        let regular_judgments = judgment.get_judgments();
         let regular_processor = RegularReportProcessor::new(
             report_adapters.clone(),
             judgment,
-            worker_pool,
+            worker_pool.clone(),
             cancel_plans_buffer,
         );
+        let worker_service = Arc::new(WorkerService::new(connection.clone()));
         ProcessorState {
             connection,
             regular_processor: Arc::new(regular_processor),
             verification_processor: Arc::new(verification_processor),
+            regular_judgments,
+            verification_judgments,
+            worker_pool,
+            worker_service,
             _result_service: result_service,
             _plan_service: plan_service,
-            _job_service: job_service,
+            job_service,
         }
     }
 }
@@ -73,16 +92,99 @@ impl Default for ProcessorState {
             connection: Arc::new(Default::default()),
             regular_processor: Arc::new(RegularReportProcessor::default()),
             verification_processor: Arc::new(VerificationReportProcessor::default()),
+            regular_judgments: vec![],
+            verification_judgments: vec![],
+            worker_pool: Arc::new(Default::default()),
+            worker_service: Arc::new(Default::default()),
             _result_service: Arc::new(Default::default()),
             _plan_service: Arc::new(Default::default()),
-            _job_service: Arc::new(Default::default()),
+            job_service: Arc::new(Default::default()),
+        }
+    }
+}
+impl ProcessorState {
+    pub fn get_judgments(&self, phase: &JobRole) -> Vec<Arc<dyn ReportCheck>> {
+        match phase {
+            JobRole::Regular => self.regular_judgments.clone(),
+            JobRole::Verification => self.verification_judgments.clone(),
         }
     }
 }
-impl ProcessorState {}
 
 impl ProcessorState {
+    /*
+     * Result batch must be signed with the key registered by the worker, and contains only its results
+     */
+    pub async fn verify_results(
+        &self,
+        worker_id: &str,
+        signature: &str,
+        body: &[u8],
+        results: &Vec<JobResult>,
+    ) -> Result<(), anyhow::Error> {
+        if let Some(result) = results.iter().find(|result| result.worker_id != worker_id) {
+            return Err(anyhow!(
+                "Result of job {} belongs to worker {}, not to sender {}",
+                result.job_id,
+                result.worker_id,
+                worker_id
+            ));
+        }
+        // Worker may be removed from working pool by health check, but its results are still valid
+        let public_key = match self.worker_pool.get_worker(worker_id.to_string()).await {
+            Some(worker) => worker.worker_info.public_key.clone(),
+            None => self
+                .worker_service
+                .get_stored_worker(worker_id)
+                .await
+                .map(|info| info.public_key)
+                .ok_or(anyhow!("Unknown worker {}", worker_id))?,
+        };
+        verify_signature(&public_key, body, signature)
+    }
+    /*
+     * Results have arrived, assignments of one-shot jobs move to DONE.
+     * Repeated jobs keep running on their workers until their plans are cancelled
+     */
+    pub async fn update_assignments_done(&self, results: &Vec<JobResult>) {
+        let job_ids = results
+            .iter()
+            .map(|result| result.job_id.clone())
+            .collect::<HashSet<JobId>>();
+        let one_shot_jobs = match self.job_service.get_job_by_ids(&job_ids).await {
+            Ok(jobs) => jobs
+                .into_iter()
+                .filter(|job| job.repeat_number == 0)
+                .map(|job| job.job_id)
+                .collect::<HashSet<JobId>>(),
+            Err(err) => {
+                warn!("Cannot get jobs of results: {:?}", err);
+                return;
+            }
+        };
+        let mut worker_jobs = HashMap::<WorkerId, HashSet<JobId>>::new();
+        for result in results
+            .iter()
+            .filter(|result| one_shot_jobs.contains(&result.job_id))
+        {
+            worker_jobs
+                .entry(result.worker_id.clone())
+                .or_default()
+                .insert(result.job_id.clone());
+        }
+        let worker_loads = self.worker_pool.get_worker_loads();
+        for (worker_id, job_ids) in worker_jobs.into_iter() {
+            let job_ids = job_ids.into_iter().collect::<Vec<JobId>>();
+            worker_loads.finish_jobs(&worker_id, &job_ids).await;
+            let res = self
+                .job_service
+                .update_assignment_status(&worker_id, &job_ids, JobStatus::DONE)
+                .await;
+            warning_if_error("update_assignment_status return error", res);
+        }
+    }
     pub async fn process_results(&self, results: Vec<JobResult>) -> Result<(), anyhow::Error> {
+        self.update_assignments_done(&results).await;
         let mut regular_results = Vec::new();
         let mut verification_result = Vec::new();
         for result in results {
@@ -1,18 +1,27 @@
 use crate::models::providers::ProviderStorage;
 use crate::models::workers::WorkerInfoStorage;
 use crate::persistence::services::plan_service::PlanService;
-use crate::persistence::services::WorkerService;
-use crate::{CONFIG, REPORT_CALLBACK};
+use crate::persistence::services::{JobService, WorkerService};
+use crate::service::delivery::PullJobBuffer;
+use crate::service::leader::LeaderElection;
+use crate::{CONFIG, REPORT_CALLBACK, REQUEST_MAX_CLOCK_SKEW, SCHEDULER_SHARD_INDEX, SHARD_MAP};
+use anyhow::anyhow;
+use common::auth::{parse_public_key, WorkerSecret};
 use common::component::ComponentInfo;
 use common::job_manage::JobRole;
+use common::jobs::JobStatus;
 use common::models::PlanEntity;
 use common::util::get_current_time;
-use common::workers::{WorkerInfo, WorkerRegisterResult};
+use common::workers::{Worker, WorkerInfo, WorkerJobsResult, WorkerRegisterResult};
+use common::{JobId, Timestamp, WorkerId};
 
 use sea_orm::DatabaseConnection;
 
 use log::{error, info};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
 
 #[derive(Default)]
 pub struct SchedulerState {
@@ -21,6 +30,12 @@ pub struct SchedulerState {
     worker_service: Arc<WorkerService>,
     worker_pool: Arc<WorkerInfoStorage>,
     providers: Arc<ProviderStorage>,
+    job_service: Arc<JobService>,
+    pull_buffer: Arc<PullJobBuffer>,
+    //Used signatures of each worker with their request timestamps, kept for the clock skew window
+    used_signatures: Mutex<HashMap<WorkerId, HashMap<String, Timestamp>>>,
+    //Without election (single instance) this scheduler always serves as leader
+    leader_election: Option<Arc<LeaderElection>>,
 }
 
 impl SchedulerState {
@@ -37,8 +52,33 @@ impl SchedulerState {
             worker_service,
             worker_pool,
             providers,
+            job_service: Default::default(),
+            pull_buffer: Default::default(),
+            used_signatures: Default::default(),
+            leader_election: None,
         }
     }
+    pub fn with_job_service(mut self, job_service: Arc<JobService>) -> Self {
+        self.job_service = job_service;
+        self
+    }
+    pub fn with_pull_buffer(mut self, pull_buffer: Arc<PullJobBuffer>) -> Self {
+        self.pull_buffer = pull_buffer;
+        self
+    }
+    pub fn with_leader_election(mut self, leader_election: Arc<LeaderElection>) -> Self {
+        self.leader_election = Some(leader_election);
+        self
+    }
+    /*
+     * Pull workers are served only by the leader, so its last polls are the only source of their liveness
+     */
+    pub fn is_leader(&self) -> bool {
+        self.leader_election
+            .as_ref()
+            .map(|election| election.is_leader())
+            .unwrap_or(true)
+    }
 }
 
 impl SchedulerState {
@@ -48,35 +88,144 @@ impl SchedulerState {
     ) -> Result<WorkerRegisterResult, anyhow::Error> {
         info!("worker_info: {:?}", &worker_info);
         let report_callback = REPORT_CALLBACK.as_str().to_string();
+        //Worker must register a valid key for signing its results
+        parse_public_key(&worker_info.public_key)?;
+        //Issue new secret for each registration, previous one is revoked
+        let worker_secret = WorkerSecret::generate();
+        let worker = Worker::new(worker_info).with_secret(worker_secret.clone());
         //Save worker to db
         if let Some(WorkerInfo { worker_id, .. }) = self
             .worker_service
             .clone()
-            .get_stored_worker(&worker_info.worker_id)
+            .get_stored_worker(&worker.worker_info.worker_id)
             .await
         {
-            self.worker_pool.add_worker(worker_info).await;
-            Ok(WorkerRegisterResult {
-                worker_id,
-                report_callback,
-            })
+            let res = self.worker_service.update_worker_credentials(&worker).await;
+            if res.is_err() {
+                error!("update_worker_credentials error: {:?}", res);
+            }
+            self.worker_pool.add_worker(worker).await;
+            Ok(
+                WorkerRegisterResult::new(worker_id, report_callback, worker_secret)
+                    .with_shard_map(SHARD_MAP.clone()),
+            )
         } else {
-            let worker_id = worker_info.worker_id.clone();
-            let res = self.worker_service.clone().store_worker(&worker_info).await;
+            let worker_id = worker.worker_info.worker_id.clone();
+            let res = self.worker_service.clone().store_worker(&worker).await;
             if res.is_err() {
                 error!("store_worker error: {:?}", res);
             }
-            self.worker_pool.add_worker(worker_info).await;
-            Ok(WorkerRegisterResult {
-                worker_id,
-                report_callback,
-            })
+            self.worker_pool.add_worker(worker).await;
+            Ok(
+                WorkerRegisterResult::new(worker_id, report_callback, worker_secret)
+                    .with_shard_map(SHARD_MAP.clone()),
+            )
         }
 
         //Add worker to ProviderStorage
     }
+    /*
+     * Worker which has a secret from a previous registration, in working pool or only in database
+     * (registered on another scheduler instance or removed by health check)
+     */
+    pub async fn is_registered_worker(&self, worker_id: &WorkerId) -> bool {
+        if let Some(worker) = self.worker_pool.get_worker(worker_id.clone()).await {
+            if !worker.secret.is_empty() {
+                return true;
+            }
+        }
+        self.worker_service
+            .get_worker(worker_id)
+            .await
+            .map(|worker| !worker.secret.is_empty())
+            .unwrap_or(false)
+    }
+    /*
+     * Verify a signed request from worker with the secret issued at its registration.
+     * Worker may be removed from working pool by health check while it is still polling.
+     * A signature is accepted only once in the clock skew window (replay protection)
+     */
+    pub async fn authenticate_worker(
+        &self,
+        worker_id: &WorkerId,
+        path: &str,
+        timestamp: Option<&str>,
+        signature: Option<&str>,
+        body: &[u8],
+    ) -> Result<(), anyhow::Error> {
+        let timestamp = timestamp
+            .ok_or(anyhow!("Missing request timestamp"))?
+            .parse::<Timestamp>()
+            .map_err(|err| anyhow!("Invalid request timestamp: {:?}", err))?;
+        let signature = signature.ok_or(anyhow!("Missing request signature"))?;
+        let now = get_current_time();
+        if (now - timestamp).abs() > *REQUEST_MAX_CLOCK_SKEW {
+            return Err(anyhow!(
+                "Request timestamp {} is out of allowed window, current time {}",
+                timestamp,
+                now
+            ));
+        }
+        let verified = match self.worker_pool.get_worker(worker_id.clone()).await {
+            Some(worker) => worker
+                .secret
+                .verify(timestamp, path, body, signature)
+                .is_ok(),
+            None => false,
+        };
+        if !verified {
+            //Worker may be registered again on another scheduler instance with a new secret
+            let secret = self
+                .worker_service
+                .get_worker(worker_id)
+                .await
+                .map(|worker| worker.secret)
+                .ok_or(anyhow!("Unknown worker {}", worker_id))?;
+            secret.verify(timestamp, path, body, signature)?;
+        }
+        let mut used_signatures = self.used_signatures.lock().await;
+        // Expired signatures are rejected by timestamp check
+        used_signatures.retain(|_, signatures| {
+            signatures.retain(|_, used_time| now - *used_time <= *REQUEST_MAX_CLOCK_SKEW);
+            !signatures.is_empty()
+        });
+        // Hex decoding ignores case, so the same signature is not accepted again in another case
+        if used_signatures
+            .entry(worker_id.clone())
+            .or_default()
+            .insert(signature.to_lowercase(), timestamp)
+            .is_some()
+        {
+            return Err(anyhow!("Request signature is already used"));
+        }
+        Ok(())
+    }
+    pub async fn poll_jobs(&self, worker_id: &WorkerId, wait: Duration) -> WorkerJobsResult {
+        self.pull_buffer.poll(worker_id, wait).await
+    }
+    /*
+     * Acknowledge jobs polled by worker, their assignments move from ASSIGNED to DELIVERED
+     */
+    pub async fn ack_jobs(
+        &self,
+        worker_id: &WorkerId,
+        job_ids: &[JobId],
+    ) -> Result<usize, anyhow::Error> {
+        let acked_ids = self.pull_buffer.ack(worker_id, job_ids).await;
+        self.job_service
+            .update_assignment_status(worker_id, &acked_ids, JobStatus::DELIVERED)
+            .await?;
+        Ok(acked_ids.len())
+    }
     pub async fn verify_node(&self, node_info: ComponentInfo) -> Result<PlanEntity, anyhow::Error> {
         log::debug!("Push node {:?} to verification queue", &node_info);
+        if !SHARD_MAP.is_owned(&node_info.id, *SCHEDULER_SHARD_INDEX) {
+            return Err(anyhow!(
+                "Node {} belongs to scheduler shard {}",
+                node_info.id,
+                SHARD_MAP.get_shard(&node_info.id)
+            ));
+        }
         //Create a scheduler in db
         let current_time = get_current_time();
         let expiry_time = current_time + CONFIG.plan_expiry_time * 1000;
@@ -95,3 +244,113 @@ impl SchedulerState {
         Ok(plan)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::{handle_route_worker_register, InvalidSignature};
+    use crate::service::webservice::WebService;
+    use crate::SCHEDULER_AUTHORIZATION;
+    use entity::seaorm::workers;
+    use sea_orm::{DatabaseBackend, MockDatabase};
+    use test_util::helper::{load_env, mock_worker};
+    use warp::hyper::body::Bytes;
+
+    #[tokio::test]
+    async fn test_authenticate_worker_replay() -> Result<(), anyhow::Error> {
+        let secret = WorkerSecret::generate();
+        let worker = Worker::new(mock_worker("worker_id")).with_secret(secret.clone());
+        let state = SchedulerState {
+            worker_pool: Arc::new(WorkerInfoStorage::new(vec![worker])),
+            ..Default::default()
+        };
+        let worker_id = "worker_id".to_string();
+        let body = b"{}";
+        let timestamp = get_current_time();
+        let signature = secret.sign(timestamp, "worker/worker_id/jobs", body)?;
+        let timestamp = timestamp.to_string();
+        state
+            .authenticate_worker(
+                &worker_id,
+                "worker/worker_id/jobs",
+                Some(&timestamp),
+                Some(&signature),
+                body,
+            )
+            .await?;
+        // Same signed request is rejected when it is sent again
+        assert!(state
+            .authenticate_worker(
+                &worker_id,
+                "worker/worker_id/jobs",
+                Some(&timestamp),
+                Some(&signature),
+                body,
+            )
+            .await
+            .is_err());
+        // Same signature in upper case is also a replay
+        assert!(state
+            .authenticate_worker(
+                &worker_id,
+                "worker/worker_id/jobs",
+                Some(&timestamp),
+                Some(&signature.to_uppercase()),
+                body,
+            )
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_worker_known_in_database() -> Result<(), anyhow::Error> {
+        load_env();
+        //Worker is not in working pool (removed by health check) but has a secret in database
+        let model = workers::Model {
+            id: 1,
+            url: "2.2.2.2:2".to_string(),
+            zone: "AS".to_string(),
+            specification: None,
+            description: None,
+            worker_id: "worker_id".to_string(),
+            worker_ip: "2.2.2.2".to_string(),
+            active: 0,
+            secret: Some(WorkerSecret::generate().as_str().to_string()),
+            public_key: None,
+            delivery_mode: None,
+        };
+        let db_conn = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![model]])
+            .into_connection();
+        let state = SchedulerState {
+            worker_service: Arc::new(WorkerService::new(Arc::new(db_conn))),
+            ..Default::default()
+        };
+        let body = Bytes::from(serde_json::to_vec(&mock_worker("worker_id"))?);
+        //Unsigned registration must not take over the worker id
+        let rejection = handle_route_worker_register(
+            Arc::new(WebService::default()),
+            Arc::new(state),
+            body,
+            SCHEDULER_AUTHORIZATION.to_string(),
+            None,
+            None,
+        )
+        .await
+        .err()
+        .expect("Registration of a worker known in database is not rejected");
+        assert!(rejection.find::<InvalidSignature>().is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_leader() {
+        //Single instance without election serves pull workers
+        assert!(SchedulerState::default().is_leader());
+        //Instance which has not acquired the leader lock is a follower
+        let election = Arc::new(LeaderElection::new(Default::default()));
+        let state = SchedulerState::default().with_leader_election(election);
+        assert!(!state.is_leader());
+    }
+}
@@ -11,11 +11,13 @@ use crate::tasks::generator::TaskApplicant;
 use common::component::{ComponentInfo, ComponentType};
 use common::job_manage::{JobBenchmark, JobDetail, JobRole};
 use common::jobs::{AssignmentConfig, Job};
-use common::tasks::{LoadConfigs, TaskConfigTrait};
+use common::tasks::{validate_phases, LoadConfigs, TaskConfigTrait};
 use common::workers::MatchedWorkers;
 use common::{NetworkType, PlanId, Timestamp, DOMAIN};
 
 use crate::{TemplateRender, CONFIG_BENCHMARK_DIR, SCHEME};
+use anyhow::anyhow;
+use async_trait::async_trait;
 use handlebars::Handlebars;
 use log::debug;
 use serde::{Deserialize, Serialize};
@@ -65,6 +67,9 @@ pub struct BenchmarkConfig {
     pub body: serde_json::Value,
     pub judge_histogram_percentile: u32,
     pub response_threshold: Timestamp,
+    // Minimum percentage of successful requests, 0 to disable
+    #[serde(default)]
+    pub success_percent_threshold: f32,
     pub assignment: Option<AssignmentConfig>,
     pub dependencies: Option<HashMap<String, Vec<String>>>,
 }
@@ -73,6 +78,31 @@ impl TaskConfigTrait for BenchmarkConfig {
     fn match_phase(&self, phase: &JobRole) -> bool {
         self.phases.contains(&String::from("*")) || self.phases.contains(&phase.to_string())
     }
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name must not be empty"));
+        }
+        validate_phases(&self.phases)?;
+        if self.url_template.is_empty() || self.http_method.is_empty() {
+            return Err(anyhow!("url_template and http_method must not be empty"));
+        }
+        if self.benchmark_duration <= 0
+            || self.benchmark_rate == 0
+            || self.benchmark_connection == 0
+        {
+            return Err(anyhow!(
+                "benchmark_duration, benchmark_rate and benchmark_connection must be positive"
+            ));
+        }
+        if !self.histograms.contains(&self.judge_histogram_percentile) {
+            return Err(anyhow!(
+                "judge_histogram_percentile {} is not in histograms {:?}",
+                self.judge_histogram_percentile,
+                self.histograms
+            ));
+        }
+        Ok(())
+    }
 
     fn get_blockchain(&self) -> &Vec<String> {
         &self.blockchains
@@ -198,6 +228,7 @@ impl BenchmarkGenerator {
         )
     }
 }
+#[async_trait]
 impl TaskApplicant for BenchmarkGenerator {
     fn get_type(&self) -> String {
         String::from("Benchmark")
@@ -251,7 +282,7 @@ impl TaskApplicant for BenchmarkGenerator {
     fn can_apply(&self, _component: &ComponentInfo) -> bool {
         true
     }
-    fn apply(
+    async fn apply(
         &self,
         plan_id: &PlanId,
         component: &ComponentInfo,
@@ -280,7 +311,9 @@ impl TaskApplicant for BenchmarkGenerator {
             }
             if let Ok(job) = self.generate_job(plan_id, component, phase.clone(), config, &context)
             {
-                assignment_buffer.assign_job(job, workers, &config.assignment);
+                assignment_buffer
+                    .assign_job(job, workers, &config.assignment)
+                    .await;
             }
         }
         debug!(
@@ -0,0 +1,263 @@
+use crate::models::chains::ChainRegistry;
+use crate::models::jobs::JobAssignmentBuffer;
+use crate::models::reloadable::Reloadable;
+use crate::service::judgment::JudgmentsResult;
+use crate::tasks::generator::TaskApplicant;
+use crate::{TemplateRender, CONFIG_SUBSTRATE_DIR, SCHEME};
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use common::component::{BlockChainFamily, ChainInfo, ComponentInfo, ComponentType};
+use common::job_manage::{JobDetail, JobRole};
+use common::jobs::{AssignmentConfig, Job};
+use common::tasks::dot::{JobSubstrate, SubstrateCheck};
+use common::tasks::{validate_phases, LoadConfigs, TaskConfigTrait};
+use common::workers::MatchedWorkers;
+use common::{NetworkType, PlanId, Timestamp, DOMAIN};
+use handlebars::Handlebars;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/*
+ * Health checks of Polkadot family nodes
+ */
+#[derive(Clone, Debug, Default)]
+pub struct SubstrateGenerator {
+    configs: Vec<SubstrateConfig>,
+    handlebars: Handlebars<'static>,
+    //Shared with task registry, replaced when chain_type.json is reloaded
+    chains: Arc<Reloadable<ChainRegistry>>,
+}
+
+/*
+ * Thresholds of substrate checks, each check uses its own thresholds
+ */
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SubstrateThresholds {
+    //health: minimum peers of a node which should have peers
+    #[serde(default = "default_min_peers")]
+    pub min_peers: u64,
+    //sync_state: maximum blocks between currentBlock and highestBlock
+    #[serde(default = "default_max_sync_lag")]
+    pub max_sync_lag: u64,
+    //finalized_head: maximum blocks between finalized head and best head
+    #[serde(default = "default_max_finality_lag")]
+    pub max_finality_lag: u64,
+    //runtime_version: minimum sampled providers before the majority version is used
+    #[serde(default = "default_min_providers")]
+    pub min_providers: usize,
+}
+
+fn default_min_peers() -> u64 {
+    3
+}
+
+fn default_max_sync_lag() -> u64 {
+    10
+}
+
+fn default_max_finality_lag() -> u64 {
+    20
+}
+
+fn default_min_providers() -> usize {
+    3
+}
+
+impl Default for SubstrateThresholds {
+    fn default() -> Self {
+        SubstrateThresholds {
+            min_peers: default_min_peers(),
+            max_sync_lag: default_max_sync_lag(),
+            max_finality_lag: default_max_finality_lag(),
+            min_providers: default_min_providers(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct SubstrateConfig {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default)]
+    pub phases: Vec<String>,
+    #[serde(default)]
+    pub provider_types: Vec<String>,
+    #[serde(default)]
+    pub blockchains: Vec<String>,
+    #[serde(default)]
+    pub networks: Vec<String>,
+    #[serde(default)]
+    pub check: SubstrateCheck,
+    #[serde(default)]
+    url_template: String,
+    #[serde(default)]
+    pub headers: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pub request_timeout: Timestamp,
+    #[serde(default)]
+    pub repeat_number: i32,
+    #[serde(default)]
+    pub interval: Timestamp,
+    #[serde(default)]
+    pub thresholds: SubstrateThresholds,
+    pub assignment: Option<AssignmentConfig>,
+}
+
+impl TaskConfigTrait for SubstrateConfig {
+    fn match_phase(&self, phase: &JobRole) -> bool {
+        self.phases.contains(&String::from("*")) || self.phases.contains(&phase.to_string())
+    }
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name must not be empty"));
+        }
+        validate_phases(&self.phases)?;
+        if self.url_template.is_empty() {
+            return Err(anyhow!("url_template must not be empty"));
+        }
+        if self.request_timeout <= 0 || self.interval <= 0 {
+            return Err(anyhow!("request_timeout and interval must be positive"));
+        }
+        Ok(())
+    }
+    fn get_blockchain(&self) -> &Vec<String> {
+        &self.blockchains
+    }
+    fn match_network(&self, network: &NetworkType) -> bool {
+        let network = network.to_lowercase();
+        self.networks.contains(&String::from("*")) || self.networks.contains(&network)
+    }
+    fn match_provider_type(&self, provider_type: &String) -> bool {
+        let provider_type = provider_type.to_lowercase();
+        self.provider_types.contains(&String::from("*"))
+            || self.provider_types.contains(&provider_type)
+    }
+    fn can_apply(&self, provider: &ComponentInfo, phase: &JobRole) -> bool {
+        self.active
+            && self.match_phase(phase)
+            && self.match_provider_type(&provider.component_type.to_string())
+            && self.match_blockchain(&provider.blockchain)
+            && self.match_network(&provider.network)
+    }
+}
+
+impl LoadConfigs<SubstrateConfig> for SubstrateConfig {}
+
+impl SubstrateGenerator {
+    pub fn get_name() -> String {
+        String::from("Substrate")
+    }
+    pub fn new(config_dir: &str, role: &JobRole, chains: Arc<Reloadable<ChainRegistry>>) -> Self {
+        let path = Path::new(config_dir).join(&*CONFIG_SUBSTRATE_DIR);
+        let configs: Vec<SubstrateConfig> = SubstrateConfig::read_configs(&path, role);
+        debug!("Substrate config {:?}", &configs);
+        SubstrateGenerator {
+            configs,
+            handlebars: Handlebars::new(),
+            chains,
+        }
+    }
+    fn create_context(component: &ComponentInfo) -> Value {
+        let mut context = json!({ "provider": component, "domain": DOMAIN.as_str(),"scheme": SCHEME.to_http_string()});
+        if let Some(obj) = context["provider"].as_object_mut() {
+            match component.component_type {
+                ComponentType::Node => obj.insert(String::from("type"), Value::from("node")),
+                ComponentType::Gateway => obj.insert(String::from("type"), Value::from("gw")),
+            };
+        };
+        context
+    }
+    pub fn generate_job(
+        &self,
+        plan_id: &PlanId,
+        component: &ComponentInfo,
+        phase: JobRole,
+        config: &SubstrateConfig,
+        context: &Value,
+    ) -> Result<Job, anyhow::Error> {
+        let url = SubstrateConfig::generate_url(&config.url_template, &self.handlebars, context)?;
+        let headers = SubstrateConfig::generate_header(&config.headers, &self.handlebars, context);
+        let detail = JobSubstrate {
+            chain_info: ChainInfo::new(component.blockchain.clone(), component.network.clone()),
+            check: config.check,
+            url: url.clone(),
+            headers,
+            timeout: config.request_timeout,
+        };
+        let mut job = Job::new(
+            plan_id.clone(),
+            Self::get_name(),
+            config.name.clone(),
+            component,
+            JobDetail::Substrate(detail),
+            phase,
+        );
+        job.parallelable = true;
+        job.component_url = url;
+        //Finalized head check sends dependent requests
+        job.timeout = 2 * config.request_timeout;
+        job.repeat_number = config.repeat_number;
+        job.interval = config.interval;
+        Ok(job)
+    }
+}
+
+#[async_trait]
+impl TaskApplicant for SubstrateGenerator {
+    fn get_type(&self) -> String {
+        Self::get_name()
+    }
+    fn get_task_names(&self) -> Vec<String> {
+        self.configs
+            .iter()
+            .map(|config| config.name.clone())
+            .collect()
+    }
+    fn can_apply(&self, component: &ComponentInfo) -> bool {
+        self.chains
+            .get()
+            .get(&component.blockchain)
+            .map(|spec| spec.family == BlockChainFamily::Polkadot)
+            .unwrap_or(false)
+    }
+    async fn apply(
+        &self,
+        plan_id: &PlanId,
+        component: &ComponentInfo,
+        phase: JobRole,
+        workers: &MatchedWorkers,
+        _task_results: &HashMap<String, JudgmentsResult>,
+    ) -> Result<JobAssignmentBuffer, Error> {
+        let mut assignment_buffer = JobAssignmentBuffer::default();
+        let context = Self::create_context(component);
+        for config in self
+            .configs
+            .iter()
+            .filter(|config| config.can_apply(component, &phase))
+        {
+            match self.generate_job(plan_id, component, phase.clone(), config, &context) {
+                Ok(job) => {
+                    assignment_buffer
+                        .assign_job(job, workers, &config.assignment)
+                        .await
+                }
+                Err(err) => debug!(
+                    "Cannot generate {} job for {:?}: {:?}",
+                    &config.name, component, err
+                ),
+            }
+        }
+        debug!(
+            "Generated {} substrate jobs and {} assignments.",
+            &assignment_buffer.jobs.len(),
+            &assignment_buffer.list_assignments.len()
+        );
+        Ok(assignment_buffer)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod generator;
+
+pub use generator::SubstrateGenerator;
@@ -1,45 +1,232 @@
+use crate::models::chains::ChainRegistry;
 use crate::models::jobs::JobAssignmentBuffer;
+use crate::models::reloadable::Reloadable;
+use crate::service::judgment::JudgmentsResult;
 use crate::tasks::generator::TaskApplicant;
-use anyhow::Error;
-use common::component::ComponentInfo;
-use common::job_manage::JobRole;
+use crate::{TemplateRender, CONFIG_RANDOM_BLOCK_DIR, SCHEME};
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use common::component::{BlockChainFamily, ChainInfo, ComponentInfo, ComponentType};
+use common::job_manage::{JobDetail, JobRole};
+use common::jobs::{AssignmentConfig, Job};
+use common::tasks::eth::random_block::JobRandomBlock;
+use common::tasks::{validate_phases, LoadConfigs, TaskConfigTrait};
 use common::workers::MatchedWorkers;
-use common::{Node, PlanId};
+use common::{NetworkType, PlanId, Timestamp, DOMAIN};
+use handlebars::Handlebars;
+use log::debug;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
 
 /*
- * Apply for node to get randome block number and response time
+ * Compare random historical blocks of Ethereum family nodes with a reference endpoint
  */
+#[derive(Clone, Debug, Default)]
+pub struct RandomBlockGenerator {
+    configs: Vec<RandomBlockConfig>,
+    handlebars: Handlebars<'static>,
+    //Shared with task registry, replaced when chain_type.json is reloaded
+    chains: Arc<Reloadable<ChainRegistry>>,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
-pub struct TaskRandomBlock {
-    assigned_at: u64, //Timestamp to assign job
-    finished_at: u64, //Timestamp when result has arrived
+pub struct RandomBlockConfig {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default)]
+    pub phases: Vec<String>,
+    #[serde(default)]
+    pub provider_types: Vec<String>,
+    #[serde(default)]
+    pub blockchains: Vec<String>,
+    #[serde(default)]
+    pub networks: Vec<String>,
+    #[serde(default)]
+    url_template: String,
+    #[serde(default)]
+    pub headers: serde_json::Map<String, serde_json::Value>,
+    //Reference endpoints by {blockchain}.{network}
+    #[serde(default)]
+    pub references: HashMap<String, String>,
+    #[serde(default)]
+    pub reference_headers: HashMap<String, String>,
+    //Blocks are picked among the latest depth blocks, 0 for archive nodes (whole chain)
+    #[serde(default)]
+    pub depth: u64,
+    #[serde(default)]
+    pub confirmations: u64,
+    #[serde(default)]
+    pub request_timeout: Timestamp,
+    #[serde(default)]
+    pub repeat_number: i32,
+    #[serde(default)]
+    pub interval: Timestamp,
+    pub assignment: Option<AssignmentConfig>,
 }
 
-impl TaskRandomBlock {
-    pub fn new() -> Self {
-        TaskRandomBlock {
-            assigned_at: 0,
-            finished_at: 0,
+impl TaskConfigTrait for RandomBlockConfig {
+    fn match_phase(&self, phase: &JobRole) -> bool {
+        self.phases.contains(&String::from("*")) || self.phases.contains(&phase.to_string())
+    }
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name must not be empty"));
+        }
+        validate_phases(&self.phases)?;
+        if self.url_template.is_empty() {
+            return Err(anyhow!("url_template must not be empty"));
+        }
+        if self.request_timeout <= 0 || self.interval <= 0 {
+            return Err(anyhow!("request_timeout and interval must be positive"));
         }
+        Ok(())
+    }
+    fn get_blockchain(&self) -> &Vec<String> {
+        &self.blockchains
+    }
+    fn match_network(&self, network: &NetworkType) -> bool {
+        let network = network.to_lowercase();
+        self.networks.contains(&String::from("*")) || self.networks.contains(&network)
+    }
+    fn match_provider_type(&self, provider_type: &String) -> bool {
+        let provider_type = provider_type.to_lowercase();
+        self.provider_types.contains(&String::from("*"))
+            || self.provider_types.contains(&provider_type)
+    }
+    fn can_apply(&self, provider: &ComponentInfo, phase: &JobRole) -> bool {
+        self.active
+            && self.match_phase(phase)
+            && self.match_provider_type(&provider.component_type.to_string())
+            && self.match_blockchain(&provider.blockchain)
+            && self.match_network(&provider.network)
     }
 }
 
-impl TaskApplicant for TaskRandomBlock {
-    fn get_name(&self) -> String {
+impl LoadConfigs<RandomBlockConfig> for RandomBlockConfig {}
+
+impl RandomBlockGenerator {
+    pub fn get_name() -> String {
         String::from("RandomBlock")
     }
-    fn can_apply(&self, _component: &ComponentInfo) -> bool {
-        true
+    pub fn new(config_dir: &str, role: &JobRole, chains: Arc<Reloadable<ChainRegistry>>) -> Self {
+        let path = Path::new(config_dir).join(&*CONFIG_RANDOM_BLOCK_DIR);
+        let configs: Vec<RandomBlockConfig> = RandomBlockConfig::read_configs(&path, role);
+        debug!("RandomBlock config {:?}", &configs);
+        RandomBlockGenerator {
+            configs,
+            handlebars: Handlebars::new(),
+            chains,
+        }
+    }
+    fn create_context(component: &ComponentInfo) -> Value {
+        let mut context = json!({ "provider": component, "domain": DOMAIN.as_str(),"scheme": SCHEME.to_http_string()});
+        if let Some(obj) = context["provider"].as_object_mut() {
+            match component.component_type {
+                ComponentType::Node => obj.insert(String::from("type"), Value::from("node")),
+                ComponentType::Gateway => obj.insert(String::from("type"), Value::from("gw")),
+            };
+        };
+        context
     }
+    pub fn generate_job(
+        &self,
+        plan_id: &PlanId,
+        component: &ComponentInfo,
+        phase: JobRole,
+        config: &RandomBlockConfig,
+        context: &Value,
+    ) -> Result<Job, anyhow::Error> {
+        let chain_info = ChainInfo::new(component.blockchain.clone(), component.network.clone());
+        let reference_url = config
+            .references
+            .get(&chain_info.to_string())
+            .ok_or_else(|| anyhow!("No reference endpoint for {}", chain_info.to_string()))?;
+        let url = RandomBlockConfig::generate_url(&config.url_template, &self.handlebars, context)?;
+        let headers =
+            RandomBlockConfig::generate_header(&config.headers, &self.handlebars, context);
+        let detail = JobRandomBlock {
+            chain_info,
+            url: url.clone(),
+            headers,
+            reference_url: reference_url.clone(),
+            reference_headers: config.reference_headers.clone(),
+            depth: config.depth,
+            confirmations: config.confirmations,
+            timeout: config.request_timeout,
+        };
+        let mut job = Job::new(
+            plan_id.clone(),
+            Self::get_name(),
+            config.name.clone(),
+            component,
+            JobDetail::RandomBlock(detail),
+            phase,
+        );
+        job.parallelable = true;
+        job.component_url = url;
+        //Reference head is requested before the blocks
+        job.timeout = 2 * config.request_timeout;
+        job.repeat_number = config.repeat_number;
+        job.interval = config.interval;
+        Ok(job)
+    }
+}
 
-    fn apply(
+#[async_trait]
+impl TaskApplicant for RandomBlockGenerator {
+    fn get_type(&self) -> String {
+        Self::get_name()
+    }
+    fn get_task_names(&self) -> Vec<String> {
+        self.configs
+            .iter()
+            .map(|config| config.name.clone())
+            .collect()
+    }
+    fn can_apply(&self, component: &ComponentInfo) -> bool {
+        self.chains
+            .get()
+            .get(&component.blockchain)
+            .map(|spec| spec.family == BlockChainFamily::Ethereum)
+            .unwrap_or(false)
+    }
+    async fn apply(
         &self,
-        _plan_id: &PlanId,
-        _node: &Node,
-        _phase: JobRole,
-        _workers: &MatchedWorkers,
+        plan_id: &PlanId,
+        component: &ComponentInfo,
+        phase: JobRole,
+        workers: &MatchedWorkers,
+        _task_results: &HashMap<String, JudgmentsResult>,
     ) -> Result<JobAssignmentBuffer, Error> {
-        Ok(JobAssignmentBuffer::default())
+        let mut assignment_buffer = JobAssignmentBuffer::default();
+        let context = Self::create_context(component);
+        for config in self
+            .configs
+            .iter()
+            .filter(|config| config.can_apply(component, &phase))
+        {
+            match self.generate_job(plan_id, component, phase.clone(), config, &context) {
+                Ok(job) => {
+                    assignment_buffer
+                        .assign_job(job, workers, &config.assignment)
+                        .await
+                }
+                Err(err) => debug!(
+                    "Cannot generate {} job for {:?}: {:?}",
+                    &config.name, component, err
+                ),
+            }
+        }
+        debug!(
+            "Generated {} random block jobs and {} assignments.",
+            &assignment_buffer.jobs.len(),
+            &assignment_buffer.list_assignments.len()
+        );
+        Ok(assignment_buffer)
     }
 }
@@ -2,15 +2,19 @@
  * Each Task description can apply to node/gateway to generate a list of jobs.
  * If task is not suitable then result is empty
  */
-use crate::models::job_result_cache::{TaskKey};
+use crate::models::chains::ChainRegistry;
+use crate::models::job_result_cache::TaskKey;
 use crate::models::jobs::JobAssignmentBuffer;
 
+use crate::models::reloadable::Reloadable;
 use crate::persistence::PlanModel;
+use crate::service::generator::TaskConfig;
 use crate::service::judgment::JudgmentsResult;
 use crate::tasks::benchmark::generator::BenchmarkGenerator;
 use crate::tasks::websocket::generator::WebsocketGenerator;
 use crate::tasks::*;
 use crate::CONFIG;
+use async_trait::async_trait;
 use common::component::ComponentInfo;
 use common::job_manage::JobRole;
 use common::jobs::{Job, JobAssignment};
@@ -21,6 +25,7 @@ use common::{PlanId, Timestamp};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+#[async_trait]
 pub trait TaskApplicant: Sync + Send {
     fn get_type(&self) -> String;
     fn get_task_names(&self) -> Vec<String> {
@@ -34,7 +39,7 @@ pub trait TaskApplicant: Sync + Send {
         true
     }
     fn can_apply(&self, component: &ComponentInfo) -> bool;
-    fn apply(
+    async fn apply(
         &self,
         plan: &PlanId,
         component: &ComponentInfo,
@@ -42,7 +47,7 @@ pub trait TaskApplicant: Sync + Send {
         workers: &MatchedWorkers,
         task_results: &HashMap<String, JudgmentsResult>,
     ) -> Result<JobAssignmentBuffer, anyhow::Error>;
-    fn apply_with_cache(
+    async fn apply_with_cache(
         &self,
         plan: &PlanId,
         component: &ComponentInfo,
@@ -57,6 +62,7 @@ pub trait TaskApplicant: Sync + Send {
             .unwrap_or_default();
         if get_current_time() - timestamp > CONFIG.generate_new_regular_timeout * 1000 {
             self.apply(plan, component, phase, workers, &HashMap::default())
+                .await
         } else {
             Ok(JobAssignmentBuffer::default())
         }
@@ -85,22 +91,89 @@ pub trait TaskApplicant: Sync + Send {
  * Todo: can add config to load required task for each phase: verification or regular
  */
 
+/*
+ * Task generators of both phases, replaced as a whole when task configs are reloaded.
+ * Plans which are being generated keep the tasks they started with.
+ * Chain registry is loaded once for each reload and shared by all generators
+ */
+#[derive(Default)]
+pub struct TaskRegistry {
+    regular: Reloadable<Vec<Arc<dyn TaskApplicant>>>,
+    verification: Reloadable<Vec<Arc<dyn TaskApplicant>>>,
+    chains: Arc<Reloadable<ChainRegistry>>,
+}
+
+impl TaskRegistry {
+    pub fn new(config_dir: &str, task_config: &TaskConfig) -> Self {
+        let registry = TaskRegistry::default();
+        registry.reload(config_dir, task_config);
+        registry
+    }
+    pub fn get_tasks(&self, phase: &JobRole) -> Arc<Vec<Arc<dyn TaskApplicant>>> {
+        match phase {
+            JobRole::Regular => self.regular.get(),
+            JobRole::Verification => self.verification.get(),
+        }
+    }
+    pub fn get_chains(&self) -> Arc<ChainRegistry> {
+        self.chains.get()
+    }
+    pub fn reload(&self, config_dir: &str, task_config: &TaskConfig) {
+        self.chains.set(ChainRegistry::load_or_builtin(config_dir));
+        self.verification.set(get_tasks(
+            config_dir,
+            JobRole::Verification,
+            &task_config.verification,
+            &self.chains,
+        ));
+        self.regular.set(get_tasks(
+            config_dir,
+            JobRole::Regular,
+            &task_config.regular,
+            &self.chains,
+        ));
+    }
+}
+
 pub fn get_tasks(
     config_dir: &str,
     role: JobRole,
     task_types: &Vec<String>,
+    chains: &Arc<Reloadable<ChainRegistry>>,
 ) -> Vec<Arc<dyn TaskApplicant>> {
     let mut result: Vec<Arc<dyn TaskApplicant>> = Default::default();
     //Generic http request task
     if task_types.contains(&HttpRequestGenerator::get_name()) {
-        result.push(Arc::new(HttpRequestGenerator::new(config_dir, &role)));
+        result.push(Arc::new(HttpRequestGenerator::new(
+            config_dir,
+            &role,
+            chains.clone(),
+        )));
     }
     if task_types.contains(&WebsocketGenerator::get_name()) {
-        result.push(Arc::new(WebsocketGenerator::new(config_dir, &role)));
+        result.push(Arc::new(WebsocketGenerator::new(
+            config_dir,
+            &role,
+            chains.clone(),
+        )));
     }
     if task_types.contains(&BenchmarkGenerator::get_name()) {
         result.push(Arc::new(BenchmarkGenerator::new(config_dir, &role)));
     }
+    if task_types.contains(&RandomBlockGenerator::get_name()) {
+        result.push(Arc::new(RandomBlockGenerator::new(
+            config_dir,
+            &role,
+            chains.clone(),
+        )));
+    }
+    if task_types.contains(&SubstrateGenerator::get_name()) {
+        result.push(Arc::new(SubstrateGenerator::new(
+            config_dir,
+            &role,
+            chains.clone(),
+        )));
+    }
     // if task_types.contains(&PingGenerator::get_name()) {
     //     result.push(Arc::new(PingGenerator::new(config_dir, &role)));
     // }
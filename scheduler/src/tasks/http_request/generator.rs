@@ -1,9 +1,12 @@
+use crate::models::chains::ChainRegistry;
 use crate::models::jobs::JobAssignmentBuffer;
+use crate::models::reloadable::Reloadable;
 use crate::persistence::PlanModel;
 use crate::service::judgment::JudgmentsResult;
 use crate::tasks::generator::TaskApplicant;
 use crate::{TemplateRender, CONFIG, CONFIG_HTTP_REQUEST_DIR, SCHEME};
 use anyhow::Error;
+use async_trait::async_trait;
 use common::component::{ChainInfo, ComponentInfo, ComponentType};
 use common::job_manage::{JobDetail, JobRole};
 use common::jobs::{Job, JobAssignment};
@@ -11,13 +14,13 @@ use common::tasks::http_request::{HttpRequestJobConfig, JobHttpRequest};
 use common::tasks::{LoadConfigs, TaskConfigTrait};
 use common::util::get_current_time;
 use common::workers::MatchedWorkers;
-use common::{BlockChainType, PlanId, Timestamp, DOMAIN};
+use common::{PlanId, Timestamp, DOMAIN};
 use handlebars::Handlebars;
-use log::{debug, trace};
+use log::{debug, trace, warn};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::path::Path;
-use std::str::FromStr;
+use std::sync::Arc;
 
 /*
  * Periodically ping to node/gateway to get response time, to make sure node/gateway is working
@@ -27,13 +30,15 @@ pub struct HttpRequestGenerator {
     //root_config: serde_json::Map<String, serde_json::Value>,
     task_configs: Vec<HttpRequestJobConfig>,
     handlebars: Handlebars<'static>,
+    //Shared with task registry, replaced when chain_type.json is reloaded
+    chains: Arc<Reloadable<ChainRegistry>>,
 }
 
 impl HttpRequestGenerator {
     pub fn get_name() -> String {
         String::from("HttpRequest")
     }
-    pub fn new(config_dir: &str, phase: &JobRole) -> Self {
+    pub fn new(config_dir: &str, phase: &JobRole, chains: Arc<Reloadable<ChainRegistry>>) -> Self {
         // let path = format!("{}/http_request", config_dir);
         let path = Path::new(config_dir).join(&*CONFIG_HTTP_REQUEST_DIR);
         let task_configs = HttpRequestJobConfig::read_configs(&path, phase);
@@ -42,10 +47,11 @@ impl HttpRequestGenerator {
             //root_config: configs,
             task_configs,
             handlebars: Handlebars::new(),
+            chains,
         }
     }
 
-    fn create_context(component: &ComponentInfo) -> Value {
+    fn create_context(&self, component: &ComponentInfo) -> Value {
         let mut context = json!({ "provider": component, "domain": DOMAIN.as_str(),"scheme": SCHEME.to_http_string() });
         if let Some(obj) = context["provider"].as_object_mut() {
             match component.component_type {
@@ -53,6 +59,10 @@ impl HttpRequestGenerator {
                 ComponentType::Gateway => obj.insert(String::from("type"), Value::from("gw")),
             };
         };
+        //Chain spec from registry, e.g. {{chain.rpc_methods.latest_block}}
+        if let Some(spec) = self.chains.get().get(&component.blockchain) {
+            context["chain"] = json!(spec);
+        }
         context
     }
     fn generate_job(
@@ -63,22 +73,36 @@ impl HttpRequestGenerator {
         config: &HttpRequestJobConfig,
         context: &Value,
     ) -> Result<Job, anyhow::Error> {
-        HttpRequestJobConfig::generate_url(&config.url_template, &self.handlebars, context).map(
-            |url| {
+        HttpRequestJobConfig::generate_url(&config.url_template, &self.handlebars, context)
+            .and_then(|url| {
                 //self.get_url(config, context).map(|url| {
                 let provider = &context["provider"];
                 let chain_info = ChainInfo::new(
-                    provider["blockchain"]
-                        .as_str()
-                        .map(|str| BlockChainType::from_str(str).unwrap())
-                        .unwrap_or_default(),
+                    component.blockchain.clone(),
                     provider["network"]
                         .as_str()
                         .map(|str| str.to_string())
                         .unwrap_or_default(),
                 );
                 let headers = config.generate_header(&self.handlebars, &context);
-                let body = config.generate_body(&self.handlebars, &context).ok();
+                let (batch, body) = if config.batch.is_empty() {
+                    (
+                        Vec::new(),
+                        config.generate_body(&self.handlebars, &context).ok(),
+                    )
+                } else {
+                    //Job without its batch calls has nothing to request, so it is not generated
+                    match config.generate_batch(&self.handlebars, &context) {
+                        Ok((batch, body)) => (batch, Some(body)),
+                        Err(err) => {
+                            warn!(
+                                "Skip job {} for {}, cannot generate batch: {:?}",
+                                &config.name, &component.id, &err
+                            );
+                            return Err(err);
+                        }
+                    }
+                };
                 let detail = JobHttpRequest {
                     url: url.clone(),
                     chain_info: Some(chain_info.clone()),
@@ -87,6 +111,7 @@ impl HttpRequestGenerator {
                     body,
                     response_type: config.response.response_type.clone(),
                     response_values: config.response.values.clone(),
+                    batch,
                 };
                 let mut job = Job::new(
                     plan_id.clone(),
@@ -101,19 +126,20 @@ impl HttpRequestGenerator {
                 job.timeout = config.request_timeout;
                 job.repeat_number = config.repeat_number;
                 job.interval = config.interval;
-                job
-            },
-        )
+                Ok(job)
+            })
     }
 }
+#[async_trait]
 impl TaskApplicant for HttpRequestGenerator {
     fn get_type(&self) -> String {
         String::from("HttpRequest")
     }
-    fn can_apply(&self, _component: &ComponentInfo) -> bool {
-        true
+    fn can_apply(&self, component: &ComponentInfo) -> bool {
+        //Only chains in chain registry are supported
+        self.chains.get().get(&component.blockchain).is_some()
     }
-    fn apply(
+    async fn apply(
         &self,
         plan_id: &PlanId,
         component: &ComponentInfo,
@@ -122,7 +148,7 @@ impl TaskApplicant for HttpRequestGenerator {
         _task_results: &HashMap<String, JudgmentsResult>,
     ) -> Result<JobAssignmentBuffer, Error> {
         let mut assignment_buffer = JobAssignmentBuffer::new();
-        let context = Self::create_context(component);
+        let context = self.create_context(component);
         log::debug!(
             "Http Request apply for component {:?} with context {:?}",
             component,
@@ -140,7 +166,9 @@ impl TaskApplicant for HttpRequestGenerator {
             }
             if let Ok(job) = self.generate_job(plan_id, component, phase.clone(), config, &context)
             {
-                assignment_buffer.assign_job(job, workers, &Some(config.assignment.clone()));
+                assignment_buffer
+                    .assign_job(job, workers, &Some(config.assignment.clone()))
+                    .await;
             }
         }
         log::debug!(
@@ -150,7 +178,7 @@ impl TaskApplicant for HttpRequestGenerator {
         );
         Ok(assignment_buffer)
     }
-    fn apply_with_cache(
+    async fn apply_with_cache(
         &self,
         plan_id: &PlanId,
         component: &ComponentInfo,
@@ -159,7 +187,7 @@ impl TaskApplicant for HttpRequestGenerator {
         latest_update: HashMap<String, Timestamp>,
     ) -> Result<JobAssignmentBuffer, Error> {
         let mut assignment_buffer = JobAssignmentBuffer::new();
-        let context = Self::create_context(component);
+        let context = self.create_context(component);
         log::debug!(
             "Http Request apply for component {:?} with context {:?}",
             component,
@@ -198,7 +226,9 @@ impl TaskApplicant for HttpRequestGenerator {
             );
             if let Ok(job) = self.generate_job(plan_id, component, phase.clone(), config, &context)
             {
-                assignment_buffer.assign_job(job, workers, &Some(config.assignment.clone()));
+                assignment_buffer
+                    .assign_job(job, workers, &Some(config.assignment.clone()))
+                    .await;
             }
         }
         log::trace!("Generated jobs {:?}", &assignment_buffer);
@@ -228,3 +258,54 @@ impl TaskApplicant for HttpRequestGenerator {
         Ok(assignments)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::tasks::http_request::JsonRpcCall;
+    use common::BlockChainType;
+    use test_util::helper::{load_env, mock_component_info};
+
+    #[test]
+    fn test_skip_job_with_invalid_batch() {
+        load_env();
+        let generator = HttpRequestGenerator::default();
+        let component = mock_component_info("node_id", &BlockChainType::Eth, &ComponentType::Node);
+        let context = generator.create_context(&component);
+        let mut config = HttpRequestJobConfig {
+            name: "batch_call".to_string(),
+            url_template: "https://{{provider.ip}}".to_string(),
+            batch: vec![JsonRpcCall {
+                name: "block".to_string(),
+                method: "eth_getBlockByNumber".to_string(),
+                params: json!(["latest", false]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let job = generator
+            .generate_job(
+                &"plan_id".to_string(),
+                &component,
+                JobRole::Regular,
+                &config,
+                &context,
+            )
+            .unwrap();
+        match job.job_detail {
+            JobDetail::HttpRequest(detail) => assert_eq!(detail.batch.len(), 1),
+            _ => panic!("Job detail is not http request"),
+        }
+        //Unclosed template in batch params
+        config.batch[0].params = json!(["{{provider.id", false]);
+        assert!(generator
+            .generate_job(
+                &"plan_id".to_string(),
+                &component,
+                JobRole::Regular,
+                &config,
+                &context
+            )
+            .is_err());
+    }
+}
@@ -4,5 +4,6 @@ pub mod eth;
 pub mod generator;
 pub mod http_request;
 pub mod websocket;
+pub use dot::*;
 pub use eth::*;
 pub use http_request::generator::HttpRequestGenerator;
@@ -1,9 +1,12 @@
+use crate::models::chains::ChainRegistry;
 use crate::models::jobs::JobAssignmentBuffer;
+use crate::models::reloadable::Reloadable;
 use crate::persistence::PlanModel;
 use crate::service::judgment::JudgmentsResult;
 use crate::tasks::generator::TaskApplicant;
 use crate::{TemplateRender, CONFIG, CONFIG_WEBSOCKET_DIR, SCHEME};
 use anyhow::Error;
+use async_trait::async_trait;
 use common::component::{ChainInfo, ComponentInfo, ComponentType};
 use common::job_manage::{JobDetail, JobRole};
 use common::jobs::{Job, JobAssignment};
@@ -11,13 +14,13 @@ use common::tasks::websocket_request::{JobWebsocket, JobWebsocketConfig};
 use common::tasks::{LoadConfigs, TaskConfigTrait};
 use common::util::get_current_time;
 use common::workers::MatchedWorkers;
-use common::{BlockChainType, PlanId, Timestamp, DOMAIN};
+use common::{PlanId, Timestamp, DOMAIN};
 use handlebars::Handlebars;
 use log::{debug, trace};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::path::Path;
-use std::str::FromStr;
+use std::sync::Arc;
 
 /*
  * Periodically ping to node/gateway to get response time, to make sure node/gateway is working
@@ -27,13 +30,15 @@ pub struct WebsocketGenerator {
     //root_config: serde_json::Map<String, serde_json::Value>,
     task_configs: Vec<JobWebsocketConfig>,
     handlebars: Handlebars<'static>,
+    //Shared with task registry, replaced when chain_type.json is reloaded
+    chains: Arc<Reloadable<ChainRegistry>>,
 }
 
 impl WebsocketGenerator {
     pub fn get_name() -> String {
         String::from("Websocket")
     }
-    pub fn new(config_dir: &str, phase: &JobRole) -> Self {
+    pub fn new(config_dir: &str, phase: &JobRole, chains: Arc<Reloadable<ChainRegistry>>) -> Self {
         // let path = format!("{}/websocket.json", config_dir);
         // let task_configs = JobWebsocketConfig::read_config(path.as_str(), phase);
         //let path = format!("{}/websocket", config_dir);
@@ -43,9 +48,10 @@ impl WebsocketGenerator {
             //root_config: configs,
             task_configs,
             handlebars: Handlebars::new(),
+            chains,
         }
     }
-    fn create_context(component: &ComponentInfo) -> Value {
+    fn create_context(&self, component: &ComponentInfo) -> Value {
         let mut context = json!({ "provider": component, "domain": DOMAIN.as_str(),"ws_scheme": SCHEME.to_ws_string() });
         if let Some(obj) = context["provider"].as_object_mut() {
             match component.component_type {
@@ -53,6 +59,10 @@ impl WebsocketGenerator {
                 ComponentType::Gateway => obj.insert(String::from("type"), Value::from("gw")),
             };
         };
+        //Chain spec from registry, e.g. {{chain.rpc_methods.latest_block}}
+        if let Some(spec) = self.chains.get().get(&component.blockchain) {
+            context["chain"] = json!(spec);
+        }
         context
     }
     fn generate_job(
@@ -68,10 +78,7 @@ impl WebsocketGenerator {
                 //self.get_url(config, context).map(|url| {
                 let provider = &context["provider"];
                 let chain_info = ChainInfo::new(
-                    provider["blockchain"]
-                        .as_str()
-                        .map(|str| BlockChainType::from_str(str).unwrap())
-                        .unwrap_or_default(),
+                    component.blockchain.clone(),
                     provider["network"]
                         .as_str()
                         .map(|str| str.to_string())
@@ -105,14 +112,16 @@ impl WebsocketGenerator {
         )
     }
 }
+#[async_trait]
 impl TaskApplicant for WebsocketGenerator {
     fn get_type(&self) -> String {
         String::from("Websocket")
     }
-    fn can_apply(&self, _component: &ComponentInfo) -> bool {
-        true
+    fn can_apply(&self, component: &ComponentInfo) -> bool {
+        //Only chains in chain registry are supported
+        self.chains.get().get(&component.blockchain).is_some()
     }
-    fn apply(
+    async fn apply(
         &self,
         plan_id: &PlanId,
         component: &ComponentInfo,
@@ -121,7 +130,7 @@ impl TaskApplicant for WebsocketGenerator {
         _task_results: &HashMap<String, JudgmentsResult>,
     ) -> Result<JobAssignmentBuffer, Error> {
         let mut assignment_buffer = JobAssignmentBuffer::new();
-        let context = Self::create_context(component);
+        let context = self.create_context(component);
         log::debug!(
             "Websocket apply for component {:?} with context {:?}",
             component,
@@ -139,7 +148,9 @@ impl TaskApplicant for WebsocketGenerator {
             }
             if let Ok(job) = self.generate_job(plan_id, component, phase.clone(), config, &context)
             {
-                assignment_buffer.assign_job(job, workers, &Some(config.assignment.clone()));
+                assignment_buffer
+                    .assign_job(job, workers, &Some(config.assignment.clone()))
+                    .await;
             }
         }
         log::debug!(
@@ -149,7 +160,7 @@ impl TaskApplicant for WebsocketGenerator {
         );
         Ok(assignment_buffer)
     }
-    fn apply_with_cache(
+    async fn apply_with_cache(
         &self,
         plan_id: &PlanId,
         component: &ComponentInfo,
@@ -158,7 +169,7 @@ impl TaskApplicant for WebsocketGenerator {
         latest_update: HashMap<String, Timestamp>,
     ) -> Result<JobAssignmentBuffer, Error> {
         let mut assignment_buffer = JobAssignmentBuffer::new();
-        let context = Self::create_context(component);
+        let context = self.create_context(component);
         log::debug!(
             "Websocket apply for component {:?} with context {:?}",
             component,
@@ -193,7 +204,9 @@ impl TaskApplicant for WebsocketGenerator {
                 if let Ok(job) =
                     self.generate_job(plan_id, component, phase.clone(), config, &context)
                 {
-                    assignment_buffer.assign_job(job, workers, &Some(config.assignment.clone()));
+                    assignment_buffer
+                        .assign_job(job, workers, &Some(config.assignment.clone()))
+                        .await;
                 }
             }
         }
@@ -15,6 +15,5 @@ export WORKER_ID=default_worker_$WORKER_IP
 export ZONE=AS
 export WORKER_ENDPOINT=http://$WORKER_IP:4040
 export WORKER_SERVICE_ENDPOINT=0.0.0.0:4040
-export BENCHMARK_WRK_PATH=benchmark
 
 /usr/local/bin/fisherman
\ No newline at end of file
@@ -0,0 +1,27 @@
+#!/bin/bash
+# Create a CA and certs signed by it for mutual TLS between scheduler and workers
+# Usage: create_certs.sh <output dir> <name> <subject alt names>
+#   create_certs.sh certs scheduler "DNS:scheduler.fisherman.massbitroute.net"
+#   create_certs.sh certs worker-as-1 "IP:10.0.0.1"
+# CA is created on first run and reused for the next certs
+set -e
+OUT_DIR=${1:-certs}
+NAME=${2:-scheduler}
+SAN=${3:-"DNS:localhost,IP:127.0.0.1"}
+DAYS=${DAYS:-365}
+
+mkdir -p $OUT_DIR
+cd $OUT_DIR
+if [ ! -f ca.pem ]; then
+  openssl req -x509 -newkey rsa:4096 -nodes -days $((DAYS * 10)) \
+    -keyout ca.key -out ca.pem -subj "/CN=Fisherman CA"
+fi
+openssl req -newkey rsa:2048 -nodes -keyout $NAME.rsa.key -out $NAME.csr -subj "/CN=$NAME"
+# reqwest identity requires pkcs8 key
+openssl pkcs8 -topk8 -nocrypt -in $NAME.rsa.key -out $NAME.key
+rm $NAME.rsa.key
+printf "subjectAltName=$SAN\nextendedKeyUsage=serverAuth,clientAuth\n" > $NAME.ext
+openssl x509 -req -in $NAME.csr -CA ca.pem -CAkey ca.key -CAcreateserial -days $DAYS \
+  -extfile $NAME.ext -out $NAME.pem
+rm $NAME.csr $NAME.ext
+echo "Created $OUT_DIR/$NAME.pem and $OUT_DIR/$NAME.key"
@@ -6,13 +6,14 @@ use common::jobs::{Job, JobResult};
 use common::logger::init_logger;
 
 use common::tasks::http_request::{
-    JobHttpRequest, JobHttpResponse, JobHttpResponseDetail, JobHttpResult,
+    HttpResponseValues, JobHttpRequest, JobHttpResponse, JobHttpResponseDetail, JobHttpResult,
 };
 use common::util::get_current_time;
 use common::workers::WorkerInfo;
 use common::{BlockChainType, ComponentInfo};
 use entity::job_result_http_requests;
 use sea_orm::{DatabaseBackend, DatabaseConnection, MockDatabase, MockExecResult};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::Hash;
@@ -86,6 +87,8 @@ pub fn mock_worker(id: &str) -> WorkerInfo {
         zone: Zone::AS,
         worker_spec: Default::default(),
         available_time_frame: None,
+        public_key: Default::default(),
+        delivery_mode: Default::default(),
     }
 }
 
@@ -169,7 +172,7 @@ pub fn mock_job_result(
         }
         JobName::LatestBlock => {
             let detail: JobHttpResponseDetail = match chain {
-                BlockChainType::Eth|BlockChainType::Bsc|BlockChainType::Matic => {
+                BlockChainType::Eth|BlockChainType::Bsc|BlockChainType::Matic|BlockChainType::Other(_) => {
                     serde_json::from_str(r###"
             {"Values": {"inner": {"hash": "0x7e915fa20e34a184701607091cf6715744889751b9485aae7b04ef165aa6cacc", "number": "0xe5a51a", "timestamp": "0x62c217d5"}}}
             "###).unwrap()
@@ -201,6 +204,37 @@ pub fn mock_job_result(
     job_result
 }
 
+/*
+ * Regular result of a configured task (e.g. a HttpRequest task) with given detail
+ */
+pub fn mock_job_result_with_detail(
+    job_name: &str,
+    chain: BlockChainType,
+    provider_id: &str,
+    result_detail: JobResultDetail,
+) -> JobResult {
+    let job = Job {
+        job_type: result_detail.get_name(),
+        job_name: job_name.to_string(),
+        component_id: provider_id.to_string(),
+        phase: JobRole::Regular,
+        ..Default::default()
+    };
+    let chain_info = ChainInfo {
+        chain,
+        network: "main".to_string(),
+    };
+    JobResult::new(result_detail, Some(chain_info), &job)
+}
+
+pub fn mock_http_values_detail(values: HashMap<String, Value>) -> JobResultDetail {
+    let resp = JobHttpResponse {
+        detail: JobHttpResponseDetail::Values(HttpResponseValues::new(values)),
+        ..Default::default()
+    };
+    JobResultDetail::HttpRequest(JobHttpResult::new(Job::default(), resp))
+}
+
 pub fn mock_job(job_name: &JobName, component_url: &str, job_id: &str, phase: &JobRole) -> Job {
     let component = ComponentInfo {
         blockchain: BlockChainType::default(),